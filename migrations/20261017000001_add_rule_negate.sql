@@ -0,0 +1,5 @@
+-- migrations/20261017000001_add_rule_negate.sql
+
+-- Negated rules fire when their condition does NOT match
+-- (e.g. email_domain @competitor.com with negate = true means "everyone except competitors")
+ALTER TABLE flag_rules ADD COLUMN negate BOOLEAN NOT NULL DEFAULT FALSE;
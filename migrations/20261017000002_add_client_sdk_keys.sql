@@ -0,0 +1,17 @@
+-- migrations/20261017000002_add_client_sdk_keys.sql
+
+-- Client-side SDK keys can only evaluate flags explicitly marked as client visible,
+-- so internal flags are never exposed to browser code
+ALTER TABLE projects ADD COLUMN client_sdk_key TEXT UNIQUE;
+
+-- Backfill existing projects with a random client key
+UPDATE projects
+SET client_sdk_key = 'client_' || replace(gen_random_uuid()::text, '-', '')
+WHERE client_sdk_key IS NULL;
+
+ALTER TABLE projects ALTER COLUMN client_sdk_key SET NOT NULL;
+
+CREATE INDEX idx_project_client_sdk_key ON projects(client_sdk_key);
+
+-- Flags are hidden from client keys unless opted in
+ALTER TABLE feature_flags ADD COLUMN client_visible BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,28 @@
+-- migrations/20261017000003_create_project_sdk_keys.sql
+
+-- SDK keys live in their own table so a project can have several active keys at once
+-- (zero-downtime rotation: create a new key, roll it out, then revoke the old one)
+CREATE TABLE project_sdk_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    key TEXT UNIQUE NOT NULL,
+    key_type TEXT NOT NULL DEFAULT 'server' CHECK (key_type IN ('server', 'client')),
+    label TEXT,
+    expires_at TIMESTAMPTZ,          -- NULL means the key never expires
+    revoked_at TIMESTAMPTZ,          -- NULL means the key is active
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Move the existing per-project keys into the new table
+INSERT INTO project_sdk_keys (project_id, key, key_type, label)
+SELECT id, sdk_key, 'server', 'Default server key' FROM projects;
+
+INSERT INTO project_sdk_keys (project_id, key, key_type, label)
+SELECT id, client_sdk_key, 'client', 'Default client key' FROM projects;
+
+DROP INDEX idx_project_sdk_key;
+DROP INDEX idx_project_client_sdk_key;
+ALTER TABLE projects DROP COLUMN sdk_key;
+ALTER TABLE projects DROP COLUMN client_sdk_key;
+
+CREATE INDEX idx_sdk_keys_project ON project_sdk_keys(project_id);
@@ -0,0 +1,16 @@
+-- migrations/20261017000004_hash_sdk_keys.sql
+
+-- Store only a SHA-256 hash of each SDK key; the plaintext is shown once when the key is issued.
+-- key_prefix keeps a short, non-secret part of the key so it can be recognised in listings.
+ALTER TABLE project_sdk_keys ADD COLUMN key_hash TEXT;
+ALTER TABLE project_sdk_keys ADD COLUMN key_prefix TEXT;
+
+UPDATE project_sdk_keys
+SET key_hash = encode(sha256(convert_to(key, 'UTF8')), 'hex'),
+    key_prefix = left(key, length(key) - 28);
+
+ALTER TABLE project_sdk_keys ALTER COLUMN key_hash SET NOT NULL;
+ALTER TABLE project_sdk_keys ALTER COLUMN key_prefix SET NOT NULL;
+ALTER TABLE project_sdk_keys ADD CONSTRAINT project_sdk_keys_key_hash_key UNIQUE (key_hash);
+
+ALTER TABLE project_sdk_keys DROP COLUMN key;
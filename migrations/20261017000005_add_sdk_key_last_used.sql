@@ -0,0 +1,4 @@
+-- migrations/20261017000005_add_sdk_key_last_used.sql
+
+-- When the key last authenticated a request (throttled, updated at most once a minute)
+ALTER TABLE project_sdk_keys ADD COLUMN last_used_at TIMESTAMPTZ;
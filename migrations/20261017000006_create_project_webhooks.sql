@@ -0,0 +1,13 @@
+-- migrations/20261017000006_create_project_webhooks.sql
+
+-- Webhooks notified whenever a flag in the project changes
+CREATE TABLE project_webhooks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    format TEXT NOT NULL DEFAULT 'json' CHECK (format IN ('json', 'slack')), -- 'json' = raw payload, 'slack' = {"text": "..."}
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_webhooks_project ON project_webhooks(project_id);
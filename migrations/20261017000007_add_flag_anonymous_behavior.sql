@@ -0,0 +1,6 @@
+-- migrations/20261017000007_add_flag_anonymous_behavior.sql
+
+-- How a flag treats requests without user_id or user_email:
+-- 'bucket' (all anonymous users share one rollout bucket), 'always_off' or 'always_on'
+ALTER TABLE feature_flags ADD COLUMN anonymous_behavior TEXT NOT NULL DEFAULT 'bucket'
+    CHECK (anonymous_behavior IN ('bucket', 'always_off', 'always_on'));
@@ -0,0 +1,4 @@
+-- migrations/20261017000008_add_flag_version.sql
+
+-- Optimistic concurrency control: bumped on every flag update so stale edits can be rejected
+ALTER TABLE feature_flags ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
@@ -0,0 +1,4 @@
+-- migrations/20261017000009_add_rule_payload.sql
+
+-- Optional JSON config returned with the evaluation when the rule fires
+ALTER TABLE flag_rules ADD COLUMN payload JSONB;
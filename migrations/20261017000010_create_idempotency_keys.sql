@@ -0,0 +1,16 @@
+-- migrations/20261017000010_create_idempotency_keys.sql
+
+-- Responses of create requests sent with an Idempotency-Key header, replayed on retries for 24h
+CREATE TABLE idempotency_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    idempotency_key TEXT NOT NULL,
+    request_path TEXT NOT NULL,      -- A key can only be reused for the same endpoint
+    resource_id UUID,                -- "id" of the created resource
+    status_code INT NOT NULL,
+    response_body JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(user_id, idempotency_key)
+);
+
+CREATE INDEX idx_idempotency_keys_created_at ON idempotency_keys(created_at);
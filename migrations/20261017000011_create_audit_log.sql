@@ -0,0 +1,14 @@
+-- migrations/20261017000011_create_audit_log.sql
+
+-- Record of operational actions taken in a project (e.g. bulk kill switch)
+CREATE TABLE audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    environment_id UUID REFERENCES environments(id) ON DELETE CASCADE,
+    actor_id UUID NOT NULL REFERENCES users(id),
+    action TEXT NOT NULL,             -- e.g. 'flags.disable_all'
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_audit_log_project_time ON audit_log(project_id, created_at DESC);
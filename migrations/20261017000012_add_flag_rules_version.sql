@@ -0,0 +1,16 @@
+-- migrations/20261017000012_add_flag_rules_version.sql
+
+-- Bumped whenever one of the flag's rules changes, so cached evaluations keyed on it go stale
+ALTER TABLE feature_flags ADD COLUMN rules_version BIGINT NOT NULL DEFAULT 1;
+
+CREATE FUNCTION bump_flag_rules_version() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE feature_flags SET rules_version = rules_version + 1
+    WHERE id = COALESCE(NEW.flag_id, OLD.flag_id);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER flag_rules_bump_version
+AFTER INSERT OR UPDATE OR DELETE ON flag_rules
+FOR EACH ROW EXECUTE FUNCTION bump_flag_rules_version();
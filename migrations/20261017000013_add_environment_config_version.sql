@@ -0,0 +1,23 @@
+-- migrations/20261017000013_add_environment_config_version.sql
+
+-- Bumped whenever a flag in the environment changes, SDKs polling /sdk/v1/config compare against it
+-- Rule changes bump the flag's rules_version, which in turn bumps this through the flag trigger
+ALTER TABLE environments ADD COLUMN config_version BIGINT NOT NULL DEFAULT 1;
+
+CREATE FUNCTION bump_environment_config_version() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        UPDATE environments SET config_version = config_version + 1 WHERE id = NEW.environment_id;
+    ELSE
+        UPDATE environments SET config_version = config_version + 1 WHERE id = OLD.environment_id;
+        IF TG_OP = 'UPDATE' AND NEW.environment_id IS DISTINCT FROM OLD.environment_id THEN
+            UPDATE environments SET config_version = config_version + 1 WHERE id = NEW.environment_id;
+        END IF;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER feature_flags_bump_config_version
+AFTER INSERT OR UPDATE OR DELETE ON feature_flags
+FOR EACH ROW EXECUTE FUNCTION bump_environment_config_version();
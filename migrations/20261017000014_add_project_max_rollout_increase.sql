@@ -0,0 +1,5 @@
+-- migrations/20261017000014_add_project_max_rollout_increase.sql
+
+-- Largest rollout_percentage increase allowed in a single flag update, NULL means no limit
+ALTER TABLE projects ADD COLUMN max_rollout_increase_percent INTEGER
+    CHECK (max_rollout_increase_percent BETWEEN 0 AND 100);
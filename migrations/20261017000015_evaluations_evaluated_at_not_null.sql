@@ -0,0 +1,5 @@
+-- migrations/20261017000015_evaluations_evaluated_at_not_null.sql
+
+-- Evaluation logs are paged by (evaluated_at, id), which needs a timestamp on every row
+UPDATE flag_evaluations SET evaluated_at = NOW() WHERE evaluated_at IS NULL;
+ALTER TABLE flag_evaluations ALTER COLUMN evaluated_at SET NOT NULL;
@@ -0,0 +1,8 @@
+-- migrations/20261017000016_normalize_user_emails.sql
+
+-- Emails are stored lowercased so login and uniqueness ignore case
+-- Fails if two accounts differ only by case, those have to be merged by hand first
+UPDATE users SET email = lower(trim(email)) WHERE email <> lower(trim(email));
+
+-- Guards against mixed-case inserts that bypass the application
+CREATE UNIQUE INDEX users_email_lower_key ON users (lower(email));
@@ -0,0 +1,10 @@
+-- migrations/20261017000017_add_flag_rule_authorship.sql
+
+-- Users who created and last modified each flag and rule, NULL for rows that predate tracking
+ALTER TABLE feature_flags
+    ADD COLUMN created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    ADD COLUMN updated_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE flag_rules
+    ADD COLUMN created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    ADD COLUMN updated_by UUID REFERENCES users(id) ON DELETE SET NULL;
@@ -0,0 +1,8 @@
+-- migrations/20261017000018_add_flag_hash_algorithm.sql
+
+-- Hash used for percentage bucketing. Existing flags keep the legacy DefaultHasher buckets,
+-- flags created from now on get the stable sha256 bucketing
+ALTER TABLE feature_flags ADD COLUMN hash_algorithm TEXT NOT NULL DEFAULT 'legacy'
+    CHECK (hash_algorithm IN ('legacy', 'sha256'));
+
+ALTER TABLE feature_flags ALTER COLUMN hash_algorithm SET DEFAULT 'sha256';
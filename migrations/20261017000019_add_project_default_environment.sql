@@ -0,0 +1,18 @@
+-- migrations/20261017000019_add_project_default_environment.sql
+
+-- Environment used by SDK evaluate requests that omit `environment`, NULL means they must name one
+ALTER TABLE projects ADD COLUMN default_environment_key TEXT;
+
+-- Keep the default pointing at the same environment when its key is renamed, and clear it on delete
+CREATE FUNCTION follow_default_environment() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE projects
+    SET default_environment_key = CASE WHEN TG_OP = 'DELETE' THEN NULL ELSE NEW.key END
+    WHERE id = OLD.project_id AND default_environment_key = OLD.key;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER environments_follow_default
+AFTER UPDATE OF key OR DELETE ON environments
+FOR EACH ROW EXECUTE FUNCTION follow_default_environment();
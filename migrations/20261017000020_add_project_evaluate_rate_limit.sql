@@ -0,0 +1,5 @@
+-- migrations/20261017000020_add_project_evaluate_rate_limit.sql
+
+-- SDK evaluate requests allowed per minute, NULL uses the server default and 0 means unlimited
+ALTER TABLE projects ADD COLUMN evaluate_rate_limit_per_minute INTEGER
+    CHECK (evaluate_rate_limit_per_minute >= 0);
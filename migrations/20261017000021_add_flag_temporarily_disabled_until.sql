@@ -0,0 +1,4 @@
+-- migrations/20261017000021_add_flag_temporarily_disabled_until.sql
+
+-- Pauses a flag: evaluation returns false until this time, leaving enabled and the rollout untouched
+ALTER TABLE feature_flags ADD COLUMN temporarily_disabled_until TIMESTAMPTZ;
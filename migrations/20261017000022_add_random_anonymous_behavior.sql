@@ -0,0 +1,6 @@
+-- migrations/20261017000022_add_random_anonymous_behavior.sql
+
+-- 'random': anonymous requests without an anonymous_id get a fresh rollout roll every time
+ALTER TABLE feature_flags DROP CONSTRAINT feature_flags_anonymous_behavior_check;
+ALTER TABLE feature_flags ADD CONSTRAINT feature_flags_anonymous_behavior_check
+    CHECK (anonymous_behavior IN ('bucket', 'always_off', 'always_on', 'random'));
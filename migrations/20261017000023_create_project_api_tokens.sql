@@ -0,0 +1,19 @@
+-- migrations/20261017000023_create_project_api_tokens.sql
+
+-- Machine tokens for CI and scripts: scoped to one project and a set of permissions,
+-- and unlike SDK keys they can change flags. Only a SHA-256 hash of the token is stored.
+CREATE TABLE project_api_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    token_hash TEXT UNIQUE NOT NULL,
+    token_prefix TEXT NOT NULL,
+    label TEXT,
+    permissions TEXT[] NOT NULL CHECK (permissions <@ ARRAY['flags:read', 'flags:write']::TEXT[]),
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    expires_at TIMESTAMPTZ,          -- NULL means the token never expires
+    revoked_at TIMESTAMPTZ,          -- NULL means the token is active
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_api_tokens_project ON project_api_tokens(project_id);
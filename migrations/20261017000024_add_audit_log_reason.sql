@@ -0,0 +1,4 @@
+-- migrations/20261017000024_add_audit_log_reason.sql
+
+-- Free-text "why" a user gave for a change, e.g. when toggling a flag during an incident
+ALTER TABLE audit_log ADD COLUMN reason TEXT;
@@ -0,0 +1,5 @@
+-- migrations/20261017000025_add_flag_bucketing_keys.sql
+
+-- Context identifiers a flag buckets users on, tried in order, e.g. {attribute:account_id,user_id}
+-- Empty means the default order: user_id, user_email, anonymous_id
+ALTER TABLE feature_flags ADD COLUMN bucketing_keys TEXT[] NOT NULL DEFAULT '{}';
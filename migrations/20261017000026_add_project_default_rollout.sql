@@ -0,0 +1,5 @@
+-- migrations/20261017000026_add_project_default_rollout.sql
+
+-- Rollout given to new flags that don't set rollout_percentage, NULL keeps the old default of 0
+ALTER TABLE projects ADD COLUMN default_rollout_percentage INTEGER
+    CHECK (default_rollout_percentage BETWEEN 0 AND 100);
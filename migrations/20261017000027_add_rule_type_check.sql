@@ -0,0 +1,8 @@
+-- migrations/20261017000027_add_rule_type_check.sql
+
+-- Rule types used to be checked by the API only; keep this list in sync with RULE_TYPES in src/routes/rules/mod.rs
+ALTER TABLE flag_rules ADD CONSTRAINT flag_rules_rule_type_check
+    CHECK (rule_type IN (
+        'user_id', 'user_email', 'email_domain', 'user_id_in', 'active_after',
+        'active_before', 'local_time_window', 'percentage', 'attribute_is_true'
+    ));
@@ -0,0 +1,5 @@
+-- migrations/20261017000028_add_rule_rollout_percentage.sql
+
+-- Share of the users a rule matches that it fires for, NULL fires for all of them
+ALTER TABLE flag_rules ADD COLUMN rollout_percentage INTEGER
+    CHECK (rollout_percentage BETWEEN 0 AND 100);
@@ -0,0 +1,23 @@
+-- migrations/20261017000029_bump_config_version_once_per_transaction.sql
+
+-- config_version used to move once per changed row, so a bulk rule import or a disable-all bumped it many times
+-- Remember which transaction last bumped each environment and skip the bump if it is the current one,
+-- so every committed mutation moves the version exactly once and readers never see the intermediate values
+ALTER TABLE environments ADD COLUMN config_version_txid BIGINT;
+
+CREATE OR REPLACE FUNCTION bump_environment_config_version() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        UPDATE environments SET config_version = config_version + 1, config_version_txid = txid_current()
+        WHERE id = NEW.environment_id AND config_version_txid IS DISTINCT FROM txid_current();
+    ELSE
+        UPDATE environments SET config_version = config_version + 1, config_version_txid = txid_current()
+        WHERE id = OLD.environment_id AND config_version_txid IS DISTINCT FROM txid_current();
+        IF TG_OP = 'UPDATE' AND NEW.environment_id IS DISTINCT FROM OLD.environment_id THEN
+            UPDATE environments SET config_version = config_version + 1, config_version_txid = txid_current()
+            WHERE id = NEW.environment_id AND config_version_txid IS DISTINCT FROM txid_current();
+        END IF;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
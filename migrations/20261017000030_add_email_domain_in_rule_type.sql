@@ -0,0 +1,9 @@
+-- migrations/20261017000030_add_email_domain_in_rule_type.sql
+
+-- Allow email_domain_in rules; keep this list in sync with RULE_TYPES in src/routes/rules/mod.rs
+ALTER TABLE flag_rules DROP CONSTRAINT flag_rules_rule_type_check;
+ALTER TABLE flag_rules ADD CONSTRAINT flag_rules_rule_type_check
+    CHECK (rule_type IN (
+        'user_id', 'user_email', 'email_domain', 'email_domain_in', 'user_id_in', 'active_after',
+        'active_before', 'local_time_window', 'percentage', 'attribute_is_true'
+    ));
@@ -0,0 +1,6 @@
+-- migrations/20261017000031_add_flag_lifecycle_state.sql
+
+-- draft flags are hidden from SDKs until promoted, deprecated flags are still served but marked in evaluations
+-- Existing flags are already in use, so they start out active
+ALTER TABLE feature_flags ADD COLUMN lifecycle_state TEXT NOT NULL DEFAULT 'active'
+    CHECK (lifecycle_state IN ('draft', 'active', 'deprecated'));
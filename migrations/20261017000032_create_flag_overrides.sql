@@ -0,0 +1,20 @@
+-- migrations/20261017000032_create_flag_overrides.sql
+
+-- Users forced into a fixed result for a flag, checked before time windows, rules and rollout
+CREATE TABLE flag_overrides (
+    flag_id UUID NOT NULL REFERENCES feature_flags(id) ON DELETE CASCADE,
+    user_identifier TEXT NOT NULL, -- A user_id, or an email matched ignoring case
+    enabled BOOLEAN NOT NULL,
+    payload JSONB, -- Returned with the evaluation instead of a rule's payload
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (flag_id, user_identifier)
+);
+
+-- Overrides change evaluation results like rules do, so they bump the flag's rules_version too,
+-- which expires cached evaluations and bumps the environment's config_version
+CREATE TRIGGER flag_overrides_bump_version
+AFTER INSERT OR UPDATE OR DELETE ON flag_overrides
+FOR EACH ROW EXECUTE FUNCTION bump_flag_rules_version();
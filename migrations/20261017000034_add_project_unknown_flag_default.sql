@@ -0,0 +1,6 @@
+-- migrations/20261017000034_add_project_unknown_flag_default.sql
+
+-- What SDK evaluate returns for requested flag keys the environment doesn't serve:
+-- 'off' answers with a disabled flag (fail open), 'error' fails the request with 404 (fail closed)
+ALTER TABLE projects ADD COLUMN unknown_flag_default TEXT NOT NULL DEFAULT 'off'
+    CHECK (unknown_flag_default IN ('off', 'error'));
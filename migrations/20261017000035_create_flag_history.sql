@@ -0,0 +1,44 @@
+-- migrations/20261017000035_create_flag_history.sql
+
+-- Snapshots of each flag with its rules and overrides, so evaluations can be replayed as of a past time
+-- Rule and override changes bump feature_flags.rules_version, so a trigger on feature_flags sees every change
+CREATE TABLE flag_history (
+    id BIGSERIAL PRIMARY KEY,
+    flag_id UUID NOT NULL REFERENCES feature_flags(id) ON DELETE CASCADE,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    txid BIGINT NOT NULL DEFAULT txid_current(),
+    flag JSONB NOT NULL,       -- The feature_flags row
+    rules JSONB NOT NULL,      -- Array of the flag's flag_rules rows
+    overrides JSONB NOT NULL   -- Array of the flag's flag_overrides rows
+);
+
+CREATE INDEX idx_flag_history_flag_recorded ON flag_history (flag_id, recorded_at DESC, id DESC);
+
+-- A transaction that changes several rules touches the flag once per rule;
+-- only its final state is kept, like config_version only moves once per transaction
+CREATE FUNCTION record_flag_history() RETURNS TRIGGER AS $$
+BEGIN
+    DELETE FROM flag_history WHERE flag_id = NEW.id AND txid = txid_current();
+    INSERT INTO flag_history (flag_id, flag, rules, overrides)
+    VALUES (
+        NEW.id,
+        to_jsonb(NEW),
+        COALESCE((SELECT jsonb_agg(to_jsonb(r)) FROM flag_rules r WHERE r.flag_id = NEW.id), '[]'::jsonb),
+        COALESCE((SELECT jsonb_agg(to_jsonb(o)) FROM flag_overrides o WHERE o.flag_id = NEW.id), '[]'::jsonb)
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER feature_flags_record_history
+AFTER INSERT OR UPDATE ON feature_flags
+FOR EACH ROW EXECUTE FUNCTION record_flag_history();
+
+-- History of existing flags starts now
+INSERT INTO flag_history (flag_id, flag, rules, overrides)
+SELECT
+    f.id,
+    to_jsonb(f),
+    COALESCE((SELECT jsonb_agg(to_jsonb(r)) FROM flag_rules r WHERE r.flag_id = f.id), '[]'::jsonb),
+    COALESCE((SELECT jsonb_agg(to_jsonb(o)) FROM flag_overrides o WHERE o.flag_id = f.id), '[]'::jsonb)
+FROM feature_flags f;
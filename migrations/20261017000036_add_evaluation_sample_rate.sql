@@ -0,0 +1,9 @@
+-- migrations/20261017000036_add_evaluation_sample_rate.sql
+
+-- Share of SDK evaluations logged to flag_evaluations, 1.0 logs every one
+ALTER TABLE projects ADD COLUMN evaluation_sample_rate DOUBLE PRECISION NOT NULL DEFAULT 1.0
+    CHECK (evaluation_sample_rate >= 0 AND evaluation_sample_rate <= 1);
+
+-- Rate each row was logged at, so counts can be extrapolated (each row stands for 1 / sample_rate
+-- evaluations) even after the project's rate changes
+ALTER TABLE flag_evaluations ADD COLUMN sample_rate DOUBLE PRECISION NOT NULL DEFAULT 1.0;
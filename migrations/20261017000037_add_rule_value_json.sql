@@ -0,0 +1,6 @@
+-- migrations/20261017000037_add_rule_value_json.sql
+
+-- Entries of user_id_in / email_domain_in rules as a JSON array of strings, so values may contain commas
+-- NULL for scalar rules and for list rules still written as a comma-separated rule_value
+ALTER TABLE flag_rules ADD COLUMN rule_value_json JSONB
+    CHECK (rule_value_json IS NULL OR jsonb_typeof(rule_value_json) = 'array');
@@ -0,0 +1,32 @@
+-- migrations/20261017000038_create_pending_changes.sql
+
+-- Flag updates in these environments are proposed as pending changes instead of going live
+ALTER TABLE environments ADD COLUMN requires_approval BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Users who may approve pending changes in a project, managed by the project owner
+CREATE TABLE project_approvers (
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (project_id, user_id)
+);
+
+-- A flag update waiting for approval; the live flag is untouched until it is approved
+CREATE TABLE pending_changes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    environment_id UUID NOT NULL REFERENCES environments(id) ON DELETE CASCADE,
+    flag_id UUID NOT NULL REFERENCES feature_flags(id) ON DELETE CASCADE,
+    mode TEXT NOT NULL CHECK (mode IN ('patch', 'replace')),
+    change JSONB NOT NULL, -- The update body, without expected_version and reason
+    base_version BIGINT NOT NULL, -- Flag version the change was proposed against, approval fails if it moved on
+    reason TEXT,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'rejected')),
+    proposed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    decided_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    decided_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_pending_changes_project ON pending_changes(project_id, created_at) WHERE status = 'pending';
@@ -0,0 +1,4 @@
+-- migrations/20261017000039_add_user_sessions_revoked_at.sql
+
+-- Login tokens issued before this time are rejected, set when a password change revokes sessions
+ALTER TABLE users ADD COLUMN sessions_revoked_at TIMESTAMPTZ;
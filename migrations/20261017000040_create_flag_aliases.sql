@@ -0,0 +1,13 @@
+-- migrations/20261017000040_create_flag_aliases.sql
+
+-- Former keys of renamed flags, SDK evaluations asking for one are answered with the flag it now names
+CREATE TABLE flag_aliases (
+    environment_id UUID NOT NULL REFERENCES environments(id) ON DELETE CASCADE,
+    alias TEXT NOT NULL,
+    flag_id UUID NOT NULL REFERENCES feature_flags(id) ON DELETE CASCADE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (environment_id, alias) -- An old key names one flag per environment
+);
+
+CREATE INDEX idx_flag_aliases_flag ON flag_aliases(flag_id);
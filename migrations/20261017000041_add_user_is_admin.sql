@@ -0,0 +1,4 @@
+-- migrations/20261017000041_add_user_is_admin.sql
+
+-- Operators who may use the /api/admin endpoints across every user's projects, granted directly in the database
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
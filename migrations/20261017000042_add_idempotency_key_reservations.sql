@@ -0,0 +1,8 @@
+-- migrations/20261017000042_add_idempotency_key_reservations.sql
+
+-- A key is reserved before its request runs: rows without a status_code are still in flight,
+-- request_hash is the SHA-256 of the request body so a key can't be reused for a different body
+ALTER TABLE idempotency_keys
+    ADD COLUMN request_hash TEXT,
+    ALTER COLUMN status_code DROP NOT NULL,
+    ALTER COLUMN response_body DROP NOT NULL;
@@ -0,0 +1,7 @@
+-- migrations/20261017000043_add_targeting_pending_changes.sql
+
+-- Rule and override edits in requires_approval environments wait for approval too
+-- Their base_version is the flag's rules_version, which every rule and override change bumps
+ALTER TABLE pending_changes DROP CONSTRAINT pending_changes_mode_check;
+ALTER TABLE pending_changes ADD CONSTRAINT pending_changes_mode_check
+    CHECK (mode IN ('patch', 'replace', 'rule_create', 'rule_update', 'rule_delete', 'override_set', 'override_clear'));
@@ -0,0 +1,116 @@
+use serde_json::Value;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+// Operational action recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    /// Every enabled flag of an environment was disabled at once
+    FlagsDisableAll,
+    /// Evaluation logs older than a cutoff were deleted for a project
+    EvaluationsPurge,
+    /// Project ownership was handed to another user
+    ProjectTransfer,
+    /// A flag was switched on or off with the toggle endpoint
+    FlagToggle,
+    /// A flag's settings were changed with the update endpoint
+    FlagUpdate,
+    /// A pending flag change was rejected by an approver
+    FlagChangeReject,
+    /// A pending rule or override edit was approved and applied
+    FlagTargetingApprove,
+    /// A flag's key was changed, the old key kept as an alias
+    FlagRename,
+}
+
+impl AuditAction {
+    /// Value stored in the `audit_log.action` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::FlagsDisableAll => "flags.disable_all",
+            AuditAction::EvaluationsPurge => "evaluations.purge",
+            AuditAction::ProjectTransfer => "project.transfer",
+            AuditAction::FlagToggle => "flag.toggle",
+            AuditAction::FlagUpdate => "flag.update",
+            AuditAction::FlagChangeReject => "flag.change_reject",
+            AuditAction::FlagTargetingApprove => "flag.targeting_approve",
+            AuditAction::FlagRename => "flag.rename",
+        }
+    }
+}
+
+// An entry to append to the audit log
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub project_id: Uuid,
+    pub environment_id: Option<Uuid>,
+    pub actor_id: Uuid,
+    pub action: AuditAction,
+    pub details: Value,
+    pub reason: Option<String>,  // Why the user made the change, if they said
+}
+
+/// Longest reason accepted for a change
+pub const MAX_REASON_LENGTH: usize = 500;
+
+/// Trim a user-supplied reason, treating a blank one as no reason
+pub fn normalize_reason(reason: Option<&str>) -> Result<Option<String>, String> {
+    let Some(reason) = reason.map(str::trim).filter(|r| !r.is_empty()) else {
+        return Ok(None);
+    };
+    if reason.chars().count() > MAX_REASON_LENGTH {
+        return Err(format!("Reason is too long (Max: {} characters)", MAX_REASON_LENGTH));
+    }
+
+    Ok(Some(reason.to_string()))
+}
+
+/// Append an entry to the audit log
+/// Takes a connection so callers can record it in the same transaction as the change itself
+pub async fn record(conn: &mut PgConnection, entry: AuditEntry) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (project_id, environment_id, actor_id, action, details, reason)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(entry.project_id)
+    .bind(entry.environment_id)
+    .bind(entry.actor_id)
+    .bind(entry.action.as_str())
+    .bind(&entry.details)
+    .bind(&entry.reason)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_action_as_str() {
+        assert_eq!(AuditAction::FlagsDisableAll.as_str(), "flags.disable_all");
+        assert_eq!(AuditAction::EvaluationsPurge.as_str(), "evaluations.purge");
+        assert_eq!(AuditAction::ProjectTransfer.as_str(), "project.transfer");
+        assert_eq!(AuditAction::FlagToggle.as_str(), "flag.toggle");
+        assert_eq!(AuditAction::FlagUpdate.as_str(), "flag.update");
+        assert_eq!(AuditAction::FlagChangeReject.as_str(), "flag.change_reject");
+        assert_eq!(AuditAction::FlagTargetingApprove.as_str(), "flag.targeting_approve");
+        assert_eq!(AuditAction::FlagRename.as_str(), "flag.rename");
+    }
+
+    #[test]
+    fn test_normalize_reason() {
+        assert_eq!(normalize_reason(None).unwrap(), None);
+        assert_eq!(normalize_reason(Some("   ")).unwrap(), None);
+        assert_eq!(
+            normalize_reason(Some("  Checkout errors spiking, see INC-42 \n")).unwrap().as_deref(),
+            Some("Checkout errors spiking, see INC-42")
+        );
+        assert!(normalize_reason(Some(&"x".repeat(MAX_REASON_LENGTH))).is_ok());
+        assert!(normalize_reason(Some(&"x".repeat(MAX_REASON_LENGTH + 1))).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::time::Duration;
+
+/// Upper bound for the delay between connection attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Connect to the database, retrying with exponential backoff while it is not reachable yet
+/// (e.g. when the service starts alongside Postgres in a container orchestrator)
+pub async fn connect_with_retry(
+    options: PgPoolOptions,
+    url: &str,
+    max_attempts: u32,
+    initial_delay: Duration,
+) -> Result<PgPool, sqlx::Error> {
+    let mut attempt = 1;
+    loop {
+        match options.clone().connect(url).await {
+            Ok(pool) => return Ok(pool),
+            Err(e) if attempt < max_attempts => {
+                let delay = backoff_delay(initial_delay, attempt);
+                eprintln!(
+                    "Database connection attempt {}/{} failed: {}. Retrying in {:?}",
+                    attempt, max_attempts, e, delay
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                eprintln!(
+                    "Database connection attempt {}/{} failed: {}. Giving up",
+                    attempt, max_attempts, e
+                );
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// Tables the service can't run without, checked once at startup
+pub const REQUIRED_TABLES: &[&str] = &[
+    "users",
+    "projects",
+    "environments",
+    "feature_flags",
+    "flag_rules",
+    "flag_evaluations",
+];
+
+/// Check the required tables exist, so a database without migrations fails at startup
+/// rather than with a 500 on the first request that touches a missing table
+pub async fn verify_schema(db: &PgPool) -> Result<(), String> {
+    let present = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT table_name::TEXT FROM information_schema.tables
+        WHERE table_schema = current_schema() AND table_name = ANY($1)
+        "#,
+    )
+    .bind(REQUIRED_TABLES)
+    .fetch_all(db)
+    .await
+    .map_err(|e| format!("Failed to check database schema: {}", e))?;
+
+    let missing = missing_tables(REQUIRED_TABLES, &present);
+    if !missing.is_empty() {
+        return Err(format!(
+            "Database is missing required tables: {}. Run the migrations (sqlx migrate run) before starting the server",
+            missing.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Required tables not in the present ones, in the order they are required
+fn missing_tables<'a>(required: &[&'a str], present: &[String]) -> Vec<&'a str> {
+    required
+        .iter()
+        .copied()
+        .filter(|table| !present.iter().any(|p| p == table))
+        .collect()
+}
+
+/// Delay before the next attempt: doubles after every failure, capped at MAX_RETRY_DELAY
+fn backoff_delay(initial_delay: Duration, attempt: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+    initial_delay.saturating_mul(factor).min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let initial = Duration::from_millis(500);
+        assert_eq!(backoff_delay(initial, 1), Duration::from_millis(500));
+        assert_eq!(backoff_delay(initial, 2), Duration::from_secs(1));
+        assert_eq!(backoff_delay(initial, 3), Duration::from_secs(2));
+        assert_eq!(backoff_delay(initial, 10), MAX_RETRY_DELAY);
+        assert_eq!(backoff_delay(initial, u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_missing_tables() {
+        let present = |tables: &[&str]| tables.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        assert!(missing_tables(REQUIRED_TABLES, &present(REQUIRED_TABLES)).is_empty());
+        assert_eq!(
+            missing_tables(REQUIRED_TABLES, &present(&["users", "projects", "environments", "feature_flags"])),
+            vec!["flag_rules", "flag_evaluations"]
+        );
+        assert_eq!(missing_tables(&["users"], &[]), vec!["users"]);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use super::FlagEvaluation;
+
+/// Identifies one evaluation result: the flag, the versions of its state and rules,
+/// and the parts of the context rules can look at. Any flag or rule mutation bumps a
+/// version, so entries computed against old configuration are never looked up again.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub flag_id: Uuid,
+    pub flag_version: i64,
+    pub rules_version: i64,
+    pub user_id: Option<String>,
+    pub user_email: Option<String>,
+    pub anonymous_id: Option<String>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    evaluation: FlagEvaluation,
+    expires_at: Instant,
+}
+
+/// Short-lived memoization of flag evaluations for the SDK evaluate path
+/// A zero TTL disables the cache entirely
+#[derive(Debug)]
+pub struct EvaluationCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+}
+
+impl EvaluationCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_entries > 0
+    }
+
+    /// Cached evaluation for `key`, if present and not expired at `now`
+    pub fn get(&self, key: &CacheKey, now: Instant) -> Option<FlagEvaluation> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|entry| entry.expires_at > now)
+            .map(|entry| entry.evaluation.clone())
+    }
+
+    /// Remember an evaluation until `now + ttl`
+    /// When full, expired entries are dropped first, then everything if that wasn't enough
+    pub fn insert(&self, key: CacheKey, evaluation: &FlagEvaluation, now: Instant) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries {
+            entries.retain(|_, entry| entry.expires_at > now);
+            if entries.len() >= self.max_entries {
+                entries.clear();
+            }
+        }
+
+        entries.insert(
+            key,
+            CacheEntry {
+                evaluation: FlagEvaluation {
+                    trace: None,
+                    ..evaluation.clone()
+                },
+                expires_at: now + self.ttl,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::{evaluate_flag, AnonymousBehavior, FlagData, HashAlgorithm, RuleData, UserContext};
+
+    fn key(flag_version: i64, rules_version: i64) -> CacheKey {
+        CacheKey {
+            flag_id: Uuid::nil(),
+            flag_version,
+            rules_version,
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+        }
+    }
+
+    fn evaluation(enabled: bool) -> FlagEvaluation {
+        FlagEvaluation {
+            enabled,
+            reason: "test".to_string(),
+            payload: None,
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_cache_hit_and_expiry() {
+        let cache = EvaluationCache::new(Duration::from_secs(5), 100);
+        let now = Instant::now();
+
+        assert!(cache.get(&key(1, 1), now).is_none());
+        cache.insert(key(1, 1), &evaluation(true), now);
+
+        assert!(cache.get(&key(1, 1), now + Duration::from_secs(4)).unwrap().enabled);
+        assert!(cache.get(&key(1, 1), now + Duration::from_secs(5)).is_none());
+    }
+
+    #[test]
+    fn test_mutation_is_not_served_stale() {
+        let cache = EvaluationCache::new(Duration::from_secs(60), 100);
+        let now = Instant::now();
+        let mut flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules: Vec<RuleData> = vec![];
+        let context = UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+
+        cache.insert(key(1, 1), &evaluate_flag(&flag, &rules, &context), now);
+        assert!(cache.get(&key(1, 1), now).unwrap().enabled);
+
+        // Toggling the flag bumps its version, so the old entry is never looked up
+        flag.enabled = false;
+        assert!(cache.get(&key(2, 1), now).is_none());
+        cache.insert(key(2, 1), &evaluate_flag(&flag, &rules, &context), now);
+        assert!(!cache.get(&key(2, 1), now).unwrap().enabled);
+
+        // Same for a rule change
+        assert!(cache.get(&key(2, 2), now).is_none());
+    }
+
+    #[test]
+    fn test_disabled_and_bounded() {
+        let disabled = EvaluationCache::new(Duration::ZERO, 100);
+        disabled.insert(key(1, 1), &evaluation(true), Instant::now());
+        assert!(disabled.get(&key(1, 1), Instant::now()).is_none());
+
+        let cache = EvaluationCache::new(Duration::from_secs(60), 2);
+        let now = Instant::now();
+        cache.insert(key(1, 1), &evaluation(true), now);
+        cache.insert(key(2, 1), &evaluation(true), now);
+        cache.insert(key(3, 1), &evaluation(true), now);
+        assert!(cache.entries.lock().unwrap().len() <= 2);
+        assert!(cache.get(&key(3, 1), now).is_some());
+    }
+
+    #[test]
+    fn test_trace_is_not_cached() {
+        let cache = EvaluationCache::new(Duration::from_secs(60), 100);
+        let now = Instant::now();
+        let mut traced = evaluation(true);
+        traced.trace = Some(vec![]);
+
+        cache.insert(key(1, 1), &traced, now);
+        assert!(cache.get(&key(1, 1), now).unwrap().trace.is_none());
+    }
+
+    /// Rough comparison of a cache hit against a full evaluation
+    /// Run with `cargo test bench_cache_hit -- --ignored`
+    #[test]
+    #[ignore]
+    fn bench_cache_hit_vs_evaluate() {
+        const ITERATIONS: u32 = 100_000;
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 50,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules: Vec<RuleData> = (0..20)
+            .map(|i| RuleData {
+                rule_type: "user_id_in".to_string(),
+                rule_value: (0..50).map(|j| format!("user_{}_{}", i, j)).collect::<Vec<_>>().join(","),
+                rule_value_json: None,
+                enabled: true,
+                priority: i,
+                negate: false,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
+            })
+            .collect();
+        let context = UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let cache = EvaluationCache::new(Duration::from_secs(60), 100);
+        let now = Instant::now();
+        cache.insert(key(1, 1), &evaluate_flag(&flag, &rules, &context), now);
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            std::hint::black_box(evaluate_flag(&flag, &rules, &context));
+        }
+        let evaluate = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            std::hint::black_box(cache.get(&key(1, 1), now));
+        }
+        let cached = start.elapsed();
+
+        assert!(
+            cached < evaluate,
+            "cache hit: {:?}/op, evaluate_flag: {:?}/op",
+            cached / ITERATIONS,
+            evaluate / ITERATIONS
+        );
+    }
+}
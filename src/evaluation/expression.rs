@@ -0,0 +1,415 @@
+use serde_json::Value;
+
+use super::{parse_bool_attribute, UserContext};
+
+// Minimal JSON-logic interpreter behind "expression" rules
+// Supports var, and, or, !, !!, ==, !=, ===, !==, <, <=, >, >= and in; anything else is rejected
+// when the rule is written, so stored expressions always parse
+
+/// Longest expression source accepted
+pub const MAX_EXPRESSION_LENGTH: usize = 4096;
+
+/// Deepest nesting of operations and lists
+pub const MAX_EXPRESSION_DEPTH: usize = 8;
+
+/// Most operations, variables and values in one expression
+pub const MAX_EXPRESSION_NODES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    And,
+    Or,
+    Not,
+    Truthy,
+    Equal,
+    NotEqual,
+    StrictEqual,
+    StrictNotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    In,
+}
+
+impl Operator {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "and" => Operator::And,
+            "or" => Operator::Or,
+            "!" => Operator::Not,
+            "!!" => Operator::Truthy,
+            "==" => Operator::Equal,
+            "!=" => Operator::NotEqual,
+            "===" => Operator::StrictEqual,
+            "!==" => Operator::StrictNotEqual,
+            "<" => Operator::Less,
+            "<=" => Operator::LessOrEqual,
+            ">" => Operator::Greater,
+            ">=" => Operator::GreaterOrEqual,
+            "in" => Operator::In,
+            _ => return None,
+        })
+    }
+
+    /// Fewest and most arguments the operator takes
+    /// `<` and `<=` take a third one for the between form, `{"<": [1, x, 10]}`
+    fn arity(&self) -> (usize, usize) {
+        match self {
+            Operator::And | Operator::Or => (1, MAX_EXPRESSION_NODES),
+            Operator::Not | Operator::Truthy => (1, 1),
+            Operator::Less | Operator::LessOrEqual => (2, 3),
+            _ => (2, 2),
+        }
+    }
+}
+
+/// Context field an expression reads
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Variable {
+    UserId,
+    UserEmail,
+    AnonymousId,
+    Attribute(String),
+}
+
+impl Variable {
+    fn parse(path: &str) -> Option<Self> {
+        match path {
+            "user_id" => Some(Variable::UserId),
+            "user_email" => Some(Variable::UserEmail),
+            "anonymous_id" => Some(Variable::AnonymousId),
+            _ => path
+                .strip_prefix("custom_attributes.")
+                .filter(|name| !name.is_empty())
+                .map(|name| Variable::Attribute(name.to_string())),
+        }
+    }
+
+    fn resolve<'a>(&self, context: &'a UserContext) -> Option<&'a str> {
+        match self {
+            Variable::UserId => context.user_id.as_deref(),
+            Variable::UserEmail => context.user_email.as_deref(),
+            Variable::AnonymousId => context.anonymous_id.as_deref(),
+            Variable::Attribute(name) => context.custom_attributes.get(name).map(String::as_str),
+        }
+    }
+}
+
+/// A parsed expression rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression(Node);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Literal(Value),
+    List(Vec<Node>),
+    Var(Variable, Value),  // Value when the context doesn't have the field
+    Operation(Operator, Vec<Node>),
+}
+
+impl Expression {
+    /// Parse and check an expression, enforcing the size, depth and node limits
+    pub fn parse(source: &str) -> Result<Self, String> {
+        if source.len() > MAX_EXPRESSION_LENGTH {
+            return Err(format!("Expression is too long (Max: {} characters)", MAX_EXPRESSION_LENGTH));
+        }
+        let value: Value = serde_json::from_str(source).map_err(|e| format!("Expression is not valid JSON: {}", e))?;
+        if !value.is_object() {
+            return Err("Expression must be a JSON-logic operation, e.g. {\"==\": [...]}".to_string());
+        }
+
+        let mut nodes = 0;
+        parse_node(&value, 1, &mut nodes).map(Expression)
+    }
+
+    /// Whether the expression holds for the context, using JSON-logic truthiness
+    pub fn matches(&self, context: &UserContext) -> bool {
+        truthy(&evaluate(&self.0, context))
+    }
+}
+
+fn parse_node(value: &Value, depth: usize, nodes: &mut usize) -> Result<Node, String> {
+    if depth > MAX_EXPRESSION_DEPTH {
+        return Err(format!("Expression is nested too deeply (Max: {} levels)", MAX_EXPRESSION_DEPTH));
+    }
+    *nodes += 1;
+    if *nodes > MAX_EXPRESSION_NODES {
+        return Err(format!("Expression is too complex (Max: {} operations and values)", MAX_EXPRESSION_NODES));
+    }
+
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.iter();
+            let (Some((name, args)), None) = (entries.next(), entries.next()) else {
+                return Err("Each operation must be an object with exactly one operator".to_string());
+            };
+            if name == "var" {
+                return parse_var(args);
+            }
+
+            let operator = Operator::parse(name).ok_or_else(|| format!("Unknown operator '{}'", name))?;
+            // Like JSON-logic, a single argument may be given without the array
+            let args: Vec<&Value> = match args {
+                Value::Array(items) => items.iter().collect(),
+                other => vec![other],
+            };
+            let (min, max) = operator.arity();
+            if args.len() < min || args.len() > max {
+                let expected = if min == max { min.to_string() } else { format!("{} to {}", min, max) };
+                return Err(format!("'{}' takes {} arguments, got {}", name, expected, args.len()));
+            }
+
+            let args = args
+                .into_iter()
+                .map(|arg| parse_node(arg, depth + 1, nodes))
+                .collect::<Result<_, _>>()?;
+            Ok(Node::Operation(operator, args))
+        }
+        Value::Array(items) => items
+            .iter()
+            .map(|item| parse_node(item, depth + 1, nodes))
+            .collect::<Result<_, _>>()
+            .map(Node::List),
+        literal => Ok(Node::Literal(literal.clone())),
+    }
+}
+
+// {"var": "user_id"} or {"var": ["custom_attributes.plan", "free"]} with a default
+fn parse_var(args: &Value) -> Result<Node, String> {
+    let (path, default) = match args {
+        Value::String(path) => (path.as_str(), Value::Null),
+        Value::Array(items) => match items.as_slice() {
+            [Value::String(path)] => (path.as_str(), Value::Null),
+            [Value::String(path), default] if !default.is_object() && !default.is_array() => {
+                (path.as_str(), default.clone())
+            }
+            _ => return Err("'var' takes a field name and an optional plain default value".to_string()),
+        },
+        _ => return Err("'var' takes a field name and an optional plain default value".to_string()),
+    };
+
+    let variable = Variable::parse(path).ok_or_else(|| {
+        format!(
+            "Unknown variable '{}', use user_id, user_email, anonymous_id or custom_attributes.<name>",
+            path
+        )
+    })?;
+    Ok(Node::Var(variable, default))
+}
+
+fn evaluate(node: &Node, context: &UserContext) -> Value {
+    match node {
+        Node::Literal(value) => value.clone(),
+        Node::List(items) => Value::Array(items.iter().map(|item| evaluate(item, context)).collect()),
+        Node::Var(variable, default) => variable
+            .resolve(context)
+            .map(|value| Value::String(value.to_string()))
+            .unwrap_or_else(|| default.clone()),
+        Node::Operation(operator, args) => {
+            let arg = |i: usize| evaluate(&args[i], context);
+            match operator {
+                // Like JSON-logic, and/or short-circuit and return the deciding value
+                Operator::And => {
+                    let mut last = Value::Null;
+                    for node in args {
+                        last = evaluate(node, context);
+                        if !truthy(&last) {
+                            break;
+                        }
+                    }
+                    last
+                }
+                Operator::Or => {
+                    let mut last = Value::Null;
+                    for node in args {
+                        last = evaluate(node, context);
+                        if truthy(&last) {
+                            break;
+                        }
+                    }
+                    last
+                }
+                Operator::Not => Value::Bool(!truthy(&arg(0))),
+                Operator::Truthy => Value::Bool(truthy(&arg(0))),
+                Operator::Equal => Value::Bool(loose_equal(&arg(0), &arg(1))),
+                Operator::NotEqual => Value::Bool(!loose_equal(&arg(0), &arg(1))),
+                Operator::StrictEqual => Value::Bool(strict_equal(&arg(0), &arg(1))),
+                Operator::StrictNotEqual => Value::Bool(!strict_equal(&arg(0), &arg(1))),
+                Operator::Less | Operator::LessOrEqual => {
+                    let allow_equal = *operator == Operator::LessOrEqual;
+                    let values: Vec<Value> = (0..args.len()).map(arg).collect();
+                    Value::Bool(values.windows(2).all(|pair| ordered(&pair[0], &pair[1], allow_equal)))
+                }
+                Operator::Greater => Value::Bool(ordered(&arg(1), &arg(0), false)),
+                Operator::GreaterOrEqual => Value::Bool(ordered(&arg(1), &arg(0), true)),
+                Operator::In => {
+                    let needle = arg(0);
+                    Value::Bool(match arg(1) {
+                        Value::Array(items) => items.iter().any(|item| loose_equal(&needle, item)),
+                        Value::String(haystack) => as_text(&needle).is_some_and(|needle| haystack.contains(&needle)),
+                        _ => false,
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// JSON-logic truthiness: false, null, 0, "" and [] are falsy
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+// Context fields and custom attributes are strings, so numbers compare by value
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok().filter(|n: &f64| n.is_finite()),
+        _ => None,
+    }
+}
+
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// `==`: strings are compared as numbers when both sides are numeric and as booleans
+/// against true/false, the way attribute_is_true reads them
+fn loose_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Null, Value::Null) => true,
+        (Value::Null, _) | (_, Value::Null) => false,
+        (Value::String(x), Value::String(y)) => x == y,
+        (Value::Bool(x), Value::Bool(y)) => x == y,
+        (Value::Bool(flag), Value::String(s)) | (Value::String(s), Value::Bool(flag)) => {
+            parse_bool_attribute(s) == Some(*flag)
+        }
+        _ => matches!((as_number(a), as_number(b)), (Some(x), Some(y)) if x == y),
+    }
+}
+
+/// `===`: same type and value, numbers by value so 1 and 1.0 are equal
+fn strict_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        _ => a == b,
+    }
+}
+
+/// `a < b`, or `a <= b` with `allow_equal`
+/// Numbers and numeric strings compare by value, other strings alphabetically, anything else never
+fn ordered(a: &Value, b: &Value, allow_equal: bool) -> bool {
+    let ordering = match (as_number(a), as_number(b)) {
+        (Some(x), Some(y)) => x.partial_cmp(&y),
+        _ => match (a, b) {
+            (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+            _ => None,
+        },
+    };
+    match ordering {
+        Some(std::cmp::Ordering::Less) => true,
+        Some(std::cmp::Ordering::Equal) => allow_equal,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(attributes: &[(&str, &str)]) -> UserContext {
+        UserContext {
+            user_id: Some("user-1".to_string()),
+            user_email: Some("ana@company.com".to_string()),
+            anonymous_id: None,
+            custom_attributes: attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+
+    fn holds(source: &str, context: &UserContext) -> bool {
+        Expression::parse(source).unwrap().matches(context)
+    }
+
+    #[test]
+    fn test_expression_and_or() {
+        let pro = context(&[("plan", "pro"), ("country", "DE")]);
+        let free = context(&[("plan", "free"), ("country", "US")]);
+
+        let both = r#"{"and": [
+            {"==": [{"var": "custom_attributes.plan"}, "pro"]},
+            {"in": [{"var": "custom_attributes.country"}, ["DE", "FR"]]}
+        ]}"#;
+        assert!(holds(both, &pro));
+        assert!(!holds(both, &free));
+
+        let either = r#"{"or": [
+            {"==": [{"var": "custom_attributes.plan"}, "pro"]},
+            {"in": ["@company.com", {"var": "user_email"}]}
+        ]}"#;
+        assert!(holds(either, &free));
+        assert!(!holds(either, &UserContext { user_email: None, ..free.clone() }));
+
+        assert!(holds(r#"{"!": {"==": [{"var": "custom_attributes.plan"}, "pro"]}}"#, &free));
+        assert!(holds(r#"{"!!": {"var": "user_id"}}"#, &free));
+        assert!(!holds(r#"{"!!": {"var": "anonymous_id"}}"#, &free));
+    }
+
+    #[test]
+    fn test_expression_comparisons() {
+        let user = context(&[("age", "42"), ("beta", "True"), ("tier", "gold")]);
+
+        // Numeric attributes compare by value, not alphabetically
+        assert!(holds(r#"{">=": [{"var": "custom_attributes.age"}, 18]}"#, &user));
+        assert!(holds(r#"{"<": [{"var": "custom_attributes.age"}, 100]}"#, &user));
+        assert!(holds(r#"{">": [{"var": "custom_attributes.age"}, "9"]}"#, &context(&[("age", "10")])));
+        assert!(holds(r#"{"<=": [18, {"var": "custom_attributes.age"}, 65]}"#, &user));
+        assert!(!holds(r#"{"<": [18, {"var": "custom_attributes.age"}, 30]}"#, &user));
+        assert!(holds(r#"{"==": [{"var": "custom_attributes.age"}, 42]}"#, &user));
+        assert!(!holds(r#"{"===": [{"var": "custom_attributes.age"}, 42]}"#, &user));
+        assert!(holds(r#"{"!=": [{"var": "custom_attributes.tier"}, "silver"]}"#, &user));
+
+        // Boolean attributes read like attribute_is_true
+        assert!(holds(r#"{"==": [{"var": "custom_attributes.beta"}, true]}"#, &user));
+
+        // Missing fields are null unless the var gives a default
+        assert!(!holds(r#"{">": [{"var": "custom_attributes.score"}, 0]}"#, &user));
+        assert!(holds(r#"{"==": [{"var": ["custom_attributes.region", "eu"]}, "eu"]}"#, &user));
+    }
+
+    #[test]
+    fn test_expression_parse_errors() {
+        assert!(Expression::parse("not json").unwrap_err().contains("valid JSON"));
+        assert!(Expression::parse(r#""user_id""#).is_err());
+        assert!(Expression::parse(r#"{"regex": ["a", "b"]}"#).unwrap_err().contains("Unknown operator"));
+        assert!(Expression::parse(r#"{"==": [1]}"#).unwrap_err().contains("takes 2 arguments"));
+        assert!(Expression::parse(r#"{"==": [1, 2], "!=": [1, 2]}"#).is_err());
+        assert!(Expression::parse(r#"{"==": [{"var": "password"}, "x"]}"#).unwrap_err().contains("Unknown variable"));
+        assert!(Expression::parse(r#"{"==": [{"var": "custom_attributes."}, "x"]}"#).is_err());
+
+        // Depth and size are capped
+        let mut deep = r#"{"var": "user_id"}"#.to_string();
+        for _ in 0..MAX_EXPRESSION_DEPTH {
+            deep = format!(r#"{{"!": {}}}"#, deep);
+        }
+        assert!(Expression::parse(&deep).unwrap_err().contains("nested too deeply"));
+
+        let wide = format!(r#"{{"in": [{{"var": "user_id"}}, [{}]]}}"#, vec!["\"u\""; MAX_EXPRESSION_NODES].join(","));
+        assert!(Expression::parse(&wide).unwrap_err().contains("too complex"));
+
+        let long = format!(r#"{{"==": [{{"var": "user_id"}}, "{}"]}}"#, "u".repeat(MAX_EXPRESSION_LENGTH));
+        assert!(Expression::parse(&long).unwrap_err().contains("too long"));
+    }
+}
@@ -1,22 +1,180 @@
+pub mod cache;
+pub mod expression;
+
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sha2::{Digest, Sha256};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use uuid::Uuid;
+
+use expression::Expression;
 
 // User context for evaluation
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, ToSchema)]
 pub struct UserContext {
     pub user_id: Option<String>,
     pub user_email: Option<String>,
+    /// Client-generated id that keeps anonymous users in a stable rollout bucket
+    #[serde(default)]
+    pub anonymous_id: Option<String>,
     #[serde(default)]
-    #[allow(dead_code)] // Reserved for future attribute-based targeting rules
     pub custom_attributes: std::collections::HashMap<String, String>,
 }
 
 // Flag evaluation result
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct FlagEvaluation {
     pub enabled: bool,
     pub reason: String,
+    // Payload of the rule that fired, if it carries one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Vec<RuleTrace>>,
+}
+
+/// Reason reported for a flag whose evaluation failed, see try_evaluate_flag
+pub const EVALUATION_ERROR_REASON: &str = "evaluation_error";
+
+impl FlagEvaluation {
+    /// Safe default for a flag that couldn't be evaluated: off, without a payload
+    pub fn evaluation_error() -> Self {
+        Self {
+            enabled: false,
+            reason: EVALUATION_ERROR_REASON.to_string(),
+            payload: None,
+            trace: None,
+        }
+    }
+}
+
+// Outcome of a single rule considered during a debug evaluation
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RuleTrace {
+    pub rule_type: String,
+    pub rule_value: String,
+    pub priority: i32,
+    pub negate: bool,
+    pub matched: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+}
+
+// How a flag treats contexts without user_id or user_email
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnonymousBehavior {
+    /// All anonymous users share the "anonymous" rollout bucket (backward compatible default)
+    #[default]
+    Bucket,
+    /// Anonymous users never get the flag
+    AlwaysOff,
+    /// Anonymous users always get the flag
+    AlwaysOn,
+    /// Anonymous users without an anonymous_id get a fresh rollout roll on every request
+    Random,
+}
+
+impl AnonymousBehavior {
+    /// Value stored in the `feature_flags.anonymous_behavior` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnonymousBehavior::Bucket => "bucket",
+            AnonymousBehavior::AlwaysOff => "always_off",
+            AnonymousBehavior::AlwaysOn => "always_on",
+            AnonymousBehavior::Random => "random",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "always_off" => AnonymousBehavior::AlwaysOff,
+            "always_on" => AnonymousBehavior::AlwaysOn,
+            "random" => AnonymousBehavior::Random,
+            _ => AnonymousBehavior::Bucket,
+        }
+    }
+}
+
+// Hash that places users into percentage buckets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgorithm {
+    /// Rust's DefaultHasher, whose output may change between toolchains
+    /// Kept so flags created before the stable hash keep their buckets
+    Legacy,
+    /// First 8 bytes of SHA-256 over "{flag_key}:{user_identifier}" as a big-endian u64, modulo 100
+    #[default]
+    Sha256,
+}
+
+impl HashAlgorithm {
+    /// Value stored in the `feature_flags.hash_algorithm` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Legacy => "legacy",
+            HashAlgorithm::Sha256 => "sha256",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "legacy" => HashAlgorithm::Legacy,
+            _ => HashAlgorithm::Sha256,
+        }
+    }
+}
+
+// Context identifier a flag can bucket users on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BucketingKey {
+    UserId,
+    UserEmail,
+    AnonymousId,
+    /// A custom attribute, e.g. an account id so a whole team lands in the same bucket
+    Attribute(String),
+}
+
+/// Bucketing order for flags that don't set their own
+pub const DEFAULT_BUCKETING_KEYS: [BucketingKey; 3] =
+    [BucketingKey::UserId, BucketingKey::UserEmail, BucketingKey::AnonymousId];
+
+/// Prefix of bucketing keys that read a custom attribute, e.g. "attribute:account_id"
+pub const ATTRIBUTE_BUCKETING_PREFIX: &str = "attribute:";
+
+impl BucketingKey {
+    /// Parse a `feature_flags.bucketing_keys` entry
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "user_id" => Some(BucketingKey::UserId),
+            "user_email" => Some(BucketingKey::UserEmail),
+            "anonymous_id" => Some(BucketingKey::AnonymousId),
+            _ => value
+                .strip_prefix(ATTRIBUTE_BUCKETING_PREFIX)
+                .filter(|name| !name.trim().is_empty() && name.trim() == *name)
+                .map(|name| BucketingKey::Attribute(name.to_string())),
+        }
+    }
+
+    /// Stored entries were validated on write, unparseable ones are dropped
+    pub fn from_db(values: &[String]) -> Vec<Self> {
+        values.iter().filter_map(|v| Self::parse(v)).collect()
+    }
+
+    /// The context's value for this identifier, None when missing or blank
+    fn resolve<'a>(&self, context: &'a UserContext) -> Option<&'a str> {
+        let value = match self {
+            BucketingKey::UserId => context.user_id.as_deref(),
+            BucketingKey::UserEmail => context.user_email.as_deref(),
+            BucketingKey::AnonymousId => context.anonymous_id.as_deref(),
+            BucketingKey::Attribute(name) => context.custom_attributes.get(name).map(String::as_str),
+        };
+        value.filter(|v| !v.trim().is_empty())
+    }
 }
 
 // Flag data needed for evaluation
@@ -25,105 +183,605 @@ pub struct FlagData {
     pub key: String,
     pub enabled: bool,
     pub rollout_percentage: i32,
+    pub anonymous_behavior: AnonymousBehavior,
+    pub hash_algorithm: HashAlgorithm,
+    pub bucketing_keys: Vec<BucketingKey>,  // Identifiers tried in order, empty for DEFAULT_BUCKETING_KEYS
+    pub temporarily_disabled_until: Option<DateTime<Utc>>,  // Paused, evaluates to false until then
+    pub overrides: Vec<FlagOverride>,  // Users forced into a state, only the ones the context can match need loading
+}
+
+impl FlagData {
+    /// Whether the flag is paused at `now`
+    pub fn is_paused(&self, now: DateTime<Utc>) -> bool {
+        self.temporarily_disabled_until.is_some_and(|until| now < until)
+    }
+
+    /// Whether percentage checks for this context are rolled at random instead of hashed,
+    /// so the result can differ between requests
+    pub fn rolls_randomly(&self, context: &UserContext) -> bool {
+        self.anonymous_behavior == AnonymousBehavior::Random && self.bucketing_identifier(context).is_none()
+    }
+
+    /// Identifier used for consistent percentage bucketing: the first of the flag's bucketing keys
+    /// the context has a value for. None when it has none of them
+    pub fn bucketing_identifier<'a>(&self, context: &'a UserContext) -> Option<&'a str> {
+        let keys = if self.bucketing_keys.is_empty() {
+            &DEFAULT_BUCKETING_KEYS[..]
+        } else {
+            &self.bucketing_keys[..]
+        };
+        keys.iter().find_map(|key| key.resolve(context))
+    }
+
+    /// Whether bucketing reads custom attributes, which evaluation cache keys leave out
+    pub fn buckets_on_attributes(&self) -> bool {
+        self.bucketing_keys.iter().any(|key| matches!(key, BucketingKey::Attribute(_)))
+    }
+}
+
+// A user forced into a fixed result for one flag, e.g. so QA can check both sides of it
+#[derive(Debug, Clone)]
+pub struct FlagOverride {
+    pub user_identifier: String,  // A user_id, or an email matched ignoring case
+    pub enabled: bool,
+    pub payload: Option<serde_json::Value>,  // Returned with the evaluation instead of a rule's payload
+}
+
+impl FlagOverride {
+    /// Whether the override is for the user in `context`
+    pub fn applies_to(&self, context: &UserContext) -> bool {
+        context.user_id.as_deref() == Some(self.user_identifier.as_str())
+            || context
+                .user_email
+                .as_deref()
+                .is_some_and(|email| email.eq_ignore_ascii_case(&self.user_identifier))
+    }
 }
 
 // Rule data for evaluation
 #[derive(Debug, Clone)]
 pub struct RuleData {
+    pub id: Option<Uuid>,  // None for draft rules that aren't stored yet
     pub rule_type: String,
     pub rule_value: String,
+    pub rule_value_json: Option<serde_json::Value>,  // Entries of a list rule, read instead of rule_value when set
     pub enabled: bool,
     pub priority: i32,
+    pub negate: bool,
+    pub payload: Option<serde_json::Value>,
+    pub rollout_percentage: Option<i32>,  // Only fires for this share of the users it matches, None for all of them
+}
+
+impl RuleData {
+    /// Whether the rule gates the flag on the current time rather than on the user
+    pub fn is_time_window(&self) -> bool {
+        matches!(self.rule_type.as_str(), "active_after" | "active_before" | "local_time_window")
+    }
+
+    /// Whether the rule reads the context's custom attributes, which evaluation cache keys leave out
+    pub fn uses_custom_attributes(&self) -> bool {
+        matches!(self.rule_type.as_str(), "attribute_is_true" | "local_time_window" | "expression")
+    }
+
+    /// Whether the stored value can't be parsed for the rule's type, e.g. one written before validation
+    /// Only types whose value the evaluator parses can be malformed, the others are compared as strings
+    pub fn is_malformed(&self) -> bool {
+        self.check_value().is_err()
+    }
+
+    /// Parse the stored value for the rule's type, describing the first problem found
+    pub fn check_value(&self) -> Result<(), String> {
+        let malformed = || format!("Malformed {} rule value: {}", self.rule_type, self.rule_value);
+        match self.rule_type.as_str() {
+            "percentage" => parse_percentage(&self.rule_value).map(drop).ok_or_else(malformed),
+            "active_after" | "active_before" => parse_timestamp(&self.rule_value).map(drop).ok_or_else(malformed),
+            "local_time_window" => parse_local_time_window(&self.rule_value).map(drop).ok_or_else(malformed),
+            "expression" => Expression::parse(&self.rule_value).map(drop).map_err(|e| format!("{} ({})", malformed(), e)),
+            _ => match &self.rule_value_json {
+                Some(value) => parse_json_list(value)
+                    .map(drop)
+                    .ok_or_else(|| format!("Malformed {} rule value_json: {}", self.rule_type, value)),
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// Entries of a user_id_in or email_domain_in rule, from rule_value_json when set, else parsed from rule_value
+    pub fn list_values(&self) -> Vec<String> {
+        match &self.rule_value_json {
+            Some(value) => parse_json_list(value).unwrap_or_default(),
+            None => parse_list_value(&self.rule_value),
+        }
+    }
+}
+
+/// Source of the current time for evaluation, so time-dependent rules can be tested
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Clock backed by the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
 }
 
-/// Evaluate if a flag should be enabled for a given user
+/// Evaluate if a flag should be enabled for a given user at the current system time
+#[allow(dead_code)] // Convenience wrapper for callers that don't carry a clock
 pub fn evaluate_flag(
     flag: &FlagData,
     rules: &[RuleData],
     context: &UserContext,
 ) -> FlagEvaluation {
+    evaluate_flag_with_clock(flag, rules, context, &SystemClock)
+}
+
+/// Evaluate if a flag should be enabled for a given user using the provided clock
+pub fn evaluate_flag_with_clock(
+    flag: &FlagData,
+    rules: &[RuleData],
+    context: &UserContext,
+    clock: &dyn Clock,
+) -> FlagEvaluation {
+    evaluate_flag_at(flag, rules, context, clock.now())
+}
+
+/// Evaluate if a flag should be enabled for a given user at a specific point in time
+pub fn evaluate_flag_at(
+    flag: &FlagData,
+    rules: &[RuleData],
+    context: &UserContext,
+    now: DateTime<Utc>,
+) -> FlagEvaluation {
+    evaluate_flag_traced(flag, rules, context, now, false)
+}
+
+/// Evaluate a flag, optionally recording a per-rule trace for debugging
+/// The trace is only allocated when `debug` is set, so the normal path stays cheap
+pub fn evaluate_flag_traced(
+    flag: &FlagData,
+    rules: &[RuleData],
+    context: &UserContext,
+    now: DateTime<Utc>,
+    debug: bool,
+) -> FlagEvaluation {
+    let mut trace = debug.then(Vec::new);
+    let mut payload = None;
+    let (enabled, reason) = evaluate_inner(flag, rules, context, now, &mut trace, &mut payload);
+
+    FlagEvaluation {
+        enabled,
+        reason,
+        payload,
+        trace,
+    }
+}
+
+/// Evaluate a flag, reporting malformed enabled rules as an error instead of guessing
+/// Lets callers evaluating many flags fall back to FlagEvaluation::evaluation_error for just this one
+pub fn try_evaluate_flag(
+    flag: &FlagData,
+    rules: &[RuleData],
+    context: &UserContext,
+    now: DateTime<Utc>,
+    debug: bool,
+) -> Result<FlagEvaluation, String> {
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        rule.check_value()?;
+    }
+
+    Ok(evaluate_flag_traced(flag, rules, context, now, debug))
+}
+
+fn evaluate_inner(
+    flag: &FlagData,
+    rules: &[RuleData],
+    context: &UserContext,
+    now: DateTime<Utc>,
+    trace: &mut Option<Vec<RuleTrace>>,
+    payload: &mut Option<serde_json::Value>,
+) -> (bool, String) {
     // Step 1: If flag is globally disabled, return false
     if !flag.enabled {
-        return FlagEvaluation {
-            enabled: false,
-            reason: "Flag is globally disabled".to_string(),
+        return (false, "Flag is globally disabled".to_string());
+    }
+
+    // A pause overrides everything else but leaves the flag's configuration as it is
+    if let Some(until) = flag.temporarily_disabled_until.filter(|_| flag.is_paused(now)) {
+        return (false, format!("Flag paused until {}", until.to_rfc3339()));
+    }
+
+    // Overrides beat time windows, rules and rollout, only the kill switch and pauses above win over them
+    if let Some(forced) = flag.overrides.iter().find(|o| o.applies_to(context)) {
+        *payload = forced.payload.clone();
+        let state = if forced.enabled { "on" } else { "off" };
+        return (forced.enabled, format!("Override for user {}: forced {}", forced.user_identifier, state));
+    }
+
+    // Step 2: Time window rules gate the flag regardless of priority
+    for rule in rules.iter().filter(|r| r.enabled && r.is_time_window()) {
+        let Some(RuleMatch { matched: in_window, .. }) = match_rule(rule, context, now) else {
+            continue;
         };
+
+        let open = in_window != rule.negate;
+        record(trace, rule, open, None);
+
+        if !open {
+            return (
+                false,
+                format!("Outside active window: {} {}", rule.rule_type, rule.rule_value),
+            );
+        }
+    }
+
+    // Step 3: Short-circuit anonymous contexts according to the flag's setting
+    let is_anonymous = context.user_id.is_none() && context.user_email.is_none();
+    if is_anonymous {
+        match flag.anonymous_behavior {
+            AnonymousBehavior::AlwaysOff => {
+                return (false, "Anonymous user, flag is always off for anonymous".to_string());
+            }
+            AnonymousBehavior::AlwaysOn => {
+                return (true, "Anonymous user, flag is always on for anonymous".to_string());
+            }
+            AnonymousBehavior::Bucket | AnonymousBehavior::Random => {}
+        }
     }
 
-    // Step 2: Sort rules by priority (highest first) and check them
+    // Step 4: Sort rules by priority (highest first) and check them
+    // The sort is stable, so rules with equal priority keep their stored order
     let mut sorted_rules = rules.to_vec();
+    #[allow(clippy::unnecessary_sort_by)]
     sorted_rules.sort_by(|a, b| b.priority.cmp(&a.priority));
 
-    for rule in sorted_rules.iter() {
+    // Set when a percentage rule was already applied to the rule before it
+    let mut percentage_applied = false;
+
+    for (i, rule) in sorted_rules.iter().enumerate() {
         if !rule.enabled {
+            record(trace, rule, false, Some("rule disabled"));
             continue; // Skip disabled rules
         }
 
-        match rule.rule_type.as_str() {
-            "user_id" => {
-                if let Some(ref user_id) = context.user_id {
-                    if user_id == &rule.rule_value {
-                        return FlagEvaluation {
-                            enabled: true,
-                            reason: format!("Matched user_id rule: {}", rule.rule_value),
-                        };
-                    }
-                }
+        if rule.rule_type == "percentage" {
+            // Only narrows the rule directly before it, never fires on its own
+            if !std::mem::take(&mut percentage_applied) {
+                record(trace, rule, false, Some("no preceding rule matched"));
             }
-            "user_email" => {
-                if let Some(ref email) = context.user_email {
-                    if email == &rule.rule_value {
-                        return FlagEvaluation {
-                            enabled: true,
-                            reason: format!("Matched user_email rule: {}", rule.rule_value),
-                        };
-                    }
-                }
+            continue;
+        }
+
+        if rule.is_time_window() {
+            continue; // Already applied in step 2
+        }
+
+        // Determine whether the rule's condition matches this user
+        let Some(RuleMatch { matched, has_attribute }) = match_rule(rule, context, now) else {
+            record(trace, rule, false, Some("unknown rule type"));
+            continue; // Unknown rule type, skip
+        };
+
+        // A negated rule fires when its condition does NOT match
+        let matched = matched != rule.negate;
+        // A rule with its own rollout only fires for that share of the users it matches,
+        // the others fall through to lower-priority rules as if it hadn't matched
+        let fired = matched && in_rule_rollout(flag, rule, context);
+        let skipped = if matched && !fired {
+            Some("not in rule rollout")
+        } else {
+            (!has_attribute && !rule.negate).then_some("no matching attribute in context")
+        };
+        record(trace, rule, fired, skipped);
+
+        if fired {
+            let mut reason = if rule.negate {
+                format!("Matched negated {} rule: {}", rule.rule_type, rule.rule_value)
+            } else {
+                format!("Matched {} rule: {}", rule.rule_type, rule.rule_value)
+            };
+            if let Some(percentage) = rule.rollout_percentage {
+                reason = format!("{} ({}% rule rollout)", reason, percentage);
             }
-            "email_domain" => {
-                if let Some(ref email) = context.user_email {
-                    if email.ends_with(&rule.rule_value) {
-                        return FlagEvaluation {
-                            enabled: true,
-                            reason: format!("Matched email_domain rule: {}", rule.rule_value),
-                        };
-                    }
+
+            // A percentage rule right after this one limits it to a share of the matched users
+            let percentage_rule = sorted_rules
+                .get(i + 1)
+                .filter(|next| next.enabled && next.rule_type == "percentage");
+            if let Some(percentage_rule) = percentage_rule {
+                let percentage = parse_percentage(&percentage_rule.rule_value).unwrap_or(0);
+                let in_bucket = in_percentage(flag, context, percentage)
+                    != percentage_rule.negate;
+                record(trace, percentage_rule, in_bucket, None);
+                percentage_applied = true;
+
+                if !in_bucket {
+                    continue; // Not in this segment's share, keep looking at lower-priority rules
                 }
+                reason = format!("{} ({}% of segment)", reason, percentage);
             }
-            _ => {} // Unknown rule type, skip
+
+            *payload = rule.payload.clone();
+            return (true, reason);
         }
     }
 
-    // Step 3: Check percentage rollout using consistent hashing
+    // Step 5: Check percentage rollout using consistent hashing
     if flag.rollout_percentage > 0 {
-        let user_identifier = context.user_id.as_ref()
-            .or(context.user_email.as_ref())
-            .map(|s| s.as_str())
-            .unwrap_or("anonymous");
+        if in_percentage(flag, context, flag.rollout_percentage) {
+            return (true, format!("User in {}% rollout", flag.rollout_percentage));
+        } else {
+            return (false, format!("User not in {}% rollout", flag.rollout_percentage));
+        }
+    }
 
-        if should_enable_for_percentage(&flag.key, user_identifier, flag.rollout_percentage) {
-            return FlagEvaluation {
-                enabled: true,
-                reason: format!("User in {}% rollout", flag.rollout_percentage),
+    // Step 6: Default - flag is enabled globally but no rules matched and no rollout
+    (true, "Flag enabled globally, no specific rules applied".to_string())
+}
+
+/// Outcome of a single rule's condition, before negation is applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleMatch {
+    pub matched: bool,
+    // False when the context lacks the attribute the rule reads, e.g. no user_email for an email rule
+    pub has_attribute: bool,
+}
+
+/// Check a rule's own condition against a context
+/// Returns None for percentage rules, which only narrow the rule before them, and unknown rule types
+pub fn match_rule(rule: &RuleData, context: &UserContext, now: DateTime<Utc>) -> Option<RuleMatch> {
+    // Email rules compare case-insensitively, see normalize_email
+    let user_email = || context.user_email.as_deref().map(normalize_email);
+
+    let (has_attribute, matched) = match rule.rule_type.as_str() {
+        "user_id" => (
+            context.user_id.is_some(),
+            context.user_id.as_ref().is_some_and(|user_id| user_id == &rule.rule_value),
+        ),
+        "user_email" => {
+            let email = user_email();
+            (email.is_some(), email.is_some_and(|email| email == normalize_email(&rule.rule_value)))
+        }
+        "email_domain" => {
+            let email = user_email();
+            (
+                email.is_some(),
+                email.is_some_and(|email| email.ends_with(&normalize_email(&rule.rule_value))),
+            )
+        }
+        "email_domain_in" => {
+            let email = user_email();
+            (
+                email.is_some(),
+                email.is_some_and(|email| {
+                    rule.list_values()
+                        .iter()
+                        .any(|domain| email.ends_with(&normalize_email(domain)))
+                }),
+            )
+        }
+        "user_id_in" => (
+            context.user_id.is_some(),
+            context
+                .user_id
+                .as_ref()
+                .is_some_and(|user_id| rule.list_values().contains(user_id)),
+        ),
+        "attribute_is_true" => {
+            let value = context.custom_attributes.get(rule.rule_value.trim());
+            (value.is_some(), value.and_then(|v| parse_bool_attribute(v)) == Some(true))
+        }
+        // Unparseable time values are rejected on write; treat them as a closed window
+        "active_after" => (true, parse_timestamp(&rule.rule_value).is_some_and(|t| now >= t)),
+        "active_before" => (true, parse_timestamp(&rule.rule_value).is_some_and(|t| now < t)),
+        "local_time_window" => (
+            true,
+            parse_local_time_window(&rule.rule_value)
+                .is_some_and(|window| window.contains(now.with_timezone(&user_timezone(context)).time())),
+        ),
+        // Invalid expressions are rejected on write; treat them as never matching
+        "expression" => (
+            true,
+            Expression::parse(&rule.rule_value).is_ok_and(|expression| expression.matches(context)),
+        ),
+        _ => return None,
+    };
+
+    Some(RuleMatch { matched, has_attribute })
+}
+
+/// Check a single draft rule against a context without the rest of the flag
+/// Percentage rules report whether the context falls in their share of the flag's buckets
+pub fn test_rule(flag: &FlagData, rule: &RuleData, context: &UserContext, now: DateTime<Utc>) -> RuleTrace {
+    if rule.rule_type == "percentage" {
+        let percentage = parse_percentage(&rule.rule_value).unwrap_or(0);
+        let in_bucket = in_percentage(flag, context, percentage)
+            != rule.negate;
+        return rule_trace(rule, in_bucket, None);
+    }
+
+    match match_rule(rule, context, now) {
+        Some(RuleMatch { matched, has_attribute }) => {
+            let matched = matched != rule.negate;
+            let fired = matched && in_rule_rollout(flag, rule, context);
+            let skipped = if matched && !fired {
+                Some("not in rule rollout")
+            } else {
+                (!has_attribute && !rule.negate).then_some("no matching attribute in context")
             };
+            rule_trace(rule, fired, skipped)
+        }
+        None => rule_trace(rule, false, Some("unknown rule type")),
+    }
+}
+
+/// Whether the context falls in the given share of the flag's users
+/// Contexts without any identifier share the "anonymous" bucket, or roll at random per request
+/// when the flag's anonymous_behavior is random
+fn in_percentage(flag: &FlagData, context: &UserContext, percentage: i32) -> bool {
+    if flag.rolls_randomly(context) {
+        return rand::thread_rng().gen_range(0..100) < percentage;
+    }
+    let user_identifier = flag.bucketing_identifier(context).unwrap_or("anonymous");
+    should_enable_for_percentage(flag.hash_algorithm, &flag.key, user_identifier, percentage)
+}
+
+/// Whether a context the rule matched falls in the rule's own rollout, always true without one
+/// Buckets hash the flag key together with the rule id, so they are independent of the
+/// flag's rollout and of other rules: 20% of a segment is not simply the flag's first 20%
+fn in_rule_rollout(flag: &FlagData, rule: &RuleData, context: &UserContext) -> bool {
+    let Some(percentage) = rule.rollout_percentage else {
+        return true;
+    };
+    if flag.rolls_randomly(context) {
+        return rand::thread_rng().gen_range(0..100) < percentage;
+    }
+    let rule_key = match rule.id {
+        Some(id) => format!("{}:{}", flag.key, id),
+        None => flag.key.clone(),
+    };
+    let user_identifier = flag.bucketing_identifier(context).unwrap_or("anonymous");
+    should_enable_for_percentage(flag.hash_algorithm, &rule_key, user_identifier, percentage)
+}
+
+/// Normalize an email address or email domain for comparison: trimmed and lowercased
+/// RFC 5321 allows a case-sensitive local part, but no real provider treats it that way,
+/// so `John@Company.com` and `john@company.com` are considered the same user
+pub fn normalize_email(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+/// Append a rule outcome to the trace when debugging is enabled
+fn record(trace: &mut Option<Vec<RuleTrace>>, rule: &RuleData, matched: bool, skipped: Option<&str>) {
+    if let Some(trace) = trace {
+        trace.push(rule_trace(rule, matched, skipped));
+    }
+}
+
+fn rule_trace(rule: &RuleData, matched: bool, skipped: Option<&str>) -> RuleTrace {
+    RuleTrace {
+        rule_type: rule.rule_type.clone(),
+        rule_value: rule.rule_value.clone(),
+        priority: rule.priority,
+        negate: rule.negate,
+        matched,
+        skipped: skipped.map(str::to_string),
+    }
+}
+
+/// Parse an RFC3339 timestamp rule value into UTC
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Custom attribute holding the user's IANA timezone name, e.g. "Europe/Berlin"
+pub const TIMEZONE_ATTRIBUTE: &str = "timezone";
+
+/// Daily range of wall-clock times, start inclusive and end exclusive
+/// A window whose end is before its start runs past midnight (e.g. 22:00-06:00)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalTimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl LocalTimeWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
         } else {
-            return FlagEvaluation {
-                enabled: false,
-                reason: format!("User not in {}% rollout", flag.rollout_percentage),
-            };
+            time >= self.start || time < self.end
         }
     }
+}
+
+/// Parse a local_time_window rule value such as "09:00-17:00", empty windows are rejected
+pub fn parse_local_time_window(value: &str) -> Option<LocalTimeWindow> {
+    let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").ok();
+    let (start, end) = value.trim().split_once('-')?;
+    let window = LocalTimeWindow {
+        start: parse(start)?,
+        end: parse(end)?,
+    };
+    (window.start != window.end).then_some(window)
+}
+
+/// Timezone for local time windows, UTC when the context has none or it is not a valid IANA name
+pub fn user_timezone(context: &UserContext) -> Tz {
+    context
+        .custom_attributes
+        .get(TIMEZONE_ATTRIBUTE)
+        .and_then(|name| name.trim().parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// Parse a percentage rule value, a whole number between 0 and 100
+pub fn parse_percentage(value: &str) -> Option<i32> {
+    value
+        .trim()
+        .parse::<i32>()
+        .ok()
+        .filter(|p| (0..=100).contains(p))
+}
+
+/// Parse a boolean custom attribute, "true" or "false" in any case
+/// Anything else is treated as unknown, so it never matches an attribute_is_true rule
+pub fn parse_bool_attribute(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
 
-    // Step 4: Default - flag is enabled globally but no rules matched and no rollout
-    FlagEvaluation {
-        enabled: true,
-        reason: "Flag enabled globally, no specific rules applied".to_string(),
+/// Parse a list rule value, either a JSON array of strings or a comma-separated list
+/// Blank entries are dropped; returns an empty list if a JSON value is malformed
+pub fn parse_list_value(value: &str) -> Vec<String> {
+    let trimmed = value.trim();
+    if trimmed.starts_with('[') {
+        return serde_json::from_str::<Vec<String>>(trimmed)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect();
+    }
+
+    trimmed
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// Entries of a rule_value_json list, None unless it is an array of strings
+/// Entries are trimmed and blank ones dropped, like parse_list_value
+pub fn parse_json_list(value: &serde_json::Value) -> Option<Vec<String>> {
+    let entries = value.as_array()?;
+    let mut list = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let entry = entry.as_str()?.trim();
+        if !entry.is_empty() {
+            list.push(entry.to_string());
+        }
     }
+    Some(list)
 }
 
 /// Consistent hashing for percentage rollout
 /// Ensures the same user always gets the same result for a given percentage
-fn should_enable_for_percentage(flag_key: &str, user_identifier: &str, percentage: i32) -> bool {
+fn should_enable_for_percentage(
+    algorithm: HashAlgorithm,
+    flag_key: &str,
+    user_identifier: &str,
+    percentage: i32,
+) -> bool {
     if percentage == 0 {
         return false;
     }
@@ -131,33 +789,66 @@ fn should_enable_for_percentage(flag_key: &str, user_identifier: &str, percentag
         return true;
     }
 
-    // Create a consistent hash from flag_key + user_identifier
-    let mut hasher = DefaultHasher::new();
-    format!("{}:{}", flag_key, user_identifier).hash(&mut hasher);
-    let hash = hasher.finish();
+    // User is in the rollout if their bucket is less than the percentage
+    (rollout_bucket(algorithm, flag_key, user_identifier) as i32) < percentage
+}
+
+/// How many of `sample` synthetic users ("user_0", "user_1", ...) a rollout at `percentage` enables
+/// Shows whether the hash spreads users evenly for this flag key before trusting a ramp
+pub fn simulate_rollout(algorithm: HashAlgorithm, flag_key: &str, percentage: i32, sample: u32) -> u32 {
+    (0..sample)
+        .filter(|i| should_enable_for_percentage(algorithm, flag_key, &format!("user_{}", i), percentage))
+        .count() as u32
+}
 
-    // Map hash to 0-99 range
-    let bucket = (hash % 100) as i32;
+/// Bucket 0-99 for a user, from a hash of flag_key + user_identifier
+fn rollout_bucket(algorithm: HashAlgorithm, flag_key: &str, user_identifier: &str) -> u64 {
+    let input = format!("{}:{}", flag_key, user_identifier);
+    let hash = match algorithm {
+        HashAlgorithm::Legacy => {
+            let mut hasher = DefaultHasher::new();
+            input.hash(&mut hasher);
+            hasher.finish()
+        }
+        HashAlgorithm::Sha256 => {
+            let digest = Sha256::digest(input.as_bytes());
+            u64::from_be_bytes(digest[..8].try_into().expect("SHA-256 digest is 32 bytes"))
+        }
+    };
 
-    // User is in the rollout if their bucket is less than the percentage
-    bucket < percentage
+    hash % 100
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Clock frozen at a fixed instant
+    struct FixedClock(DateTime<Utc>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.0
+        }
+    }
+
     #[test]
     fn test_globally_disabled_flag() {
         let flag = FlagData {
             key: "test_flag".to_string(),
             enabled: false,
             rollout_percentage: 100,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
         };
         let rules = vec![];
         let context = UserContext {
             user_id: Some("user123".to_string()),
             user_email: None,
+            anonymous_id: None,
             custom_attributes: Default::default(),
         };
 
@@ -167,65 +858,291 @@ mod tests {
     }
 
     #[test]
-    fn test_user_id_rule_match() {
+    fn test_malformed_rule_is_an_evaluation_error() {
         let flag = FlagData {
             key: "test_flag".to_string(),
             enabled: true,
-            rollout_percentage: 0,
+            rollout_percentage: 100,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
         };
-        let rules = vec![RuleData {
-            rule_type: "user_id".to_string(),
-            rule_value: "user123".to_string(),
+        // Stored before validation existed, the evaluator can't tell when the window opens
+        let mut broken = RuleData {
+            rule_type: "active_after".to_string(),
+            rule_value: "next tuesday".to_string(),
+            rule_value_json: None,
             enabled: true,
             priority: 10,
-        }];
+            negate: false,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        };
         let context = UserContext {
             user_id: Some("user123".to_string()),
             user_email: None,
+            anonymous_id: None,
             custom_attributes: Default::default(),
         };
+        let now = Utc::now();
 
-        let result = evaluate_flag(&flag, &rules, &context);
+        let err = try_evaluate_flag(&flag, std::slice::from_ref(&broken), &context, now, false).unwrap_err();
+        assert_eq!(err, "Malformed active_after rule value: next tuesday");
+
+        let fallback = FlagEvaluation::evaluation_error();
+        assert!(!fallback.enabled);
+        assert_eq!(fallback.reason, EVALUATION_ERROR_REASON);
+
+        let expression = RuleData {
+            rule_type: "expression".to_string(),
+            rule_value: r#"{"nope": []}"#.to_string(),
+            ..broken.clone()
+        };
+        assert!(expression.is_malformed());
+        let err = try_evaluate_flag(&flag, std::slice::from_ref(&expression), &context, now, false).unwrap_err();
+        assert!(err.starts_with(r#"Malformed expression rule value: {"nope": []} ("#), "{}", err);
+        // Plain string values are compared as they are, there is nothing to parse
+        let user_id = RuleData {
+            rule_type: "user_id".to_string(),
+            ..broken.clone()
+        };
+        assert!(!user_id.is_malformed());
+
+        // Disabled rules aren't evaluated, so they can't break the flag
+        broken.enabled = false;
+        let result = try_evaluate_flag(&flag, &[broken], &context, now, false).unwrap();
         assert!(result.enabled);
-        assert!(result.reason.contains("user_id"));
     }
 
     #[test]
-    fn test_email_domain_match() {
-        let flag = FlagData {
+    fn test_override_beats_rules_and_rollout() {
+        let mut flag = FlagData {
             key: "test_flag".to_string(),
             enabled: true,
-            rollout_percentage: 0,
+            rollout_percentage: 100,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: vec![FlagOverride {
+                user_identifier: "qa-user".to_string(),
+                enabled: false,
+                payload: None,
+            }],
         };
         let rules = vec![RuleData {
-            rule_type: "email_domain".to_string(),
-            rule_value: "@company.com".to_string(),
+            rule_type: "user_id".to_string(),
+            rule_value: "qa-user".to_string(),
+            rule_value_json: None,
             enabled: true,
-            priority: 5,
+            priority: 10,
+            negate: false,
+            payload: Some(serde_json::json!({"variant": "rule"})),
+            id: None,
+            rollout_percentage: None,
         }];
-        let context = UserContext {
-            user_id: None,
-            user_email: Some("john@company.com".to_string()),
+        let qa = UserContext {
+            user_id: Some("qa-user".to_string()),
+            user_email: None,
+            anonymous_id: None,
             custom_attributes: Default::default(),
         };
 
-        let result = evaluate_flag(&flag, &rules, &context);
+        // Forced off although a rule matches and the rollout covers everyone
+        let result = evaluate_flag(&flag, &rules, &qa);
+        assert!(!result.enabled);
+        assert_eq!(result.reason, "Override for user qa-user: forced off");
+        assert_eq!(result.payload, None);
+
+        // Forced on with its own payload when neither a rule nor the rollout would enable the flag
+        flag.rollout_percentage = 0;
+        flag.overrides = vec![FlagOverride {
+            user_identifier: "QA@Example.com".to_string(),
+            enabled: true,
+            payload: Some(serde_json::json!({"variant": "forced"})),
+        }];
+        let by_email = UserContext {
+            user_id: Some("someone-else".to_string()),
+            user_email: Some("qa@example.com".to_string()),
+            ..qa.clone()
+        };
+        let result = evaluate_flag(&flag, &[], &by_email);
         assert!(result.enabled);
-        assert!(result.reason.contains("email_domain"));
-    }
+        assert!(result.reason.starts_with("Override for user"));
+        assert_eq!(result.payload, Some(serde_json::json!({"variant": "forced"})));
+
+        // Other users are evaluated as usual
+        let result = evaluate_flag(&flag, &rules, &qa);
+        assert!(result.enabled);
+        assert!(result.reason.contains("user_id"));
+
+        // The kill switch still wins
+        flag.enabled = false;
+        assert!(!evaluate_flag(&flag, &[], &by_email).enabled);
+    }
+
+    #[test]
+    fn test_user_id_rule_match() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules = vec![RuleData {
+            rule_type: "user_id".to_string(),
+            rule_value: "user123".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 10,
+            negate: false,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        }];
+        let context = UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+
+        let result = evaluate_flag(&flag, &rules, &context);
+        assert!(result.enabled);
+        assert!(result.reason.contains("user_id"));
+    }
+
+    #[test]
+    fn test_email_domain_match() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules = vec![RuleData {
+            rule_type: "email_domain".to_string(),
+            rule_value: "@company.com".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 5,
+            negate: false,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        }];
+        let context = UserContext {
+            user_id: None,
+            user_email: Some("john@company.com".to_string()),
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+
+        let result = evaluate_flag(&flag, &rules, &context);
+        assert!(result.enabled);
+        assert!(result.reason.contains("email_domain"));
+    }
+
+    #[test]
+    fn test_mixed_case_emails() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let email_rule = |rule_type: &str, rule_value: &str| RuleData {
+            rule_type: rule_type.to_string(),
+            rule_value: rule_value.to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 5,
+            negate: false,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        };
+        let context = UserContext {
+            user_id: None,
+            user_email: Some(" John@Company.COM ".to_string()),
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+
+        let matches = |rule: RuleData| evaluate_flag(&flag, &[rule], &context).reason.starts_with("Matched");
+
+        assert!(matches(email_rule("user_email", "john@company.com")));
+        // Rules stored before normalization was added still match
+        assert!(matches(email_rule("user_email", "JOHN@company.com")));
+        assert!(matches(email_rule("email_domain", "@Company.com")));
+        assert!(!matches(email_rule("email_domain", "@other.com")));
+        assert!(matches(email_rule("email_domain_in", "@other.com, @Company.com")));
+
+        assert_eq!(normalize_email("  Alice@Example.COM\n"), "alice@example.com");
+    }
 
     #[test]
     fn test_consistent_hashing() {
         // Same user should always get same result
-        let result1 = should_enable_for_percentage("test_flag", "user123", 50);
-        let result2 = should_enable_for_percentage("test_flag", "user123", 50);
-        assert_eq!(result1, result2);
+        for algorithm in [HashAlgorithm::Legacy, HashAlgorithm::Sha256] {
+            let result1 = should_enable_for_percentage(algorithm, "test_flag", "user123", 50);
+            let result2 = should_enable_for_percentage(algorithm, "test_flag", "user123", 50);
+            assert_eq!(result1, result2);
+
+            // 0% should always be false
+            assert!(!should_enable_for_percentage(algorithm, "test_flag", "user123", 0));
+
+            // 100% should always be true
+            assert!(should_enable_for_percentage(algorithm, "test_flag", "user123", 100));
+        }
+    }
+
+    #[test]
+    fn test_rollout_buckets_are_pinned() {
+        // SDKs that evaluate locally reproduce these, so they must never change
+        assert_eq!(rollout_bucket(HashAlgorithm::Sha256, "test_flag", "user123"), 95);
+        assert_eq!(rollout_bucket(HashAlgorithm::Sha256, "new_checkout", "alice@example.com"), 98);
+        assert_eq!(rollout_bucket(HashAlgorithm::Sha256, "dark-mode", "anonymous"), 47);
+
+        // A toolchain upgrade that changes DefaultHasher would silently re-bucket legacy flags
+        assert_eq!(rollout_bucket(HashAlgorithm::Legacy, "test_flag", "user123"), 27);
+        assert_eq!(rollout_bucket(HashAlgorithm::Legacy, "new_checkout", "alice@example.com"), 64);
+    }
+
+    #[test]
+    fn test_simulate_rollout() {
+        for algorithm in [HashAlgorithm::Legacy, HashAlgorithm::Sha256] {
+            assert_eq!(simulate_rollout(algorithm, "new_checkout", 0, 1000), 0);
+            assert_eq!(simulate_rollout(algorithm, "new_checkout", 100, 1000), 1000);
+            assert_eq!(simulate_rollout(algorithm, "new_checkout", 30, 0), 0);
 
-        // 0% should always be false
-        assert!(!should_enable_for_percentage("test_flag", "user123", 0));
+            // Synthetic users are fixed, so the same flag always reports the same fraction
+            let enabled = simulate_rollout(algorithm, "new_checkout", 30, 10_000);
+            assert_eq!(enabled, simulate_rollout(algorithm, "new_checkout", 30, 10_000));
+            assert!((2_800..=3_200).contains(&enabled), "{:?} enabled {}", algorithm, enabled);
+        }
+    }
 
-        // 100% should always be true
-        assert!(should_enable_for_percentage("test_flag", "user123", 100));
+    #[test]
+    fn test_hash_algorithm_db_round_trip() {
+        for algorithm in [HashAlgorithm::Legacy, HashAlgorithm::Sha256] {
+            assert_eq!(HashAlgorithm::from_db(algorithm.as_str()), algorithm);
+        }
     }
 
     #[test]
@@ -234,25 +1151,41 @@ mod tests {
             key: "test_flag".to_string(),
             enabled: true,
             rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
         };
         // Higher priority rule should be evaluated first
         let rules = vec![
             RuleData {
                 rule_type: "user_id".to_string(),
                 rule_value: "user123".to_string(),
+                rule_value_json: None,
                 enabled: true,
                 priority: 10,
+                negate: false,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
             },
             RuleData {
                 rule_type: "email_domain".to_string(),
                 rule_value: "@company.com".to_string(),
+                rule_value_json: None,
                 enabled: true,
                 priority: 5,
+                negate: false,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
             },
         ];
         let context = UserContext {
             user_id: Some("user123".to_string()),
             user_email: Some("john@company.com".to_string()),
+            anonymous_id: None,
             custom_attributes: Default::default(),
         };
 
@@ -261,4 +1194,1082 @@ mod tests {
         // Should match the higher priority user_id rule
         assert!(result.reason.contains("user_id"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_negated_user_id_rule() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules = vec![RuleData {
+            rule_type: "user_id".to_string(),
+            rule_value: "user123".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 10,
+            negate: true,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        }];
+
+        // Excluded user does not trigger the rule and falls through
+        let excluded = UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        // No rule fires and there's no rollout, so the flag's global state applies
+        let result = evaluate_flag(&flag, &rules, &excluded);
+        assert!(result.enabled);
+        assert_eq!(result.reason, "Flag enabled globally, no specific rules applied");
+
+        // Everyone else matches the negated rule
+        let other = UserContext {
+            user_id: Some("user456".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let result = evaluate_flag(&flag, &rules, &other);
+        assert!(result.enabled);
+        assert_eq!(result.reason, "Matched negated user_id rule: user123");
+    }
+
+    #[test]
+    fn test_negated_user_email_rule() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules = vec![RuleData {
+            rule_type: "user_email".to_string(),
+            rule_value: "blocked@example.com".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 10,
+            negate: true,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        }];
+
+        let blocked = UserContext {
+            user_id: None,
+            user_email: Some("blocked@example.com".to_string()),
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        assert!(!evaluate_flag(&flag, &rules, &blocked).reason.contains("negated"));
+
+        let allowed = UserContext {
+            user_id: None,
+            user_email: Some("someone@example.com".to_string()),
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let result = evaluate_flag(&flag, &rules, &allowed);
+        assert!(result.enabled);
+        assert!(result.reason.contains("negated user_email"));
+    }
+
+    #[test]
+    fn test_negated_email_domain_with_priority() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 100,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        // "Everyone except competitors", but a specific competitor user is allowed first
+        let rules = vec![
+            RuleData {
+                rule_type: "email_domain".to_string(),
+                rule_value: "@competitor.com".to_string(),
+                rule_value_json: None,
+                enabled: true,
+                priority: 5,
+                negate: true,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
+            },
+            RuleData {
+                rule_type: "user_id".to_string(),
+                rule_value: "partner".to_string(),
+                rule_value_json: None,
+                enabled: true,
+                priority: 10,
+                negate: false,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
+            },
+        ];
+
+        let competitor = UserContext {
+            user_id: Some("rival".to_string()),
+            user_email: Some("rival@competitor.com".to_string()),
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let result = evaluate_flag(&flag, &rules, &competitor);
+        assert!(result.reason.contains("rollout"));
+
+        let partner = UserContext {
+            user_id: Some("partner".to_string()),
+            user_email: Some("partner@competitor.com".to_string()),
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let result = evaluate_flag(&flag, &rules, &partner);
+        assert!(result.enabled);
+        assert!(result.reason.contains("Matched user_id"));
+
+        let customer = UserContext {
+            user_id: Some("customer".to_string()),
+            user_email: Some("customer@acme.com".to_string()),
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let result = evaluate_flag(&flag, &rules, &customer);
+        assert!(result.enabled);
+        assert!(result.reason.contains("negated email_domain"));
+    }
+
+    #[test]
+    fn test_parse_list_value() {
+        assert_eq!(parse_list_value("a, b ,c"), vec!["a", "b", "c"]);
+        assert_eq!(parse_list_value(r#"["a", "b"]"#), vec!["a", "b"]);
+        assert!(parse_list_value(" , ").is_empty());
+        assert!(parse_list_value("[not json").is_empty());
+    }
+
+    #[test]
+    fn test_user_id_in_rule() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules = vec![RuleData {
+            rule_type: "user_id_in".to_string(),
+            rule_value: "alice, bob, carol".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 10,
+            negate: false,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        }];
+
+        // Present member
+        let member = UserContext {
+            user_id: Some("bob".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let result = evaluate_flag(&flag, &rules, &member);
+        assert!(result.enabled);
+        assert!(result.reason.contains("user_id_in"));
+
+        // Absent member
+        let outsider = UserContext {
+            user_id: Some("mallory".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        assert!(!evaluate_flag(&flag, &rules, &outsider).reason.contains("user_id_in"));
+
+        // Empty context
+        let anonymous = UserContext {
+            user_id: None,
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        assert!(!evaluate_flag(&flag, &rules, &anonymous).reason.contains("user_id_in"));
+    }
+
+    #[test]
+    fn test_user_id_in_rule_json() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let mut rule = RuleData {
+            rule_type: "user_id_in".to_string(),
+            rule_value: r#"["acme,inc","bob"]"#.to_string(),
+            rule_value_json: Some(serde_json::json!(["acme,inc", "bob"])),
+            enabled: true,
+            priority: 10,
+            negate: false,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        };
+        let matches = |rule: &RuleData, user_id: &str| {
+            let context = UserContext {
+                user_id: Some(user_id.to_string()),
+                user_email: None,
+                anonymous_id: None,
+                custom_attributes: Default::default(),
+            };
+            evaluate_flag(&flag, std::slice::from_ref(rule), &context).reason.contains("user_id_in")
+        };
+
+        // Entries containing commas are matched whole
+        assert!(!rule.is_malformed());
+        assert!(matches(&rule, "acme,inc"));
+        assert!(!matches(&rule, "acme"));
+
+        // The JSON column wins over rule_value
+        rule.rule_value = "mallory".to_string();
+        assert!(matches(&rule, "bob"));
+        assert!(!matches(&rule, "mallory"));
+
+        // A value that isn't an array of strings matches nobody
+        rule.rule_value_json = Some(serde_json::json!({ "ids": ["bob"] }));
+        assert!(rule.is_malformed());
+        assert!(!matches(&rule, "bob"));
+    }
+
+    #[test]
+    fn test_email_domain_in_rule() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules = vec![RuleData {
+            rule_type: "email_domain_in".to_string(),
+            rule_value: "@company.com, @subsidiary.io".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 10,
+            negate: false,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        }];
+        let context = |email: Option<&str>| UserContext {
+            user_id: None,
+            user_email: email.map(str::to_string),
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+
+        // Any domain in the list matches
+        let result = evaluate_flag(&flag, &rules, &context(Some("jane@subsidiary.io")));
+        assert!(result.enabled);
+        assert!(result.reason.contains("email_domain_in"));
+        assert!(evaluate_flag(&flag, &rules, &context(Some("john@company.com"))).enabled);
+
+        // Domains outside the list, and contexts without an email, fall through
+        assert!(!evaluate_flag(&flag, &rules, &context(Some("eve@example.org"))).reason.contains("email_domain_in"));
+        assert!(!evaluate_flag(&flag, &rules, &context(None)).reason.contains("email_domain_in"));
+    }
+
+    #[test]
+    fn test_active_window_rules() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules = vec![
+            RuleData {
+                rule_type: "active_after".to_string(),
+                rule_value: "2025-01-01T00:00:00Z".to_string(),
+                rule_value_json: None,
+                enabled: true,
+                priority: 0,
+                negate: false,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
+            },
+            RuleData {
+                rule_type: "active_before".to_string(),
+                rule_value: "2025-02-01T00:00:00Z".to_string(),
+                rule_value_json: None,
+                enabled: true,
+                priority: 0,
+                negate: false,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
+            },
+        ];
+        let context = UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let at = |ts: &str| parse_timestamp(ts).unwrap();
+
+        // Before the window opens
+        let result = evaluate_flag_at(&flag, &rules, &context, at("2024-12-31T23:59:59Z"));
+        assert!(!result.enabled);
+        assert!(result.reason.contains("active_after"));
+
+        // Within the window
+        let result = evaluate_flag_at(&flag, &rules, &context, at("2025-01-15T12:00:00Z"));
+        assert!(result.enabled);
+
+        // After the window closes
+        let result = evaluate_flag_at(&flag, &rules, &context, at("2025-02-01T00:00:00Z"));
+        assert!(!result.enabled);
+        assert!(result.reason.contains("active_before"));
+    }
+
+    #[test]
+    fn test_local_time_window_across_dst() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules = vec![RuleData {
+            rule_type: "local_time_window".to_string(),
+            rule_value: "09:00-17:00".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 0,
+            negate: false,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        }];
+        let context = |timezone: Option<&str>| UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: timezone
+                .map(|tz| [(TIMEZONE_ATTRIBUTE.to_string(), tz.to_string())].into())
+                .unwrap_or_default(),
+        };
+        let at = |ts: &str| parse_timestamp(ts).unwrap();
+        let new_york = context(Some("America/New_York"));
+
+        // New York switches from UTC-5 to UTC-4 on 2025-03-09
+        // 09:30 local is 14:30 UTC the day before and 13:30 UTC the day after
+        assert!(evaluate_flag_at(&flag, &rules, &new_york, at("2025-03-08T14:30:00Z")).enabled);
+        assert!(!evaluate_flag_at(&flag, &rules, &new_york, at("2025-03-08T13:30:00Z")).enabled);
+        assert!(evaluate_flag_at(&flag, &rules, &new_york, at("2025-03-10T13:30:00Z")).enabled);
+
+        // 17:00 local closes the window, an hour earlier in UTC once DST starts
+        assert!(evaluate_flag_at(&flag, &rules, &new_york, at("2025-03-08T21:59:00Z")).enabled);
+        let result = evaluate_flag_at(&flag, &rules, &new_york, at("2025-03-10T21:00:00Z"));
+        assert!(!result.enabled);
+        assert!(result.reason.contains("local_time_window"));
+
+        // Missing or unknown timezones fall back to UTC
+        for fallback in [context(None), context(Some("Mars/Olympus_Mons"))] {
+            assert!(evaluate_flag_at(&flag, &rules, &fallback, at("2025-03-10T09:00:00Z")).enabled);
+            assert!(!evaluate_flag_at(&flag, &rules, &fallback, at("2025-03-10T08:59:00Z")).enabled);
+        }
+    }
+
+    #[test]
+    fn test_parse_local_time_window() {
+        let time = |t: &str| NaiveTime::parse_from_str(t, "%H:%M").unwrap();
+
+        let window = parse_local_time_window(" 09:00 - 17:30 ").unwrap();
+        assert!(window.contains(time("09:00")));
+        assert!(window.contains(time("17:29")));
+        assert!(!window.contains(time("17:30")));
+
+        // Overnight windows wrap past midnight
+        let night = parse_local_time_window("22:00-06:00").unwrap();
+        assert!(night.contains(time("23:00")));
+        assert!(night.contains(time("05:59")));
+        assert!(!night.contains(time("12:00")));
+
+        assert!(parse_local_time_window("09:00-09:00").is_none());
+        assert!(parse_local_time_window("9am-5pm").is_none());
+        assert!(parse_local_time_window("09:00").is_none());
+        assert!(parse_local_time_window("25:00-26:00").is_none());
+    }
+
+    #[test]
+    fn test_rule_against_sample_contexts() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: false,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rule = |rule_type: &str, rule_value: &str, negate: bool| RuleData {
+            rule_type: rule_type.to_string(),
+            rule_value: rule_value.to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 0,
+            negate,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        };
+        let context = |user_id: Option<&str>, user_email: Option<&str>| UserContext {
+            user_id: user_id.map(str::to_string),
+            user_email: user_email.map(str::to_string),
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let now = Utc::now();
+        let alice = context(Some("alice"), Some("Alice@Company.com"));
+        let bob = context(Some("bob"), None);
+
+        // The flag being disabled does not matter, only the rule is checked
+        let domain = rule("email_domain", "@company.com", false);
+        assert!(test_rule(&flag, &domain, &alice, now).matched);
+        let outcome = test_rule(&flag, &domain, &bob, now);
+        assert!(!outcome.matched);
+        assert_eq!(outcome.skipped.as_deref(), Some("no matching attribute in context"));
+
+        let negated = rule("email_domain", "@company.com", true);
+        assert!(!test_rule(&flag, &negated, &alice, now).matched);
+        assert!(test_rule(&flag, &negated, &bob, now).matched);
+
+        // Percentage rules use the flag's buckets, test_flag/user123 lands in bucket 95
+        let user123 = context(Some("user123"), None);
+        assert!(test_rule(&flag, &rule("percentage", "96", false), &user123, now).matched);
+        assert!(!test_rule(&flag, &rule("percentage", "95", false), &user123, now).matched);
+
+        let window = rule("active_after", "2025-01-01T00:00:00Z", false);
+        assert!(test_rule(&flag, &window, &bob, parse_timestamp("2025-06-01T00:00:00Z").unwrap()).matched);
+        assert!(!test_rule(&flag, &window, &bob, parse_timestamp("2024-06-01T00:00:00Z").unwrap()).matched);
+    }
+
+    #[test]
+    fn test_paused_flag_evaluates_to_false_until_the_pause_ends() {
+        let until = parse_timestamp("2025-03-10T13:00:00Z").unwrap();
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 100,
+            anonymous_behavior: AnonymousBehavior::AlwaysOn,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: Some(until),
+            overrides: Vec::new(),
+        };
+        let rules = vec![RuleData {
+            rule_type: "user_id".to_string(),
+            rule_value: "user123".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 10,
+            negate: false,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        }];
+        let context = UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+
+        // Even a matching rule doesn't fire during the pause
+        let paused = evaluate_flag_at(&flag, &rules, &context, until - chrono::Duration::minutes(1));
+        assert!(!paused.enabled);
+        assert_eq!(paused.reason, "Flag paused until 2025-03-10T13:00:00+00:00");
+
+        // Afterwards the untouched configuration applies again
+        let resumed = evaluate_flag_at(&flag, &rules, &context, until);
+        assert!(resumed.enabled);
+        assert_eq!(resumed.reason, "Matched user_id rule: user123");
+    }
+
+    #[test]
+    fn test_evaluate_with_fixed_clock() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules = vec![RuleData {
+            rule_type: "active_after".to_string(),
+            rule_value: "2025-06-01T00:00:00Z".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 0,
+            negate: false,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        }];
+        let context = UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+
+        let before = FixedClock(parse_timestamp("2025-05-31T23:59:59Z").unwrap());
+        assert!(!evaluate_flag_with_clock(&flag, &rules, &context, &before).enabled);
+
+        let after = FixedClock(parse_timestamp("2025-06-01T00:00:00Z").unwrap());
+        assert!(evaluate_flag_with_clock(&flag, &rules, &context, &after).enabled);
+    }
+
+    #[test]
+    fn test_debug_trace() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules = vec![
+            RuleData {
+                rule_type: "user_id".to_string(),
+                rule_value: "someone_else".to_string(),
+                rule_value_json: None,
+                enabled: false,
+                priority: 30,
+                negate: false,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
+            },
+            RuleData {
+                rule_type: "user_email".to_string(),
+                rule_value: "john@company.com".to_string(),
+                rule_value_json: None,
+                enabled: true,
+                priority: 20,
+                negate: false,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
+            },
+            RuleData {
+                rule_type: "user_id".to_string(),
+                rule_value: "user123".to_string(),
+                rule_value_json: None,
+                enabled: true,
+                priority: 10,
+                negate: false,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
+            },
+        ];
+        let context = UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let now = Utc::now();
+
+        // Production path carries no trace
+        assert!(evaluate_flag_at(&flag, &rules, &context, now).trace.is_none());
+
+        let result = evaluate_flag_traced(&flag, &rules, &context, now, true);
+        assert!(result.enabled);
+        let trace = result.trace.unwrap();
+        assert_eq!(trace.len(), 3);
+        assert_eq!(trace[0].skipped.as_deref(), Some("rule disabled"));
+        assert_eq!(trace[1].skipped.as_deref(), Some("no matching attribute in context"));
+        assert!(!trace[1].matched);
+        assert!(trace[2].matched);
+        assert!(trace[2].skipped.is_none());
+    }
+
+    #[test]
+    fn test_anonymous_behavior() {
+        let mut flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 100,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let anonymous = UserContext {
+            user_id: None,
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let identified = UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+
+        // Bucket: anonymous users go through the normal rollout
+        let result = evaluate_flag(&flag, &[], &anonymous);
+        assert!(result.enabled);
+        assert!(result.reason.contains("rollout"));
+
+        // Always off: anonymous users never get the flag, identified users are unaffected
+        flag.anonymous_behavior = AnonymousBehavior::AlwaysOff;
+        let result = evaluate_flag(&flag, &[], &anonymous);
+        assert!(!result.enabled);
+        assert!(result.reason.contains("Anonymous"));
+        assert!(evaluate_flag(&flag, &[], &identified).enabled);
+
+        // Always on: anonymous users always get the flag, even outside the rollout
+        flag.anonymous_behavior = AnonymousBehavior::AlwaysOn;
+        flag.rollout_percentage = 0;
+        let result = evaluate_flag(&flag, &[], &anonymous);
+        assert!(result.enabled);
+        assert!(result.reason.contains("Anonymous"));
+    }
+
+    #[test]
+    fn test_anonymous_id_bucketing() {
+        let mut flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 50,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let with_id = |id: &str| UserContext {
+            user_id: None,
+            user_email: None,
+            anonymous_id: Some(id.to_string()),
+            custom_attributes: Default::default(),
+        };
+        let anonymous = with_id("");
+
+        // A provided anonymous_id is sticky and hashed like a user_id
+        for behavior in [AnonymousBehavior::Bucket, AnonymousBehavior::Random] {
+            flag.anonymous_behavior = behavior;
+            for i in 0..20 {
+                let context = with_id(&format!("device-{}", i));
+                let expected = should_enable_for_percentage(flag.hash_algorithm, &flag.key, &format!("device-{}", i), 50);
+                assert!((0..10).all(|_| evaluate_flag(&flag, &[], &context).enabled == expected));
+                assert!(!flag.rolls_randomly(&context));
+            }
+        }
+
+        // Different ids land in different buckets rather than one shared bucket
+        let enabled = (0..100)
+            .filter(|i| evaluate_flag(&flag, &[], &with_id(&format!("device-{}", i))).enabled)
+            .count();
+        assert!(enabled > 0 && enabled < 100);
+
+        // Absent (or blank) id with bucket: every anonymous request shares the "anonymous" bucket
+        flag.anonymous_behavior = AnonymousBehavior::Bucket;
+        let shared = should_enable_for_percentage(flag.hash_algorithm, &flag.key, "anonymous", 50);
+        assert!((0..50).all(|_| evaluate_flag(&flag, &[], &anonymous).enabled == shared));
+        assert!(!flag.rolls_randomly(&anonymous));
+
+        // Absent id with random: each request rolls on its own, so results are not sticky
+        flag.anonymous_behavior = AnonymousBehavior::Random;
+        assert!(flag.rolls_randomly(&anonymous));
+        let enabled = (0..1_000).filter(|_| evaluate_flag(&flag, &[], &anonymous).enabled).count();
+        assert!(enabled > 0 && enabled < 1_000);
+
+        // A full rollout stays deterministic even when rolling at random
+        flag.rollout_percentage = 100;
+        assert!((0..100).all(|_| evaluate_flag(&flag, &[], &anonymous).enabled));
+    }
+
+    #[test]
+    fn test_bucketing_key_fallback_order() {
+        let mut flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 50,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let context = |user_id: Option<&str>, user_email: Option<&str>, account_id: Option<&str>| UserContext {
+            user_id: user_id.map(String::from),
+            user_email: user_email.map(String::from),
+            anonymous_id: Some("device-1".to_string()),
+            custom_attributes: account_id
+                .map(|id| [("account_id".to_string(), id.to_string())].into_iter().collect())
+                .unwrap_or_default(),
+        };
+        let full = context(Some("user123"), Some("alice@example.com"), Some("acme"));
+
+        // Default order: user_id, then user_email, then anonymous_id
+        assert_eq!(flag.bucketing_identifier(&full), Some("user123"));
+        assert_eq!(flag.bucketing_identifier(&context(None, Some("alice@example.com"), None)), Some("alice@example.com"));
+        assert_eq!(flag.bucketing_identifier(&context(Some(""), None, None)), Some("device-1"));
+
+        // A flag can prefer an account id, falling back to the user
+        flag.bucketing_keys = BucketingKey::from_db(&[
+            "attribute:account_id".to_string(),
+            "user_id".to_string(),
+            "user_email".to_string(),
+        ]);
+        assert_eq!(flag.bucketing_identifier(&full), Some("acme"));
+        assert_eq!(flag.bucketing_identifier(&context(Some("user123"), None, Some(" "))), Some("user123"));
+        assert_eq!(flag.bucketing_identifier(&context(None, Some("alice@example.com"), None)), Some("alice@example.com"));
+        // The chain is exhaustive: anonymous_id isn't in it, so it's not used
+        assert_eq!(flag.bucketing_identifier(&context(None, None, None)), None);
+        assert!(flag.buckets_on_attributes());
+
+        // Everyone on an account shares the account's bucket
+        let expected = should_enable_for_percentage(flag.hash_algorithm, &flag.key, "acme", 50);
+        for user in ["alice", "bob", "carol", "dave"] {
+            assert_eq!(evaluate_flag(&flag, &[], &context(Some(user), None, Some("acme"))).enabled, expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_bucketing_key() {
+        assert_eq!(BucketingKey::parse("user_id"), Some(BucketingKey::UserId));
+        assert_eq!(BucketingKey::parse("anonymous_id"), Some(BucketingKey::AnonymousId));
+        assert_eq!(
+            BucketingKey::parse("attribute:account_id"),
+            Some(BucketingKey::Attribute("account_id".to_string()))
+        );
+        assert_eq!(BucketingKey::parse("attribute:"), None);
+        assert_eq!(BucketingKey::parse("attribute: account_id"), None);
+        assert_eq!(BucketingKey::parse("account_id"), None);
+    }
+
+    #[test]
+    fn test_matched_rule_payload() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 100,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let payload = serde_json::json!({ "timeout_ms": 2500, "variants": ["a", "b"] });
+        let rules = vec![RuleData {
+            rule_type: "user_id".to_string(),
+            rule_value: "user123".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 0,
+            negate: false,
+            payload: Some(payload.clone()),
+            id: None,
+            rollout_percentage: None,
+        }];
+
+        // The payload of the rule that fired is returned with the result
+        let context = UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let result = evaluate_flag(&flag, &rules, &context);
+        assert!(result.enabled);
+        assert_eq!(result.payload, Some(payload));
+
+        // No rule fired, so no payload even though the rollout enables the flag
+        let context = UserContext {
+            user_id: Some("user456".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let result = evaluate_flag(&flag, &rules, &context);
+        assert!(result.enabled);
+        assert!(result.payload.is_none());
+    }
+
+    #[test]
+    fn test_percentage_of_segment() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 1, // Only 1% outside the segment, so segment hits are easy to tell apart
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        // "50% of users on the beta list": the percentage rule sits right below its segment rule
+        let beta_users: Vec<String> = (0..200).map(|i| format!("user_{}", i)).collect();
+        let rules = vec![
+            RuleData {
+                rule_type: "user_id_in".to_string(),
+                rule_value: beta_users.join(","),
+                rule_value_json: None,
+                enabled: true,
+                priority: 10,
+                negate: false,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
+            },
+            RuleData {
+                rule_type: "percentage".to_string(),
+                rule_value: "50".to_string(),
+                rule_value_json: None,
+                enabled: true,
+                priority: 9,
+                negate: false,
+                payload: None,
+                id: None,
+                rollout_percentage: None,
+            },
+        ];
+        let context = |user_id: &str| UserContext {
+            user_id: Some(user_id.to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+
+        let in_segment_share = beta_users
+            .iter()
+            .filter(|user_id| {
+                let result = evaluate_flag(&flag, &rules, &context(user_id));
+                // Each user always gets the same result
+                assert_eq!(result.enabled, evaluate_flag(&flag, &rules, &context(user_id)).enabled);
+                result.reason.contains("50% of segment")
+            })
+            .count();
+        assert!((70..=130).contains(&in_segment_share), "got {}", in_segment_share);
+
+        // Users outside the share fall through to the flag's rollout, like non-matching users
+        let excluded = beta_users
+            .iter()
+            .find(|user_id| !evaluate_flag(&flag, &rules, &context(user_id)).reason.contains("segment"))
+            .unwrap();
+        let result = evaluate_flag(&flag, &rules, &context(excluded));
+        assert!(result.reason.contains("rollout"));
+
+        // The percentage rule never fires on its own
+        let outsider = evaluate_flag(&flag, &rules, &context("not_on_the_list"));
+        assert!(!outsider.reason.contains("segment"));
+
+        // The trace shows the percentage rule once, as applied to the segment rule
+        let result = evaluate_flag_traced(&flag, &rules, &context(excluded), Utc::now(), true);
+        let trace = result.trace.unwrap();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[1].rule_type, "percentage");
+        assert!(!trace[1].matched);
+        assert!(trace[1].skipped.is_none());
+    }
+
+    #[test]
+    fn test_rule_rollout() {
+        let flag = FlagData {
+            key: "test_flag".to_string(),
+            enabled: true,
+            rollout_percentage: 1,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        // "20% of company users get variant A, the other company users variant B"
+        let gated = RuleData {
+            id: Some(Uuid::new_v4()),
+            rule_type: "email_domain".to_string(),
+            rule_value: "@company.com".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 10,
+            negate: false,
+            payload: Some(serde_json::json!("A")),
+            rollout_percentage: Some(20),
+        };
+        let fallback = RuleData {
+            id: Some(Uuid::new_v4()),
+            priority: 5,
+            payload: Some(serde_json::json!("B")),
+            rollout_percentage: None,
+            ..gated.clone()
+        };
+        let rules = vec![gated.clone(), fallback];
+        let context = |i: usize| UserContext {
+            user_id: Some(format!("user_{}", i)),
+            user_email: Some(format!("user_{}@company.com", i)),
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+
+        let in_rule_rollout: Vec<usize> = (0..500)
+            .filter(|&i| {
+                let result = evaluate_flag(&flag, &rules, &context(i));
+                assert!(result.enabled);
+                // Each user always gets the same result
+                assert_eq!(result.reason, evaluate_flag(&flag, &rules, &context(i)).reason);
+                if result.payload == Some(serde_json::json!("A")) {
+                    assert!(result.reason.contains("20% rule rollout"), "{}", result.reason);
+                    true
+                } else {
+                    // Users outside the rule's rollout continue to the next rule
+                    assert_eq!(result.payload, Some(serde_json::json!("B")));
+                    false
+                }
+            })
+            .collect();
+        assert!((60..=140).contains(&in_rule_rollout.len()), "got {}", in_rule_rollout.len());
+
+        // Buckets depend on the rule, so another rule with the same rollout picks other users
+        let other = RuleData { id: Some(Uuid::new_v4()), ..gated.clone() };
+        let other_share: Vec<usize> = (0..500)
+            .filter(|&i| evaluate_flag(&flag, std::slice::from_ref(&other), &context(i)).payload.is_some())
+            .collect();
+        assert_ne!(in_rule_rollout, other_share);
+
+        // Users the rule doesn't match never fire it, whatever their bucket
+        let outsider = UserContext {
+            user_email: Some("someone@elsewhere.com".to_string()),
+            ..context(0)
+        };
+        assert!(evaluate_flag(&flag, &rules, &outsider).payload.is_none());
+
+        // The trace tells a rollout miss apart from a non-match
+        let excluded = (0..500).find(|i| !in_rule_rollout.contains(i)).unwrap();
+        let trace = evaluate_flag_traced(&flag, &rules, &context(excluded), Utc::now(), true).trace.unwrap();
+        assert!(!trace[0].matched);
+        assert_eq!(trace[0].skipped.as_deref(), Some("not in rule rollout"));
+        assert!(trace[1].matched);
+
+        // 0% never fires, 100% behaves like no rollout
+        let never = RuleData { rollout_percentage: Some(0), ..gated.clone() };
+        assert!(evaluate_flag(&flag, &[never], &context(in_rule_rollout[0])).payload.is_none());
+        let always = RuleData { rollout_percentage: Some(100), ..gated };
+        assert!((0..50).all(|i| evaluate_flag(&flag, std::slice::from_ref(&always), &context(i)).payload.is_some()));
+    }
+
+    #[test]
+    fn test_attribute_is_true_rule() {
+        let flag = FlagData {
+            key: "beta_dashboard".to_string(),
+            enabled: true,
+            rollout_percentage: 0,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::Sha256,
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let rules = vec![RuleData {
+            rule_type: "attribute_is_true".to_string(),
+            rule_value: "beta_opt_in".to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority: 10,
+            negate: false,
+            payload: None,
+            id: None,
+            rollout_percentage: None,
+        }];
+        let context_with = |value: Option<&str>| UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: value
+                .map(|v| [("beta_opt_in".to_string(), v.to_string())].into_iter().collect())
+                .unwrap_or_default(),
+        };
+        let matches = |value: Option<&str>| {
+            evaluate_flag(&flag, &rules, &context_with(value))
+                .reason
+                .starts_with("Matched attribute_is_true")
+        };
+
+        assert!(matches(Some("true")));
+        assert!(matches(Some(" TRUE ")));
+        assert!(!matches(Some("false")));
+        assert!(!matches(None)); // missing attribute
+        assert!(!matches(Some("yes"))); // garbage is not true
+        assert!(!matches(Some("")));
+    }
+
+    #[test]
+    fn test_parse_bool_attribute() {
+        assert_eq!(parse_bool_attribute("true"), Some(true));
+        assert_eq!(parse_bool_attribute("False"), Some(false));
+        assert_eq!(parse_bool_attribute("1"), None);
+        assert_eq!(parse_bool_attribute("maybe"), None);
+    }
+
+    #[test]
+    fn test_parse_percentage() {
+        assert_eq!(parse_percentage("0"), Some(0));
+        assert_eq!(parse_percentage(" 50 "), Some(50));
+        assert_eq!(parse_percentage("100"), Some(100));
+        assert_eq!(parse_percentage("101"), None);
+        assert_eq!(parse_percentage("-5"), None);
+        assert_eq!(parse_percentage("12.5"), None);
+    }
+}
@@ -1,23 +1,61 @@
+mod audit;
 mod config;
+mod db;
+mod password;
+mod rate_limit;
 mod routes;
 mod state;
 mod evaluation;
+mod webhooks;
 
-use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+use std::sync::Arc;
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::CorsLayer;
 
 #[tokio::main]
 async fn main() {
     let config = config::Config::from_env();
 
-    let db = PgPool::connect(&std::env::var("DATABASE_URL").unwrap())
-        .await
-        .expect("Error connecting DB");
+    let pool_options = PgPoolOptions::new()
+        .max_connections(config.db_max_connections)
+        .acquire_timeout(config.db_acquire_timeout)
+        .idle_timeout(config.db_idle_timeout);
 
-    let state = state::AppState { db: db.clone() };
+    let db = db::connect_with_retry(
+        pool_options,
+        &config.database_url,
+        config.db_connect_max_attempts,
+        config.db_connect_retry_interval,
+    )
+    .await
+    .expect("Error connecting DB");
 
-    let app = routes::routes().with_state(state)
+    // Refuse to serve requests against a database the migrations haven't been run on
+    db::verify_schema(&db).await.unwrap_or_else(|e| panic!("{}", e));
+
+    let state = state::AppState {
+        db: db.clone(),
+        clock: Arc::new(evaluation::SystemClock),
+        http: reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .expect("Error building HTTP client"),
+        evaluation_cache: Arc::new(evaluation::cache::EvaluationCache::new(
+            config.evaluation_cache_ttl,
+            config.evaluation_cache_max_entries,
+        )),
+        password_policy: config.password_policy.clone(),
+        rate_limiter: Arc::new(rate_limit::RateLimiter::new(
+            rate_limit::RATE_LIMIT_WINDOW,
+            config.evaluate_rate_limit_per_minute,
+        )),
+        strict_flag_coherence: config.strict_flag_coherence,
+    };
+
+    let app = routes::routes(&config).with_state(state)
         .layer(axum::Extension(db))
+        .layer(compression())
         .layer(CorsLayer::permissive());
 
     let listener = tokio::net::TcpListener::bind(config.addr()).await.unwrap();
@@ -26,3 +64,56 @@ async fn main() {
 
     axum::serve(listener, app).await.unwrap();
 }
+
+/// gzip or deflate responses when the client sends Accept-Encoding
+/// The default predicate leaves bodies under 32 bytes, images and text/event-stream uncompressed,
+/// so streamed responses are never buffered
+fn compression() -> CompressionLayer {
+    CompressionLayer::new().gzip(true).deflate(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::header, response::IntoResponse, routing::get, Json, Router};
+
+    #[tokio::test]
+    async fn test_compression_follows_accept_encoding() {
+        let flags: serde_json::Map<String, serde_json::Value> = (0..200)
+            .map(|i| (format!("flag_{}", i), serde_json::json!({"enabled": true, "reason": "Flag enabled globally"})))
+            .collect();
+        let app = Router::new()
+            .route("/large", get(move || async move { Json(flags.clone()) }))
+            .route("/small", get(|| async { "ok" }))
+            .route(
+                "/events",
+                get(|| async { ([(header::CONTENT_TYPE, "text/event-stream")], "data: x\n\n".repeat(100)).into_response() }),
+            )
+            .layer(compression());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = reqwest::Client::new();
+        let content_encoding = |path: &'static str, accept: Option<&'static str>| {
+            let mut request = client.get(format!("http://{}{}", addr, path));
+            if let Some(accept) = accept {
+                request = request.header(header::ACCEPT_ENCODING, accept);
+            }
+            async move {
+                let response = request.send().await.unwrap();
+                response
+                    .headers()
+                    .get(header::CONTENT_ENCODING)
+                    .map(|v| v.to_str().unwrap().to_string())
+            }
+        };
+
+        assert_eq!(content_encoding("/large", Some("gzip")).await.as_deref(), Some("gzip"));
+        assert_eq!(content_encoding("/large", Some("deflate")).await.as_deref(), Some("deflate"));
+        assert_eq!(content_encoding("/large", None).await, None);
+        assert_eq!(content_encoding("/small", Some("gzip")).await, None);
+        assert_eq!(content_encoding("/events", Some("gzip")).await, None);
+    }
+}
@@ -0,0 +1,130 @@
+use serde::Deserialize;
+use std::fmt;
+
+/// Shortest password accepted regardless of configuration
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Strength rules a new password must satisfy
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_digit: bool,
+    pub require_mixed_case: bool,
+    pub require_symbol: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: MIN_PASSWORD_LENGTH,
+            require_digit: true,
+            require_mixed_case: true,
+            require_symbol: false,
+        }
+    }
+}
+
+/// The first rule a password failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordError {
+    TooShort(usize),
+    MissingDigit,
+    MissingMixedCase,
+    MissingSymbol,
+}
+
+impl PasswordError {
+    /// Stable name of the failed rule, returned to clients next to the message
+    pub fn rule(&self) -> &'static str {
+        match self {
+            PasswordError::TooShort(_) => "min_length",
+            PasswordError::MissingDigit => "require_digit",
+            PasswordError::MissingMixedCase => "require_mixed_case",
+            PasswordError::MissingSymbol => "require_symbol",
+        }
+    }
+}
+
+impl fmt::Display for PasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordError::TooShort(min) => write!(f, "Password must be at least {} characters", min),
+            PasswordError::MissingDigit => f.write_str("Password must contain at least one digit"),
+            PasswordError::MissingMixedCase => {
+                f.write_str("Password must contain both uppercase and lowercase letters")
+            }
+            PasswordError::MissingSymbol => {
+                f.write_str("Password must contain at least one symbol (e.g. ! # $ %)")
+            }
+        }
+    }
+}
+
+/// Check a password against the policy, the 8 character floor applies even if configured lower
+pub fn validate_password(password: &str, policy: &PasswordPolicy) -> Result<(), PasswordError> {
+    let min_length = policy.min_length.max(MIN_PASSWORD_LENGTH);
+    if password.chars().count() < min_length {
+        return Err(PasswordError::TooShort(min_length));
+    }
+
+    if policy.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+        return Err(PasswordError::MissingDigit);
+    }
+
+    let has_upper = password.chars().any(char::is_uppercase);
+    let has_lower = password.chars().any(char::is_lowercase);
+    if policy.require_mixed_case && !(has_upper && has_lower) {
+        return Err(PasswordError::MissingMixedCase);
+    }
+
+    if policy.require_symbol && !password.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace()) {
+        return Err(PasswordError::MissingSymbol);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weak_passwords() {
+        let policy = PasswordPolicy::default();
+
+        assert_eq!(validate_password("Ab1", &policy), Err(PasswordError::TooShort(8)));
+        assert_eq!(validate_password("Password", &policy), Err(PasswordError::MissingDigit));
+        assert_eq!(validate_password("password1", &policy), Err(PasswordError::MissingMixedCase));
+        assert_eq!(validate_password("PASSWORD1", &policy), Err(PasswordError::MissingMixedCase));
+
+        let strict = PasswordPolicy { require_symbol: true, ..PasswordPolicy::default() };
+        assert_eq!(validate_password("Password1", &strict), Err(PasswordError::MissingSymbol));
+    }
+
+    #[test]
+    fn test_strong_passwords() {
+        let policy = PasswordPolicy::default();
+        assert!(validate_password("Password1", &policy).is_ok());
+        assert!(validate_password("correct Horse battery 9", &policy).is_ok());
+
+        let strict = PasswordPolicy { require_symbol: true, min_length: 12, ..PasswordPolicy::default() };
+        assert!(validate_password("Password1!xy", &strict).is_ok());
+        assert_eq!(validate_password("Password1!x", &strict), Err(PasswordError::TooShort(12)));
+    }
+
+    #[test]
+    fn test_minimum_length_floor() {
+        // A lower configured length never goes below 8 characters
+        let lax = PasswordPolicy {
+            min_length: 4,
+            require_digit: false,
+            require_mixed_case: false,
+            require_symbol: false,
+        };
+        assert_eq!(validate_password("abcdefg", &lax), Err(PasswordError::TooShort(8)));
+        assert!(validate_password("abcdefgh", &lax).is_ok());
+
+        assert_eq!(PasswordError::TooShort(8).rule(), "min_length");
+        assert_eq!(PasswordError::MissingMixedCase.rule(), "require_mixed_case");
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+/// Length of the sliding window SDK evaluate limits are counted over
+pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Per-project limit on SDK evaluate requests, counted over a sliding window
+/// Counts live in process memory, so with several instances behind a load balancer
+/// each one enforces the limit on its own share of the traffic
+#[derive(Debug)]
+pub struct RateLimiter {
+    window: Duration,
+    default_limit: u32,
+    requests: Mutex<HashMap<Uuid, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    /// `default_limit` applies to projects without their own limit, 0 means unlimited
+    pub fn new(window: Duration, default_limit: u32) -> Self {
+        Self {
+            window,
+            default_limit,
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Requests allowed per window: the project's own limit if set, else the default
+    /// None when unlimited
+    pub fn effective_limit(&self, project_limit: Option<i32>) -> Option<u32> {
+        let limit = project_limit.map_or(self.default_limit, |limit| limit.max(0) as u32);
+        (limit > 0).then_some(limit)
+    }
+
+    /// Count a request for the project at `now`
+    /// Returns how long to wait before retrying when the window is already full
+    pub fn check(&self, project_id: Uuid, project_limit: Option<i32>, now: Instant) -> Result<(), Duration> {
+        let Some(limit) = self.effective_limit(project_limit) else {
+            return Ok(());
+        };
+
+        let mut requests = self.requests.lock().unwrap();
+        let timestamps = requests.entry(project_id).or_default();
+        while timestamps
+            .front()
+            .is_some_and(|t| now.saturating_duration_since(*t) >= self.window)
+        {
+            timestamps.pop_front();
+        }
+
+        if timestamps.len() >= limit as usize {
+            // The oldest request leaving the window frees the next slot
+            let oldest = *timestamps.front().expect("limit is at least 1");
+            return Err(self.window.saturating_sub(now.saturating_duration_since(oldest)));
+        }
+
+        timestamps.push_back(now);
+        Ok(())
+    }
+}
+
+/// Whole seconds for a Retry-After header, rounded up and at least 1
+pub fn retry_after_secs(wait: Duration) -> u64 {
+    wait.as_secs() + u64::from(wait.subsec_nanos() > 0 || wait.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_is_limited_per_project() {
+        let limiter = RateLimiter::new(RATE_LIMIT_WINDOW, 0);
+        let (project, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let start = Instant::now();
+
+        // A burst of 10 within one second only gets 5 through
+        let allowed = (0..10)
+            .filter(|i| limiter.check(project, Some(5), start + Duration::from_millis(i * 100)).is_ok())
+            .count();
+        assert_eq!(allowed, 5);
+
+        // The oldest request leaves the window after a minute, so retry in ~59s
+        let wait = limiter.check(project, Some(5), start + Duration::from_secs(1)).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(59));
+        assert_eq!(retry_after_secs(wait), 59);
+
+        // Other projects have their own window
+        assert!(limiter.check(other, Some(5), start + Duration::from_secs(1)).is_ok());
+
+        // Slots free up one by one as old requests slide out
+        assert!(limiter.check(project, Some(5), start + Duration::from_secs(60)).is_ok());
+        assert!(limiter.check(project, Some(5), start + Duration::from_secs(60)).is_err());
+        assert!(limiter.check(project, Some(5), start + Duration::from_millis(60_100)).is_ok());
+    }
+
+    #[test]
+    fn test_effective_limit() {
+        let unlimited = RateLimiter::new(RATE_LIMIT_WINDOW, 0);
+        assert_eq!(unlimited.effective_limit(None), None);
+        assert_eq!(unlimited.effective_limit(Some(100)), Some(100));
+
+        // A project limit of 0 opts out of the server default
+        let limited = RateLimiter::new(RATE_LIMIT_WINDOW, 600);
+        assert_eq!(limited.effective_limit(None), Some(600));
+        assert_eq!(limited.effective_limit(Some(50)), Some(50));
+        assert_eq!(limited.effective_limit(Some(0)), None);
+
+        let project = Uuid::new_v4();
+        let now = Instant::now();
+        assert!((0..1_000).all(|_| unlimited.check(project, None, now).is_ok()));
+    }
+
+    #[test]
+    fn test_retry_after_secs() {
+        assert_eq!(retry_after_secs(Duration::from_millis(1)), 1);
+        assert_eq!(retry_after_secs(Duration::from_millis(1_500)), 2);
+        assert_eq!(retry_after_secs(Duration::from_secs(30)), 30);
+        assert_eq!(retry_after_secs(Duration::ZERO), 1);
+    }
+}
@@ -0,0 +1,22 @@
+pub mod routes;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// MODELS
+
+/// A project as listed to operators, with its owner and size
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct AdminProjectResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub owner_id: Uuid,
+    pub owner_email: String,
+    pub environment_count: i64,
+    /// Flags across all environments, each environment's copy counted separately
+    pub flag_count: i64,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,40 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+
+use crate::routes::error::ApiError;
+use crate::state::AppState;
+use super::AdminProjectResponse;
+
+/// List every project across all users, newest first, for operators
+/// Only users with is_admin get here, see `require_admin`
+#[utoipa::path(
+    get,
+    path = "/api/admin/projects",
+    tag = "admin",
+    responses(
+        (status = 200, description = "All projects with their owner and counts", body = Vec<AdminProjectResponse>),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "The user is not an admin, or the request used an API token"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_projects(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let projects = sqlx::query_as::<_, AdminProjectResponse>(
+        r#"
+        SELECT p.id, p.name, p.description, p.created_by AS owner_id, u.email AS owner_email,
+            (SELECT COUNT(*) FROM environments e WHERE e.project_id = p.id) AS environment_count,
+            (SELECT COUNT(*) FROM feature_flags f WHERE f.project_id = p.id) AS flag_count,
+            p.created_at
+        FROM projects p
+        JOIN users u ON u.id = p.created_by
+        ORDER BY p.created_at DESC, p.id
+        "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch projects: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch projects".to_string())
+    })?;
+
+    Ok(Json(projects))
+}
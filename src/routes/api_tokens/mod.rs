@@ -0,0 +1,210 @@
+pub mod routes;
+
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Prefix that tells API tokens apart from user JWTs in the Authorization header
+pub const API_TOKEN_PREFIX: &str = "fftok_";
+
+// MODELS
+
+/// What an API token may do within its project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiTokenPermission {
+    /// List and read flags and their rules
+    #[serde(rename = "flags:read")]
+    FlagsRead,
+    /// Everything flags:read allows, plus creating, changing, toggling and deleting flags and rules
+    #[serde(rename = "flags:write")]
+    FlagsWrite,
+}
+
+impl ApiTokenPermission {
+    /// Value stored in the `project_api_tokens.permissions` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiTokenPermission::FlagsRead => "flags:read",
+            ApiTokenPermission::FlagsWrite => "flags:write",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub token_hash: String,
+    pub token_prefix: String,
+    pub label: Option<String>,
+    pub permissions: Vec<String>,
+    pub created_by: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateApiTokenRequest {
+    pub label: Option<String>,
+    pub permissions: Vec<ApiTokenPermission>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiTokenResponse {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    // Plaintext token, only returned once when the token is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    pub token_prefix: String,
+    pub label: Option<String>,
+    pub permissions: Vec<String>,
+    pub created_by: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiToken> for ApiTokenResponse {
+    fn from(t: ApiToken) -> Self {
+        ApiTokenResponse {
+            id: t.id,
+            project_id: t.project_id,
+            token: None,
+            token_prefix: t.token_prefix,
+            label: t.label,
+            permissions: t.permissions,
+            created_by: t.created_by,
+            expires_at: t.expires_at,
+            revoked_at: t.revoked_at,
+            last_used_at: t.last_used_at,
+            created_at: t.created_at,
+        }
+    }
+}
+
+// HELPER FUNCTIONS
+
+/// Validate the requested permissions and return them deduplicated, in storage form
+pub fn normalize_permissions(permissions: &[ApiTokenPermission]) -> Result<Vec<String>, String> {
+    if permissions.is_empty() {
+        return Err("permissions must not be empty".to_string());
+    }
+
+    let mut normalized: Vec<String> = permissions.iter().map(|p| p.as_str().to_string()).collect();
+    normalized.sort();
+    normalized.dedup();
+    Ok(normalized)
+}
+
+/// Whether a token's stored permissions cover `needed`; flags:write includes flags:read
+pub fn token_allows(permissions: &[String], needed: ApiTokenPermission) -> bool {
+    permissions.iter().any(|p| {
+        p == needed.as_str()
+            || (needed == ApiTokenPermission::FlagsRead && p == ApiTokenPermission::FlagsWrite.as_str())
+    })
+}
+
+/// The project and permission an API token needs for a management API request
+/// None for requests API tokens can never make, e.g. managing projects, tokens or SDK keys
+/// Reads are GET and HEAD; every other method needs flags:write
+pub fn required_permission(method: &Method, path: &str) -> Option<(Uuid, ApiTokenPermission)> {
+    let mut segments = path.trim_matches('/').split('/').peekable();
+    // The path may or may not still carry the /api prefix depending on where the middleware runs
+    segments.next_if_eq(&"api");
+    if segments.next() != Some("projects") {
+        return None;
+    }
+    let project_id = Uuid::parse_str(segments.next()?).ok()?;
+    let rest: Vec<&str> = segments.collect();
+
+    let is_read = matches!(*method, Method::GET | Method::HEAD);
+    let permission = match rest.as_slice() {
+        // Cross-environment flag lookups are read-only
+        ["flags"] | ["flags", "by-key", _] | ["flags", "by-key", _, "rules"] if is_read => ApiTokenPermission::FlagsRead,
+        ["environments", _, "flags", ..] if is_read => ApiTokenPermission::FlagsRead,
+        ["environments", _, "flags", ..] => ApiTokenPermission::FlagsWrite,
+        _ => return None,
+    };
+
+    Some((project_id, permission))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_can_toggle_flag_in_its_project() {
+        let project = Uuid::new_v4();
+        let toggle = format!("/api/projects/{}/environments/{}/flags/{}/toggle", project, Uuid::new_v4(), Uuid::new_v4());
+
+        let (token_project, needed) = required_permission(&Method::POST, &toggle).unwrap();
+        assert_eq!(token_project, project);
+        assert_eq!(needed, ApiTokenPermission::FlagsWrite);
+
+        let write = normalize_permissions(&[ApiTokenPermission::FlagsWrite]).unwrap();
+        let read = normalize_permissions(&[ApiTokenPermission::FlagsRead]).unwrap();
+        assert!(token_allows(&write, needed));
+        assert!(!token_allows(&read, needed));
+
+        // The prefix may already be stripped by the time the middleware sees the path
+        let stripped = toggle.strip_prefix("/api").unwrap();
+        assert_eq!(required_permission(&Method::POST, stripped), Some((project, needed)));
+    }
+
+    #[test]
+    fn test_required_permission() {
+        let project = Uuid::new_v4();
+        let flags = format!("/projects/{}/environments/{}/flags", project, Uuid::new_v4());
+
+        assert_eq!(required_permission(&Method::GET, &flags), Some((project, ApiTokenPermission::FlagsRead)));
+        assert_eq!(required_permission(&Method::POST, &flags), Some((project, ApiTokenPermission::FlagsWrite)));
+        let rule = format!("{}/{}/rules/{}", flags, Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(required_permission(&Method::PUT, &rule), Some((project, ApiTokenPermission::FlagsWrite)));
+        let by_key = format!("/projects/{}/flags/by-key/dark_mode", project);
+        assert_eq!(required_permission(&Method::GET, &by_key), Some((project, ApiTokenPermission::FlagsRead)));
+        let by_key_rules = format!("{}/rules", by_key);
+        assert_eq!(required_permission(&Method::GET, &by_key_rules), Some((project, ApiTokenPermission::FlagsRead)));
+
+        // Tokens never manage projects, environments, tokens or keys, nor approve changes or use admin endpoints
+        for path in [
+            format!("/projects/{}", project),
+            format!("/projects/{}/tokens", project),
+            format!("/projects/{}/approvers", project),
+            format!("/projects/{}/pending-changes/{}/approve", project, Uuid::new_v4()),
+            format!("/projects/{}/sdk-keys", project),
+            format!("/projects/{}/environments", project),
+            "/projects".to_string(),
+            "/me".to_string(),
+            "/admin/projects".to_string(),
+            "/api/admin/projects".to_string(),
+            "/projects/not-a-uuid/environments/x/flags".to_string(),
+        ] {
+            assert_eq!(required_permission(&Method::GET, &path), None, "{}", path);
+            assert_eq!(required_permission(&Method::DELETE, &path), None, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_token_allows() {
+        let read_write = normalize_permissions(&[ApiTokenPermission::FlagsWrite, ApiTokenPermission::FlagsRead]).unwrap();
+        assert_eq!(read_write, vec!["flags:read", "flags:write"]);
+
+        // flags:write includes flags:read
+        let write = vec!["flags:write".to_string()];
+        assert!(token_allows(&write, ApiTokenPermission::FlagsRead));
+        assert!(!token_allows(&[], ApiTokenPermission::FlagsRead));
+
+        assert!(normalize_permissions(&[]).is_err());
+        let parsed: Vec<ApiTokenPermission> = serde_json::from_str(r#"["flags:write", "flags:write"]"#).unwrap();
+        assert_eq!(normalize_permissions(&parsed).unwrap(), vec!["flags:write"]);
+        assert!(serde_json::from_str::<Vec<ApiTokenPermission>>(r#"["projects:write"]"#).is_err());
+    }
+}
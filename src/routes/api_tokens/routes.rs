@@ -0,0 +1,127 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::{normalize_permissions, ApiToken, ApiTokenResponse, CreateApiTokenRequest};
+use crate::routes::error::{ApiError, JsonBody};
+use crate::routes::projects::{generate_api_token, hash_sdk_key, sdk_key_prefix};
+use crate::routes::sdk_keys::{validate_expires_at, validate_label};
+use crate::routes::ownership::VerifiedProject;
+use crate::state::AppState;
+
+// HANDLERS
+
+/// Create an API token for a project
+/// The plaintext token is only returned in this response; only its hash is stored
+pub async fn create(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, user_id }: VerifiedProject,
+    JsonBody(payload): JsonBody<CreateApiTokenRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let permissions = normalize_permissions(&payload.permissions).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if let Some(expires_at) = payload.expires_at {
+        validate_expires_at(expires_at, Utc::now()).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    if let Some(ref label) = payload.label {
+        validate_label(label).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+
+    let token = generate_api_token();
+
+    let api_token = sqlx::query_as::<_, ApiToken>(
+        r#"
+        INSERT INTO project_api_tokens (project_id, token_hash, token_prefix, label, permissions, created_by, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, project_id, token_hash, token_prefix, label, permissions, created_by, expires_at, revoked_at, last_used_at, created_at
+        "#,
+    )
+    .bind(project_id)
+    .bind(hash_sdk_key(&token))
+    .bind(sdk_key_prefix(&token))
+    .bind(&payload.label)
+    .bind(&permissions)
+    .bind(user_id)
+    .bind(payload.expires_at)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to create API token: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to create API token".to_string(),
+        )
+    })?;
+
+    let response = ApiTokenResponse {
+        token: Some(token),
+        ..ApiTokenResponse::from(api_token)
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// List all API tokens for a project, including revoked and expired ones
+pub async fn list(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+) -> Result<impl IntoResponse, ApiError> {
+    let tokens = sqlx::query_as::<_, ApiToken>(
+        r#"
+        SELECT id, project_id, token_hash, token_prefix, label, permissions, created_by, expires_at, revoked_at, last_used_at, created_at
+        FROM project_api_tokens
+        WHERE project_id = $1
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch API tokens: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch API tokens".to_string(),
+        )
+    })?;
+
+    let response: Vec<ApiTokenResponse> = tokens.into_iter().map(ApiTokenResponse::from).collect();
+
+    Ok(Json(response))
+}
+
+/// Revoke an API token; requests using it are rejected immediately
+pub async fn revoke(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    Path((_, token_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let result = sqlx::query(
+        r#"
+        UPDATE project_api_tokens
+        SET revoked_at = NOW()
+        WHERE id = $1 AND project_id = $2 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(token_id)
+    .bind(project_id)
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to revoke API token: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to revoke API token".to_string(),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "API token not found".to_string()).into());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
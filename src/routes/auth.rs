@@ -1,10 +1,15 @@
+use crate::evaluation::normalize_email;
+use crate::password::{validate_password, PasswordError, PasswordPolicy};
+use crate::routes::error::{json_error, JsonBody};
+use crate::routes::request_id;
+use crate::routes::middleware_auth::JwtUser;
 use crate::state::AppState;
 use argon2::password_hash::{PasswordHash, SaltString};
 use argon2::{Argon2, PasswordHasher, PasswordVerifier};
 use axum::{
     extract::{Json, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
 use chrono::Duration;
 use chrono::Utc;
@@ -26,6 +31,28 @@ pub struct RegisterResponse {
     pub email: String,
 }
 
+#[derive(Serialize)]
+pub struct PasswordErrorResponse {
+    pub status: u16,
+    pub error: String,
+    pub code: &'static str,
+    pub rule: &'static str,  // Name of the failed rule, e.g. "require_digit"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// 400 naming the password rule that failed
+fn password_error(error: String, rule: &'static str) -> Response {
+    let body = PasswordErrorResponse {
+        status: StatusCode::BAD_REQUEST.as_u16(),
+        error,
+        code: "bad_request",
+        rule,
+        request_id: request_id::current(),
+    };
+    (StatusCode::BAD_REQUEST, Json(body)).into_response()
+}
+
 #[derive(Deserialize)]
 pub struct LoginRequest {
     pub email: String,
@@ -37,6 +64,22 @@ pub struct LoginResponse {
     pub token: String,
 }
 
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+    #[serde(default)]
+    pub revoke_sessions: bool,  // Also log out every existing login token, including the one making the request
+}
+
+// Why a password change was refused
+#[derive(Debug, PartialEq, Eq)]
+enum PasswordChangeError {
+    WrongCurrentPassword,
+    SamePassword,
+    Weak(PasswordError),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
     sub: String,
@@ -46,10 +89,17 @@ struct Claims {
 
 pub async fn register(
     State(state): State<AppState>,
-    Json(payload): Json<RegistrationRequest>,
+    JsonBody(payload): JsonBody<RegistrationRequest>,
 ) -> impl IntoResponse {
-    if payload.email.trim().is_empty() || payload.password.len() < 8 {
-        return (StatusCode::BAD_REQUEST, "invalid payload").into_response();
+    // Emails are case-insensitive, User@x.com and user@x.com are the same account
+    let email = normalize_email(&payload.email);
+    if email.is_empty() {
+        return json_error(StatusCode::BAD_REQUEST, "invalid payload");
+    }
+
+    // Tell the client which strength rule failed so it can guide the user
+    if let Err(e) = validate_password(&payload.password, &state.password_policy) {
+        return password_error(e.to_string(), e.rule());
     }
 
     let salt = SaltString::generate(&mut OsRng);
@@ -67,7 +117,7 @@ pub async fn register(
         VALUES ($1,$2,$3)
         "#,
         user_id,
-        payload.email,
+        email,
         password_hash
     )
     .execute(&state.db)
@@ -78,36 +128,49 @@ pub async fn register(
             StatusCode::CREATED,
             Json(RegisterResponse {
                 id: user_id,
-                email: payload.email,
+                email,
             }),
         )
             .into_response(),
         Err(e) => {
-            eprintln!("DB insert error: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "could not create user").into_response()
+            let (status, message) = register_error(&e);
+            json_error(status, message)
+        }
+    }
+}
+
+/// Map a failed user insert to a response, a unique violation on the email is a 409
+fn register_error(e: &sqlx::Error) -> (StatusCode, &'static str) {
+    if let sqlx::Error::Database(db_error) = e {
+        if db_error.code() == Some(std::borrow::Cow::Borrowed("23505")) {
+            return (StatusCode::CONFLICT, "email already registered");
         }
     }
+
+    eprintln!("DB insert error: {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "could not create user")
 }
 
 pub async fn login(
     State(state): State<AppState>,
-    Json(payload): Json<LoginRequest>,
+    JsonBody(payload): JsonBody<LoginRequest>,
 ) -> impl IntoResponse {
     let row = sqlx::query!(
         r#"
         SELECT id, password_hash FROM users WHERE email = $1
         "#,
-        payload.email
+        normalize_email(&payload.email)
     )
     .fetch_optional(&state.db)
     .await;
 
     let row = match row {
         Ok(Some(r)) => r,
-        Ok(None) => return (StatusCode::UNAUTHORIZED, "Invalid Credential").into_response(),
+        // Same response as a wrong password, so login doesn't reveal which emails have accounts
+        Ok(None) => return json_error(StatusCode::UNAUTHORIZED, "Invalid credentials"),
         Err(e) => {
             eprintln!("DB Error: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "db error").into_response();
+            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "db error");
         }
     };
 
@@ -118,7 +181,7 @@ pub async fn login(
         .is_ok();
 
     if !verify {
-        return (StatusCode::UNAUTHORIZED, "Invalid credentials").into_response();
+        return json_error(StatusCode::UNAUTHORIZED, "Invalid credentials");
     }
 
     // create JWT
@@ -143,6 +206,208 @@ pub async fn login(
 
     match token {
         Ok(t) => (StatusCode::OK, Json(LoginResponse { token: t })).into_response(),
-        Err(err_resp) => err_resp.into_response(),
+        Err((status, message)) => json_error(status, message),
+    }
+}
+
+pub async fn change_password(
+    State(state): State<AppState>,
+    JwtUser(user_id): JwtUser,
+    JsonBody(payload): JsonBody<ChangePasswordRequest>,
+) -> impl IntoResponse {
+    let stored_hash = sqlx::query_scalar::<_, String>("SELECT password_hash FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await;
+
+    let stored_hash = match stored_hash {
+        Ok(Some(hash)) => hash,
+        Ok(None) => return json_error(StatusCode::UNAUTHORIZED, "invalid token"),
+        Err(e) => {
+            eprintln!("DB Error: {}", e);
+            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "db error");
+        }
+    };
+
+    match check_password_change(&stored_hash, &payload, &state.password_policy) {
+        Ok(()) => {}
+        Err(PasswordChangeError::WrongCurrentPassword) => {
+            return json_error(StatusCode::FORBIDDEN, "Current password is incorrect");
+        }
+        Err(PasswordChangeError::SamePassword) => {
+            return password_error("New password must differ from the current one".to_string(), "not_reused");
+        }
+        Err(PasswordChangeError::Weak(e)) => {
+            return password_error(e.to_string(), e.rule());
+        }
+    }
+
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(payload.new_password.as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+
+    let res = sqlx::query(
+        r#"
+        UPDATE users
+        SET password_hash = $2,
+            sessions_revoked_at = CASE WHEN $3 THEN NOW() ELSE sessions_revoked_at END
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(&password_hash)
+    .bind(payload.revoke_sessions)
+    .execute(&state.db)
+    .await;
+
+    match res {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => {
+            eprintln!("DB update error: {}", e);
+            json_error(StatusCode::INTERNAL_SERVER_ERROR, "could not change password")
+        }
+    }
+}
+
+/// Check a password change: the current password must verify against the stored hash,
+/// and the new one must differ from it and meet the strength rules
+/// The current password is checked first, so a stolen token can't probe the policy
+fn check_password_change(
+    stored_hash: &str,
+    request: &ChangePasswordRequest,
+    policy: &PasswordPolicy,
+) -> Result<(), PasswordChangeError> {
+    let argon = Argon2::default();
+    let verifies = |password: &str| {
+        PasswordHash::new(stored_hash)
+            .map(|hash| argon.verify_password(password.as_bytes(), &hash).is_ok())
+            .unwrap_or(false)
+    };
+
+    if !verifies(&request.current_password) {
+        return Err(PasswordChangeError::WrongCurrentPassword);
+    }
+    if request.new_password == request.current_password {
+        return Err(PasswordChangeError::SamePassword);
+    }
+    validate_password(&request.new_password, policy).map_err(PasswordChangeError::Weak)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emails_differing_by_case_collide() {
+        // Both registrations store the same address, so the unique index rejects the second
+        assert_eq!(normalize_email("User@X.com"), normalize_email("user@x.com"));
+        assert_eq!(normalize_email("  user@x.com "), "user@x.com");
+        assert_ne!(normalize_email("user@x.com"), normalize_email("user2@x.com"));
+    }
+
+    #[tokio::test]
+    async fn test_password_error_body() {
+        let response = password_error("Password must contain at least one digit".to_string(), "require_digit");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["code"], "bad_request");
+        assert_eq!(body["rule"], "require_digit");
+        assert_eq!(body["error"], "Password must contain at least one digit");
+    }
+
+    // What Postgres reports when the second registration hits the unique email index
+    #[derive(Debug)]
+    struct UniqueViolation;
+
+    impl std::fmt::Display for UniqueViolation {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("duplicate key value violates unique constraint \"users_email_key\"")
+        }
+    }
+
+    impl std::error::Error for UniqueViolation {}
+
+    impl sqlx::error::DatabaseError for UniqueViolation {
+        fn message(&self) -> &str {
+            "duplicate key value violates unique constraint \"users_email_key\""
+        }
+
+        fn code(&self) -> Option<std::borrow::Cow<'_, str>> {
+            Some(std::borrow::Cow::Borrowed("23505"))
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> sqlx::error::ErrorKind {
+            sqlx::error::ErrorKind::UniqueViolation
+        }
+    }
+
+    fn change(current: &str, new: &str) -> ChangePasswordRequest {
+        ChangePasswordRequest {
+            current_password: current.to_string(),
+            new_password: new.to_string(),
+            revoke_sessions: false,
+        }
+    }
+
+    #[test]
+    fn test_check_password_change() {
+        let salt = SaltString::generate(&mut OsRng);
+        let stored = Argon2::default().hash_password(b"Current1pass", &salt).unwrap().to_string();
+        let policy = PasswordPolicy::default();
+
+        assert_eq!(check_password_change(&stored, &change("Current1pass", "Better2pass"), &policy), Ok(()));
+
+        // Wrong current password, checked before anything about the new one
+        assert_eq!(
+            check_password_change(&stored, &change("current1pass", "Better2pass"), &policy),
+            Err(PasswordChangeError::WrongCurrentPassword)
+        );
+        assert_eq!(
+            check_password_change(&stored, &change("wrong", "weak"), &policy),
+            Err(PasswordChangeError::WrongCurrentPassword)
+        );
+
+        // Weak new password names the failed rule
+        assert_eq!(
+            check_password_change(&stored, &change("Current1pass", "short1A"), &policy),
+            Err(PasswordChangeError::Weak(PasswordError::TooShort(8)))
+        );
+        assert_eq!(
+            check_password_change(&stored, &change("Current1pass", "nodigitsHere"), &policy),
+            Err(PasswordChangeError::Weak(PasswordError::MissingDigit))
+        );
+
+        // Reusing the current password
+        assert_eq!(
+            check_password_change(&stored, &change("Current1pass", "Current1pass"), &policy),
+            Err(PasswordChangeError::SamePassword)
+        );
+    }
+
+    #[test]
+    fn test_register_duplicate_email_is_conflict() {
+        let duplicate = sqlx::Error::Database(Box::new(UniqueViolation));
+        assert_eq!(
+            register_error(&duplicate),
+            (StatusCode::CONFLICT, "email already registered")
+        );
+
+        // Anything else is still a server error
+        assert_eq!(register_error(&sqlx::Error::PoolTimedOut).0, StatusCode::INTERNAL_SERVER_ERROR);
     }
 }
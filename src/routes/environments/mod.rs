@@ -1,9 +1,12 @@
 pub mod routes;
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::routes::error::ValidationError;
+
 // MODELS
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -13,57 +16,97 @@ pub struct Environment {
     pub name: String,
     pub key: String,
     pub description: Option<String>,
+    pub requires_approval: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct CreateEnvironmentRequest {
     pub name: String,
-    pub key: String,
+    /// Derived from the name when omitted, e.g. "EU Staging" becomes "eu-staging"
+    pub key: Option<String>,
     pub description: Option<String>,
+    /// Flag updates wait for a project approver instead of going live, default false
+    pub requires_approval: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct UpdateEnvironmentRequest {
     pub name: Option<String>,
+    /// Renaming the key breaks SDK clients that still reference the old key
+    pub key: Option<String>,
     pub description: Option<String>,
+    pub requires_approval: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CloneEnvironmentRequest {
+    pub name: String,
+    pub key: String,
+    pub description: Option<String>,  // Defaults to the source environment's description
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct EnvironmentResponse {
     pub id: Uuid,
     pub project_id: Uuid,
     pub name: String,
     pub key: String,
     pub description: Option<String>,
+    pub requires_approval: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// A newly cloned environment with how much was copied into it
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CloneEnvironmentResponse {
+    #[serde(flatten)]
+    pub environment: EnvironmentResponse,
+    pub flag_count: u64,
+    pub rule_count: u64,
+}
+
 // HELPER FUNCTIONS
 
-/// Validate environment key format
-pub fn validate_environment_key(key: &str) -> Result<(), String> {
+/// Normalize environment key to the lowercase form stored in the database
+pub fn normalize_environment_key(key: &str) -> String {
+    key.to_ascii_lowercase()
+}
+
+/// Validate environment key format (mixed case is accepted and normalized before insert)
+pub fn validate_environment_key(key: &str) -> Result<(), ValidationError> {
     if key.is_empty() {
-        return Err("Environment key cannot be empty".to_string());
+        return Err(ValidationError::new("key", "required", "Environment key cannot be empty"));
     }
 
     if key.len() > 64 {
-        return Err("Environment key is too long (Max: 64 characters)".to_string());
+        return Err(ValidationError::new("key", "too_long", "Environment key is too long (Max: 64 characters)"));
     }
 
     if !key.chars().next().unwrap().is_ascii_alphabetic() {
-        return Err("Environment key must start with a letter".to_string());
+        return Err(ValidationError::new("key", "invalid_start", "Environment key must start with a letter"));
     }
 
-    if !key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-') {
-        return Err("Environment key can only contain lowercase letters, numbers, underscores, and hyphens".to_string());
+    if !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(ValidationError::new(
+            "key",
+            "invalid_char",
+            "Environment key can only contain letters, numbers, underscores, and hyphens",
+        ));
     }
 
     Ok(())
 }
 
+/// Conflict message for a key that is already taken (keys are only unique per project)
+pub fn duplicate_environment_key_message(key: &str) -> String {
+    format!("Environment key '{}' already exists in this project", key)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,11 +117,43 @@ mod tests {
         assert!(validate_environment_key("staging").is_ok());
         assert!(validate_environment_key("dev-test").is_ok());
         assert!(validate_environment_key("env_123").is_ok());
+        assert!(validate_environment_key("Production").is_ok()); // mixed case is normalized
 
         assert!(validate_environment_key("").is_err());
-        assert!(validate_environment_key("Production").is_err()); // uppercase
         assert!(validate_environment_key("_invalid").is_err());   // starts with underscore
         assert!(validate_environment_key("has space").is_err());  // space
         assert!(validate_environment_key("has.dot").is_err());    // dot
+
+        let err = validate_environment_key("has.dot").unwrap_err();
+        assert_eq!((err.field, err.code), ("key", "invalid_char"));
+    }
+
+    #[test]
+    fn test_normalize_environment_key() {
+        assert_eq!(normalize_environment_key("Production"), "production");
+        assert_eq!(normalize_environment_key("dev-test"), "dev-test");
+    }
+
+    #[test]
+    fn test_environment_key_rename() {
+        // Valid rename is normalized like on create
+        assert!(validate_environment_key("Prod-EU").is_ok());
+        assert_eq!(normalize_environment_key("Prod-EU"), "prod-eu");
+
+        // Invalid new key is rejected before touching the database
+        assert!(validate_environment_key("9production").is_err());
+        assert!(validate_environment_key("prod eu").is_err());
+
+        // Conflicting new key reports the normalized key that collided
+        assert_eq!(
+            duplicate_environment_key_message(&normalize_environment_key("STAGING")),
+            "Environment key 'staging' already exists in this project"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_environment_key_message() {
+        let message = duplicate_environment_key_message(&normalize_environment_key("Staging"));
+        assert_eq!(message, "Environment key 'staging' already exists in this project");
     }
 }
@@ -1,57 +1,62 @@
 use axum::{
-    extract::{Path, State},
+    extract::State,
     http::StatusCode,
     response::IntoResponse,
     Json,
 };
-use uuid::Uuid;
 
 use super::{
-    validate_environment_key, CreateEnvironmentRequest, Environment, EnvironmentResponse,
-    UpdateEnvironmentRequest,
+    duplicate_environment_key_message, normalize_environment_key, validate_environment_key, CloneEnvironmentRequest,
+    CloneEnvironmentResponse, CreateEnvironmentRequest, Environment, EnvironmentResponse, UpdateEnvironmentRequest,
 };
-use crate::routes::middleware_auth::JwtUser;
+use crate::routes::error::{ApiError, JsonBody, ValidationErrorBody};
+use crate::routes::slug::key_or_slug;
+use crate::routes::ownership::{VerifiedEnvironment, VerifiedProject};
 use crate::state::AppState;
 
 /// Create a new environment within a project
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments",
+    tag = "environments",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the original response when a create is retried with the same key"),
+    ),
+    request_body = CreateEnvironmentRequest,
+    responses(
+        (status = 201, description = "Environment created", body = EnvironmentResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Invalid environment key", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "Environment key already exists"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn create(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path(project_id): Path<Uuid>,
-    Json(payload): Json<CreateEnvironmentRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Validate environment key
-    validate_environment_key(&payload.key).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-
-    // Check if project exists and is owned by the user
-    let project_exists = sqlx::query_scalar::<_, bool>(
-        "SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1 AND created_by = $2)",
-    )
-    .bind(project_id)
-    .bind(user_id)
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        eprintln!("Failed to check project: {:?}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
-    })?;
-
-    if !project_exists {
-        return Err((StatusCode::NOT_FOUND, "Project not found".to_string()));
-    }
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    JsonBody(payload): JsonBody<CreateEnvironmentRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    // Validate environment key (derived from the name if omitted), then normalize it to lowercase before insert
+    let requested_key = key_or_slug(payload.key.as_deref(), &payload.name)?;
+    validate_environment_key(&requested_key)?;
+    let key = normalize_environment_key(&requested_key);
 
     // Create the environment
     let environment = match sqlx::query_as::<_, Environment>(
         r#"
-        INSERT INTO environments (project_id, name, key, description)
-        VALUES ($1, $2, $3, $4)
-        RETURNING id, project_id, name, key, description, created_at, updated_at
+        INSERT INTO environments (project_id, name, key, description, requires_approval)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, project_id, name, key, description, requires_approval, created_at, updated_at
         "#,
     )
     .bind(project_id)
     .bind(&payload.name)
-    .bind(&payload.key)
+    .bind(&key)
     .bind(&payload.description)
+    .bind(payload.requires_approval.unwrap_or(false))
     .fetch_one(&state.db)
     .await
     {
@@ -61,14 +66,16 @@ pub async fn create(
                 if db_error.code() == Some(std::borrow::Cow::Borrowed("23505")) {
                     return Err((
                         StatusCode::CONFLICT,
-                        "Environment key already exists".to_string(),
-                    ));
+                        duplicate_environment_key_message(&key),
+                    )
+                    .into());
                 }
             }
             return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Database error: {}", e),
-            ));
+            )
+            .into());
         }
     };
 
@@ -78,6 +85,7 @@ pub async fn create(
         name: environment.name,
         key: environment.key,
         description: environment.description,
+        requires_approval: environment.requires_approval,
         created_at: environment.created_at,
         updated_at: environment.updated_at,
     };
@@ -86,31 +94,27 @@ pub async fn create(
 }
 
 /// List all environments for a project
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/environments",
+    tag = "environments",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+    ),
+    responses(
+        (status = 200, description = "Environments of the project", body = Vec<EnvironmentResponse>),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn list(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path(project_id): Path<Uuid>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Check if project exists and is owned by the user
-    let project_exists = sqlx::query_scalar::<_, bool>(
-        "SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1 AND created_by = $2)",
-    )
-    .bind(project_id)
-    .bind(user_id)
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        eprintln!("Failed to check project: {:?}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
-    })?;
-
-    if !project_exists {
-        return Err((StatusCode::NOT_FOUND, "Project not found".to_string()));
-    }
-
+    VerifiedProject { project_id, .. }: VerifiedProject,
+) -> Result<impl IntoResponse, ApiError> {
     let environments = sqlx::query_as::<_, Environment>(
         r#"
-        SELECT id, project_id, name, key, description, created_at, updated_at
+        SELECT id, project_id, name, key, description, requires_approval, created_at, updated_at
         FROM environments
         WHERE project_id = $1
         ORDER BY created_at ASC
@@ -135,6 +139,7 @@ pub async fn list(
             name: e.name,
             key: e.key,
             description: e.description,
+            requires_approval: e.requires_approval,
             created_at: e.created_at,
             updated_at: e.updated_at,
         })
@@ -144,22 +149,32 @@ pub async fn list(
 }
 
 /// Get a single environment by ID
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/environments/{environment_id}",
+    tag = "environments",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+    ),
+    responses(
+        (status = 200, description = "Environment", body = EnvironmentResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Environment not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id)): Path<(Uuid, Uuid)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    VerifiedEnvironment { environment_id, .. }: VerifiedEnvironment,
+) -> Result<impl IntoResponse, ApiError> {
     let environment = sqlx::query_as::<_, Environment>(
         r#"
-        SELECT e.id, e.project_id, e.name, e.key, e.description, e.created_at, e.updated_at
-        FROM environments e
-        JOIN projects p ON e.project_id = p.id
-        WHERE e.id = $1 AND e.project_id = $2 AND p.created_by = $3
+        SELECT id, project_id, name, key, description, requires_approval, created_at, updated_at
+        FROM environments WHERE id = $1
         "#,
     )
     .bind(environment_id)
-    .bind(project_id)
-    .bind(user_id)
     .fetch_optional(&state.db)
     .await
     .map_err(|e| {
@@ -178,69 +193,110 @@ pub async fn get(
                 name: e.name,
                 key: e.key,
                 description: e.description,
+                requires_approval: e.requires_approval,
                 created_at: e.created_at,
                 updated_at: e.updated_at,
             };
             Ok(Json(response))
         }
-        None => Err((StatusCode::NOT_FOUND, "Environment not found".to_string())),
+        None => Err((StatusCode::NOT_FOUND, "Environment not found".to_string()).into()),
     }
 }
 
+/// Check an environment exists in a project of yours without fetching it, 200 or 404 with no body
+#[utoipa::path(
+    head,
+    path = "/api/projects/{project_id}/environments/{environment_id}",
+    tag = "environments",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+    ),
+    responses(
+        (status = 200, description = "Environment exists"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Environment not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn exists(_: VerifiedEnvironment) -> StatusCode {
+    StatusCode::OK
+}
+
 /// Update an environment
+#[utoipa::path(
+    put,
+    path = "/api/projects/{project_id}/environments/{environment_id}",
+    tag = "environments",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+    ),
+    request_body = UpdateEnvironmentRequest,
+    responses(
+        (status = 200, description = "Updated environment", body = EnvironmentResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Invalid environment key", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Environment not found"),
+        (status = 409, description = "Environment key already exists"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn update(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id)): Path<(Uuid, Uuid)>,
-    Json(payload): Json<UpdateEnvironmentRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Check if environment exists and user owns the project
-    let exists = sqlx::query_scalar::<_, bool>(
-        r#"
-        SELECT EXISTS(
-            SELECT 1 FROM environments e
-            JOIN projects p ON e.project_id = p.id
-            WHERE e.id = $1 AND e.project_id = $2 AND p.created_by = $3
-        )
-        "#,
-    )
-    .bind(environment_id)
-    .bind(project_id)
-    .bind(user_id)
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        eprintln!("Failed to check environment: {:?}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
-    })?;
-
-    if !exists {
-        return Err((StatusCode::NOT_FOUND, "Environment not found".to_string()));
-    }
+    VerifiedEnvironment { environment_id, .. }: VerifiedEnvironment,
+    JsonBody(payload): JsonBody<UpdateEnvironmentRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    // Validate the new key if one was provided, then normalize it like on create
+    let key = match payload.key.as_deref() {
+        Some(key) => {
+            validate_environment_key(key)?;
+            Some(normalize_environment_key(key))
+        }
+        None => None,
+    };
 
-    let environment = sqlx::query_as::<_, Environment>(
+    let environment = match sqlx::query_as::<_, Environment>(
         r#"
         UPDATE environments
         SET
             name = COALESCE($2, name),
-            description = COALESCE($3, description),
+            key = COALESCE($3, key),
+            description = COALESCE($4, description),
+            requires_approval = COALESCE($5, requires_approval),
             updated_at = NOW()
         WHERE id = $1
-        RETURNING id, project_id, name, key, description, created_at, updated_at
+        RETURNING id, project_id, name, key, description, requires_approval, created_at, updated_at
         "#,
     )
     .bind(environment_id)
     .bind(payload.name.as_deref())
+    .bind(key.as_deref())
     .bind(payload.description.as_deref())
+    .bind(payload.requires_approval)
     .fetch_one(&state.db)
     .await
-    .map_err(|e| {
-        eprintln!("Failed to update environment: {:?}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to update environment".to_string(),
-        )
-    })?;
+    {
+        Ok(env) => env,
+        Err(e) => {
+            if let Some(db_error) = e.as_database_error() {
+                if db_error.code() == Some(std::borrow::Cow::Borrowed("23505")) {
+                    return Err((
+                        StatusCode::CONFLICT,
+                        duplicate_environment_key_message(key.as_deref().unwrap_or_default()),
+                    )
+                    .into());
+                }
+            }
+            eprintln!("Failed to update environment: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to update environment".to_string(),
+            )
+            .into());
+        }
+    };
 
     let response = EnvironmentResponse {
         id: environment.id,
@@ -248,6 +304,7 @@ pub async fn update(
         name: environment.name,
         key: environment.key,
         description: environment.description,
+        requires_approval: environment.requires_approval,
         created_at: environment.created_at,
         updated_at: environment.updated_at,
     };
@@ -256,21 +313,27 @@ pub async fn update(
 }
 
 /// Delete an environment (this will cascade delete all flags in this environment)
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{project_id}/environments/{environment_id}",
+    tag = "environments",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+    ),
+    responses(
+        (status = 204, description = "Environment deleted"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Environment not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn delete(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id)): Path<(Uuid, Uuid)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let result = sqlx::query(
-        r#"
-        DELETE FROM environments
-        WHERE id = $1 AND project_id = $2
-        AND EXISTS(SELECT 1 FROM projects WHERE id = $2 AND created_by = $3)
-        "#,
-    )
+    VerifiedEnvironment { environment_id, .. }: VerifiedEnvironment,
+) -> Result<impl IntoResponse, ApiError> {
+    let result = sqlx::query("DELETE FROM environments WHERE id = $1")
     .bind(environment_id)
-    .bind(project_id)
-    .bind(user_id)
     .execute(&state.db)
     .await
     .map_err(|e| {
@@ -282,8 +345,154 @@ pub async fn delete(
     })?;
 
     if result.rows_affected() == 0 {
-        return Err((StatusCode::NOT_FOUND, "Environment not found".to_string()));
+        return Err((StatusCode::NOT_FOUND, "Environment not found".to_string()).into());
     }
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Create a new environment as a copy of an existing one, including every flag and rule
+/// Runs in one transaction so a failed clone leaves nothing behind
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/clone",
+    tag = "environments",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+    ),
+    request_body = CloneEnvironmentRequest,
+    responses(
+        (status = 201, description = "New environment with copies of all flags and rules", body = CloneEnvironmentResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Invalid environment key", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Environment not found"),
+        (status = 409, description = "Environment key already exists"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn clone(
+    State(state): State<AppState>,
+    VerifiedEnvironment { project_id, environment_id, user_id }: VerifiedEnvironment,
+    JsonBody(payload): JsonBody<CloneEnvironmentRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_environment_key(&payload.key)?;
+    let key = normalize_environment_key(&payload.key);
+
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    let (source_description, requires_approval) = sqlx::query_as::<_, (Option<String>, bool)>(
+        "SELECT description, requires_approval FROM environments WHERE id = $1",
+    )
+    .bind(environment_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch environment: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Environment not found".to_string()))?;
+
+    let environment = match sqlx::query_as::<_, Environment>(
+        r#"
+        INSERT INTO environments (project_id, name, key, description, requires_approval)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, project_id, name, key, description, requires_approval, created_at, updated_at
+        "#,
+    )
+    .bind(project_id)
+    .bind(&payload.name)
+    .bind(&key)
+    .bind(payload.description.or(source_description))
+    .bind(requires_approval)
+    .fetch_one(&mut *tx)
+    .await
+    {
+        Ok(env) => env,
+        Err(e) => {
+            if let Some(db_error) = e.as_database_error() {
+                if db_error.code() == Some(std::borrow::Cow::Borrowed("23505")) {
+                    return Err((
+                        StatusCode::CONFLICT,
+                        duplicate_environment_key_message(&key),
+                    )
+                    .into());
+                }
+            }
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: {}", e),
+            )
+            .into());
+        }
+    };
+
+    // Copy flags with their current state, versions start over in the new environment
+    let flag_count = sqlx::query(
+        r#"
+        INSERT INTO feature_flags (project_id, environment_id, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, hash_algorithm, bucketing_keys, lifecycle_state, created_by, updated_by)
+        SELECT project_id, $2, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, hash_algorithm, bucketing_keys, lifecycle_state, $3, $3
+        FROM feature_flags
+        WHERE environment_id = $1
+        "#,
+    )
+    .bind(environment_id)
+    .bind(environment.id)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to clone flags: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to clone environment".to_string())
+    })?
+    .rows_affected();
+
+    // Copy rules, matching each source flag to its copy by key (unique per environment)
+    let rule_count = sqlx::query(
+        r#"
+        INSERT INTO flag_rules (flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by)
+        SELECT target.id, r.rule_type, r.rule_value, r.rule_value_json, r.enabled, r.priority, r.negate, r.payload, r.rollout_percentage, $3, $3
+        FROM flag_rules r
+        JOIN feature_flags source ON r.flag_id = source.id
+        JOIN feature_flags target ON target.environment_id = $2 AND target.key = source.key
+        WHERE source.environment_id = $1
+        ORDER BY r.created_at
+        "#,
+    )
+    .bind(environment_id)
+    .bind(environment.id)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to clone rules: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to clone environment".to_string())
+    })?
+    .rows_affected();
+
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    let response = CloneEnvironmentResponse {
+        environment: EnvironmentResponse {
+            id: environment.id,
+            project_id: environment.project_id,
+            name: environment.name,
+            key: environment.key,
+            description: environment.description,
+            requires_approval: environment.requires_approval,
+            created_at: environment.created_at,
+            updated_at: environment.updated_at,
+        },
+        flag_count,
+        rule_count,
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
@@ -0,0 +1,358 @@
+use axum::{
+    extract::{rejection::JsonRejection, FromRequest, OptionalFromRequest, Request},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+use crate::rate_limit::retry_after_secs;
+use crate::routes::request_id;
+
+/// JSON error body shared by every error that isn't a field validation failure
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub status: u16,
+    pub error: String,
+    pub code: String,  // Machine-readable status, e.g. "not_found"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,  // Set by the request_id middleware, quote it when reporting a problem
+}
+
+/// Build a JSON error response with the given status
+pub fn json_error(status: StatusCode, message: impl Into<String>) -> Response {
+    json_error_with_code(status, status_code(status), message)
+}
+
+/// `json_error` with a code more specific than the status, e.g. "approval_required" for a 409
+fn json_error_with_code(status: StatusCode, code: impl Into<String>, message: impl Into<String>) -> Response {
+    let body = ErrorBody {
+        status: status.as_u16(),
+        error: message.into(),
+        code: code.into(),
+        request_id: request_id::current(),
+    };
+    (status, Json(body)).into_response()
+}
+
+/// Code of the 409 for writes the environment's approval workflow doesn't allow
+pub const APPROVAL_REQUIRED_CODE: &str = "approval_required";
+
+/// Snake-case name of a status, e.g. "not_found" for 404, "error" for codes without a name
+fn status_code(status: StatusCode) -> String {
+    status
+        .canonical_reason()
+        .map(|reason| reason.to_ascii_lowercase().replace([' ', '-'], "_").replace('\'', ""))
+        .unwrap_or_else(|| "error".to_string())
+}
+
+/// Fallback for unregistered paths
+pub async fn not_found() -> Response {
+    json_error(StatusCode::NOT_FOUND, "Route not found")
+}
+
+/// Fallback for registered paths called with an unsupported method
+pub async fn method_not_allowed() -> Response {
+    json_error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed")
+}
+
+/// Rewrite the plain-text 413 and 408 responses produced by the body limit and timeout
+/// layers (and axum's body extractors) into the JSON error format
+pub async fn json_limit_errors(req: Request, next: Next) -> Response {
+    let response = next.run(req).await;
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    if is_json {
+        return response;
+    }
+
+    match response.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => json_error(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large"),
+        StatusCode::REQUEST_TIMEOUT => json_error(StatusCode::REQUEST_TIMEOUT, "Request timed out"),
+        _ => response,
+    }
+}
+
+/// `Json` extractor whose rejections follow the API's status policy:
+/// - 400 when the body isn't a JSON document of the expected shape (syntax errors, missing fields, wrong types)
+/// - 422 is left to handlers, for well-formed requests that fail validation, see `ApiError::Validation`
+///
+/// axum answers 422 for bodies that parse but don't fit the target type, which would blur the two
+#[derive(Debug, Default)]
+pub struct JsonBody<T>(pub T);
+
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(JsonBody(value)),
+            Err(rejection) => Err(json_rejection(rejection)),
+        }
+    }
+}
+
+impl<T, S> OptionalFromRequest<S> for JsonBody<T>
+where
+    Json<T>: OptionalFromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        match <Json<T> as OptionalFromRequest<S>>::from_request(req, state).await {
+            Ok(value) => Ok(value.map(|Json(value)| JsonBody(value))),
+            Err(rejection) => Err(json_rejection(rejection)),
+        }
+    }
+}
+
+/// JSON error for a body the Json extractor couldn't read
+fn json_rejection(rejection: JsonRejection) -> Response {
+    let status = match rejection {
+        JsonRejection::JsonDataError(_) | JsonRejection::JsonSyntaxError(_) => StatusCode::BAD_REQUEST,
+        _ => rejection.status(),
+    };
+    json_error(status, rejection.body_text())
+}
+
+/// Challenge sent with 401s from the management API, JWTs and project API tokens
+pub const BEARER_CHALLENGE: &str = "Bearer";
+
+/// Challenge sent with 401s from the SDK API, keys go in the X-SDK-Key header
+pub const SDK_KEY_CHALLENGE: &str = "SDK-Key header=\"X-SDK-Key\"";
+
+/// Rejection from the auth middlewares, following one policy everywhere:
+/// - 401 when the credential is missing or not valid (unknown, malformed, expired or revoked),
+///   with a `WWW-Authenticate` challenge so clients know to (re)authenticate
+/// - 403 when the credential is valid but doesn't cover the request, retrying won't help
+///
+/// Handlers answer 404 rather than 403 for resources of other users, see `require_auth`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthRejection {
+    pub status: StatusCode,
+    pub challenge: Option<&'static str>,
+    pub message: &'static str,
+}
+
+impl AuthRejection {
+    /// 401, the request carries no credential or one that isn't valid
+    pub fn unauthorized(challenge: &'static str, message: &'static str) -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            challenge: Some(challenge),
+            message,
+        }
+    }
+
+    /// 403, the credential is valid but not allowed to make this request
+    pub fn forbidden(message: &'static str) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            challenge: None,
+            message,
+        }
+    }
+
+    /// 500, the credential couldn't be checked
+    pub fn internal(message: &'static str) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            challenge: None,
+            message,
+        }
+    }
+}
+
+impl IntoResponse for AuthRejection {
+    fn into_response(self) -> Response {
+        let mut response = json_error(self.status, self.message);
+        if let Some(challenge) = self.challenge {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static(challenge));
+        }
+        response
+    }
+}
+
+/// A request field that failed validation, with a stable code next to the human message
+/// so frontends can highlight the offending input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub field: &'static str,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(field: &'static str, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// JSON body of a 422 caused by a ValidationError
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValidationErrorBody {
+    pub status: u16,
+    pub error: String,
+    pub field: &'static str,  // Request field, e.g. "key"
+    pub code: &'static str,   // Machine-readable reason, e.g. "invalid_char"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// Error returned by the handlers, always rendered as JSON
+/// Validation failures are 422: the request was well-formed JSON but its values aren't acceptable
+/// Every other error is a status and message, helpers returning `(StatusCode, String)` convert with `?`
+#[derive(Debug)]
+pub enum ApiError {
+    Validation(ValidationError),
+    RateLimited(Duration),  // 429 with a Retry-After header, the wait until the next slot frees up
+    ApprovalRequired(String),  // 409 approval_required, the environment requires approval and the write can't be proposed
+    Status(StatusCode, String),
+}
+
+impl From<ValidationError> for ApiError {
+    fn from(error: ValidationError) -> Self {
+        ApiError::Validation(error)
+    }
+}
+
+impl From<(StatusCode, String)> for ApiError {
+    fn from((status, message): (StatusCode, String)) -> Self {
+        ApiError::Status(status, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            ApiError::Validation(error) => {
+                let body = ValidationErrorBody {
+                    status: StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+                    error: error.message,
+                    field: error.field,
+                    code: error.code,
+                    request_id: request_id::current(),
+                };
+                (StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response()
+            }
+            ApiError::RateLimited(wait) => {
+                let mut response = json_error(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded");
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, header::HeaderValue::from(retry_after_secs(wait)));
+                response
+            }
+            ApiError::ApprovalRequired(message) => json_error_with_code(StatusCode::CONFLICT, APPROVAL_REQUIRED_CODE, message),
+            ApiError::Status(status, message) => json_error(status, message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_validation_error_response() {
+        let error = ValidationError::new("key", "invalid_char", "Flag key can only contain letters");
+        let response = ApiError::from(error).into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["field"], "key");
+        assert_eq!(body["code"], "invalid_char");
+        assert_eq!(body["error"], "Flag key can only contain letters");
+        assert_eq!(body["status"], 422);
+
+        let response = ApiError::RateLimited(Duration::from_millis(2_500)).into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "3");
+
+        // Other errors use the same JSON body, with the status as the code
+        let response = ApiError::from((StatusCode::NOT_FOUND, "Flag not found".to_string())).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["status"], 404);
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["error"], "Flag not found");
+
+        // Refused writes in approval environments are a 409 clients can tell from other conflicts
+        let response = ApiError::ApprovalRequired("Renaming a flag can't be proposed".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["status"], 409);
+        assert_eq!(body["code"], "approval_required");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_body_is_bad_request() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Body {
+            #[allow(dead_code)]
+            key: String,
+        }
+
+        async fn extract(body: &'static str) -> Result<Option<JsonBody<Body>>, Response> {
+            let req = Request::builder()
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap();
+            <JsonBody<Body> as OptionalFromRequest<()>>::from_request(req, &()).await
+        }
+
+        // Well-formed bodies pass through, validating their values is up to the handler (422)
+        assert!(extract(r#"{"key": ""}"#).await.unwrap().is_some());
+
+        // Broken JSON, missing fields and wrong types are all 400 with a JSON body
+        for body in [r#"{"key": "#, r#"{}"#, r#"{"key": 5}"#] {
+            let response = extract(body).await.unwrap_err();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", body);
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(body["status"], 400);
+        }
+    }
+
+    #[test]
+    fn test_status_code() {
+        assert_eq!(status_code(StatusCode::CONFLICT), "conflict");
+        assert_eq!(status_code(StatusCode::INTERNAL_SERVER_ERROR), "internal_server_error");
+        assert_eq!(status_code(StatusCode::IM_A_TEAPOT), "im_a_teapot");
+        assert_eq!(status_code(StatusCode::from_u16(599).unwrap()), "error");
+    }
+
+    #[test]
+    fn test_auth_rejection_response() {
+        // Missing or invalid credentials tell the client how to authenticate
+        let response = AuthRejection::unauthorized(BEARER_CHALLENGE, "invalid token").into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        let response = AuthRejection::unauthorized(SDK_KEY_CHALLENGE, "Invalid SDK key").into_response();
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], r#"SDK-Key header="X-SDK-Key""#);
+
+        // A valid credential without access gets no challenge, authenticating again won't help
+        let response = AuthRejection::forbidden("API token does not allow this request").into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(response.headers().get(header::WWW_AUTHENTICATE).is_none());
+    }
+}
@@ -0,0 +1,172 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::evaluation::{AnonymousBehavior, BucketingKey, FlagData, FlagOverride, HashAlgorithm, RuleData};
+
+// Flag configurations as they were at a past time, read from the flag_history snapshots
+// Each snapshot holds the rows as to_jsonb wrote them, so only the columns evaluation needs are read
+
+#[derive(Debug, Deserialize)]
+struct FlagSnapshot {
+    key: String,
+    enabled: bool,
+    rollout_percentage: i32,
+    anonymous_behavior: String,
+    hash_algorithm: String,
+    bucketing_keys: Vec<String>,
+    temporarily_disabled_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleSnapshot {
+    id: Uuid,
+    rule_type: String,
+    rule_value: String,
+    #[serde(default)]
+    rule_value_json: Option<serde_json::Value>,  // Missing from snapshots taken before the column existed
+    enabled: bool,
+    priority: i32,
+    negate: bool,
+    payload: Option<serde_json::Value>,
+    rollout_percentage: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OverrideSnapshot {
+    user_identifier: String,
+    enabled: bool,
+    payload: Option<serde_json::Value>,
+}
+
+/// The flag and its rules from the latest snapshot recorded at or before `as_of`
+/// None when there is none: the flag didn't exist yet, or predates history recording
+pub async fn load_flag_at(
+    db: &PgPool,
+    flag_id: Uuid,
+    as_of: DateTime<Utc>,
+) -> Result<Option<(FlagData, Vec<RuleData>)>, sqlx::Error> {
+    let snapshot = sqlx::query_as::<_, (serde_json::Value, serde_json::Value, serde_json::Value)>(
+        r#"
+        SELECT flag, rules, overrides FROM flag_history
+        WHERE flag_id = $1 AND recorded_at <= $2
+        ORDER BY recorded_at DESC, id DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(flag_id)
+    .bind(as_of)
+    .fetch_optional(db)
+    .await?;
+
+    snapshot
+        .map(|(flag, rules, overrides)| {
+            snapshot_flag_data(flag, rules, overrides).map_err(|e| sqlx::Error::Decode(Box::new(e)))
+        })
+        .transpose()
+}
+
+/// Rebuild evaluation input from a snapshot's JSON, rules sorted by priority like the live queries
+fn snapshot_flag_data(
+    flag: serde_json::Value,
+    rules: serde_json::Value,
+    overrides: serde_json::Value,
+) -> Result<(FlagData, Vec<RuleData>), serde_json::Error> {
+    let flag: FlagSnapshot = serde_json::from_value(flag)?;
+    let mut rules: Vec<RuleSnapshot> = serde_json::from_value(rules)?;
+    let overrides: Vec<OverrideSnapshot> = serde_json::from_value(overrides)?;
+    rules.sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+
+    let flag_data = FlagData {
+        key: flag.key,
+        enabled: flag.enabled,
+        rollout_percentage: flag.rollout_percentage,
+        anonymous_behavior: AnonymousBehavior::from_db(&flag.anonymous_behavior),
+        hash_algorithm: HashAlgorithm::from_db(&flag.hash_algorithm),
+        bucketing_keys: BucketingKey::from_db(&flag.bucketing_keys),
+        temporarily_disabled_until: flag.temporarily_disabled_until,
+        overrides: overrides
+            .into_iter()
+            .map(|o| FlagOverride {
+                user_identifier: o.user_identifier,
+                enabled: o.enabled,
+                payload: o.payload,
+            })
+            .collect(),
+    };
+    let rules = rules
+        .into_iter()
+        .map(|rule| RuleData {
+            id: Some(rule.id),
+            rule_type: rule.rule_type,
+            rule_value: rule.rule_value,
+            rule_value_json: rule.rule_value_json,
+            enabled: rule.enabled,
+            priority: rule.priority,
+            negate: rule.negate,
+            payload: rule.payload,
+            rollout_percentage: rule.rollout_percentage,
+        })
+        .collect();
+
+    Ok((flag_data, rules))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::{evaluate_flag_at, UserContext};
+
+    #[test]
+    fn test_snapshot_flag_data() {
+        // Shaped like to_jsonb output, including columns evaluation doesn't read
+        let flag = serde_json::json!({
+            "id": "8f14e45f-ceea-467f-a0e6-7a3b1c2d9e10",
+            "key": "new_checkout",
+            "name": "New Checkout",
+            "enabled": true,
+            "rollout_percentage": 0,
+            "anonymous_behavior": "bucket",
+            "hash_algorithm": "sha256",
+            "bucketing_keys": [],
+            "temporarily_disabled_until": null,
+            "version": 3,
+            "updated_at": "2026-10-13T09:30:00.123456+00:00",
+        });
+        let rules = serde_json::json!([
+            {
+                "id": "c9f0f895-fb98-4b91-9d2b-6a3e5f1d2c77", "flag_id": "8f14e45f-ceea-467f-a0e6-7a3b1c2d9e10",
+                "rule_type": "email_domain", "rule_value": "@company.com", "enabled": true,
+                "priority": 1, "negate": false, "payload": null, "rollout_percentage": null,
+            },
+            {
+                "id": "45c48cce-2e2d-4fbd-8a1e-0f9c3b7d6e55", "flag_id": "8f14e45f-ceea-467f-a0e6-7a3b1c2d9e10",
+                "rule_type": "user_id", "rule_value": "vip", "enabled": true,
+                "priority": 10, "negate": false, "payload": {"variant": "b"}, "rollout_percentage": null,
+            },
+        ]);
+        let overrides = serde_json::json!([
+            { "flag_id": "8f14e45f-ceea-467f-a0e6-7a3b1c2d9e10", "user_identifier": "qa-user", "enabled": false, "payload": null },
+        ]);
+
+        let (flag_data, rules) = snapshot_flag_data(flag, rules, overrides).unwrap();
+        assert_eq!(flag_data.key, "new_checkout");
+        assert_eq!(rules.iter().map(|r| r.priority).collect::<Vec<_>>(), vec![10, 1]);
+
+        let context = |user_id: &str| UserContext {
+            user_id: Some(user_id.to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        let as_of = "2026-10-13T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let vip = evaluate_flag_at(&flag_data, &rules, &context("vip"), as_of);
+        assert!(vip.enabled);
+        assert_eq!(vip.payload, Some(serde_json::json!({"variant": "b"})));
+        assert!(!evaluate_flag_at(&flag_data, &rules, &context("qa-user"), as_of).enabled);
+
+        let malformed = snapshot_flag_data(serde_json::json!({"key": "x"}), serde_json::json!([]), serde_json::json!([]));
+        assert!(malformed.is_err());
+    }
+}
@@ -1,9 +1,16 @@
+pub mod history;
 pub mod routes;
 
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::evaluation::{AnonymousBehavior, BucketingKey, FlagData, FlagEvaluation, HashAlgorithm, RuleData, UserContext};
+use crate::routes::error::ValidationError;
+use crate::routes::rules::{validate_rule_list, CreateRuleRequest, MAX_BULK_RULES};
+
 // MODELS
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -16,28 +23,181 @@ pub struct FeatureFlag {
     pub description: Option<String>,
     pub enabled: bool,
     pub rollout_percentage: i32,
+    pub client_visible: bool,
+    pub anonymous_behavior: String,
+    pub hash_algorithm: String,
+    pub bucketing_keys: Vec<String>,  // Identifiers bucketed on in order, empty for the default order
+    pub temporarily_disabled_until: Option<DateTime<Utc>>,  // Paused until then, see the pause endpoint
+    pub lifecycle_state: String,  // draft, active or deprecated, see LifecycleState
+    pub version: i64,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct CreateFlagRequest {
     pub name: String,
-    pub key: String,
+    /// Derived from the name when omitted, e.g. "New Checkout" becomes "new-checkout"
+    pub key: Option<String>,
     pub description: Option<String>,
     pub enabled: Option<bool>,
     pub rollout_percentage: Option<i32>,
+    pub client_visible: Option<bool>,
+    pub anonymous_behavior: Option<AnonymousBehavior>,
+    /// Identifiers to bucket on in order, e.g. ["attribute:account_id", "user_id"]
+    pub bucketing_keys: Option<Vec<String>>,
+    /// "draft" keeps the new flag away from SDKs until it is promoted, defaults to "active"
+    pub lifecycle_state: Option<LifecycleState>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct UpdateFlagRequest {
     pub name: Option<String>,
     pub description: Option<String>,
     pub enabled: Option<bool>,
     pub rollout_percentage: Option<i32>,
+    pub client_visible: Option<bool>,
+    pub anonymous_behavior: Option<AnonymousBehavior>,
+    /// Switching re-buckets every user in the percentage rollout
+    pub hash_algorithm: Option<HashAlgorithm>,
+    /// Changing re-buckets users too, an empty list restores the default order
+    pub bucketing_keys: Option<Vec<String>>,
+    /// Reject the update with 409 if the stored version differs (alternative to an If-Match header)
+    pub expected_version: Option<i64>,
+    /// Why the flag is being changed, kept in the project's audit log
+    pub reason: Option<String>,
+}
+
+impl UpdateFlagRequest {
+    /// Names of the settings this update changes, for the audit log
+    pub fn changed_fields(&self) -> Vec<&'static str> {
+        [
+            ("name", self.name.is_some()),
+            ("description", self.description.is_some()),
+            ("enabled", self.enabled.is_some()),
+            ("rollout_percentage", self.rollout_percentage.is_some()),
+            ("client_visible", self.client_visible.is_some()),
+            ("anonymous_behavior", self.anonymous_behavior.is_some()),
+            ("hash_algorithm", self.hash_algorithm.is_some()),
+            ("bucketing_keys", self.bucketing_keys.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(field, changed)| changed.then_some(field))
+        .collect()
+    }
+}
+
+// Body of PUT: every mutable setting, so the stored flag ends up exactly as sent
+// Omitting `description` clears it, use PATCH (UpdateFlagRequest) to change single fields
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReplaceFlagRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub rollout_percentage: i32,
+    pub client_visible: bool,
+    pub anonymous_behavior: AnonymousBehavior,
+    pub hash_algorithm: HashAlgorithm,
+    /// An empty list buckets in the default order
+    pub bucketing_keys: Vec<String>,
+    /// Reject the update with 409 if the stored version differs (alternative to an If-Match header)
+    pub expected_version: Option<i64>,
+    /// Why the flag is being changed, kept in the project's audit log
+    pub reason: Option<String>,
+}
+
+impl From<ReplaceFlagRequest> for UpdateFlagRequest {
+    fn from(request: ReplaceFlagRequest) -> Self {
+        Self {
+            name: Some(request.name),
+            description: request.description,
+            enabled: Some(request.enabled),
+            rollout_percentage: Some(request.rollout_percentage),
+            client_visible: Some(request.client_visible),
+            anonymous_behavior: Some(request.anonymous_behavior),
+            hash_algorithm: Some(request.hash_algorithm),
+            bucketing_keys: Some(request.bucketing_keys),
+            expected_version: request.expected_version,
+            reason: request.reason,
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
+// How a flag update treats the optional fields its body leaves out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateMode {
+    /// PATCH: omitted fields keep their stored value
+    Patch,
+    /// PUT: the body is the whole flag, an omitted description is cleared
+    Replace,
+}
+
+impl UpdateMode {
+    /// Value stored in the `pending_changes.mode` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpdateMode::Patch => "patch",
+            UpdateMode::Replace => "replace",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "patch" => Some(UpdateMode::Patch),
+            "replace" => Some(UpdateMode::Replace),
+            _ => None,
+        }
+    }
+
+    /// Description stored after the update
+    pub fn description(&self, requested: Option<&str>, current: Option<&str>) -> Option<String> {
+        match self {
+            UpdateMode::Patch => requested.or(current).map(str::to_string),
+            UpdateMode::Replace => requested.map(str::to_string),
+        }
+    }
+
+    /// Settings the update wrote, for the audit log
+    pub fn changed_fields(&self, update: &UpdateFlagRequest) -> Vec<&'static str> {
+        let mut fields = update.changed_fields();
+        if *self == UpdateMode::Replace && update.description.is_none() {
+            fields.push("description");
+        }
+        fields
+    }
+}
+
+// Optional body of the toggle endpoint
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct ToggleFlagRequest {
+    /// Why the flag is being flipped, kept in the project's audit log
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RenameFlagRequest {
+    /// The new key, normalized to lowercase like on create
+    pub key: String,
+    /// Why the flag is being renamed, kept in the project's audit log
+    pub reason: Option<String>,
+}
+
+// A former key of a flag, still answered by SDK evaluations
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct FlagAliasResponse {
+    pub alias: String,
+    pub flag_id: Uuid,
+    pub environment_id: Uuid,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct FlagResponse {
     pub id: Uuid,
     pub project_id: Uuid,
@@ -47,33 +207,310 @@ pub struct FlagResponse {
     pub description: Option<String>,
     pub enabled: bool,
     pub rollout_percentage: i32,
+    pub client_visible: bool,
+    pub anonymous_behavior: String,
+    pub hash_algorithm: String,
+    pub bucketing_keys: Vec<String>,  // Identifiers bucketed on in order, empty for the default order
+    pub temporarily_disabled_until: Option<DateTime<Utc>>,  // Paused until then, see the pause endpoint
+    pub lifecycle_state: String,  // draft, active or deprecated, see LifecycleState
+    pub version: i64,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl From<FeatureFlag> for FlagResponse {
+    fn from(flag: FeatureFlag) -> Self {
+        Self {
+            id: flag.id,
+            project_id: flag.project_id,
+            environment_id: flag.environment_id,
+            name: flag.name,
+            key: flag.key,
+            description: flag.description,
+            enabled: flag.enabled,
+            rollout_percentage: flag.rollout_percentage,
+            client_visible: flag.client_visible,
+            anonymous_behavior: flag.anonymous_behavior,
+            hash_algorithm: flag.hash_algorithm,
+            bucketing_keys: flag.bucketing_keys,
+            temporarily_disabled_until: flag.temporarily_disabled_until,
+            lifecycle_state: flag.lifecycle_state,
+            version: flag.version,
+            created_by: flag.created_by,
+            updated_by: flag.updated_by,
+            created_at: flag.created_at,
+            updated_at: flag.updated_at,
+        }
+    }
+}
+
+// Created or updated flag plus non-blocking warnings about confusing configuration
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateFlagResponse {
+    #[serde(flatten)]
+    pub flag: FlagResponse,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+// A flag as defined in one environment, for the by-key lookup
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FlagInEnvironment {
+    pub environment_key: String,
+    #[serde(flatten)]
+    pub flag: FlagResponse,
+}
+
+// Result of creating one flag in every environment of a project
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateFlagInAllEnvironmentsResponse {
+    pub flags: Vec<FlagInEnvironment>,  // Sorted by environment key
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+// Result of the environment kill switch
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DisableAllResponse {
+    pub disabled_count: usize,
+    pub flag_keys: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExplainParams {
+    pub as_of: Option<DateTime<Utc>>,  // RFC3339, evaluate the flag as it was configured at that time
+}
+
+/// Synthetic users bucketed by the rollout preview when the request doesn't say
+pub const DEFAULT_ROLLOUT_PREVIEW_SAMPLE: u32 = 10_000;
+
+/// Most synthetic users a rollout preview buckets, each one is a hash
+pub const MAX_ROLLOUT_PREVIEW_SAMPLE: u32 = 100_000;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RolloutPreviewParams {
+    pub sample: Option<u32>,  // Synthetic users to bucket, default 10000, max 100000
+}
+
+/// How a flag's rollout spreads a sample of synthetic users
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RolloutPreviewResponse {
+    pub flag_key: String,
+    pub hash_algorithm: HashAlgorithm,
+    pub rollout_percentage: i32,
+    pub sample: u32,
+    /// Synthetic users the rollout would enable
+    pub enabled_count: u32,
+    /// enabled_count / sample, compare with rollout_percentage / 100
+    pub enabled_fraction: f64,
+}
+
+/// Sample size of a rollout preview, rejecting 0 and anything above the cap
+pub fn resolve_preview_sample(sample: Option<u32>) -> Result<u32, String> {
+    match sample.unwrap_or(DEFAULT_ROLLOUT_PREVIEW_SAMPLE) {
+        0 => Err("sample must be at least 1".to_string()),
+        sample if sample > MAX_ROLLOUT_PREVIEW_SAMPLE => {
+            Err(format!("sample is too large (Max: {})", MAX_ROLLOUT_PREVIEW_SAMPLE))
+        }
+        sample => Ok(sample),
+    }
+}
+
+/// Reject point-in-time evaluations that ask about the future, there is no history for them yet
+pub fn validate_as_of(as_of: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), String> {
+    if as_of > now {
+        return Err("as_of cannot be in the future".to_string());
+    }
+    Ok(())
+}
+
+// Result of previewing a flag for a user context from the management API
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FlagExplanation {
+    pub flag_key: String,
+    pub evaluated_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub evaluation: FlagEvaluation,  // Always includes the rule trace
+}
+
+// Draft rule of an evaluation preview
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PreviewRule {
+    pub id: Option<Uuid>,  // Id of the saved rule being edited, keeps its rule rollout buckets
+    #[serde(flatten)]
+    pub rule: CreateRuleRequest,
+}
+
+// Unsaved flag definition to evaluate, shaped like a flag create plus its rules
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PreviewFlag {
+    pub key: String,  // Buckets hash the key, so use the real flag's key to see its users' buckets
+    pub enabled: bool,
+    pub rollout_percentage: Option<i32>,
+    pub anonymous_behavior: Option<AnonymousBehavior>,
+    pub hash_algorithm: Option<HashAlgorithm>,
+    pub bucketing_keys: Option<Vec<String>>,
+    #[serde(default)]
+    pub rules: Vec<PreviewRule>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EvaluatePreviewRequest {
+    pub flag: PreviewFlag,
+    pub context: UserContext,
+}
+
+// One logged SDK evaluation of a flag
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct FlagEvaluationEntry {
+    pub id: i64,
+    pub user_identifier: String,
+    pub result: bool,
+    pub sample_rate: f64,  // The project's sample rate when logged, the row stands for 1 / sample_rate evaluations
+    pub evaluated_at: DateTime<Utc>,
+}
+
+// Pause a flag for a while, give either an end time or a duration
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PauseFlagRequest {
+    pub until: Option<DateTime<Utc>>,
+    pub duration_minutes: Option<i64>,
+}
+
+// One flag's state in a single environment, as loaded for the cross-environment view
+#[derive(Debug)]
+pub struct FlagEnvironmentRow {
+    pub flag_key: String,
+    pub environment_key: String,
+    pub enabled: bool,
+    pub rollout_percentage: i32,
+}
+
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct FlagEnvironmentState {
+    pub enabled: bool,
+    pub rollout_percentage: i32,
+}
+
+// A flag key with its state in every environment that defines it, keyed by environment key
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct FlagMatrixEntry {
+    pub key: String,
+    pub environments: BTreeMap<String, FlagEnvironmentState>,
+}
+
 // HELPER FUNCTIONS
 
-// Validating the flag key
-pub fn validate_flag_key(key: &str) -> Result<(), String> {
+// Groups per-environment rows by flag key, sorted by key
+pub fn build_flag_matrix(rows: Vec<FlagEnvironmentRow>) -> Vec<FlagMatrixEntry> {
+    let mut by_key: BTreeMap<String, BTreeMap<String, FlagEnvironmentState>> = BTreeMap::new();
+    for row in rows {
+        by_key.entry(row.flag_key).or_default().insert(
+            row.environment_key,
+            FlagEnvironmentState {
+                enabled: row.enabled,
+                rollout_percentage: row.rollout_percentage,
+            },
+        );
+    }
+
+    by_key
+        .into_iter()
+        .map(|(key, environments)| FlagMatrixEntry { key, environments })
+        .collect()
+}
+
+// Normalizing the flag key (keys are stored lowercase)
+pub fn normalize_flag_key(key: &str) -> String {
+    key.to_ascii_lowercase()
+}
+
+// Validating the flag key (mixed case is accepted and normalized before insert)
+pub fn validate_flag_key(key: &str) -> Result<(), ValidationError> {
     if key.is_empty() {                                         // Checks if flag key is empty
-        return Err("Flag key cannot be empty".to_string());
+        return Err(ValidationError::new("key", "required", "Flag key cannot be empty"));
     }
 
     if key.len()>64 {
-        return Err("Flag key is too long (Max: 64 characters)".to_string());      // Max size of flag
+        return Err(ValidationError::new("key", "too_long", "Flag key is too long (Max: 64 characters)"));      // Max size of flag
     }
 
     if !key.chars().next().unwrap().is_ascii_alphabetic() {
-        return Err("Flag must start with an alphabet".to_string());
+        return Err(ValidationError::new("key", "invalid_start", "Flag must start with an alphabet"));
     }
 
-    if !key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-') {
-        return Err("flag can only be \n - lowercase letters\n - numbers\n - underscores\n - and hypens.".to_string());
+    if !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(ValidationError::new(
+            "key",
+            "invalid_char",
+            "flag can only be \n - letters\n - numbers\n - underscores\n - and hypens.",
+        ));
     }
 
     Ok(())
 }
 
+// Validates the key a flag is renamed to and normalizes it, renaming to the current key is rejected
+pub fn check_rename(current_key: &str, requested: &str) -> Result<String, ValidationError> {
+    validate_flag_key(requested)?;
+    let key = normalize_flag_key(requested);
+    if key == current_key {
+        return Err(ValidationError::new("key", "unchanged", format!("Flag key is already '{}'", key)));
+    }
+    Ok(key)
+}
+
+// Validates a draft flag and builds what evaluate_flag needs from it, nothing is stored
+pub fn preview_flag_data(flag: &PreviewFlag) -> Result<(FlagData, Vec<RuleData>), ValidationError> {
+    validate_flag_key(&flag.key)?;
+    let rollout_percentage = flag.rollout_percentage.unwrap_or(0);
+    validate_rollout_percentage(rollout_percentage)
+        .map_err(|e| ValidationError::new("rollout_percentage", "out_of_range", e))?;
+    let bucketing_keys = flag.bucketing_keys.as_deref().unwrap_or_default();
+    validate_bucketing_keys(bucketing_keys)?;
+    if flag.rules.len() > MAX_BULK_RULES {
+        return Err(ValidationError::new(
+            "rules",
+            "too_long",
+            format!("Too many rules (Max: {})", MAX_BULK_RULES),
+        ));
+    }
+    let rule_values = validate_rule_list(flag.rules.iter().map(|r| &r.rule))?;
+
+    let flag_data = FlagData {
+        key: normalize_flag_key(&flag.key),
+        enabled: flag.enabled,
+        rollout_percentage,
+        anonymous_behavior: flag.anonymous_behavior.unwrap_or_default(),
+        hash_algorithm: flag.hash_algorithm.unwrap_or_default(),
+        bucketing_keys: BucketingKey::from_db(bucketing_keys),
+        temporarily_disabled_until: None,
+        overrides: Vec::new(),
+    };
+    let rules = flag
+        .rules
+        .iter()
+        .zip(rule_values)
+        .map(|(preview, rule_value)| RuleData {
+            id: preview.id,
+            rule_type: preview.rule.rule_type.clone(),
+            rule_value: rule_value.text,
+            rule_value_json: rule_value.json,
+            enabled: preview.rule.enabled.unwrap_or(true),
+            priority: preview.rule.priority.unwrap_or(0),
+            negate: preview.rule.negate.unwrap_or(false),
+            payload: preview.rule.payload.clone(),
+            rollout_percentage: preview.rule.rollout_percentage,
+        })
+        .collect();
+
+    Ok((flag_data, rules))
+}
+
 // Checks if percentage number is between the number 0 to 100
 pub fn validate_rollout_percentage(percentage: i32) -> Result<(), String> {
     if !(0..=100).contains(&percentage) {
@@ -83,6 +520,266 @@ pub fn validate_rollout_percentage(percentage: i32) -> Result<(), String> {
     Ok(())
 }
 
+// Rollout for a new flag: an explicit value wins over the project's default_rollout_percentage
+pub fn resolve_rollout_percentage(requested: Option<i32>, project_default: Option<i32>) -> i32 {
+    requested.or(project_default).unwrap_or(0)
+}
+
+// Rejects a single update that raises the rollout by more than the project allows
+pub fn check_rollout_increase(current: i32, attempted: i32, max_increase: Option<i32>) -> Result<(), String> {
+    match max_increase {
+        Some(max) if attempted - current > max => Err(format!(
+            "Rollout percentage can increase by at most {} points per update (current {}%, attempted {}%)",
+            max, current, attempted
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Most identifiers a flag can fall back through when bucketing
+pub const MAX_BUCKETING_KEYS: usize = 5;
+
+// Bucketing keys must be known identifiers or "attribute:<name>", each listed once
+pub fn validate_bucketing_keys(keys: &[String]) -> Result<(), ValidationError> {
+    if keys.len() > MAX_BUCKETING_KEYS {
+        return Err(ValidationError::new(
+            "bucketing_keys",
+            "too_long",
+            format!("At most {} bucketing keys are allowed", MAX_BUCKETING_KEYS),
+        ));
+    }
+
+    for (i, key) in keys.iter().enumerate() {
+        if BucketingKey::parse(key).is_none() {
+            return Err(ValidationError::new(
+                "bucketing_keys",
+                "invalid_format",
+                format!(
+                    "Unknown bucketing key '{}', use user_id, user_email, anonymous_id or attribute:<name>",
+                    key
+                ),
+            ));
+        }
+        if keys[..i].contains(key) {
+            return Err(ValidationError::new(
+                "bucketing_keys",
+                "duplicate",
+                format!("Bucketing key '{}' is listed twice", key),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Soft checks on a new flag: the flag is still created, the client just gets told
+/// Conflict message when a key to create in every environment is already taken in some of them
+pub fn key_taken_message(key: &str, environment_keys: &[String]) -> Option<String> {
+    in_environments(environment_keys).map(|environments| format!("Flag key '{}' already exists in {}", key, environments))
+}
+
+/// Conflict message when a key to create in every environment is an alias of a renamed flag in some of them
+pub fn key_aliased_message(key: &str, environment_keys: &[String]) -> Option<String> {
+    in_environments(environment_keys)
+        .map(|environments| format!("Flag key '{}' is an alias of another flag in {}", key, environments))
+}
+
+// "environment 'prod'" or "environments 'dev', 'prod'", None for no environments
+fn in_environments(environment_keys: &[String]) -> Option<String> {
+    match environment_keys {
+        [] => None,
+        [environment_key] => Some(format!("environment '{}'", environment_key)),
+        _ => Some(format!(
+            "environments {}",
+            environment_keys.iter().map(|k| format!("'{}'", k)).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+pub fn create_flag_warnings(requested_key: &str, stored_key: &str, enabled: bool, rollout_percentage: i32) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if requested_key != stored_key {
+        warnings.push(format!(
+            "Key '{}' was stored as '{}', SDKs must use the lowercase key",
+            requested_key, stored_key
+        ));
+    }
+
+    // A new flag never has rules yet
+    warnings.extend(rollout_coherence_warnings(enabled, rollout_percentage, false));
+    warnings
+}
+
+// Enabled state and rollout that together probably don't do what the user expects
+pub fn rollout_coherence_warnings(enabled: bool, rollout_percentage: i32, has_enabled_rules: bool) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    // A rollout of 0 means "no rollout", not "nobody", see evaluate_flag
+    if enabled && rollout_percentage == 0 && !has_enabled_rules {
+        warnings.push(
+            "Flag is enabled with rollout_percentage 0 and no rules, which does not limit it: every user gets the flag"
+                .to_string(),
+        );
+    }
+
+    if !enabled && rollout_percentage > 0 {
+        warnings.push(format!(
+            "Flag is disabled, the {}% rollout has no effect until it is enabled",
+            rollout_percentage
+        ));
+    }
+
+    warnings
+}
+
+// With STRICT_FLAG_COHERENCE, refuse to set a rollout on a flag that stays disabled
+// Only requests that set rollout_percentage are checked, so disabling a flag keeps its rollout
+// The rollout isn't reset to 0 instead, since 0 means "everyone" once the flag is enabled
+pub fn check_rollout_coherence(
+    strict: bool,
+    enabled: bool,
+    requested_rollout: Option<i32>,
+) -> Result<(), ValidationError> {
+    match requested_rollout {
+        Some(percentage) if strict && !enabled && percentage > 0 => Err(ValidationError::new(
+            "rollout_percentage",
+            "conflict",
+            "rollout_percentage has no effect while the flag is disabled, set enabled to true or leave the rollout out",
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Reads the expected flag version from an If-Match header ("3", "\"3\"" or weak W/"3")
+pub fn parse_if_match(value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    let value = value.strip_prefix("W/").unwrap_or(value);
+    value
+        .trim_matches('"')
+        .parse::<i64>()
+        .map_err(|_| "If-Match must contain a flag version number".to_string())
+}
+
+// Picks the expected version from If-Match or the body, both must agree when both are sent
+pub fn resolve_expected_version(if_match: Option<&str>, body: Option<i64>) -> Result<Option<i64>, String> {
+    let header = if_match.map(parse_if_match).transpose()?;
+    match (header, body) {
+        (Some(h), Some(b)) if h != b => {
+            Err("If-Match and expected_version disagree".to_string())
+        }
+        (header, body) => Ok(header.or(body)),
+    }
+}
+
+// Where a flag is in its life, independent of enabled
+// Only the promote and deprecate endpoints move a flag between states
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleState {
+    /// Still being set up, SDKs don't see the flag at all
+    Draft,
+    /// Served to SDKs, the state of every flag created before lifecycles existed
+    #[default]
+    Active,
+    /// Still served, but evaluations mark it so callers can remove their checks
+    Deprecated,
+}
+
+impl LifecycleState {
+    pub const ALL: [LifecycleState; 3] = [LifecycleState::Draft, LifecycleState::Active, LifecycleState::Deprecated];
+
+    /// Value stored in the `feature_flags.lifecycle_state` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleState::Draft => "draft",
+            LifecycleState::Active => "active",
+            LifecycleState::Deprecated => "deprecated",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "draft" => LifecycleState::Draft,
+            "deprecated" => LifecycleState::Deprecated,
+            _ => LifecycleState::Active,
+        }
+    }
+
+    /// Whether SDK evaluate and config include the flag
+    pub fn is_served(&self) -> bool {
+        *self != LifecycleState::Draft
+    }
+
+    /// Column values of the states SDK queries select
+    pub fn served_states() -> Vec<&'static str> {
+        Self::ALL.iter().filter(|state| state.is_served()).map(LifecycleState::as_str).collect()
+    }
+
+    /// State after a promote: drafts go live and deprecated flags are reinstated
+    pub fn promote(&self) -> Result<Self, String> {
+        match self {
+            LifecycleState::Active => Err("Flag is already active".to_string()),
+            LifecycleState::Draft | LifecycleState::Deprecated => Ok(LifecycleState::Active),
+        }
+    }
+
+    /// State after a deprecate, only active flags can be deprecated
+    pub fn deprecate(&self) -> Result<Self, String> {
+        match self {
+            LifecycleState::Active => Ok(LifecycleState::Deprecated),
+            LifecycleState::Draft => Err("Draft flags can't be deprecated, promote or delete them instead".to_string()),
+            LifecycleState::Deprecated => Err("Flag is already deprecated".to_string()),
+        }
+    }
+}
+
+/// New flags start as drafts or active, deprecating one right away makes no sense
+pub fn validate_initial_lifecycle(state: LifecycleState) -> Result<(), ValidationError> {
+    if state == LifecycleState::Deprecated {
+        return Err(ValidationError::new(
+            "lifecycle_state",
+            "invalid_state",
+            "New flags can only be created as draft or active",
+        ));
+    }
+    Ok(())
+}
+
+/// Longest a flag can be paused for (30 days), use enabled for anything permanent
+pub const MAX_PAUSE_MINUTES: i64 = 30 * 24 * 60;
+
+// Resolves when a pause requested at `now` ends
+pub fn resolve_pause_until(request: &PauseFlagRequest, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let until = match (request.until, request.duration_minutes) {
+        (Some(until), None) => until,
+        (None, Some(minutes)) if (1..=MAX_PAUSE_MINUTES).contains(&minutes) => now + chrono::Duration::minutes(minutes),
+        (None, Some(_)) => {
+            return Err(format!("duration_minutes must be between 1 and {}", MAX_PAUSE_MINUTES));
+        }
+        _ => return Err("Provide exactly one of until or duration_minutes".to_string()),
+    };
+
+    if until <= now {
+        return Err("until must be in the future".to_string());
+    }
+    if until > now + chrono::Duration::minutes(MAX_PAUSE_MINUTES) {
+        return Err(format!("A flag can be paused for at most {} days", MAX_PAUSE_MINUTES / (24 * 60)));
+    }
+    Ok(until)
+}
+
+// Rejects an update made against a stale version of the flag
+pub fn check_flag_version(expected: Option<i64>, current: i64) -> Result<(), String> {
+    match expected {
+        Some(expected) if expected != current => Err(format!(
+            "Flag was modified by someone else (expected version {}, current version {})",
+            expected, current
+        )),
+        _ => Ok(()),
+    }
+}
+
 
 
 
@@ -97,12 +794,135 @@ mod tests {
         assert!(validate_flag_key("new_checkout").is_ok());
         assert!(validate_flag_key("dark-mode").is_ok());
         assert!(validate_flag_key("beta_features_2024").is_ok());
+        assert!(validate_flag_key("New_Checkout").is_ok()); // mixed case is normalized
         
         assert!(validate_flag_key("").is_err());
-        assert!(validate_flag_key("New_Checkout").is_err()); // uppercase
         assert!(validate_flag_key("_invalid").is_err()); // starts with underscore
         assert!(validate_flag_key("has space").is_err()); // space
         assert!(validate_flag_key("has.dot").is_err()); // dot
+
+        let err = validate_flag_key("has space").unwrap_err();
+        assert_eq!((err.field, err.code), ("key", "invalid_char"));
+        assert_eq!(validate_flag_key("_invalid").unwrap_err().code, "invalid_start");
+        assert_eq!(validate_flag_key("").unwrap_err().code, "required");
+        assert_eq!(validate_flag_key(&"a".repeat(65)).unwrap_err().code, "too_long");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_fields_rejected() {
+        use axum::extract::{FromRequest, Request};
+        use axum::http::{header, StatusCode};
+        use crate::routes::error::JsonBody;
+
+        async fn extract(body: &'static str) -> Result<CreateFlagRequest, (StatusCode, String)> {
+            let req = Request::builder()
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap();
+            match JsonBody::<CreateFlagRequest>::from_request(req, &()).await {
+                Ok(JsonBody(request)) => Ok(request),
+                Err(response) => {
+                    let status = response.status();
+                    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+                    Err((status, body["error"].as_str().unwrap().to_string()))
+                }
+            }
+        }
+
+        // A typo'd field is a 400 naming it, instead of being dropped
+        let (status, error) = extract(r#"{"name": "Checkout", "rollout": 50}"#).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error.contains("unknown field `rollout`"), "{}", error);
+
+        // Optional fields can still be left out or sent as null
+        let request = extract(r#"{"name": "Checkout", "key": null}"#).await.unwrap();
+        assert_eq!((request.key, request.rollout_percentage, request.enabled), (None, None, None));
+
+        // Update bodies too, an empty PATCH still parses
+        let update: UpdateFlagRequest = serde_json::from_str("{}").unwrap();
+        assert!(update.changed_fields().is_empty());
+        let err = serde_json::from_str::<UpdateFlagRequest>(r#"{"enabeld": true}"#).unwrap_err();
+        assert!(err.to_string().contains("unknown field `enabeld`"));
+        // The key isn't part of a PUT body, it only changes through the rename endpoint
+        let mut replace = serde_json::json!({
+            "name": "Checkout", "enabled": true, "rollout_percentage": 10, "client_visible": false,
+            "anonymous_behavior": "bucket", "hash_algorithm": "sha256", "bucketing_keys": [],
+        });
+        assert!(serde_json::from_value::<ReplaceFlagRequest>(replace.clone()).is_ok());
+        replace["key"] = "checkout".into();
+        assert!(serde_json::from_value::<ReplaceFlagRequest>(replace).is_err());
+    }
+
+    #[test]
+    fn test_check_rename() {
+        assert_eq!(check_rename("old_checkout", "New_Checkout").unwrap(), "new_checkout");
+        assert_eq!(check_rename("old_checkout", "has space").unwrap_err().code, "invalid_char");
+
+        // Only the case differs, which normalizes to the key the flag already has
+        let err = check_rename("new_checkout", "NEW_CHECKOUT").unwrap_err();
+        assert_eq!((err.field, err.code), ("key", "unchanged"));
+    }
+
+    #[test]
+    fn test_normalize_flag_key() {
+        assert_eq!(normalize_flag_key("New_Checkout"), "new_checkout");
+        assert_eq!(normalize_flag_key("dark-mode"), "dark-mode");
+    }
+
+    #[test]
+    fn test_preview_flag_data() {
+        let request: EvaluatePreviewRequest = serde_json::from_value(serde_json::json!({
+            "flag": {
+                "key": "New_Checkout",
+                "enabled": true,
+                "rollout_percentage": 0,
+                "rules": [
+                    { "rule_type": "email_domain", "rule_value": "@Company.com", "priority": 10, "payload": "beta" },
+                    { "id": Uuid::nil(), "rule_type": "user_id", "rule_value": "alice", "enabled": false }
+                ]
+            },
+            "context": { "user_id": "bob", "user_email": "bob@company.com" }
+        }))
+        .unwrap();
+
+        let (flag, rules) = preview_flag_data(&request.flag).unwrap();
+        assert_eq!(flag.key, "new_checkout");
+        assert_eq!(flag.hash_algorithm, HashAlgorithm::Sha256);
+        assert_eq!(rules[0].rule_value, "@company.com");
+        assert_eq!(rules[1].id, Some(Uuid::nil()));
+        assert!(!rules[1].enabled);
+
+        let result = crate::evaluation::evaluate_flag(&flag, &rules, &request.context);
+        assert!(result.enabled);
+        assert_eq!(result.payload, Some(serde_json::json!("beta")));
+
+        // Drafts are validated like saved flags and rules
+        let invalid = |flag: serde_json::Value| {
+            let flag: PreviewFlag = serde_json::from_value(flag).unwrap();
+            preview_flag_data(&flag).unwrap_err()
+        };
+        assert_eq!(invalid(serde_json::json!({ "key": "1flag", "enabled": true })).field, "key");
+        let err = invalid(serde_json::json!({ "key": "flag", "enabled": true, "rollout_percentage": 101 }));
+        assert_eq!((err.field, err.code), ("rollout_percentage", "out_of_range"));
+        let err = invalid(serde_json::json!({
+            "key": "flag",
+            "enabled": true,
+            "rules": [{ "rule_type": "user_email", "rule_value": "not-an-email" }]
+        }));
+        assert_eq!(err.field, "rule_value");
+        assert!(err.message.starts_with("Rule 0: "));
+    }
+
+    #[test]
+    fn test_resolve_rollout_percentage() {
+        // New flags inherit the project default
+        assert_eq!(resolve_rollout_percentage(None, Some(10)), 10);
+        // An explicit value overrides it, including 0
+        assert_eq!(resolve_rollout_percentage(Some(50), Some(10)), 50);
+        assert_eq!(resolve_rollout_percentage(Some(0), Some(10)), 0);
+        // Projects without a default keep the old behavior
+        assert_eq!(resolve_rollout_percentage(None, None), 0);
     }
 
     #[test]
@@ -114,4 +934,325 @@ mod tests {
         assert!(validate_rollout_percentage(-1).is_err());
         assert!(validate_rollout_percentage(101).is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_build_flag_matrix() {
+        let row = |flag_key: &str, environment_key: &str, enabled, rollout_percentage| FlagEnvironmentRow {
+            flag_key: flag_key.to_string(),
+            environment_key: environment_key.to_string(),
+            enabled,
+            rollout_percentage,
+        };
+        let matrix = build_flag_matrix(vec![
+            row("new_checkout", "production", false, 10),
+            row("dark_mode", "staging", true, 100),
+            row("new_checkout", "staging", true, 50),
+        ]);
+
+        assert_eq!(matrix.len(), 2);
+        assert_eq!(matrix[0].key, "dark_mode");
+        assert_eq!(matrix[0].environments.len(), 1); // Only defined in staging
+        assert_eq!(matrix[1].key, "new_checkout");
+        assert_eq!(
+            matrix[1].environments["production"],
+            FlagEnvironmentState { enabled: false, rollout_percentage: 10 }
+        );
+        assert_eq!(
+            matrix[1].environments["staging"],
+            FlagEnvironmentState { enabled: true, rollout_percentage: 50 }
+        );
+
+        assert!(build_flag_matrix(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_if_match() {
+        assert_eq!(parse_if_match("3"), Ok(3));
+        assert_eq!(parse_if_match("\"3\""), Ok(3));
+        assert_eq!(parse_if_match("W/\"3\""), Ok(3));
+
+        assert!(parse_if_match("*").is_err());
+        assert!(parse_if_match("abc").is_err());
+    }
+
+    #[test]
+    fn test_resolve_expected_version() {
+        assert_eq!(resolve_expected_version(None, None), Ok(None));
+        assert_eq!(resolve_expected_version(Some("\"2\""), None), Ok(Some(2)));
+        assert_eq!(resolve_expected_version(None, Some(2)), Ok(Some(2)));
+        assert_eq!(resolve_expected_version(Some("2"), Some(2)), Ok(Some(2)));
+
+        assert!(resolve_expected_version(Some("2"), Some(3)).is_err());
+    }
+
+    #[test]
+    fn test_lifecycle_transitions() {
+        use LifecycleState::*;
+
+        // draft -> active -> deprecated -> active
+        assert_eq!(Draft.promote(), Ok(Active));
+        assert_eq!(Active.deprecate(), Ok(Deprecated));
+        assert_eq!(Deprecated.promote(), Ok(Active));
+
+        // Everything else is refused and leaves the flag alone
+        assert!(Active.promote().is_err());
+        assert!(Draft.deprecate().is_err());
+        assert!(Deprecated.deprecate().is_err());
+
+        // Only drafts are hidden from SDKs
+        assert!(!Draft.is_served());
+        assert!(Active.is_served());
+        assert!(Deprecated.is_served());
+        assert_eq!(LifecycleState::served_states(), vec!["active", "deprecated"]);
+
+        for state in LifecycleState::ALL {
+            assert_eq!(LifecycleState::from_db(state.as_str()), state);
+        }
+        assert_eq!(LifecycleState::default(), Active);
+
+        assert!(validate_initial_lifecycle(Draft).is_ok());
+        assert!(validate_initial_lifecycle(Active).is_ok());
+        assert_eq!(validate_initial_lifecycle(Deprecated).unwrap_err().field, "lifecycle_state");
+    }
+
+    #[test]
+    fn test_resolve_pause_until() {
+        let now = Utc::now();
+        let request = |until: Option<DateTime<Utc>>, duration_minutes: Option<i64>| PauseFlagRequest { until, duration_minutes };
+
+        assert_eq!(resolve_pause_until(&request(None, Some(60)), now), Ok(now + chrono::Duration::hours(1)));
+        let until = now + chrono::Duration::minutes(15);
+        assert_eq!(resolve_pause_until(&request(Some(until), None), now), Ok(until));
+
+        assert!(resolve_pause_until(&request(None, None), now).is_err());
+        assert!(resolve_pause_until(&request(Some(until), Some(60)), now).is_err());
+        assert!(resolve_pause_until(&request(None, Some(0)), now).is_err());
+        assert!(resolve_pause_until(&request(None, Some(MAX_PAUSE_MINUTES + 1)), now).is_err());
+        assert!(resolve_pause_until(&request(Some(now - chrono::Duration::minutes(1)), None), now).is_err());
+        assert!(resolve_pause_until(&request(Some(now + chrono::Duration::days(31)), None), now).is_err());
+    }
+
+    #[test]
+    fn test_check_flag_version() {
+        // Versioned update against the current version succeeds
+        assert!(check_flag_version(Some(4), 4).is_ok());
+        // Unversioned updates keep the old last-write-wins behavior
+        assert!(check_flag_version(None, 4).is_ok());
+
+        // Stale version is rejected
+        let err = check_flag_version(Some(3), 4).unwrap_err();
+        assert!(err.contains("expected version 3"));
+        assert!(err.contains("current version 4"));
+    }
+
+    #[test]
+    fn test_create_flag_warnings() {
+        // Clean configuration, no warnings
+        assert!(create_flag_warnings("new_checkout", "new_checkout", true, 25).is_empty());
+        assert!(create_flag_warnings("new_checkout", "new_checkout", false, 0).is_empty());
+
+        let warnings = create_flag_warnings("New_Checkout", "new_checkout", false, 0);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("'new_checkout'"));
+
+        let warnings = create_flag_warnings("new_checkout", "new_checkout", true, 0);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("rollout_percentage 0"));
+
+        // Rules limit who gets an enabled flag, so 0% isn't surprising anymore
+        assert!(rollout_coherence_warnings(true, 0, true).is_empty());
+        assert_eq!(rollout_coherence_warnings(true, 0, false).len(), 1);
+        assert_eq!(rollout_coherence_warnings(false, 30, true).len(), 1);
+
+        let warnings = create_flag_warnings("new_checkout", "new_checkout", false, 30);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("30% rollout"));
+    }
+
+    #[test]
+    fn test_resolve_preview_sample() {
+        assert_eq!(resolve_preview_sample(None), Ok(DEFAULT_ROLLOUT_PREVIEW_SAMPLE));
+        assert_eq!(resolve_preview_sample(Some(1)), Ok(1));
+        assert_eq!(resolve_preview_sample(Some(MAX_ROLLOUT_PREVIEW_SAMPLE)), Ok(MAX_ROLLOUT_PREVIEW_SAMPLE));
+        assert!(resolve_preview_sample(Some(0)).is_err());
+        assert!(resolve_preview_sample(Some(MAX_ROLLOUT_PREVIEW_SAMPLE + 1)).is_err());
+    }
+
+    #[test]
+    fn test_validate_as_of() {
+        let now = "2026-10-17T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert!(validate_as_of(now - chrono::Duration::days(4), now).is_ok());
+        assert!(validate_as_of(now, now).is_ok());
+        assert!(validate_as_of(now + chrono::Duration::seconds(1), now).is_err());
+
+        let params: ExplainParams = serde_json::from_value(serde_json::json!({ "as_of": "2026-10-13T09:00:00+02:00" })).unwrap();
+        assert_eq!(params.as_of, Some("2026-10-13T07:00:00Z".parse().unwrap()));
+        assert!(serde_json::from_value::<ExplainParams>(serde_json::json!({ "as_of": "last tuesday" })).is_err());
+    }
+
+    #[test]
+    fn test_key_taken_message() {
+        assert_eq!(key_taken_message("new_checkout", &[]), None);
+        assert_eq!(
+            key_taken_message("new_checkout", &["staging".to_string()]).unwrap(),
+            "Flag key 'new_checkout' already exists in environment 'staging'"
+        );
+        assert_eq!(
+            key_taken_message("new_checkout", &["production".to_string(), "staging".to_string()]).unwrap(),
+            "Flag key 'new_checkout' already exists in environments 'production', 'staging'"
+        );
+    }
+
+    #[test]
+    fn test_key_aliased_message() {
+        assert_eq!(key_aliased_message("checkout_v2", &[]), None);
+        assert_eq!(
+            key_aliased_message("checkout_v2", &["staging".to_string()]).unwrap(),
+            "Flag key 'checkout_v2' is an alias of another flag in environment 'staging'"
+        );
+        assert_eq!(
+            key_aliased_message("checkout_v2", &["production".to_string(), "staging".to_string()]).unwrap(),
+            "Flag key 'checkout_v2' is an alias of another flag in environments 'production', 'staging'"
+        );
+    }
+
+    #[test]
+    fn test_put_replaces_and_patch_merges() {
+        let current = Some("Redesigned checkout");
+
+        // PATCH without a description keeps the stored one
+        let patch: UpdateFlagRequest = serde_json::from_str(r#"{"enabled": true}"#).unwrap();
+        assert_eq!(UpdateMode::Patch.description(patch.description.as_deref(), current).as_deref(), current);
+        assert_eq!(UpdateMode::Patch.changed_fields(&patch), vec!["enabled"]);
+
+        // PUT without a description clears it
+        let put: ReplaceFlagRequest = serde_json::from_value(serde_json::json!({
+            "name": "New Checkout",
+            "enabled": true,
+            "rollout_percentage": 25,
+            "client_visible": false,
+            "anonymous_behavior": "bucket",
+            "hash_algorithm": "sha256",
+            "bucketing_keys": [],
+        }))
+        .unwrap();
+        let put = UpdateFlagRequest::from(put);
+        assert_eq!(UpdateMode::Replace.description(put.description.as_deref(), current), None);
+        assert!(UpdateMode::Replace.changed_fields(&put).contains(&"description"));
+        assert_eq!(put.rollout_percentage, Some(25));
+
+        // Both write a description that is sent
+        assert_eq!(UpdateMode::Patch.description(Some("New"), current).as_deref(), Some("New"));
+        assert_eq!(UpdateMode::Replace.description(Some("New"), current).as_deref(), Some("New"));
+
+        // PUT needs the whole flag, a partial body is rejected
+        assert!(serde_json::from_str::<ReplaceFlagRequest>(r#"{"name": "New Checkout", "enabled": true}"#).is_err());
+    }
+
+    #[test]
+    fn test_change_reasons() {
+        // The toggle body is optional and so is the reason in it
+        let toggle: ToggleFlagRequest = serde_json::from_str(r#"{"reason": "Checkout errors spiking"}"#).unwrap();
+        assert_eq!(toggle.reason.as_deref(), Some("Checkout errors spiking"));
+        assert!(ToggleFlagRequest::default().reason.is_none());
+
+        let update: UpdateFlagRequest =
+            serde_json::from_str(r#"{"enabled": false, "rollout_percentage": 10, "reason": "Rolling back"}"#).unwrap();
+        assert_eq!(update.reason.as_deref(), Some("Rolling back"));
+        assert_eq!(update.changed_fields(), vec!["enabled", "rollout_percentage"]);
+
+        // The reason comes back from the audit log next to the change it explains
+        let entry = crate::routes::projects::AuditLogEntry {
+            id: 1,
+            environment_id: None,
+            actor_id: Uuid::nil(),
+            action: crate::audit::AuditAction::FlagToggle.as_str().to_string(),
+            details: serde_json::json!({ "flag_key": "new_checkout", "enabled": false }),
+            reason: toggle.reason,
+            created_at: Utc::now(),
+        };
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["action"], "flag.toggle");
+        assert_eq!(json["reason"], "Checkout errors spiking");
+    }
+
+    #[test]
+    fn test_validate_bucketing_keys() {
+        let keys = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+
+        assert!(validate_bucketing_keys(&[]).is_ok());
+        assert!(validate_bucketing_keys(&keys(&["attribute:account_id", "user_id", "user_email"])).is_ok());
+
+        let err = validate_bucketing_keys(&keys(&["user_id", "account_id"])).unwrap_err();
+        assert_eq!((err.field, err.code), ("bucketing_keys", "invalid_format"));
+        let err = validate_bucketing_keys(&keys(&["user_id", "user_id"])).unwrap_err();
+        assert_eq!(err.code, "duplicate");
+        let err = validate_bucketing_keys(&keys(&["attribute:a", "attribute:b", "attribute:c", "attribute:d", "user_id", "user_email"]))
+            .unwrap_err();
+        assert_eq!(err.code, "too_long");
+    }
+
+    #[test]
+    fn test_check_rollout_coherence() {
+        // Lenient mode never rejects, the warnings cover it
+        assert!(check_rollout_coherence(false, false, Some(50)).is_ok());
+
+        let err = check_rollout_coherence(true, false, Some(50)).unwrap_err();
+        assert_eq!((err.field, err.code), ("rollout_percentage", "conflict"));
+
+        assert!(check_rollout_coherence(true, true, Some(50)).is_ok());
+        assert!(check_rollout_coherence(true, false, Some(0)).is_ok());
+        // Disabling a flag without touching its rollout is always fine
+        assert!(check_rollout_coherence(true, false, None).is_ok());
+    }
+
+    #[test]
+    fn test_check_rollout_increase() {
+        // No guardrail configured
+        assert!(check_rollout_increase(1, 100, None).is_ok());
+
+        assert!(check_rollout_increase(1, 11, Some(10)).is_ok());
+        assert!(check_rollout_increase(10, 5, Some(10)).is_ok()); // Decreases are always allowed
+        assert!(check_rollout_increase(50, 50, Some(0)).is_ok());
+
+        let err = check_rollout_increase(1, 100, Some(10)).unwrap_err();
+        assert!(err.contains("current 1%"));
+        assert!(err.contains("attempted 100%"));
+    }
+
+    #[test]
+    fn test_flag_response_includes_acting_users() {
+        let creator = Uuid::new_v4();
+        let editor = Uuid::new_v4();
+        let flag = FeatureFlag {
+            id: Uuid::nil(),
+            project_id: Uuid::nil(),
+            environment_id: None,
+            name: "New Checkout".to_string(),
+            key: "new_checkout".to_string(),
+            description: None,
+            enabled: true,
+            rollout_percentage: 0,
+            client_visible: false,
+            anonymous_behavior: "bucket".to_string(),
+            hash_algorithm: "sha256".to_string(),
+            bucketing_keys: Vec::new(),
+            temporarily_disabled_until: None,
+            lifecycle_state: "active".to_string(),
+            version: 2,
+            created_by: Some(creator),
+            updated_by: Some(editor),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let response = FlagResponse::from(flag);
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["created_by"], creator.to_string());
+        assert_eq!(json["updated_by"], editor.to_string());
+
+        // Rows that predate tracking serialize as null
+        let json = serde_json::to_value(FlagResponse { created_by: None, updated_by: None, ..response }).unwrap();
+        assert!(json["created_by"].is_null());
+        assert!(json["updated_by"].is_null());
+    }
+}
@@ -1,76 +1,80 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-
+use sqlx::PgConnection;
 use uuid::Uuid;
 
-use crate::routes::{flags::validate_flag_key, middleware_auth::JwtUser};
+use crate::audit::{self, AuditAction, AuditEntry};
+use crate::evaluation::{evaluate_flag_traced, simulate_rollout, AnonymousBehavior, BucketingKey, FlagData, HashAlgorithm, RuleData, UserContext};
+use crate::routes::{error::{ApiError, JsonBody, ValidationErrorBody}, flags::{normalize_flag_key, validate_flag_key}};
+use crate::routes::pagination::{paginate, Cursor, Page, PageParams};
+use crate::routes::slug::key_or_slug;
+use crate::routes::overrides::load_overrides;
+use crate::routes::ownership::{VerifiedEnvironment, VerifiedFlag, VerifiedProject};
+use crate::routes::pending_changes::{self, FlagWrite, NewPendingChange, PendingChange, PendingChangeResponse, ProposedChange, WriteGate};
 use crate::state::AppState;
+use crate::webhooks::{self, FlagChangeAction, FlagChangeEvent};
 use super::{
-    CreateFlagRequest, UpdateFlagRequest, FeatureFlag, FlagResponse,
-    validate_rollout_percentage
+    CreateFlagInAllEnvironmentsResponse, CreateFlagRequest, CreateFlagResponse, EvaluatePreviewRequest, ExplainParams, ReplaceFlagRequest, UpdateFlagRequest, UpdateMode, DisableAllResponse, FeatureFlag, FlagEnvironmentRow, FlagEvaluationEntry, FlagExplanation, FlagInEnvironment, FlagMatrixEntry, FlagResponse,
+    LifecycleState, PauseFlagRequest, ToggleFlagRequest, build_flag_matrix, check_flag_version, key_aliased_message, key_taken_message, create_flag_warnings, check_rollout_coherence, check_rollout_increase, resolve_expected_version,
+    preview_flag_data, resolve_pause_until, resolve_rollout_percentage, rollout_coherence_warnings, validate_bucketing_keys, validate_initial_lifecycle,
+    validate_as_of, validate_rollout_percentage, history, resolve_preview_sample, RolloutPreviewParams, RolloutPreviewResponse,
+    check_rename, FlagAliasResponse, RenameFlagRequest,
 };
 
 /// Create a new feature flag within an environment
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the original response when a create is retried with the same key"),
+    ),
+    request_body = CreateFlagRequest,
+    responses(
+        (status = 201, description = "Flag created, with warnings about confusing settings", body = CreateFlagResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Invalid request, key and strict rollout validation failures name the field", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Environment not found"),
+        (status = 409, description = "Flag key already exists, or is an alias of another flag"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn create(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id)): Path<(Uuid, Uuid)>,
-    Json(payload): Json<CreateFlagRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Validate flag key
-    validate_flag_key(&payload.key).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    VerifiedEnvironment { project_id, environment_id, user_id }: VerifiedEnvironment,
+    JsonBody(payload): JsonBody<CreateFlagRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (requested_key, key, lifecycle_state) = validate_create(&state, &payload)?;
 
-    // Validate rollout percentage if provided
-    if let Some(percentage) = payload.rollout_percentage {
-        validate_rollout_percentage(percentage).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-    }
+    // The project's default rollout for new flags
+    let project_default_rollout = project_default_rollout(&state, project_id).await?;
 
-    // Check if environment exists, belongs to the project, and user owns the project
-    let environment_exists = sqlx::query_scalar::<_, bool>(
-        r#"
-        SELECT EXISTS(
-            SELECT 1 FROM environments e
-            JOIN projects p ON e.project_id = p.id
-            WHERE e.id = $1 AND e.project_id = $2 AND p.created_by = $3
-        )
-        "#,
-    )
-    .bind(environment_id)
-    .bind(project_id)
-    .bind(user_id)
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        eprintln!("Failed to check environment: {:?}", e);
+    // The alias check and the insert are done together
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
     })?;
 
-    if !environment_exists {
-        return Err((StatusCode::NOT_FOUND, "Environment not found".to_string()));
+    // Share-lock the environment, renames lock it too, so the key can't become an alias meanwhile
+    let aliased = aliased_environments(&mut tx, &[environment_id], &key).await?;
+    if !aliased.is_empty() {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Flag key '{}' is an alias of another flag in this environment", key),
+        )
+        .into());
     }
 
     // Create the flag
-    let flag = match sqlx::query_as::<_, FeatureFlag>(
-        r#"
-        INSERT INTO feature_flags (project_id, environment_id, name, key, description, enabled, rollout_percentage)
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
-        RETURNING id, project_id, environment_id, name, key, description, enabled, rollout_percentage, created_at, updated_at
-        "#,
-    )
-    .bind(project_id)
-    .bind(environment_id)
-    .bind(&payload.name)
-    .bind(&payload.key)
-    .bind(&payload.description)
-    .bind(payload.enabled.unwrap_or(false))
-    .bind(payload.rollout_percentage.unwrap_or(0))
-    .fetch_one(&state.db)
-    .await
-    {
+    let rollout_percentage = resolve_rollout_percentage(payload.rollout_percentage, project_default_rollout);
+    let flag = match insert_flag(&mut *tx, (project_id, environment_id), &key, rollout_percentage, lifecycle_state, &payload, user_id).await {
         Ok(flag) => flag,
         Err(e) => {
             if let Some(db_error) = e.as_database_error() {
@@ -78,65 +82,291 @@ pub async fn create(
                     return Err((
                         StatusCode::CONFLICT,
                         "Flag key already exists in this environment".to_string(),
-                    ));
+                    )
+                    .into());
                 }
             }
             return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Database error: {}", e),
-            ));
+            )
+            .into());
         }
     };
 
-    let response = FlagResponse {
-        id: flag.id,
-        project_id: flag.project_id,
-        environment_id: flag.environment_id,
-        name: flag.name,
-        key: flag.key,
-        description: flag.description,
-        enabled: flag.enabled,
-        rollout_percentage: flag.rollout_percentage,
-        created_at: flag.created_at,
-        updated_at: flag.updated_at,
-    };
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
 
-    Ok((StatusCode::CREATED, Json(response)))
+    webhooks::dispatch(
+        state.db.clone(),
+        state.http.clone(),
+        FlagChangeEvent {
+            project_id,
+            environment_id,
+            flag_key: flag.key.clone(),
+            action: FlagChangeAction::Created,
+            actor_id: user_id,
+        },
+    );
+
+    let warnings = create_flag_warnings(&requested_key, &flag.key, flag.enabled, flag.rollout_percentage);
+
+    Ok((StatusCode::CREATED, Json(CreateFlagResponse { flag: flag.into(), warnings })))
 }
 
-/// List all flags in an environment
-pub async fn list(
+/// Create the same flag in every environment of a project in one transaction
+/// Fails without creating anything when the key is taken in any environment
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}/flags",
+    tag = "flags",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the original response when a create is retried with the same key"),
+    ),
+    request_body = CreateFlagRequest,
+    responses(
+        (status = 201, description = "The flag in every environment, sorted by environment key", body = CreateFlagInAllEnvironmentsResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Invalid request, or the project has no environments", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+        (status = 409, description = "Flag key already exists, or is an alias of another flag, in at least one environment"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_in_all_environments(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id)): Path<(Uuid, Uuid)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Check if environment exists and user owns the project
-    let environment_exists = sqlx::query_scalar::<_, bool>(
-        r#"
-        SELECT EXISTS(
-            SELECT 1 FROM environments e
-            JOIN projects p ON e.project_id = p.id
-            WHERE e.id = $1 AND e.project_id = $2 AND p.created_by = $3
+    VerifiedProject { project_id, user_id }: VerifiedProject,
+    JsonBody(payload): JsonBody<CreateFlagRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (requested_key, key, lifecycle_state) = validate_create(&state, &payload)?;
+
+    // The project's default rollout for new flags
+    let project_default_rollout = project_default_rollout(&state, project_id).await?;
+    let rollout_percentage = resolve_rollout_percentage(payload.rollout_percentage, project_default_rollout);
+
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    // Share-lock the environments so none is deleted while the flag is created in them
+    let environments = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT id, key FROM environments WHERE project_id = $1 ORDER BY key FOR SHARE",
+    )
+    .bind(project_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch environments: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch environments".to_string())
+    })?;
+    if environments.is_empty() {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Project has no environments to create the flag in".to_string(),
         )
+        .into());
+    }
+
+    // Name every environment that already has the key, not just the first insert to collide
+    let taken = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT e.key FROM feature_flags f
+        JOIN environments e ON f.environment_id = e.id
+        WHERE e.project_id = $1 AND f.key = $2
+        ORDER BY e.key
         "#,
     )
-    .bind(environment_id)
     .bind(project_id)
-    .bind(user_id)
-    .fetch_one(&state.db)
+    .bind(&key)
+    .fetch_all(&mut *tx)
     .await
     .map_err(|e| {
-        eprintln!("Failed to check environment: {:?}", e);
+        eprintln!("Failed to check flag key: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+    if let Some(message) = key_taken_message(&key, &taken) {
+        return Err((StatusCode::CONFLICT, message).into());
+    }
+    let environment_ids: Vec<Uuid> = environments.iter().map(|(id, _)| *id).collect();
+    let aliased = aliased_environments(&mut tx, &environment_ids, &key).await?;
+    if let Some(message) = key_aliased_message(&key, &aliased) {
+        return Err((StatusCode::CONFLICT, message).into());
+    }
+
+    let mut flags = Vec::with_capacity(environments.len());
+    let mut environment_ids = Vec::with_capacity(environments.len());
+    for (environment_id, environment_key) in environments {
+        let flag = insert_flag(&mut *tx, (project_id, environment_id), &key, rollout_percentage, lifecycle_state, &payload, user_id)
+            .await
+            .map_err(|e| {
+                // A concurrent create of the same key loses on the unique index
+                if e.as_database_error().and_then(|db_error| db_error.code()).as_deref() == Some("23505") {
+                    return (StatusCode::CONFLICT, format!("Flag key '{}' already exists in environment '{}'", key, environment_key));
+                }
+                eprintln!("Failed to create flag in environment '{}': {:?}", environment_key, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))
+            })?;
+        flags.push(FlagInEnvironment {
+            environment_key,
+            flag: flag.into(),
+        });
+        environment_ids.push(environment_id);
+    }
+
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
     })?;
 
-    if !environment_exists {
-        return Err((StatusCode::NOT_FOUND, "Environment not found".to_string()));
+    for environment_id in environment_ids {
+        webhooks::dispatch(
+            state.db.clone(),
+            state.http.clone(),
+            FlagChangeEvent {
+                project_id,
+                environment_id,
+                flag_key: key.clone(),
+                action: FlagChangeAction::Created,
+                actor_id: user_id,
+            },
+        );
     }
 
+    // Every copy has the same settings, so the warnings are the same for all of them
+    let enabled = payload.enabled.unwrap_or(false);
+    let warnings = create_flag_warnings(&requested_key, &key, enabled, rollout_percentage);
+
+    Ok((StatusCode::CREATED, Json(CreateFlagInAllEnvironmentsResponse { flags, warnings })))
+}
+
+// Keys of the environments where `key` is an alias of a renamed flag, sorted
+// Share-locks the environments, so a rename can't add the alias until the caller commits
+async fn aliased_environments(conn: &mut PgConnection, environment_ids: &[Uuid], key: &str) -> Result<Vec<String>, (StatusCode, String)> {
+    sqlx::query_scalar::<_, Uuid>("SELECT id FROM environments WHERE id = ANY($1) FOR SHARE")
+        .bind(environment_ids)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to lock environments: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?;
+
+    sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT e.key FROM flag_aliases a
+        JOIN environments e ON a.environment_id = e.id
+        WHERE a.environment_id = ANY($1) AND a.alias = $2
+        ORDER BY e.key
+        "#,
+    )
+    .bind(environment_ids)
+    .bind(key)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to check flag aliases: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })
+}
+
+// Default rollout for new flags in a project whose ownership the caller already checked
+async fn project_default_rollout(state: &AppState, project_id: Uuid) -> Result<Option<i32>, (StatusCode, String)> {
+    sqlx::query_scalar::<_, Option<i32>>("SELECT default_rollout_percentage FROM projects WHERE id = $1")
+        .bind(project_id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to fetch project: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+// Checks shared by both create endpoints, returns the key as requested, the key to store and the lifecycle state
+fn validate_create(state: &AppState, payload: &CreateFlagRequest) -> Result<(String, String, LifecycleState), ApiError> {
+    // Validate flag key (derived from the name if omitted), then normalize it to lowercase before insert
+    let requested_key = key_or_slug(payload.key.as_deref(), &payload.name)?;
+    validate_flag_key(&requested_key)?;
+    let key = normalize_flag_key(&requested_key);
+
+    // Validate rollout percentage if provided
+    if let Some(percentage) = payload.rollout_percentage {
+        validate_rollout_percentage(percentage).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    }
+    if let Some(ref keys) = payload.bucketing_keys {
+        validate_bucketing_keys(keys)?;
+    }
+    let lifecycle_state = payload.lifecycle_state.unwrap_or_default();
+    validate_initial_lifecycle(lifecycle_state)?;
+    // Only an explicit rollout is checked, a project default rollout just gets the warnings
+    check_rollout_coherence(
+        state.strict_flag_coherence,
+        payload.enabled.unwrap_or(false),
+        payload.rollout_percentage,
+    )?;
+
+    Ok((requested_key, key, lifecycle_state))
+}
+
+async fn insert_flag<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    (project_id, environment_id): (Uuid, Uuid),
+    key: &str,
+    rollout_percentage: i32,
+    lifecycle_state: LifecycleState,
+    payload: &CreateFlagRequest,
+    user_id: Uuid,
+) -> Result<FeatureFlag, sqlx::Error> {
+    sqlx::query_as::<_, FeatureFlag>(
+        r#"
+        INSERT INTO feature_flags (project_id, environment_id, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, bucketing_keys, lifecycle_state, created_by, updated_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $11, $12, $10, $10)
+        RETURNING id, project_id, environment_id, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, hash_algorithm, bucketing_keys, temporarily_disabled_until, lifecycle_state, version, created_by, updated_by, created_at, updated_at
+        "#,
+    )
+    .bind(project_id)
+    .bind(environment_id)
+    .bind(&payload.name)
+    .bind(key)
+    .bind(&payload.description)
+    .bind(payload.enabled.unwrap_or(false))
+    .bind(rollout_percentage)
+    .bind(payload.client_visible.unwrap_or(false))
+    .bind(payload.anonymous_behavior.unwrap_or_default().as_str())
+    .bind(user_id)
+    .bind(payload.bucketing_keys.as_deref().unwrap_or_default())
+    .bind(lifecycle_state.as_str())
+    .fetch_one(executor)
+    .await
+}
+
+/// List all flags in an environment
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+    ),
+    responses(
+        (status = 200, description = "Flags of the environment", body = Vec<FlagResponse>),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Environment not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    VerifiedEnvironment { environment_id, .. }: VerifiedEnvironment,
+) -> Result<impl IntoResponse, ApiError> {
     let flags = sqlx::query_as::<_, FeatureFlag>(
         r#"
-        SELECT id, project_id, environment_id, name, key, description, enabled, rollout_percentage, created_at, updated_at
+        SELECT id, project_id, environment_id, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, hash_algorithm, bucketing_keys, temporarily_disabled_until, lifecycle_state, version, created_by, updated_by, created_at, updated_at
         FROM feature_flags
         WHERE environment_id = $1
         ORDER BY created_at DESC
@@ -152,17 +382,124 @@ pub async fn list(
 
     let response: Vec<FlagResponse> = flags
         .into_iter()
-        .map(|f| FlagResponse {
-            id: f.id,
-            project_id: f.project_id,
-            environment_id: f.environment_id,
-            name: f.name,
-            key: f.key,
-            description: f.description,
-            enabled: f.enabled,
-            rollout_percentage: f.rollout_percentage,
-            created_at: f.created_at,
-            updated_at: f.updated_at,
+        .map(FlagResponse::from)
+        .collect();
+
+    Ok(Json(response))
+}
+
+// Row of the cross-environment query; flag columns are NULL for environments without flags
+#[derive(Debug, sqlx::FromRow)]
+struct FlagMatrixRow {
+    flag_key: Option<String>,
+    environment_key: Option<String>,
+    enabled: Option<bool>,
+    rollout_percentage: Option<i32>,
+}
+
+/// List every flag key in a project with its state in each environment side by side
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/flags",
+    tag = "flags",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    responses(
+        (status = 200, description = "Every flag key with its state per environment", body = Vec<FlagMatrixEntry>),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_across_environments(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+) -> Result<impl IntoResponse, ApiError> {
+    let rows: Vec<FlagMatrixRow> = sqlx::query_as(
+        r#"
+        SELECT f.key AS flag_key, e.key AS environment_key, f.enabled, f.rollout_percentage
+        FROM environments e
+        LEFT JOIN feature_flags f ON f.environment_id = e.id
+        WHERE e.project_id = $1
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flags: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flags".to_string())
+    })?;
+
+    // Skip environments that have no flags
+    let rows = rows
+        .into_iter()
+        .filter_map(|row| {
+            Some(FlagEnvironmentRow {
+                flag_key: row.flag_key?,
+                environment_key: row.environment_key?,
+                enabled: row.enabled?,
+                rollout_percentage: row.rollout_percentage?,
+            })
+        })
+        .collect();
+
+    Ok(Json(build_flag_matrix(rows)))
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct FlagByKeyRow {
+    environment_key: String,
+    #[sqlx(flatten)]
+    flag: FeatureFlag,
+}
+
+/// Find a flag key in every environment of a project where it is defined
+/// An owned project without the key returns an empty list, only a missing project is a 404
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/flags/by-key/{key}",
+    tag = "flags",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        ("key" = String, Path, description = "Flag key"),
+    ),
+    responses(
+        (status = 200, description = "The flag in every environment that defines it", body = Vec<FlagInEnvironment>),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project or flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_by_key(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    Path((_, key)): Path<(Uuid, String)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let rows = sqlx::query_as::<_, FlagByKeyRow>(
+        r#"
+        SELECT e.key AS environment_key, f.id, f.project_id, f.environment_id, f.name, f.key, f.description, f.enabled, f.rollout_percentage, f.client_visible, f.anonymous_behavior, f.hash_algorithm, f.bucketing_keys, f.temporarily_disabled_until, f.lifecycle_state, f.version, f.created_by, f.updated_by, f.created_at, f.updated_at
+        FROM feature_flags f
+        JOIN environments e ON f.environment_id = e.id
+        WHERE e.project_id = $1 AND f.key = $2
+        ORDER BY e.key
+        "#,
+    )
+    .bind(project_id)
+    .bind(normalize_flag_key(&key))
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flags: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flags".to_string())
+    })?;
+
+    let response: Vec<FlagInEnvironment> = rows
+        .into_iter()
+        .map(|row| FlagInEnvironment {
+            environment_key: row.environment_key,
+            flag: FlagResponse::from(row.flag),
         })
         .collect();
 
@@ -170,87 +507,293 @@ pub async fn list(
 }
 
 /// Get a single flag by ID
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    responses(
+        (status = 200, description = "Flag", body = FlagResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id, flag_id)): Path<(Uuid, Uuid, Uuid)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    VerifiedFlag { flag_id, .. }: VerifiedFlag,
+) -> Result<impl IntoResponse, ApiError> {
     let flag = sqlx::query_as::<_, FeatureFlag>(
         r#"
-        SELECT f.id, f.project_id, f.environment_id, f.name, f.key, f.description, f.enabled, f.rollout_percentage, f.created_at, f.updated_at
-        FROM feature_flags f
-        JOIN environments e ON f.environment_id = e.id
-        JOIN projects p ON e.project_id = p.id
-        WHERE f.id = $1 AND f.environment_id = $2 AND e.project_id = $3 AND p.created_by = $4
+        SELECT id, project_id, environment_id, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, hash_algorithm, bucketing_keys, temporarily_disabled_until, lifecycle_state, version, created_by, updated_by, created_at, updated_at
+        FROM feature_flags
+        WHERE id = $1
         "#,
     )
     .bind(flag_id)
-    .bind(environment_id)
-    .bind(project_id)
-    .bind(user_id)
     .fetch_optional(&state.db)
     .await
     .map_err(|e| {
         eprintln!("Failed to fetch flag: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flag".to_string())
-    })?;
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Flag not found".to_string()))?;
 
-    match flag {
-        Some(f) => {
-            let response = FlagResponse {
-                id: f.id,
-                project_id: f.project_id,
-                environment_id: f.environment_id,
-                name: f.name,
-                key: f.key,
-                description: f.description,
-                enabled: f.enabled,
-                rollout_percentage: f.rollout_percentage,
-                created_at: f.created_at,
-                updated_at: f.updated_at,
-            };
-            Ok(Json(response))
-        }
-        None => Err((StatusCode::NOT_FOUND, "Flag not found".to_string())),
-    }
+    Ok(Json(FlagResponse::from(flag)))
+}
+
+/// Check a flag exists in a project of yours without fetching it, 200 or 404 with no body
+#[utoipa::path(
+    head,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    responses(
+        (status = 200, description = "Flag exists"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn exists(_: VerifiedFlag) -> StatusCode {
+    StatusCode::OK
 }
 
-/// Update a feature flag
+/// Update some fields of a feature flag, omitted fields keep their value
+#[utoipa::path(
+    patch,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ("If-Match" = Option<String>, Header, description = "Expected flag version"),
+    ),
+    request_body = UpdateFlagRequest,
+    responses(
+        (status = 200, description = "Updated flag, with warnings about confusing settings", body = CreateFlagResponse),
+        (status = 202, description = "The environment requires approval, the update was stored as a pending change", body = PendingChangeResponse),
+        (status = 400, description = "Malformed JSON body, missing fields or malformed If-Match header"),
+        (status = 422, description = "Invalid request, strict rollout validation failures name the field", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+        (status = 409, description = "Flag version does not match"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn update(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id, flag_id)): Path<(Uuid, Uuid, Uuid)>,
-    Json(payload): Json<UpdateFlagRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    verified: VerifiedFlag,
+    headers: HeaderMap,
+    JsonBody(payload): JsonBody<UpdateFlagRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    apply_update(state, verified, &headers, payload, UpdateMode::Patch).await
+}
+
+/// Replace a feature flag's settings, omitted optional fields are cleared
+#[utoipa::path(
+    put,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ("If-Match" = Option<String>, Header, description = "Expected flag version"),
+    ),
+    request_body = ReplaceFlagRequest,
+    responses(
+        (status = 200, description = "Updated flag, with warnings about confusing settings", body = CreateFlagResponse),
+        (status = 202, description = "The environment requires approval, the update was stored as a pending change", body = PendingChangeResponse),
+        (status = 400, description = "Malformed JSON body, missing fields or malformed If-Match header"),
+        (status = 422, description = "Invalid request, strict rollout validation failures name the field", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+        (status = 409, description = "Flag version does not match"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn replace(
+    State(state): State<AppState>,
+    verified: VerifiedFlag,
+    headers: HeaderMap,
+    JsonBody(payload): JsonBody<ReplaceFlagRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    apply_update(state, verified, &headers, payload.into(), UpdateMode::Replace).await
+}
+
+// Shared by PATCH and PUT, which differ only in how omitted fields are treated
+async fn apply_update(
+    state: AppState,
+    VerifiedFlag { project_id, environment_id, flag_id, user_id }: VerifiedFlag,
+    headers: &HeaderMap,
+    payload: UpdateFlagRequest,
+    mode: UpdateMode,
+) -> Result<Response, ApiError> {
     // Validate rollout percentage if provided
     if let Some(percentage) = payload.rollout_percentage {
-        validate_rollout_percentage(percentage).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        validate_rollout_percentage(percentage).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
     }
 
-    // Check if flag exists and user owns the project
-    let exists = sqlx::query_scalar::<_, bool>(
-        r#"
-        SELECT EXISTS(
-            SELECT 1 FROM feature_flags f
-            JOIN environments e ON f.environment_id = e.id
-            JOIN projects p ON e.project_id = p.id
-            WHERE f.id = $1 AND f.environment_id = $2 AND e.project_id = $3 AND p.created_by = $4
+    // Optional optimistic concurrency check via If-Match header or expected_version
+    let if_match = headers
+        .get(header::IF_MATCH)
+        .map(|v| v.to_str().unwrap_or_default());
+    let expected_version = resolve_expected_version(if_match, payload.expected_version)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let reason = audit::normalize_reason(payload.reason.as_deref()).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    if let Some(ref keys) = payload.bucketing_keys {
+        validate_bucketing_keys(keys)?;
+    }
+
+    let current = load_update_target(&state, flag_id).await?;
+    check_update(&state, &current, expected_version, &payload)?;
+
+    // In environments that require approval the change waits for an approver, the live flag is untouched
+    if pending_changes::check_direct_write(current.requires_approval, FlagWrite::Update)? == WriteGate::Propose {
+        let change = pending_changes::propose(
+            &state.db,
+            NewPendingChange {
+                project_id,
+                environment_id,
+                flag_id,
+                proposed_by: user_id,
+                change: ProposedChange::Flag(mode, &payload),
+                base_version: current.version,
+                reason,
+            },
         )
+        .await?;
+        return Ok((StatusCode::ACCEPTED, Json(PendingChangeResponse::from(change))).into_response());
+    }
+
+    let response = write_update(&state, (project_id, environment_id), &current, &payload, mode, user_id, reason, None).await?;
+    Ok(Json(response).into_response())
+}
+
+/// Apply an approved pending change, as if its proposer had sent the update now
+/// Fails with 409 if the flag changed since the change was proposed
+pub async fn apply_pending_change(
+    state: &AppState,
+    change: &PendingChange,
+    approver_id: Uuid,
+) -> Result<CreateFlagResponse, ApiError> {
+    let payload: UpdateFlagRequest = serde_json::from_value(change.change.clone()).map_err(|e| {
+        eprintln!("Failed to read pending change {}: {:?}", change.id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to apply change".to_string())
+    })?;
+    let mode = UpdateMode::parse(&change.mode)
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Failed to apply change".to_string()))?;
+
+    let current = load_update_target(state, change.flag_id).await?;
+    check_update(state, &current, Some(change.base_version), &payload)?;
+
+    let approval = Approval {
+        change_id: change.id,
+        proposed_by: change.proposed_by,
+    };
+    write_update(
+        state,
+        (change.project_id, change.environment_id),
+        &current,
+        &payload,
+        mode,
+        approver_id,
+        change.reason.clone(),
+        Some(approval),
+    )
+    .await
+}
+
+// The flag an update is checked against, with the project's rollout guardrail
+#[derive(sqlx::FromRow)]
+struct UpdateTarget {
+    id: Uuid,
+    version: i64,
+    enabled: bool,
+    rollout_percentage: i32,
+    description: Option<String>,
+    max_rollout_increase: Option<i32>,
+    requires_approval: bool,
+}
+
+// A pending change being applied by write_update
+struct Approval {
+    change_id: Uuid,
+    proposed_by: Option<Uuid>,
+}
+
+async fn load_update_target(state: &AppState, flag_id: Uuid) -> Result<UpdateTarget, (StatusCode, String)> {
+    sqlx::query_as::<_, UpdateTarget>(
+        r#"
+        SELECT f.id, f.version, f.enabled, f.rollout_percentage, f.description,
+            p.max_rollout_increase_percent AS max_rollout_increase, e.requires_approval
+        FROM feature_flags f
+        JOIN environments e ON f.environment_id = e.id
+        JOIN projects p ON e.project_id = p.id
+        WHERE f.id = $1
         "#,
     )
     .bind(flag_id)
-    .bind(environment_id)
-    .bind(project_id)
-    .bind(user_id)
-    .fetch_one(&state.db)
+    .fetch_optional(&state.db)
     .await
     .map_err(|e| {
         eprintln!("Failed to check flag: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Flag not found".to_string()))
+}
+
+// Checks an update must pass against the current flag, both when sent and when approved
+fn check_update(
+    state: &AppState,
+    current: &UpdateTarget,
+    expected_version: Option<i64>,
+    payload: &UpdateFlagRequest,
+) -> Result<(), ApiError> {
+    check_flag_version(expected_version, current.version).map_err(|e| (StatusCode::CONFLICT, e))?;
+
+    // The version guard on the UPDATE in write_update ensures the rollout is still the prior value
+    if let Some(percentage) = payload.rollout_percentage {
+        check_rollout_increase(current.rollout_percentage, percentage, current.max_rollout_increase)
+            .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    }
+    check_rollout_coherence(
+        state.strict_flag_coherence,
+        payload.enabled.unwrap_or(current.enabled),
+        payload.rollout_percentage,
+    )?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn write_update(
+    state: &AppState,
+    (project_id, environment_id): (Uuid, Uuid),
+    current: &UpdateTarget,
+    payload: &UpdateFlagRequest,
+    mode: UpdateMode,
+    user_id: Uuid,
+    reason: Option<String>,
+    approval: Option<Approval>,
+) -> Result<CreateFlagResponse, ApiError> {
+    // The update, its audit entry and the approval are written together
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
     })?;
 
-    if !exists {
-        return Err((StatusCode::NOT_FOUND, "Flag not found".to_string()));
+    if let Some(approval) = &approval {
+        pending_changes::mark_approved(&mut tx, approval.change_id, user_id).await?;
     }
 
     let flag = sqlx::query_as::<_, FeatureFlag>(
@@ -258,119 +801,1130 @@ pub async fn update(
         UPDATE feature_flags
         SET
             name = COALESCE($2, name),
-            description = COALESCE($3, description),
+            description = $3,
             enabled = COALESCE($4, enabled),
             rollout_percentage = COALESCE($5, rollout_percentage),
+            client_visible = COALESCE($6, client_visible),
+            anonymous_behavior = COALESCE($7, anonymous_behavior),
+            hash_algorithm = COALESCE($10, hash_algorithm),
+            bucketing_keys = COALESCE($11, bucketing_keys),
+            version = version + 1,
+            updated_by = $9,
             updated_at = NOW()
-        WHERE id = $1
-        RETURNING id, project_id, environment_id, name, key, description, enabled, rollout_percentage, created_at, updated_at
+        WHERE id = $1 AND version = $8
+        RETURNING id, project_id, environment_id, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, hash_algorithm, bucketing_keys, temporarily_disabled_until, lifecycle_state, version, created_by, updated_by, created_at, updated_at
         "#,
     )
-    .bind(flag_id)
+    .bind(current.id)
     .bind(payload.name.as_deref())
-    .bind(payload.description.as_deref())
+    .bind(mode.description(payload.description.as_deref(), current.description.as_deref()))
     .bind(payload.enabled)
     .bind(payload.rollout_percentage)
-    .fetch_one(&state.db)
+    .bind(payload.client_visible)
+    .bind(payload.anonymous_behavior.map(|b| b.as_str()))
+    .bind(current.version)
+    .bind(user_id)
+    .bind(payload.hash_algorithm.map(|a| a.as_str()))
+    .bind(payload.bucketing_keys.as_deref())
+    .fetch_optional(&mut *tx)
     .await
     .map_err(|e| {
         eprintln!("Failed to update flag: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update flag".to_string())
+    })?
+    // Someone else updated the flag between the version check and the write
+    .ok_or((
+        StatusCode::CONFLICT,
+        "Flag was modified by someone else, reload and try again".to_string(),
+    ))?;
+
+    let mut details = serde_json::json!({
+        "flag_id": flag.id,
+        "flag_key": flag.key,
+        "fields": mode.changed_fields(payload),
+    });
+    if let Some(approval) = &approval {
+        details["pending_change_id"] = serde_json::json!(approval.change_id);
+        details["proposed_by"] = serde_json::json!(approval.proposed_by);
+    }
+    audit::record(
+        &mut tx,
+        AuditEntry {
+            project_id,
+            environment_id: Some(environment_id),
+            actor_id: user_id,
+            action: AuditAction::FlagUpdate,
+            details,
+            reason,
+        },
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to record audit entry: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update flag".to_string())
     })?;
 
-    let response = FlagResponse {
-        id: flag.id,
-        project_id: flag.project_id,
-        environment_id: flag.environment_id,
-        name: flag.name,
-        key: flag.key,
-        description: flag.description,
-        enabled: flag.enabled,
-        rollout_percentage: flag.rollout_percentage,
-        created_at: flag.created_at,
-        updated_at: flag.updated_at,
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    // A PUT always sends `enabled`, so only report a toggle when the state actually changed
+    let toggled = payload.enabled.filter(|enabled| mode == UpdateMode::Patch || *enabled != current.enabled);
+    let action = match toggled {
+        Some(true) => FlagChangeAction::Enabled,
+        Some(false) => FlagChangeAction::Disabled,
+        None => FlagChangeAction::Updated,
     };
+    webhooks::dispatch(
+        state.db.clone(),
+        state.http.clone(),
+        FlagChangeEvent {
+            project_id,
+            environment_id,
+            flag_key: flag.key.clone(),
+            action,
+            actor_id: user_id,
+        },
+    );
 
-    Ok(Json(response))
+    // Only warn about the rollout when this request changed it or turned the flag on
+    let warnings = if payload.rollout_percentage.is_some() || payload.enabled == Some(true) {
+        let has_enabled_rules = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM flag_rules WHERE flag_id = $1 AND enabled)",
+        )
+        .bind(flag.id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to check flag rules: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?;
+        rollout_coherence_warnings(flag.enabled, flag.rollout_percentage, has_enabled_rules)
+    } else {
+        Vec::new()
+    };
+
+    let flag = FlagResponse::from(flag);
+
+    Ok(CreateFlagResponse { flag, warnings })
 }
 
 /// Delete a feature flag
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    responses(
+        (status = 204, description = "Flag deleted"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+        (status = 409, description = "The environment requires approval"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn delete(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id, flag_id)): Path<(Uuid, Uuid, Uuid)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let result = sqlx::query(
-        r#"
-        DELETE FROM feature_flags f
-        USING environments e, projects p
-        WHERE f.id = $1 AND f.environment_id = $2
-        AND e.id = f.environment_id AND e.project_id = $3
-        AND p.id = e.project_id AND p.created_by = $4
-        "#,
-    )
+    VerifiedFlag { project_id, environment_id, flag_id, user_id }: VerifiedFlag,
+) -> Result<impl IntoResponse, ApiError> {
+    pending_changes::direct_write_gate(&state.db, environment_id, FlagWrite::Delete).await?;
+    let deleted_key = sqlx::query_scalar::<_, String>("DELETE FROM feature_flags WHERE id = $1 RETURNING key")
     .bind(flag_id)
-    .bind(environment_id)
-    .bind(project_id)
-    .bind(user_id)
-    .execute(&state.db)
+    .fetch_optional(&state.db)
     .await
     .map_err(|e| {
         eprintln!("Failed to delete flag: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete flag".to_string())
     })?;
 
-    if result.rows_affected() == 0 {
-        return Err((StatusCode::NOT_FOUND, "Flag not found".to_string()));
-    }
+    let flag_key = match deleted_key {
+        Some(key) => key,
+        None => return Err((StatusCode::NOT_FOUND, "Flag not found".to_string()).into()),
+    };
+
+    webhooks::dispatch(
+        state.db.clone(),
+        state.http.clone(),
+        FlagChangeEvent {
+            project_id,
+            environment_id,
+            flag_key,
+            action: FlagChangeAction::Deleted,
+            actor_id: user_id,
+        },
+    );
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 /// Toggle a flag's enabled state
+/// An optional `reason` is kept with the audit log entry for the toggle
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/toggle",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    request_body(content = Option<ToggleFlagRequest>, description = "Optional reason for the toggle"),
+    responses(
+        (status = 200, description = "Flag with enabled flipped", body = FlagResponse),
+        (status = 202, description = "The environment requires approval, the flip was stored as a pending change", body = PendingChangeResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Reason too long"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn toggle(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id, flag_id)): Path<(Uuid, Uuid, Uuid)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    VerifiedFlag { project_id, environment_id, flag_id, user_id }: VerifiedFlag,
+    payload: Option<JsonBody<ToggleFlagRequest>>,
+) -> Result<Response, ApiError> {
+    let JsonBody(payload) = payload.unwrap_or_default();
+    let reason = audit::normalize_reason(payload.reason.as_deref()).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    // In environments that require approval the flip is proposed like any other update
+    let current = load_update_target(&state, flag_id).await?;
+    if pending_changes::check_direct_write(current.requires_approval, FlagWrite::Toggle)? == WriteGate::Propose {
+        let update = UpdateFlagRequest {
+            enabled: Some(!current.enabled),
+            ..Default::default()
+        };
+        let change = pending_changes::propose(
+            &state.db,
+            NewPendingChange {
+                project_id,
+                environment_id,
+                flag_id,
+                proposed_by: user_id,
+                change: ProposedChange::Flag(UpdateMode::Patch, &update),
+                base_version: current.version,
+                reason,
+            },
+        )
+        .await?;
+        return Ok((StatusCode::ACCEPTED, Json(PendingChangeResponse::from(change))).into_response());
+    }
+
+    // The toggle and its audit entry are written together
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
     let flag = sqlx::query_as::<_, FeatureFlag>(
         r#"
-        UPDATE feature_flags f
-        SET enabled = NOT f.enabled, updated_at = NOW()
-        FROM environments e, projects p
-        WHERE f.id = $1 AND f.environment_id = $2
-        AND e.id = f.environment_id AND e.project_id = $3
-        AND p.id = e.project_id AND p.created_by = $4
-        RETURNING f.id, f.project_id, f.environment_id, f.name, f.key, f.description, f.enabled, f.rollout_percentage, f.created_at, f.updated_at
+        UPDATE feature_flags
+        SET enabled = NOT enabled, version = version + 1, updated_by = $2, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, project_id, environment_id, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, hash_algorithm, bucketing_keys, temporarily_disabled_until, lifecycle_state, version, created_by, updated_by, created_at, updated_at
         "#,
     )
     .bind(flag_id)
-    .bind(environment_id)
-    .bind(project_id)
     .bind(user_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(|e| {
         eprintln!("Failed to toggle flag: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to toggle flag".to_string())
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Flag not found".to_string()))?;
+
+    audit::record(
+        &mut tx,
+        AuditEntry {
+            project_id,
+            environment_id: Some(environment_id),
+            actor_id: user_id,
+            action: AuditAction::FlagToggle,
+            details: serde_json::json!({
+                "flag_id": flag.id,
+                "flag_key": flag.key,
+                "enabled": flag.enabled,
+            }),
+            reason,
+        },
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to record audit entry: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to toggle flag".to_string())
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    let action = if flag.enabled {
+        FlagChangeAction::Enabled
+    } else {
+        FlagChangeAction::Disabled
+    };
+    webhooks::dispatch(
+        state.db.clone(),
+        state.http.clone(),
+        FlagChangeEvent {
+            project_id,
+            environment_id,
+            flag_key: flag.key.clone(),
+            action,
+            actor_id: user_id,
+        },
+    );
+
+    let response = FlagResponse::from(flag);
+    Ok(Json(response).into_response())
+}
+
+/// Pause a flag: it evaluates to false until the pause ends, without touching enabled or the rollout
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/pause",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    request_body = PauseFlagRequest,
+    responses(
+        (status = 200, description = "Flag with temporarily_disabled_until set", body = FlagResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Missing, past or too distant end of the pause"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+        (status = 409, description = "The environment requires approval"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn pause(
+    State(state): State<AppState>,
+    verified: VerifiedFlag,
+    JsonBody(payload): JsonBody<PauseFlagRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let until = resolve_pause_until(&payload, state.clock.now()).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    set_pause(&state, verified, Some(until)).await
+}
+
+/// End a flag's pause early
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/pause",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    responses(
+        (status = 200, description = "Flag with temporarily_disabled_until cleared", body = FlagResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+        (status = 409, description = "The environment requires approval"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn resume(
+    State(state): State<AppState>,
+    verified: VerifiedFlag,
+) -> Result<impl IntoResponse, ApiError> {
+    set_pause(&state, verified, None).await
+}
+
+// Sets or clears temporarily_disabled_until and notifies webhooks
+async fn set_pause(
+    state: &AppState,
+    VerifiedFlag { project_id, environment_id, flag_id, user_id }: VerifiedFlag,
+    until: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<Json<FlagResponse>, ApiError> {
+    pending_changes::direct_write_gate(&state.db, environment_id, FlagWrite::Pause).await?;
+    let flag = sqlx::query_as::<_, FeatureFlag>(
+        r#"
+        UPDATE feature_flags
+        SET temporarily_disabled_until = $3, version = version + 1, updated_by = $2, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, project_id, environment_id, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, hash_algorithm, bucketing_keys, temporarily_disabled_until, lifecycle_state, version, created_by, updated_by, created_at, updated_at
+        "#,
+    )
+    .bind(flag_id)
+    .bind(user_id)
+    .bind(until)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to pause flag: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update flag".to_string())
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Flag not found".to_string()))?;
+
+    webhooks::dispatch(
+        state.db.clone(),
+        state.http.clone(),
+        FlagChangeEvent {
+            project_id,
+            environment_id,
+            flag_key: flag.key.clone(),
+            action: if until.is_some() { FlagChangeAction::Paused } else { FlagChangeAction::Resumed },
+            actor_id: user_id,
+        },
+    );
+
+    Ok(Json(FlagResponse::from(flag)))
+}
+
+/// Promote a flag: drafts start being served to SDKs, deprecated flags are reinstated
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/promote",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    responses(
+        (status = 200, description = "Flag with lifecycle_state active", body = FlagResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+        (status = 409, description = "Flag is already active, or the environment requires approval"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn promote(
+    State(state): State<AppState>,
+    verified: VerifiedFlag,
+) -> Result<impl IntoResponse, ApiError> {
+    set_lifecycle(&state, verified, LifecycleState::promote).await
+}
+
+/// Deprecate an active flag: it keeps being served, but evaluations mark it as deprecated
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/deprecate",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    responses(
+        (status = 200, description = "Flag with lifecycle_state deprecated", body = FlagResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+        (status = 409, description = "Flag is a draft or already deprecated, or the environment requires approval"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn deprecate(
+    State(state): State<AppState>,
+    verified: VerifiedFlag,
+) -> Result<impl IntoResponse, ApiError> {
+    set_lifecycle(&state, verified, LifecycleState::deprecate).await
+}
+
+// Moves a flag to the state `transition` allows from its current one and notifies webhooks
+// The current state is locked so two concurrent transitions can't both pass the check
+async fn set_lifecycle(
+    state: &AppState,
+    VerifiedFlag { project_id, environment_id, flag_id, user_id }: VerifiedFlag,
+    transition: fn(&LifecycleState) -> Result<LifecycleState, String>,
+) -> Result<Json<FlagResponse>, ApiError> {
+    pending_changes::direct_write_gate(&state.db, environment_id, FlagWrite::Lifecycle).await?;
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
     })?;
 
-    match flag {
-        Some(f) => {
-            let response = FlagResponse {
-                id: f.id,
-                project_id: f.project_id,
-                environment_id: f.environment_id,
-                name: f.name,
-                key: f.key,
-                description: f.description,
-                enabled: f.enabled,
-                rollout_percentage: f.rollout_percentage,
-                created_at: f.created_at,
-                updated_at: f.updated_at,
-            };
-            Ok(Json(response))
+    let current = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT lifecycle_state FROM feature_flags WHERE id = $1 FOR UPDATE
+        "#,
+    )
+    .bind(flag_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flag: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update flag".to_string())
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Flag not found".to_string()))?;
+
+    let next = transition(&LifecycleState::from_db(&current)).map_err(|e| (StatusCode::CONFLICT, e))?;
+
+    let flag = sqlx::query_as::<_, FeatureFlag>(
+        r#"
+        UPDATE feature_flags
+        SET lifecycle_state = $2, version = version + 1, updated_by = $3, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, project_id, environment_id, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, hash_algorithm, bucketing_keys, temporarily_disabled_until, lifecycle_state, version, created_by, updated_by, created_at, updated_at
+        "#,
+    )
+    .bind(flag_id)
+    .bind(next.as_str())
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to update flag lifecycle: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update flag".to_string())
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    webhooks::dispatch(
+        state.db.clone(),
+        state.http.clone(),
+        FlagChangeEvent {
+            project_id,
+            environment_id,
+            flag_key: flag.key.clone(),
+            action: if next == LifecycleState::Deprecated {
+                FlagChangeAction::Deprecated
+            } else {
+                FlagChangeAction::Promoted
+            },
+            actor_id: user_id,
+        },
+    );
+
+    Ok(Json(FlagResponse::from(flag)))
+}
+
+/// Change a flag's key, keeping the old key as an alias so SDKs asking for it keep getting the flag
+/// Fails with 409 when the new key is another flag's key or alias in the environment
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rename",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    request_body = RenameFlagRequest,
+    responses(
+        (status = 200, description = "Flag with its new key", body = FlagResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Invalid key, the flag's current key, or reason too long", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+        (status = 409, description = "Key is taken by another flag or alias in the environment, or the environment requires approval"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn rename(
+    State(state): State<AppState>,
+    VerifiedFlag { project_id, environment_id, flag_id, user_id }: VerifiedFlag,
+    JsonBody(payload): JsonBody<RenameFlagRequest>,
+) -> Result<Json<FlagResponse>, ApiError> {
+    pending_changes::direct_write_gate(&state.db, environment_id, FlagWrite::Rename).await?;
+    let reason = audit::normalize_reason(payload.reason.as_deref()).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    // The key change, its alias and the audit entry are written together
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    // Wait for flag creates in the environment, which check the aliases under a share lock
+    sqlx::query("SELECT id FROM environments WHERE id = $1 FOR NO KEY UPDATE")
+        .bind(environment_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to lock environment: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to rename flag".to_string())
+        })?;
+
+    // Locked so a concurrent rename can't alias the same old key twice
+    let old_key = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT key FROM feature_flags WHERE id = $1 FOR UPDATE
+        "#,
+    )
+    .bind(flag_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flag: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to rename flag".to_string())
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Flag not found".to_string()))?;
+
+    let key = check_rename(&old_key, &payload.key)?;
+
+    // Renaming back to one of the flag's own aliases is allowed, the alias is dropped
+    let alias_of = sqlx::query_scalar::<_, Uuid>(
+        "SELECT flag_id FROM flag_aliases WHERE environment_id = $1 AND alias = $2",
+    )
+    .bind(environment_id)
+    .bind(&key)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to check flag aliases: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to rename flag".to_string())
+    })?;
+    match alias_of {
+        Some(other) if other != flag_id => {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("Flag key '{}' is an alias of another flag in this environment", key),
+            )
+            .into());
         }
-        None => Err((StatusCode::NOT_FOUND, "Flag not found".to_string())),
+        Some(_) => {
+            sqlx::query("DELETE FROM flag_aliases WHERE environment_id = $1 AND alias = $2")
+                .bind(environment_id)
+                .bind(&key)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| {
+                    eprintln!("Failed to delete flag alias: {:?}", e);
+                    (StatusCode::INTERNAL_SERVER_ERROR, "Failed to rename flag".to_string())
+                })?;
+        }
+        None => {}
     }
+
+    let flag = sqlx::query_as::<_, FeatureFlag>(
+        r#"
+        UPDATE feature_flags
+        SET key = $2, version = version + 1, updated_by = $3, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, project_id, environment_id, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, hash_algorithm, bucketing_keys, temporarily_disabled_until, lifecycle_state, version, created_by, updated_by, created_at, updated_at
+        "#,
+    )
+    .bind(flag_id)
+    .bind(&key)
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        if let Some(db_error) = e.as_database_error() {
+            if db_error.code() == Some(std::borrow::Cow::Borrowed("23505")) {
+                return (StatusCode::CONFLICT, "Flag key already exists in this environment".to_string());
+            }
+        }
+        eprintln!("Failed to rename flag: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to rename flag".to_string())
+    })?;
+
+    // An alias left over from a flag that used to hold the old key now points here, as the live key did
+    sqlx::query(
+        r#"
+        INSERT INTO flag_aliases (environment_id, alias, flag_id, created_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (environment_id, alias)
+        DO UPDATE SET flag_id = EXCLUDED.flag_id, created_by = EXCLUDED.created_by, created_at = NOW()
+        "#,
+    )
+    .bind(environment_id)
+    .bind(&old_key)
+    .bind(flag_id)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to insert flag alias: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to rename flag".to_string())
+    })?;
+
+    audit::record(
+        &mut tx,
+        AuditEntry {
+            project_id,
+            environment_id: Some(environment_id),
+            actor_id: user_id,
+            action: AuditAction::FlagRename,
+            details: serde_json::json!({
+                "flag_id": flag.id,
+                "old_key": old_key,
+                "new_key": flag.key,
+            }),
+            reason,
+        },
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to record audit entry: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to rename flag".to_string())
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    webhooks::dispatch(
+        state.db.clone(),
+        state.http.clone(),
+        FlagChangeEvent {
+            project_id,
+            environment_id,
+            flag_key: flag.key.clone(),
+            action: FlagChangeAction::Updated,
+            actor_id: user_id,
+        },
+    );
+
+    Ok(Json(flag.into()))
+}
+
+/// List the former keys SDK evaluations still resolve to a flag
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/aliases",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    responses(
+        (status = 200, description = "Aliases of the flag, newest first", body = [FlagAliasResponse]),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_aliases(
+    State(state): State<AppState>,
+    VerifiedFlag { flag_id, .. }: VerifiedFlag,
+) -> Result<impl IntoResponse, ApiError> {
+    let aliases = sqlx::query_as::<_, FlagAliasResponse>(
+        r#"
+        SELECT alias, flag_id, environment_id, created_by, created_at
+        FROM flag_aliases
+        WHERE flag_id = $1
+        ORDER BY created_at DESC, alias
+        "#,
+    )
+    .bind(flag_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flag aliases: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch aliases".to_string())
+    })?;
+
+    Ok(Json(aliases))
+}
+
+/// Remove an alias once clients have moved to the new key, SDKs asking for it then get the unknown flag answer
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/aliases/{alias}",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ("alias" = String, Path, description = "Former key of the flag"),
+    ),
+    responses(
+        (status = 204, description = "Alias removed"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag or alias not found"),
+        (status = 409, description = "The environment requires approval"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_alias(
+    State(state): State<AppState>,
+    VerifiedFlag { environment_id, flag_id, .. }: VerifiedFlag,
+    Path((_, _, _, alias)): Path<(Uuid, Uuid, Uuid, String)>,
+) -> Result<StatusCode, ApiError> {
+    pending_changes::direct_write_gate(&state.db, environment_id, FlagWrite::AliasDelete).await?;
+    let result = sqlx::query("DELETE FROM flag_aliases WHERE flag_id = $1 AND alias = $2")
+        .bind(flag_id)
+        .bind(normalize_flag_key(&alias))
+        .execute(&state.db)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to delete flag alias: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete alias".to_string())
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Alias not found".to_string()).into());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Kill switch: disable every enabled flag in an environment in one transaction
+/// Records an audit entry and notifies webhooks for each flag that was turned off
+/// Applies immediately in environments that require approval too: it only turns flags off, and
+/// an incident can't wait for an approver (see `FlagWrite::DisableAll`)
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/disable-all",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+    ),
+    responses(
+        (status = 200, description = "Flags that were disabled", body = DisableAllResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Environment not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn disable_all(
+    State(state): State<AppState>,
+    VerifiedEnvironment { project_id, environment_id, user_id }: VerifiedEnvironment,
+) -> Result<impl IntoResponse, ApiError> {
+    // Exempt from approval, checked anyway so the policy of every write stays in FlagWrite
+    pending_changes::direct_write_gate(&state.db, environment_id, FlagWrite::DisableAll).await?;
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    let mut flag_keys = sqlx::query_scalar::<_, String>(
+        r#"
+        UPDATE feature_flags
+        SET enabled = FALSE, version = version + 1, updated_by = $2, updated_at = NOW()
+        WHERE environment_id = $1 AND enabled
+        RETURNING key
+        "#,
+    )
+    .bind(environment_id)
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to disable flags: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to disable flags".to_string())
+    })?;
+    flag_keys.sort();
+
+    audit::record(
+        &mut tx,
+        AuditEntry {
+            project_id,
+            environment_id: Some(environment_id),
+            actor_id: user_id,
+            action: AuditAction::FlagsDisableAll,
+            details: serde_json::json!({ "flag_keys": flag_keys }),
+            reason: None,
+        },
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to record audit entry: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to disable flags".to_string())
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    for flag_key in &flag_keys {
+        webhooks::dispatch(
+            state.db.clone(),
+            state.http.clone(),
+            FlagChangeEvent {
+                project_id,
+                environment_id,
+                flag_key: flag_key.clone(),
+                action: FlagChangeAction::Disabled,
+                actor_id: user_id,
+            },
+        );
+    }
+
+    Ok(Json(DisableAllResponse {
+        disabled_count: flag_keys.len(),
+        flag_keys,
+    }))
+}
+
+/// List a flag's logged SDK evaluations, newest first
+/// Uses keyset pagination on (evaluated_at, id), pass `next_cursor` back as `?cursor=`
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/evaluations",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        PageParams,
+    ),
+    responses(
+        (status = 200, description = "One page of logged evaluations, newest first", body = Page<FlagEvaluationEntry>),
+        (status = 400, description = "Invalid cursor"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_evaluations(
+    State(state): State<AppState>,
+    VerifiedFlag { flag_id, .. }: VerifiedFlag,
+    Query(params): Query<PageParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let cursor = params.cursor().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let limit = params.limit();
+
+    let rows = sqlx::query_as::<_, FlagEvaluationEntry>(
+        r#"
+        SELECT id, user_identifier, result, sample_rate, evaluated_at
+        FROM flag_evaluations
+        WHERE flag_id = $1
+        AND ($2::timestamptz IS NULL OR (evaluated_at, id) < ($2, $3))
+        ORDER BY evaluated_at DESC, id DESC
+        LIMIT $4
+        "#,
+    )
+    .bind(flag_id)
+    .bind(cursor.map(|c| c.created_at))
+    .bind(cursor.map(|c| c.id))
+    .bind(limit + 1)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch evaluations: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch evaluations".to_string())
+    })?;
+
+    let page = paginate(rows, limit, |row| Cursor {
+        created_at: row.evaluated_at,
+        id: row.id,
+    });
+
+    Ok(Json(page))
+}
+
+/// Bucket a sample of synthetic users through the flag's current rollout and report the share enabled
+/// Rules, overrides and the enabled switch are ignored; nothing is stored or logged
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rollout-preview",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        RolloutPreviewParams,
+    ),
+    responses(
+        (status = 200, description = "Share of the sample the rollout enables", body = RolloutPreviewResponse),
+        (status = 400, description = "Sample is 0 or above the cap"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn rollout_preview(
+    State(state): State<AppState>,
+    VerifiedFlag { flag_id, .. }: VerifiedFlag,
+    Query(params): Query<RolloutPreviewParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let sample = resolve_preview_sample(params.sample).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let (flag_key, hash_algorithm, rollout_percentage) = sqlx::query_as::<_, (String, String, i32)>(
+        "SELECT key, hash_algorithm, rollout_percentage FROM feature_flags WHERE id = $1",
+    )
+    .bind(flag_id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flag: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+    let hash_algorithm = HashAlgorithm::from_db(&hash_algorithm);
+
+    // Up to MAX_ROLLOUT_PREVIEW_SAMPLE hashes, kept off the async workers
+    let key = flag_key.clone();
+    let enabled_count = tokio::task::spawn_blocking(move || simulate_rollout(hash_algorithm, &key, rollout_percentage, sample))
+        .await
+        .map_err(|e| {
+            eprintln!("Rollout preview failed: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Rollout preview failed".to_string())
+        })?;
+
+    Ok(Json(RolloutPreviewResponse {
+        flag_key,
+        hash_algorithm,
+        rollout_percentage,
+        sample,
+        enabled_count,
+        enabled_fraction: enabled_count as f64 / sample as f64,
+    }))
+}
+
+/// Evaluate an unsaved flag definition for a user context, including the per-rule trace
+/// Powers "what if" views of flag edits; nothing is stored or logged
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}/evaluate-preview",
+    tag = "flags",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    request_body = EvaluatePreviewRequest,
+    responses(
+        (status = 200, description = "Evaluation of the draft with the rule trace", body = FlagExplanation),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Invalid draft flag or rule", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn evaluate_preview(
+    State(state): State<AppState>,
+    _: VerifiedProject,
+    JsonBody(payload): JsonBody<EvaluatePreviewRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (flag_data, rules) = preview_flag_data(&payload.flag)?;
+    let now = state.clock.now();
+    let evaluation = evaluate_flag_traced(&flag_data, &rules, &payload.context, now, true);
+
+    Ok(Json(FlagExplanation {
+        flag_key: flag_data.key,
+        evaluated_at: now,
+        evaluation,
+    }))
+}
+
+/// Preview how a flag evaluates for a user context, including the per-rule trace
+/// Lets the flag's owner test targeting without an SDK key; nothing is logged to flag_evaluations
+/// `?as_of=` replays the evaluation against the flag's configuration at that time, from flag_history
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/evaluate",
+    tag = "flags",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ExplainParams,
+    ),
+    request_body = UserContext,
+    responses(
+        (status = 200, description = "Evaluation result with the rule trace", body = FlagExplanation),
+        (status = 400, description = "as_of is malformed, in the future, or before the flag's recorded history"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn explain(
+    State(state): State<AppState>,
+    VerifiedFlag { flag_id, .. }: VerifiedFlag,
+    Query(params): Query<ExplainParams>,
+    JsonBody(context): JsonBody<UserContext>,
+) -> Result<impl IntoResponse, ApiError> {
+    let flag = sqlx::query_as::<_, FeatureFlag>(
+        r#"
+        SELECT id, project_id, environment_id, name, key, description, enabled, rollout_percentage, client_visible, anonymous_behavior, hash_algorithm, bucketing_keys, temporarily_disabled_until, lifecycle_state, version, created_by, updated_by, created_at, updated_at
+        FROM feature_flags WHERE id = $1
+        "#,
+    )
+    .bind(flag_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flag: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flag".to_string())
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Flag not found".to_string()))?;
+
+    // A past configuration is evaluated at that time too, so time windows read as they did then
+    if let Some(as_of) = params.as_of {
+        validate_as_of(as_of, state.clock.now()).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        let (flag_data, rules) = history::load_flag_at(&state.db, flag_id, as_of)
+            .await
+            .map_err(|e| {
+                eprintln!("Failed to fetch flag history: {:?}", e);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flag history".to_string())
+            })?
+            .ok_or((
+                StatusCode::BAD_REQUEST,
+                format!("No history for this flag at {}, it didn't exist yet or predates history recording", as_of.to_rfc3339()),
+            ))?;
+        let evaluation = evaluate_flag_traced(&flag_data, &rules, &context, as_of, true);
+
+        return Ok(Json(FlagExplanation {
+            flag_key: flag_data.key,
+            evaluated_at: as_of,
+            evaluation,
+        }));
+    }
+
+    let rules = sqlx::query_as::<_, (Uuid, String, String, Option<serde_json::Value>, bool, i32, bool, Option<serde_json::Value>, Option<i32>)>(
+        r#"
+        SELECT id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage
+        FROM flag_rules
+        WHERE flag_id = $1
+        ORDER BY priority DESC
+        "#,
+    )
+    .bind(flag_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch rules: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch rules".to_string())
+    })?
+    .into_iter()
+    .map(|(id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage)| RuleData {
+        id: Some(id),
+        rule_type,
+        rule_value,
+        rule_value_json,
+        enabled,
+        priority,
+        negate,
+        payload,
+        rollout_percentage,
+    })
+    .collect::<Vec<_>>();
+
+    let overrides = load_overrides(&state.db, &[flag_id], &context)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to fetch overrides: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch overrides".to_string())
+        })?
+        .remove(&flag_id)
+        .unwrap_or_default();
+
+    let flag_data = FlagData {
+        key: flag.key.clone(),
+        enabled: flag.enabled,
+        rollout_percentage: flag.rollout_percentage,
+        anonymous_behavior: AnonymousBehavior::from_db(&flag.anonymous_behavior),
+        hash_algorithm: HashAlgorithm::from_db(&flag.hash_algorithm),
+        bucketing_keys: BucketingKey::from_db(&flag.bucketing_keys),
+        temporarily_disabled_until: flag.temporarily_disabled_until,
+        overrides,
+    };
+    let now = state.clock.now();
+    let evaluation = evaluate_flag_traced(&flag_data, &rules, &context, now, true);
+
+    Ok(Json(FlagExplanation {
+        flag_key: flag.key,
+        evaluated_at: now,
+        evaluation,
+    }))
 }
@@ -0,0 +1,384 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::routes::error::json_error;
+
+/// Header clients send to make a create request safe to retry
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Header set on responses replayed from a previous request
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Maximum accepted length of an Idempotency-Key
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+#[derive(Debug, PartialEq, sqlx::FromRow)]
+struct StoredResponse {
+    request_path: String,
+    request_hash: Option<String>,  // None for keys stored before bodies were hashed
+    status_code: Option<i32>,      // None while the first request with the key is still running
+    response_body: Option<serde_json::Value>,
+}
+
+/// Middleware for create endpoints: when an Idempotency-Key header is present, a repeat of
+/// the same request within 24h returns the original response instead of creating again.
+/// The key is reserved before the handler runs, so a concurrent repeat gets a 409 rather than
+/// creating twice, and reusing a key with a different body is a 422.
+/// Must run after `require_auth`, keys are scoped per user.
+pub async fn idempotent(req: Request, next: Next) -> Result<Response, Response> {
+    let key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) => {
+            let key = value.to_str().unwrap_or_default();
+            validate_idempotency_key(key).map_err(|e| json_error(StatusCode::BAD_REQUEST, e))?;
+            key.to_string()
+        }
+        None => return Ok(next.run(req).await),
+    };
+
+    let user_id = req
+        .extensions()
+        .get::<Uuid>()
+        .copied()
+        .ok_or_else(|| json_error(StatusCode::UNAUTHORIZED, "missing user"))?;
+    let pool = req
+        .extensions()
+        .get::<PgPool>()
+        .cloned()
+        .ok_or_else(|| json_error(StatusCode::INTERNAL_SERVER_ERROR, "Database pool not found"))?;
+    let request_path = req.uri().path().to_string();
+
+    // Short of a dropped connection, reading the body only fails on the body limit
+    let (parts, body) = req.into_parts();
+    let body = to_bytes(body, usize::MAX)
+        .await
+        .map_err(|_| json_error(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large"))?;
+    let request_hash = hash_request_body(&body);
+    let req = Request::from_parts(parts, Body::from(body));
+
+    // Reserve the key, taking over an expired one; the unique constraint lets one request win
+    let reserved = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO idempotency_keys (user_id, idempotency_key, request_path, request_hash)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id, idempotency_key) DO UPDATE SET
+            request_path = EXCLUDED.request_path,
+            request_hash = EXCLUDED.request_hash,
+            resource_id = NULL,
+            status_code = NULL,
+            response_body = NULL,
+            created_at = NOW()
+        WHERE idempotency_keys.created_at <= NOW() - INTERVAL '24 hours'
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(&key)
+    .bind(&request_path)
+    .bind(&request_hash)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to reserve idempotency key: {:?}", e);
+        json_error(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+    })?;
+
+    // When the key is taken, look up what is stored under it to replay it, or tell the client why not
+    let stored = match reserved {
+        Some(_) => None,
+        None => sqlx::query_as::<_, StoredResponse>(
+            r#"
+            SELECT request_path, request_hash, status_code, response_body
+            FROM idempotency_keys
+            WHERE user_id = $1 AND idempotency_key = $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(&key)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to look up idempotency key: {:?}", e);
+            json_error(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+        })?,
+    };
+
+    let id = match decide(reserved, stored, &request_path, &request_hash).map_err(|(status, message)| json_error(status, message))? {
+        KeyDecision::Run(id) => id,
+        KeyDecision::Replay(stored) => return Ok(replay(stored)),
+    };
+
+    // Released if the request fails or is cancelled (e.g. by the timeout layer) before completing
+    let mut reservation = Reservation { pool: pool.clone(), id: Some(id) };
+
+    let response = next.run(req).await;
+
+    if !should_remember(response.status()) {
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = to_bytes(body, usize::MAX).await.map_err(|e| {
+        eprintln!("Failed to buffer response body: {:?}", e);
+        json_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to read response")
+    })?;
+
+    if let Ok(body) = serde_json::from_slice::<serde_json::Value>(&bytes) {
+        let result = sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET resource_id = $2, status_code = $3, response_body = $4
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(resource_id(&body))
+        .bind(i32::from(parts.status.as_u16()))
+        .bind(&body)
+        .execute(&pool)
+        .await;
+        match result {
+            Ok(_) => reservation.id = None,
+            Err(e) => eprintln!("Failed to store idempotency key: {:?}", e),
+        }
+    }
+
+    Ok(Response::from_parts(parts, Body::from(bytes)))
+}
+
+const IN_FLIGHT_MESSAGE: &str = "A request with this Idempotency-Key is still in progress";
+
+/// A key reserved by a request in flight, deleted on drop unless its response was stored
+struct Reservation {
+    pool: PgPool,
+    id: Option<Uuid>,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let Some(id) = self.id.take() else { return };
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            let result = sqlx::query("DELETE FROM idempotency_keys WHERE id = $1 AND status_code IS NULL")
+                .bind(id)
+                .execute(&pool)
+                .await;
+            if let Err(e) = result {
+                eprintln!("Failed to release idempotency key: {:?}", e);
+            }
+        });
+    }
+}
+
+/// What to do with a request after trying to reserve its key
+#[derive(Debug, PartialEq)]
+enum KeyDecision {
+    /// The key was reserved for this request, run the handler
+    Run(Uuid),
+    /// The key's first request finished, answer with its response
+    Replay(StoredResponse),
+}
+
+/// Decide from the reservation attempt and, when it lost, the row stored under the key
+fn decide(
+    reserved: Option<Uuid>,
+    stored: Option<StoredResponse>,
+    request_path: &str,
+    request_hash: &str,
+) -> Result<KeyDecision, (StatusCode, &'static str)> {
+    if let Some(id) = reserved {
+        return Ok(KeyDecision::Run(id));
+    }
+    // Released by a failed request since the reservation attempt, retrying will reserve it
+    let Some(stored) = stored else {
+        return Err((StatusCode::CONFLICT, IN_FLIGHT_MESSAGE));
+    };
+    check_stored(&stored, request_path, request_hash)?;
+    Ok(KeyDecision::Replay(stored))
+}
+
+/// Only successful creates are remembered, so a failed request can be retried with the same key
+fn should_remember(status: StatusCode) -> bool {
+    status.is_success()
+}
+
+/// Check a request may be answered with the response stored under its key
+/// A different endpoint or body is a 422, a key whose first request hasn't finished is a 409
+fn check_stored(stored: &StoredResponse, request_path: &str, request_hash: &str) -> Result<(), (StatusCode, &'static str)> {
+    if stored.request_path != request_path {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Idempotency-Key was already used for a different request",
+        ));
+    }
+    if stored.request_hash.as_deref().is_some_and(|hash| hash != request_hash) {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Idempotency-Key was already used with a different request body",
+        ));
+    }
+    if stored.status_code.is_none() {
+        return Err((StatusCode::CONFLICT, IN_FLIGHT_MESSAGE));
+    }
+    Ok(())
+}
+
+/// SHA-256 of a request body, hex encoded
+fn hash_request_body(body: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(body))
+}
+
+/// Rebuild the original response from the stored status and body
+fn replay(stored: StoredResponse) -> Response {
+    let status = stored
+        .status_code
+        .and_then(|s| u16::try_from(s).ok())
+        .and_then(|s| StatusCode::from_u16(s).ok())
+        .unwrap_or(StatusCode::OK);
+
+    let body = stored.response_body.unwrap_or_default();
+    let mut response = (status, axum::Json(body)).into_response();
+    response
+        .headers_mut()
+        .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// Validate an Idempotency-Key header value
+pub fn validate_idempotency_key(key: &str) -> Result<(), String> {
+    if key.trim().is_empty() {
+        return Err("Idempotency-Key cannot be empty".to_string());
+    }
+
+    if key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+        return Err(format!(
+            "Idempotency-Key is too long (Max: {} characters)",
+            MAX_IDEMPOTENCY_KEY_LENGTH
+        ));
+    }
+
+    Ok(())
+}
+
+/// The created resource's id, taken from the "id" field of the response body
+fn resource_id(body: &serde_json::Value) -> Option<Uuid> {
+    body.get("id")?.as_str()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(status_code: Option<i32>) -> StoredResponse {
+        StoredResponse {
+            request_path: "/api/projects".to_string(),
+            request_hash: Some(hash_request_body(br#"{"name":"Web"}"#)),
+            status_code,
+            response_body: status_code.map(|_| serde_json::json!({ "id": Uuid::nil() })),
+        }
+    }
+
+    #[test]
+    fn test_validate_idempotency_key() {
+        assert!(validate_idempotency_key("create-project-7f3a").is_ok());
+        assert!(validate_idempotency_key(&"k".repeat(MAX_IDEMPOTENCY_KEY_LENGTH)).is_ok());
+
+        assert!(validate_idempotency_key("").is_err());
+        assert!(validate_idempotency_key("   ").is_err());
+        assert!(validate_idempotency_key(&"k".repeat(MAX_IDEMPOTENCY_KEY_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_resource_id() {
+        let id = Uuid::new_v4();
+        assert_eq!(resource_id(&serde_json::json!({ "id": id.to_string(), "name": "x" })), Some(id));
+
+        assert_eq!(resource_id(&serde_json::json!({ "name": "x" })), None);
+        assert_eq!(resource_id(&serde_json::json!({ "id": "not-a-uuid" })), None);
+        assert_eq!(resource_id(&serde_json::json!([1, 2, 3])), None);
+    }
+
+    #[test]
+    fn test_replay() {
+        let response = replay(stored(Some(201)));
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[IDEMPOTENT_REPLAYED_HEADER], "true");
+    }
+
+    #[test]
+    fn test_check_stored() {
+        let hash = hash_request_body(br#"{"name":"Web"}"#);
+
+        // A finished request with the same endpoint and body is replayed
+        assert_eq!(check_stored(&stored(Some(201)), "/api/projects", &hash), Ok(()));
+
+        // Another endpoint or body under the same key is a client mistake
+        let (status, _) = check_stored(&stored(Some(201)), "/api/projects/other", &hash).unwrap_err();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let other = hash_request_body(br#"{"name":"Mobile"}"#);
+        assert_eq!(
+            check_stored(&stored(Some(201)), "/api/projects", &other),
+            Err((StatusCode::UNPROCESSABLE_ENTITY, "Idempotency-Key was already used with a different request body"))
+        );
+
+        // The first request is still running
+        assert_eq!(check_stored(&stored(None), "/api/projects", &hash), Err((StatusCode::CONFLICT, IN_FLIGHT_MESSAGE)));
+
+        // Keys stored before bodies were hashed match any body
+        let legacy = StoredResponse { request_hash: None, ..stored(Some(201)) };
+        assert_eq!(check_stored(&legacy, "/api/projects", &other), Ok(()));
+    }
+
+    #[test]
+    fn test_decide() {
+        let hash = hash_request_body(br#"{"name":"Web"}"#);
+        let id = Uuid::new_v4();
+
+        // The request that reserved the key runs, whatever else is stored
+        assert_eq!(decide(Some(id), None, "/api/projects", &hash), Ok(KeyDecision::Run(id)));
+
+        // A repeat of a finished request is replayed
+        assert_eq!(
+            decide(None, Some(stored(Some(201))), "/api/projects", &hash),
+            Ok(KeyDecision::Replay(stored(Some(201))))
+        );
+
+        // A concurrent repeat while the first request runs, or after it released the key, is a 409
+        assert_eq!(
+            decide(None, Some(stored(None)), "/api/projects", &hash),
+            Err((StatusCode::CONFLICT, IN_FLIGHT_MESSAGE))
+        );
+        assert_eq!(decide(None, None, "/api/projects", &hash), Err((StatusCode::CONFLICT, IN_FLIGHT_MESSAGE)));
+
+        // The same key with a different body is a 422, even while the first request runs
+        let other = hash_request_body(br#"{"name":"Mobile"}"#);
+        assert_eq!(
+            decide(None, Some(stored(None)), "/api/projects", &other),
+            Err((StatusCode::UNPROCESSABLE_ENTITY, "Idempotency-Key was already used with a different request body"))
+        );
+    }
+
+    #[test]
+    fn test_hash_request_body() {
+        assert_eq!(hash_request_body(br#"{"name":"Web"}"#), hash_request_body(br#"{"name":"Web"}"#));
+        assert_ne!(hash_request_body(br#"{"name":"Web"}"#), hash_request_body(br#"{"name": "Web"}"#));
+        assert_eq!(hash_request_body(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+
+    #[test]
+    fn test_should_remember() {
+        assert!(should_remember(StatusCode::CREATED));
+        assert!(should_remember(StatusCode::OK));
+        assert!(!should_remember(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(!should_remember(StatusCode::CONFLICT));
+        assert!(!should_remember(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+}
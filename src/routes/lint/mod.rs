@@ -0,0 +1,294 @@
+pub mod routes;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::evaluation::RuleData;
+
+// MODELS
+
+// How bad a finding is: errors change what users get, warnings are likely mistakes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One problem in a project's configuration
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Finding {
+    pub severity: Severity,
+    pub code: &'static str,  // Stable identifier of the check, e.g. "duplicate_rule"
+    pub environment_key: String,
+    pub flag_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<Uuid>,  // The rule the finding is about, none for flag-level findings
+    pub message: String,
+}
+
+/// Result of validating every flag and rule of a project
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValidationReport {
+    pub error_count: usize,
+    pub warning_count: usize,
+    pub findings: Vec<Finding>,  // Errors first, then by environment, flag and rule priority
+}
+
+impl ValidationReport {
+    pub fn new(mut findings: Vec<Finding>) -> Self {
+        // Stable, so findings of one flag keep the order the checks produced them in
+        findings.sort_by(|a, b| {
+            (a.severity, &a.environment_key, &a.flag_key).cmp(&(b.severity, &b.environment_key, &b.flag_key))
+        });
+        let error_count = findings.iter().filter(|f| f.severity == Severity::Error).count();
+        Self {
+            error_count,
+            warning_count: findings.len() - error_count,
+            findings,
+        }
+    }
+}
+
+/// A flag with its rules, as the checks see it
+#[derive(Debug, Clone)]
+pub struct FlagConfig {
+    pub environment_key: String,
+    pub key: String,
+    pub enabled: bool,
+    pub rollout_percentage: i32,
+    pub rules: Vec<RuleData>,  // Highest priority first, ties in stored order, like evaluation
+}
+
+impl FlagConfig {
+    fn finding(&self, severity: Severity, code: &'static str, rule: Option<&RuleData>, message: String) -> Finding {
+        Finding {
+            severity,
+            code,
+            environment_key: self.environment_key.clone(),
+            flag_key: self.key.clone(),
+            rule_id: rule.and_then(|rule| rule.id),
+            message,
+        }
+    }
+}
+
+// CHECKS
+
+/// A check over one flag, each check looks for one kind of problem
+pub type Check = fn(&FlagConfig) -> Vec<Finding>;
+
+/// Every check the validate endpoint runs, in report order for a flag
+pub const CHECKS: &[Check] = &[
+    check_unlimited_rollout,
+    check_malformed_rules,
+    check_orphan_percentage_rules,
+    check_duplicate_rules,
+];
+
+/// Run all checks over all flags
+pub fn validate_flags(flags: &[FlagConfig]) -> ValidationReport {
+    ValidationReport::new(flags.iter().flat_map(|flag| CHECKS.iter().flat_map(move |check| check(flag))).collect())
+}
+
+/// Enabled with a 0% rollout and no enabled rules: 0 means no rollout, so every user gets the flag
+pub fn check_unlimited_rollout(flag: &FlagConfig) -> Vec<Finding> {
+    let has_enabled_rules = flag.rules.iter().any(|rule| rule.enabled);
+    if !flag.enabled || flag.rollout_percentage != 0 || has_enabled_rules {
+        return Vec::new();
+    }
+    vec![flag.finding(
+        Severity::Warning,
+        "unlimited_rollout",
+        None,
+        "Flag is enabled with rollout_percentage 0 and no rules, every user gets it".to_string(),
+    )]
+}
+
+/// Enabled rules whose stored value doesn't parse, SDK evaluate serves such flags off as evaluation_error
+pub fn check_malformed_rules(flag: &FlagConfig) -> Vec<Finding> {
+    flag.rules
+        .iter()
+        .filter(|rule| rule.enabled && rule.is_malformed())
+        .map(|rule| {
+            flag.finding(
+                Severity::Error,
+                "malformed_rule",
+                Some(rule),
+                format!(
+                    "{} rule value '{}' can't be parsed, the flag is served disabled until it is fixed",
+                    rule.rule_type, rule.rule_value
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Percentage rules only narrow the targeting rule directly before them, so anywhere else they never apply
+pub fn check_orphan_percentage_rules(flag: &FlagConfig) -> Vec<Finding> {
+    flag.rules
+        .iter()
+        .enumerate()
+        .filter(|(i, rule)| {
+            let narrows = i
+                .checked_sub(1)
+                .map(|previous| &flag.rules[previous])
+                .is_some_and(|previous| previous.enabled && previous.rule_type != "percentage" && !previous.is_time_window());
+            rule.enabled && rule.rule_type == "percentage" && !narrows
+        })
+        .map(|(_, rule)| {
+            flag.finding(
+                Severity::Warning,
+                "orphan_percentage_rule",
+                Some(rule),
+                "Percentage rule has no targeting rule right before it, so it never applies".to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Enabled rules identical to a higher-priority one can never change the result
+pub fn check_duplicate_rules(flag: &FlagConfig) -> Vec<Finding> {
+    let enabled: Vec<&RuleData> = flag.rules.iter().filter(|rule| rule.enabled).collect();
+    enabled
+        .iter()
+        .enumerate()
+        .filter(|(i, rule)| {
+            // A percentage rule depends on its neighbour, so equal values aren't duplicates
+            rule.rule_type != "percentage" && enabled[..*i].iter().any(|earlier| same_condition(earlier, rule))
+        })
+        .map(|(_, rule)| {
+            flag.finding(
+                Severity::Warning,
+                "duplicate_rule",
+                Some(rule),
+                format!(
+                    "{} rule '{}' duplicates a higher-priority rule and never fires on its own",
+                    rule.rule_type, rule.rule_value
+                ),
+            )
+        })
+        .collect()
+}
+
+fn same_condition(a: &RuleData, b: &RuleData) -> bool {
+    a.rule_type == b.rule_type
+        && a.rule_value.trim() == b.rule_value.trim()
+        && a.negate == b.negate
+        && a.rollout_percentage == b.rollout_percentage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(rule_type: &str, rule_value: &str, priority: i32) -> RuleData {
+        RuleData {
+            id: Some(Uuid::new_v4()),
+            rule_type: rule_type.to_string(),
+            rule_value: rule_value.to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority,
+            negate: false,
+            payload: None,
+            rollout_percentage: None,
+        }
+    }
+
+    fn flag(enabled: bool, rollout_percentage: i32, rules: Vec<RuleData>) -> FlagConfig {
+        FlagConfig {
+            environment_key: "production".to_string(),
+            key: "new_checkout".to_string(),
+            enabled,
+            rollout_percentage,
+            rules,
+        }
+    }
+
+    fn codes(findings: &[Finding]) -> Vec<&'static str> {
+        findings.iter().map(|f| f.code).collect()
+    }
+
+    #[test]
+    fn test_check_unlimited_rollout() {
+        assert_eq!(codes(&check_unlimited_rollout(&flag(true, 0, vec![]))), vec!["unlimited_rollout"]);
+        assert!(check_unlimited_rollout(&flag(true, 25, vec![])).is_empty());
+        assert!(check_unlimited_rollout(&flag(false, 0, vec![])).is_empty());
+        assert!(check_unlimited_rollout(&flag(true, 0, vec![rule("user_id", "alice", 1)])).is_empty());
+
+        // A disabled rule doesn't limit anything
+        let disabled = RuleData { enabled: false, ..rule("user_id", "alice", 1) };
+        assert_eq!(check_unlimited_rollout(&flag(true, 0, vec![disabled])).len(), 1);
+    }
+
+    #[test]
+    fn test_check_malformed_rules() {
+        let broken = rule("active_after", "next tuesday", 5);
+        let findings = check_malformed_rules(&flag(true, 10, vec![broken.clone(), rule("user_id", "alice", 1)]));
+        assert_eq!(codes(&findings), vec!["malformed_rule"]);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(findings[0].rule_id, broken.id);
+
+        let disabled = RuleData { enabled: false, ..broken };
+        assert!(check_malformed_rules(&flag(true, 10, vec![disabled])).is_empty());
+    }
+
+    #[test]
+    fn test_check_orphan_percentage_rules() {
+        // Narrowing the rule right before it is the intended use
+        let segment = vec![rule("email_domain", "@company.com", 10), rule("percentage", "50", 9)];
+        assert!(check_orphan_percentage_rules(&flag(true, 0, segment)).is_empty());
+
+        let first = vec![rule("percentage", "50", 10), rule("user_id", "alice", 1)];
+        assert_eq!(codes(&check_orphan_percentage_rules(&flag(true, 0, first))), vec!["orphan_percentage_rule"]);
+
+        let stacked = vec![rule("user_id", "alice", 10), rule("percentage", "50", 9), rule("percentage", "20", 8)];
+        let findings = check_orphan_percentage_rules(&flag(true, 0, stacked.clone()));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule_id, stacked[2].id);
+
+        // A disabled rule in between detaches it, as in evaluation
+        let detached = vec![
+            rule("user_id", "alice", 10),
+            RuleData { enabled: false, ..rule("user_id", "bob", 9) },
+            rule("percentage", "50", 8),
+        ];
+        assert_eq!(check_orphan_percentage_rules(&flag(true, 0, detached)).len(), 1);
+    }
+
+    #[test]
+    fn test_check_duplicate_rules() {
+        let rules = vec![
+            rule("email_domain", "@company.com", 10),
+            rule("user_id", "alice", 5),
+            rule("email_domain", " @company.com ", 1),
+        ];
+        let findings = check_duplicate_rules(&flag(true, 0, rules.clone()));
+        assert_eq!(codes(&findings), vec!["duplicate_rule"]);
+        assert_eq!(findings[0].rule_id, rules[2].id);
+
+        // Negation or a rule rollout makes it a different condition
+        let negated = RuleData { negate: true, ..rule("user_id", "alice", 1) };
+        let partial = RuleData { rollout_percentage: Some(20), ..rule("user_id", "alice", 1) };
+        assert!(check_duplicate_rules(&flag(true, 0, vec![rule("user_id", "alice", 5), negated, partial])).is_empty());
+    }
+
+    #[test]
+    fn test_validate_flags_report() {
+        let flags = vec![
+            FlagConfig { environment_key: "staging".to_string(), ..flag(true, 0, vec![]) },
+            flag(true, 10, vec![rule("local_time_window", "9-5", 1)]),
+            flag(false, 10, vec![rule("user_id", "alice", 1)]),
+        ];
+        let report = validate_flags(&flags);
+        assert_eq!((report.error_count, report.warning_count), (1, 1));
+        // Errors come first whatever the environment
+        assert_eq!(codes(&report.findings), vec!["malformed_rule", "unlimited_rollout"]);
+
+        let json = serde_json::to_value(&report.findings[1]).unwrap();
+        assert_eq!(json["severity"], "warning");
+        assert!(json.get("rule_id").is_none());
+    }
+}
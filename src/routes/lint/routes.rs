@@ -0,0 +1,117 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::evaluation::RuleData;
+use crate::routes::error::ApiError;
+use crate::routes::ownership::VerifiedProject;
+use crate::state::AppState;
+use super::{validate_flags, FlagConfig, ValidationReport};
+
+#[derive(Debug, sqlx::FromRow)]
+struct FlagRow {
+    id: Uuid,
+    environment_key: String,
+    key: String,
+    enabled: bool,
+    rollout_percentage: i32,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct RuleRow {
+    id: Uuid,
+    flag_id: Uuid,
+    rule_type: String,
+    rule_value: String,
+    rule_value_json: Option<serde_json::Value>,
+    enabled: bool,
+    priority: i32,
+    negate: bool,
+    rollout_percentage: Option<i32>,
+}
+
+// HANDLERS
+
+/// Check every flag and rule of a project for likely mistakes
+/// Nothing is changed, the report lists errors and warnings with the flag and rule they are about
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/validate",
+    tag = "projects",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    responses(
+        (status = 200, description = "Findings across all environments, errors first", body = ValidationReport),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn validate(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+) -> Result<impl IntoResponse, ApiError> {
+    let flags = sqlx::query_as::<_, FlagRow>(
+        r#"
+        SELECT f.id, e.key AS environment_key, f.key, f.enabled, f.rollout_percentage
+        FROM feature_flags f
+        JOIN environments e ON f.environment_id = e.id
+        WHERE e.project_id = $1
+        ORDER BY e.key, f.key
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flags: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flags".to_string())
+    })?;
+
+    // Rules come in evaluation order, the checks rely on it
+    let flag_ids: Vec<Uuid> = flags.iter().map(|f| f.id).collect();
+    let rules = sqlx::query_as::<_, RuleRow>(
+        r#"
+        SELECT id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, rollout_percentage
+        FROM flag_rules
+        WHERE flag_id = ANY($1)
+        ORDER BY priority DESC
+        "#,
+    )
+    .bind(&flag_ids)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch rules: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch rules".to_string())
+    })?;
+
+    let mut rules_by_flag: HashMap<Uuid, Vec<RuleData>> = HashMap::new();
+    for rule in rules {
+        rules_by_flag.entry(rule.flag_id).or_default().push(RuleData {
+            id: Some(rule.id),
+            rule_type: rule.rule_type,
+            rule_value: rule.rule_value,
+            rule_value_json: rule.rule_value_json,
+            enabled: rule.enabled,
+            priority: rule.priority,
+            negate: rule.negate,
+            payload: None,
+            rollout_percentage: rule.rollout_percentage,
+        });
+    }
+
+    let flags: Vec<FlagConfig> = flags
+        .into_iter()
+        .map(|flag| FlagConfig {
+            rules: rules_by_flag.remove(&flag.id).unwrap_or_default(),
+            environment_key: flag.environment_key,
+            key: flag.key,
+            enabled: flag.enabled,
+            rollout_percentage: flag.rollout_percentage,
+        })
+        .collect();
+
+    Ok(Json(validate_flags(&flags)))
+}
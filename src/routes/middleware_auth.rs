@@ -7,16 +7,23 @@ use axum::{
 };
 use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde::Deserialize;
+use sqlx::PgPool;
 use std::env;
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 
+use crate::routes::api_tokens::{required_permission, token_allows, API_TOKEN_PREFIX};
+use crate::routes::error::{ApiError, AuthRejection, BEARER_CHALLENGE};
+use crate::routes::projects::hash_sdk_key;
+use crate::routes::sdk_keys::should_touch_last_used;
+
 pub struct JwtUser(pub Uuid);
 
 impl<S> FromRequestParts<S> for JwtUser
 where
     S: Send + Sync,
 {
-    type Rejection = (StatusCode, &'static str);
+    type Rejection = ApiError;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
         parts
@@ -24,7 +31,7 @@ where
             .get::<Uuid>()
             .copied()
             .map(JwtUser)
-            .ok_or((StatusCode::UNAUTHORIZED, "missing user"))
+            .ok_or_else(|| ApiError::Status(StatusCode::UNAUTHORIZED, "missing user".to_string()))
     }
 }
 
@@ -36,6 +43,16 @@ struct Claims {
     iat: usize,
 }
 
+/// Whether a login token was issued before the user revoked their sessions
+/// Token times have whole seconds, so a token from the second of the revocation stays valid
+fn issued_before_revocation(iat: usize, sessions_revoked_at: Option<chrono::DateTime<chrono::Utc>>) -> bool {
+    sessions_revoked_at.is_some_and(|revoked_at| (iat as i64) < revoked_at.timestamp())
+}
+
+/// Authenticate management API requests with a user JWT or a project API token
+/// Handlers then check ownership and answer 404, not 403, for other users' projects and
+/// everything in them, so a user can't probe which ids exist. 403 is only returned here,
+/// for API tokens used outside their project or permissions
 pub async fn require_auth(mut req: Request, next: Next) -> Result<Response, impl IntoResponse> {
     let auth_header = req
         .headers()
@@ -45,10 +62,15 @@ pub async fn require_auth(mut req: Request, next: Next) -> Result<Response, impl
     let token = match auth_header {
         Some(h) if h.starts_with("Bearer ") => &h[7..],
         _ => {
-            return Err((StatusCode::UNAUTHORIZED, "missing token"));
+            return Err(AuthRejection::unauthorized(BEARER_CHALLENGE, "missing token"));
         }
     };
 
+    if token.starts_with(API_TOKEN_PREFIX) {
+        let token = token.to_string();
+        return authenticate_api_token(req, next, &token).await;
+    }
+
     let secret = env::var("JWT_SECRET").expect("JWT is not found");
 
     let token_data = match decode::<Claims>(
@@ -59,15 +81,172 @@ pub async fn require_auth(mut req: Request, next: Next) -> Result<Response, impl
         Ok(data) => data,
         Err(e) => {
             eprintln!("JWT decode error: {}", e);
-            return Err((StatusCode::UNAUTHORIZED, "invalid token"));
+            return Err(AuthRejection::unauthorized(BEARER_CHALLENGE, "invalid token"));
         }
     };
 
-    match Uuid::parse_str(&token_data.claims.sub) {
-        Ok(user_id) => {
-            req.extensions_mut().insert(user_id);
-            Ok(next.run(req).await)
+    let Ok(user_id) = Uuid::parse_str(&token_data.claims.sub) else {
+        return Err(AuthRejection::unauthorized(BEARER_CHALLENGE, "invalid subject"));
+    };
+
+    // Tokens issued before a password change that revoked sessions are no longer accepted
+    let pool = req
+        .extensions()
+        .get::<PgPool>()
+        .ok_or(AuthRejection::internal("Database pool not found"))?;
+    let sessions_revoked_at = sqlx::query_scalar::<_, Option<chrono::DateTime<chrono::Utc>>>(
+        "SELECT sessions_revoked_at FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        eprintln!("Database error checking sessions: {:?}", e);
+        AuthRejection::internal("Database error")
+    })?
+    .flatten();
+    if issued_before_revocation(token_data.claims.iat, sessions_revoked_at) {
+        return Err(AuthRejection::unauthorized(BEARER_CHALLENGE, "token revoked"));
+    }
+
+    req.extensions_mut().insert(user_id);
+    Ok(next.run(req).await)
+}
+
+/// Whether a user may use the admin endpoints, None for a user that no longer exists
+fn check_admin(is_admin: Option<bool>) -> Result<(), AuthRejection> {
+    match is_admin {
+        Some(true) => Ok(()),
+        _ => Err(AuthRejection::forbidden("admin access required")),
+    }
+}
+
+/// Gate the admin endpoints on the user's is_admin flag, runs after `require_auth`
+/// The flag is read on every request rather than carried in the JWT, so revoking it takes effect at once.
+/// API tokens never get this far, `required_permission` only covers project paths
+pub async fn require_admin(req: Request, next: Next) -> Result<Response, AuthRejection> {
+    let user_id = req
+        .extensions()
+        .get::<Uuid>()
+        .copied()
+        .ok_or(AuthRejection::unauthorized(BEARER_CHALLENGE, "missing user"))?;
+    let pool = req
+        .extensions()
+        .get::<PgPool>()
+        .ok_or(AuthRejection::internal("Database pool not found"))?;
+
+    let is_admin = sqlx::query_scalar::<_, bool>("SELECT is_admin FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            eprintln!("Database error checking admin: {:?}", e);
+            AuthRejection::internal("Database error")
+        })?;
+    check_admin(is_admin)?;
+
+    Ok(next.run(req).await)
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ApiTokenRow {
+    id: Uuid,
+    project_id: Uuid,
+    token_hash: String,
+    permissions: Vec<String>,
+    last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    owner_id: Uuid,
+}
+
+/// Authenticate a project API token and check it covers the request
+/// The request then runs as the project's owner, so handlers apply their usual ownership checks
+async fn authenticate_api_token(
+    mut req: Request,
+    next: Next,
+    token: &str,
+) -> Result<Response, AuthRejection> {
+    let Some((project_id, needed)) = required_permission(req.method(), req.uri().path()) else {
+        return Err(AuthRejection::forbidden("API tokens cannot be used for this endpoint"));
+    };
+
+    let pool = req
+        .extensions()
+        .get::<PgPool>()
+        .ok_or(AuthRejection::internal("Database pool not found"))?;
+
+    let token_hash = hash_sdk_key(token);
+    let row: Option<ApiTokenRow> = sqlx::query_as(
+        r#"
+        SELECT t.id, t.project_id, t.token_hash, t.permissions, t.last_used_at, p.created_by AS owner_id
+        FROM project_api_tokens t
+        JOIN projects p ON t.project_id = p.id
+        WHERE t.token_hash = $1
+        AND t.revoked_at IS NULL
+        AND (t.expires_at IS NULL OR t.expires_at > NOW())
+        "#,
+    )
+    .bind(&token_hash)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        eprintln!("Database error validating API token: {:?}", e);
+        AuthRejection::internal("Database error")
+    })?;
+
+    // Re-check the stored hash in constant time before trusting the row
+    let Some(row) = row.filter(|t| bool::from(t.token_hash.as_bytes().ct_eq(token_hash.as_bytes()))) else {
+        return Err(AuthRejection::unauthorized(BEARER_CHALLENGE, "invalid token"));
+    };
+
+    if row.project_id != project_id || !token_allows(&row.permissions, needed) {
+        return Err(AuthRejection::forbidden("API token does not allow this request"));
+    }
+
+    // Record token usage in the background (throttled), like SDK keys
+    if should_touch_last_used(row.last_used_at, chrono::Utc::now()) {
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            let result = sqlx::query("UPDATE project_api_tokens SET last_used_at = NOW() WHERE id = $1")
+                .bind(row.id)
+                .execute(&pool)
+                .await;
+            if let Err(e) = result {
+                eprintln!("Failed to update API token last_used_at: {:?}", e);
+            }
+        });
+    }
+
+    req.extensions_mut().insert(row.owner_id);
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_check_admin() {
+        assert!(check_admin(Some(true)).is_ok());
+
+        // Regular users and users deleted since their token was issued get a 403, not a 401
+        for is_admin in [Some(false), None] {
+            let rejection = check_admin(is_admin).unwrap_err();
+            assert_eq!(rejection.status, StatusCode::FORBIDDEN);
+            assert!(rejection.challenge.is_none());
         }
-        Err(_) => Err((StatusCode::UNAUTHORIZED, "invalid subject")),
+    }
+
+    #[test]
+    fn test_issued_before_revocation() {
+        let revoked_at = chrono::Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap();
+        let at = |seconds: i64| (revoked_at.timestamp() + seconds) as usize;
+
+        assert!(!issued_before_revocation(at(-3600), None));
+        assert!(issued_before_revocation(at(-3600), Some(revoked_at)));
+        assert!(issued_before_revocation(at(-1), Some(revoked_at)));
+        // A login right after the change gets a token that is accepted
+        assert!(!issued_before_revocation(at(0), Some(revoked_at)));
+        assert!(!issued_before_revocation(at(60), Some(revoked_at)));
     }
 }
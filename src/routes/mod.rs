@@ -1,46 +1,115 @@
 use axum::{
-    extract::Request,
+    extract::{DefaultBodyLimit, Request},
     middleware,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 
+mod admin;
+mod api_tokens;
 mod auth;
+mod error;
 mod health;
+mod idempotency;
+mod lint;
 mod middleware_auth;
+mod openapi;
+mod overrides;
+mod ownership;
+mod pagination;
+mod pending_changes;
 mod projects;
+mod request_id;
 mod flags;
 mod rules;
 mod sdk_auth;
 mod sdk;
+mod sdk_keys;
+mod slug;
+mod webhooks;
 pub mod environments; 
 
 pub use auth::register;
 pub use health::health;
 
+use tower_http::{limit::RequestBodyLimitLayer, timeout::TimeoutLayer};
+
+use crate::config::Config;
 use crate::routes::auth::login;
 use crate::state::AppState;
 
-pub fn routes() -> Router<AppState> {
+pub fn routes(config: &Config) -> Router<AppState> {
     let projects_router = Router::new()
         .route(
             "/",
-            post(projects::routes::create).get(projects::routes::list),
+            post(projects::routes::create)
+                .layer(middleware::from_fn(idempotency::idempotent))
+                .get(projects::routes::list),
         )
         .route(
             "/{id}",
             get(projects::routes::get)
+                .head(projects::routes::exists)
                 .put(projects::routes::update)
                 .delete(projects::routes::delete),
         )
+        .route("/{id}/summary", get(projects::routes::summary))
+        .route("/{id}/evaluations", delete(projects::routes::purge_evaluations))
+        .route("/{id}/audit-log", get(projects::routes::audit_log))
+        .route("/{id}/validate", get(lint::routes::validate))
+        .route(
+            "/{id}/flags",
+            post(flags::routes::create_in_all_environments)
+                .layer(middleware::from_fn(idempotency::idempotent))
+                .get(flags::routes::list_across_environments),
+        )
+        .route("/{id}/flags/by-key/{key}", get(flags::routes::list_by_key))
+        .route("/{id}/flags/by-key/{key}/rules", get(rules::routes::list_by_flag_key))
+        .route("/{id}/evaluate-preview", post(flags::routes::evaluate_preview))
+        .route("/{id}/transfer", post(projects::routes::transfer))
+        .route(
+            "/{id}/approvers",
+            get(pending_changes::routes::list_approvers).post(pending_changes::routes::add_approver),
+        )
+        .route("/{id}/approvers/{user_id}", delete(pending_changes::routes::remove_approver))
+        .route("/{id}/pending-changes", get(pending_changes::routes::list))
+        .route("/{id}/pending-changes/{change_id}/approve", post(pending_changes::routes::approve))
+        .route("/{id}/pending-changes/{change_id}/reject", post(pending_changes::routes::reject))
         .route(
             "/{id}/regenerate-key",
             post(projects::routes::regenerate_key),
         );
 
+    // SDK keys router - handles /sdk-keys and /sdk-keys/{key_id}
+    let sdk_keys_router = Router::new()
+        .route("/", post(sdk_keys::routes::create).get(sdk_keys::routes::list))
+        .route("/{key_id}", delete(sdk_keys::routes::revoke));
+
+    // API tokens router - handles /tokens and /tokens/{token_id}
+    let api_tokens_router = Router::new()
+        .route("/", post(api_tokens::routes::create).get(api_tokens::routes::list))
+        .route("/{token_id}", delete(api_tokens::routes::revoke));
+
+    // Webhooks router - handles /webhooks and /webhooks/{webhook_id}
+    let webhooks_router = Router::new()
+        .route("/", post(webhooks::routes::create).get(webhooks::routes::list))
+        .route("/{webhook_id}", delete(webhooks::routes::delete));
+
     // Rules router - handles /rules and /rules/{rule_id}
     let rules_router = Router::new()
-        .route("/", post(rules::routes::create).get(rules::routes::list))
+        .route(
+            "/",
+            post(rules::routes::create)
+                .layer(middleware::from_fn(idempotency::idempotent))
+                .get(rules::routes::list),
+        )
+        .route(
+            "/bulk",
+            post(rules::routes::bulk_create).layer(middleware::from_fn(idempotency::idempotent)),
+        )
+        .route("/reorder", put(rules::routes::reorder))
+        .route("/test", post(rules::routes::test))
+        .route("/{rule_id}/duplicate", post(rules::routes::duplicate))
         .route(
             "/{rule_id}",
             get(rules::routes::get)
@@ -48,51 +117,104 @@ pub fn routes() -> Router<AppState> {
                 .delete(rules::routes::delete),
         );
 
+    // Overrides router - handles /overrides and /overrides/{user_identifier}
+    let overrides_router = Router::new()
+        .route("/", get(overrides::routes::list))
+        .route(
+            "/{user_identifier}",
+            put(overrides::routes::set).delete(overrides::routes::clear),
+        );
+
     // Flags router - handles flags AND nests rules under /{flag_id}/rules
     let flags_router = Router::new()
-        .route("/", post(flags::routes::create).get(flags::routes::list))
+        .route(
+            "/",
+            post(flags::routes::create)
+                .layer(middleware::from_fn(idempotency::idempotent))
+                .get(flags::routes::list),
+        )
         .route(
             "/{flag_id}",
             get(flags::routes::get)
-                .put(flags::routes::update)
+                .head(flags::routes::exists)
+                .put(flags::routes::replace)
+                .patch(flags::routes::update)
                 .delete(flags::routes::delete),
         )
+        .route("/disable-all", post(flags::routes::disable_all))
         .route("/{flag_id}/toggle", post(flags::routes::toggle))
-        .nest("/{flag_id}/rules", rules_router);
+        .route("/{flag_id}/pause", post(flags::routes::pause).delete(flags::routes::resume))
+        .route("/{flag_id}/promote", post(flags::routes::promote))
+        .route("/{flag_id}/deprecate", post(flags::routes::deprecate))
+        .route("/{flag_id}/evaluations", get(flags::routes::list_evaluations))
+        .route("/{flag_id}/evaluate", post(flags::routes::explain))
+        .route("/{flag_id}/rollout-preview", get(flags::routes::rollout_preview))
+        .route("/{flag_id}/rename", post(flags::routes::rename))
+        .route("/{flag_id}/aliases", get(flags::routes::list_aliases))
+        .route("/{flag_id}/aliases/{alias}", delete(flags::routes::delete_alias))
+        .nest("/{flag_id}/rules", rules_router)
+        .nest("/{flag_id}/overrides", overrides_router);
 
     // Environments router - handles /environments and /environments/{environment_id}
     let environments_router = Router::new()
         .route(
             "/",
-            post(environments::routes::create).get(environments::routes::list),
+            post(environments::routes::create)
+                .layer(middleware::from_fn(idempotency::idempotent))
+                .get(environments::routes::list),
         )
         .route(
             "/{environment_id}",
             get(environments::routes::get)
+                .head(environments::routes::exists)
                 .put(environments::routes::update)
                 .delete(environments::routes::delete),
-        );  
+        )
+        .route("/{environment_id}/clone", post(environments::routes::clone));  
+
+    // Admin router - operator endpoints across all users, gated on users.is_admin
+    let admin_router = Router::new()
+        .route("/projects", get(admin::routes::list_projects))
+        .layer(middleware::from_fn(middleware_auth::require_admin));
 
     Router::new()
         .route("/", get(root))
         .route("/health", get(health))
+        .route("/openapi.json", get(openapi::openapi))
         .route("/auth/register", post(register))
         .route("/auth/login", post(login))
         .nest(
             "/api",
             Router::new()
                 .route("/me", get(me_handler))
+                .route("/me/change-password", post(auth::change_password))
                 .nest("/projects", projects_router)
+                .nest("/projects/{project_id}/sdk-keys", sdk_keys_router)
+                .nest("/projects/{project_id}/tokens", api_tokens_router)
+                .nest("/projects/{project_id}/webhooks", webhooks_router)
                 .nest("/projects/{project_id}/environments", environments_router)
                 .nest("/projects/{project_id}/environments/{environment_id}/flags", flags_router)
-                .layer(middleware::from_fn(middleware_auth::require_auth)),
+                .nest("/admin", admin_router)
+                .layer(middleware::from_fn(middleware_auth::require_auth))
+                .layer(DefaultBodyLimit::disable())
+                .layer(RequestBodyLimitLayer::new(config.api_body_limit_bytes)),
         )
         .nest(
             "/sdk/v1",
             Router::new()
                 .route("/evaluate", post(sdk::routes::evaluate))
-                .layer(middleware::from_fn(sdk_auth::require_sdk_key)),
+                .route("/config", get(sdk::routes::config))
+                .route("/flags", get(sdk::routes::flags))
+                .layer(middleware::from_fn(sdk_auth::require_sdk_key))
+                .layer(DefaultBodyLimit::disable())
+                .layer(RequestBodyLimitLayer::new(config.sdk_body_limit_bytes)),
         )
+        // Uniform JSON errors for unknown paths and wrong methods
+        .fallback(error::not_found)
+        .method_not_allowed_fallback(error::method_not_allowed)
+        .layer(TimeoutLayer::new(config.request_timeout))
+        .layer(middleware::from_fn(error::json_limit_errors))
+        .layer(middleware::from_fn(request_id::request_id))
 }
 
 async fn root() -> &'static str {
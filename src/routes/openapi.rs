@@ -0,0 +1,178 @@
+use axum::Json;
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use crate::routes::sdk::CompactEvaluateResponse;
+use crate::routes::sdk_auth::SdkKeyType;
+use crate::routes::{admin, environments, flags, lint, overrides, pending_changes, projects, rules, sdk};
+
+/// OpenAPI description of the management and SDK endpoints
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Feature Flag Service"),
+    paths(
+        projects::routes::create,
+        projects::routes::list,
+        projects::routes::get,
+        projects::routes::exists,
+        projects::routes::update,
+        projects::routes::delete,
+        projects::routes::summary,
+        projects::routes::audit_log,
+        projects::routes::purge_evaluations,
+        projects::routes::transfer,
+        projects::routes::regenerate_key,
+        lint::routes::validate,
+        environments::routes::create,
+        environments::routes::list,
+        environments::routes::get,
+        environments::routes::exists,
+        environments::routes::update,
+        environments::routes::delete,
+        environments::routes::clone,
+        flags::routes::create,
+        flags::routes::list,
+        flags::routes::list_across_environments,
+        flags::routes::create_in_all_environments,
+        flags::routes::list_by_key,
+        flags::routes::get,
+        flags::routes::exists,
+        flags::routes::update,
+        flags::routes::replace,
+        flags::routes::delete,
+        flags::routes::toggle,
+        flags::routes::pause,
+        flags::routes::resume,
+        flags::routes::promote,
+        flags::routes::deprecate,
+        flags::routes::disable_all,
+        flags::routes::list_evaluations,
+        flags::routes::explain,
+        flags::routes::rollout_preview,
+        flags::routes::rename,
+        flags::routes::list_aliases,
+        flags::routes::delete_alias,
+        flags::routes::evaluate_preview,
+        rules::routes::create,
+        rules::routes::list,
+        rules::routes::list_by_flag_key,
+        rules::routes::bulk_create,
+        rules::routes::reorder,
+        rules::routes::test,
+        rules::routes::get,
+        rules::routes::update,
+        rules::routes::duplicate,
+        rules::routes::delete,
+        overrides::routes::list,
+        overrides::routes::set,
+        overrides::routes::clear,
+        pending_changes::routes::list_approvers,
+        pending_changes::routes::add_approver,
+        pending_changes::routes::remove_approver,
+        pending_changes::routes::list,
+        pending_changes::routes::approve,
+        pending_changes::routes::reject,
+        admin::routes::list_projects,
+        sdk::routes::evaluate,
+        sdk::routes::config,
+        sdk::routes::flags,
+    ),
+    // Only referenced from query parameters or descriptions, which utoipa does not collect on its own
+    components(schemas(SdkKeyType, CompactEvaluateResponse)),
+    modifiers(&SecuritySchemes),
+    tags(
+        (name = "projects", description = "Projects and their audit log"),
+        (name = "environments", description = "Environments within a project"),
+        (name = "flags", description = "Feature flags within an environment"),
+        (name = "rules", description = "Targeting rules of a flag"),
+        (name = "overrides", description = "Users forced into a flag state"),
+        (name = "pending-changes", description = "Flag updates waiting for approval, and who may approve them"),
+        (name = "sdk", description = "Flag evaluation for client applications"),
+        (name = "admin", description = "Operator endpoints across all users, for admins only"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Registers the JWT bearer and X-SDK-Key schemes referenced by the paths
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "sdk_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-SDK-Key"))),
+        );
+    }
+}
+
+/// Serve the OpenAPI document
+pub async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Collect every "$ref" in the document
+    fn collect_refs(value: &serde_json::Value, refs: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, v) in map {
+                    match (key.as_str(), v) {
+                        ("$ref", serde_json::Value::String(r)) => refs.push(r.clone()),
+                        _ => collect_refs(v, refs),
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| collect_refs(v, refs)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_openapi_documents_management_and_sdk_paths() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let paths = &doc["paths"];
+
+        assert!(paths["/api/projects"]["post"].is_object());
+        assert!(paths["/api/projects/{id}/audit-log"]["get"].is_object());
+        assert!(paths["/api/projects/{project_id}/environments/{environment_id}/clone"]["post"].is_object());
+        assert!(paths["/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}"]["put"].is_object());
+        assert!(paths["/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rules/reorder"]["put"].is_object());
+        assert!(paths["/sdk/v1/evaluate"]["post"].is_object());
+        assert!(paths["/sdk/v1/config"]["get"].is_object());
+
+        let schemes = &doc["components"]["securitySchemes"];
+        assert_eq!(schemes["bearer_auth"]["scheme"], "bearer");
+        assert_eq!(schemes["sdk_key"]["name"], "X-SDK-Key");
+    }
+
+    #[test]
+    fn test_openapi_schema_refs_resolve() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let schemas = &doc["components"]["schemas"];
+        assert!(schemas["FlagResponse"].is_object());
+        assert!(schemas["CreateFlagRequest"].is_object());
+
+        let mut refs = Vec::new();
+        collect_refs(&doc, &mut refs);
+        assert!(!refs.is_empty());
+        for r in refs {
+            let name = r.strip_prefix("#/components/schemas/").unwrap_or_else(|| panic!("unexpected ref {}", r));
+            assert!(schemas[name].is_object(), "missing schema {}", name);
+        }
+    }
+}
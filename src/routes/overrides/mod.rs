@@ -0,0 +1,160 @@
+pub mod routes;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::evaluation::{FlagOverride, UserContext};
+use crate::routes::error::ValidationError;
+
+// MODELS
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserOverride {
+    pub flag_id: Uuid,
+    pub user_identifier: String,
+    pub enabled: bool,
+    pub payload: Option<serde_json::Value>,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetOverrideRequest {
+    pub enabled: bool,  // Result the user always gets, whatever the rules and rollout say
+    pub payload: Option<serde_json::Value>,  // Returned with the evaluation instead of a rule's payload
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OverrideResponse {
+    pub flag_id: Uuid,
+    pub user_identifier: String,
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<serde_json::Value>,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<UserOverride> for OverrideResponse {
+    fn from(row: UserOverride) -> Self {
+        Self {
+            flag_id: row.flag_id,
+            user_identifier: row.user_identifier,
+            enabled: row.enabled,
+            payload: row.payload,
+            created_by: row.created_by,
+            updated_by: row.updated_by,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct OverrideRow {
+    flag_id: Uuid,
+    user_identifier: String,
+    enabled: bool,
+    payload: Option<serde_json::Value>,
+}
+
+// HELPER FUNCTIONS
+
+/// Longest user_id or email an override can be set for
+pub const MAX_USER_IDENTIFIER_LENGTH: usize = 255;
+
+/// Trim the user an override is for, a user_id or an email
+pub fn normalize_user_identifier(value: &str) -> Result<String, ValidationError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(ValidationError::new("user_identifier", "required", "User identifier cannot be empty"));
+    }
+    if value.chars().count() > MAX_USER_IDENTIFIER_LENGTH {
+        return Err(ValidationError::new(
+            "user_identifier",
+            "too_long",
+            format!("User identifier is too long (Max: {} characters)", MAX_USER_IDENTIFIER_LENGTH),
+        ));
+    }
+    Ok(value.to_string())
+}
+
+/// Load the overrides of `flag_ids` that can apply to `context`, grouped by flag
+/// Anonymous contexts can't be overridden, so they skip the query
+pub async fn load_overrides(
+    db: &PgPool,
+    flag_ids: &[Uuid],
+    context: &UserContext,
+) -> Result<HashMap<Uuid, Vec<FlagOverride>>, sqlx::Error> {
+    if flag_ids.is_empty() || (context.user_id.is_none() && context.user_email.is_none()) {
+        return Ok(HashMap::new());
+    }
+
+    let rows: Vec<OverrideRow> = sqlx::query_as(
+        r#"
+        SELECT flag_id, user_identifier, enabled, payload
+        FROM flag_overrides
+        WHERE flag_id = ANY($1) AND (user_identifier = $2 OR lower(user_identifier) = lower($3))
+        "#,
+    )
+    .bind(flag_ids)
+    .bind(context.user_id.as_deref())
+    .bind(context.user_email.as_deref())
+    .fetch_all(db)
+    .await?;
+
+    Ok(group_overrides(rows))
+}
+
+/// Load every override of `flag_ids`, grouped by flag, for SDKs that evaluate locally
+pub async fn load_all_overrides(db: &PgPool, flag_ids: &[Uuid]) -> Result<HashMap<Uuid, Vec<FlagOverride>>, sqlx::Error> {
+    let rows: Vec<OverrideRow> = sqlx::query_as(
+        r#"
+        SELECT flag_id, user_identifier, enabled, payload
+        FROM flag_overrides
+        WHERE flag_id = ANY($1)
+        ORDER BY user_identifier
+        "#,
+    )
+    .bind(flag_ids)
+    .fetch_all(db)
+    .await?;
+
+    Ok(group_overrides(rows))
+}
+
+fn group_overrides(rows: Vec<OverrideRow>) -> HashMap<Uuid, Vec<FlagOverride>> {
+    let mut by_flag: HashMap<Uuid, Vec<FlagOverride>> = HashMap::new();
+    for row in rows {
+        by_flag.entry(row.flag_id).or_default().push(FlagOverride {
+            user_identifier: row.user_identifier,
+            enabled: row.enabled,
+            payload: row.payload,
+        });
+    }
+    by_flag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_user_identifier() {
+        assert_eq!(normalize_user_identifier("  qa-user ").unwrap(), "qa-user");
+        assert_eq!(normalize_user_identifier("QA@Example.com").unwrap(), "QA@Example.com");
+        assert_eq!(normalize_user_identifier("   ").unwrap_err().code, "required");
+
+        let long = "u".repeat(MAX_USER_IDENTIFIER_LENGTH + 1);
+        assert_eq!(normalize_user_identifier(&long).unwrap_err().code, "too_long");
+        assert!(normalize_user_identifier(&long[1..]).is_ok());
+    }
+}
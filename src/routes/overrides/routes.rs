@@ -0,0 +1,196 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use uuid::Uuid;
+
+use crate::routes::error::{ApiError, JsonBody, ValidationErrorBody};
+use crate::routes::ownership::VerifiedFlag;
+use crate::routes::pending_changes::{self, FlagWrite, PendingChangeResponse, TargetingChange, WriteGate};
+use crate::state::AppState;
+use super::{normalize_user_identifier, OverrideResponse, SetOverrideRequest, UserOverride};
+
+// HANDLERS
+
+/// List the users forced into a state for a flag
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/overrides",
+    tag = "overrides",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    responses(
+        (status = 200, description = "Overrides of the flag, most recently changed first", body = [OverrideResponse]),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    VerifiedFlag { flag_id, .. }: VerifiedFlag,
+) -> Result<impl IntoResponse, ApiError> {
+    let overrides = sqlx::query_as::<_, UserOverride>(
+        r#"
+        SELECT flag_id, user_identifier, enabled, payload, created_by, updated_by, created_at, updated_at
+        FROM flag_overrides
+        WHERE flag_id = $1
+        ORDER BY updated_at DESC
+        "#,
+    )
+    .bind(flag_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch overrides: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch overrides".to_string())
+    })?;
+
+    let response: Vec<OverrideResponse> = overrides.into_iter().map(OverrideResponse::from).collect();
+    Ok(Json(response))
+}
+
+/// Force a user into a state for a flag, replacing any override they already have
+#[utoipa::path(
+    put,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/overrides/{user_identifier}",
+    tag = "overrides",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ("user_identifier" = String, Path, description = "user_id, or email matched ignoring case"),
+    ),
+    request_body = SetOverrideRequest,
+    responses(
+        (status = 200, description = "Override as stored", body = OverrideResponse),
+        (status = 202, description = "The environment requires approval, the override was stored as a pending change", body = PendingChangeResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Invalid user identifier", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn set(
+    State(state): State<AppState>,
+    verified: VerifiedFlag,
+    Path((_, _, _, user_identifier)): Path<(Uuid, Uuid, Uuid, String)>,
+    JsonBody(payload): JsonBody<SetOverrideRequest>,
+) -> Result<Response, ApiError> {
+    let user_identifier = normalize_user_identifier(&user_identifier)?;
+
+    // In environments that require approval the override waits for an approver
+    if pending_changes::direct_write_gate(&state.db, verified.environment_id, FlagWrite::OverrideSet).await? == WriteGate::Propose {
+        let change = TargetingChange::OverrideSet { user_identifier, value: payload };
+        return pending_changes::propose_targeting(&state.db, &verified, &change).await;
+    }
+
+    let stored = upsert_override(&state.db, verified.flag_id, &user_identifier, &payload, verified.user_id).await?;
+    Ok(Json(OverrideResponse::from(stored)).into_response())
+}
+
+/// Set an override of the flag, directly or when it is approved
+pub async fn upsert_override<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    flag_id: Uuid,
+    user_identifier: &str,
+    payload: &SetOverrideRequest,
+    user_id: Uuid,
+) -> Result<UserOverride, ApiError> {
+    let stored = sqlx::query_as::<_, UserOverride>(
+        r#"
+        INSERT INTO flag_overrides (flag_id, user_identifier, enabled, payload, created_by, updated_by)
+        VALUES ($1, $2, $3, $4, $5, $5)
+        ON CONFLICT (flag_id, user_identifier) DO UPDATE
+        SET enabled = EXCLUDED.enabled, payload = EXCLUDED.payload, updated_by = EXCLUDED.updated_by, updated_at = NOW()
+        RETURNING flag_id, user_identifier, enabled, payload, created_by, updated_by, created_at, updated_at
+        "#,
+    )
+    .bind(flag_id)
+    .bind(user_identifier)
+    .bind(payload.enabled)
+    .bind(&payload.payload)
+    .bind(user_id)
+    .fetch_one(executor)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to set override: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to set override".to_string())
+    })?;
+
+    Ok(stored)
+}
+
+/// Stop forcing a user's state for a flag
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/overrides/{user_identifier}",
+    tag = "overrides",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ("user_identifier" = String, Path, description = "user_id or email the override was set for"),
+    ),
+    responses(
+        (status = 204, description = "Override cleared"),
+        (status = 202, description = "The environment requires approval, clearing was stored as a pending change", body = PendingChangeResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag or override not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn clear(
+    State(state): State<AppState>,
+    verified: VerifiedFlag,
+    Path((_, _, _, user_identifier)): Path<(Uuid, Uuid, Uuid, String)>,
+) -> Result<Response, ApiError> {
+    let user_identifier = user_identifier.trim().to_string();
+
+    // In environments that require approval clearing waits for an approver
+    if pending_changes::direct_write_gate(&state.db, verified.environment_id, FlagWrite::OverrideClear).await? == WriteGate::Propose {
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM flag_overrides WHERE flag_id = $1 AND user_identifier = $2)",
+        )
+        .bind(verified.flag_id)
+        .bind(&user_identifier)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to check override: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?;
+        if !exists {
+            return Err((StatusCode::NOT_FOUND, "Override not found".to_string()).into());
+        }
+        return pending_changes::propose_targeting(&state.db, &verified, &TargetingChange::OverrideClear { user_identifier }).await;
+    }
+
+    delete_override(&state.db, verified.flag_id, &user_identifier).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Clear an override of the flag, directly or when clearing it is approved
+pub async fn delete_override<'e>(executor: impl sqlx::PgExecutor<'e>, flag_id: Uuid, user_identifier: &str) -> Result<(), ApiError> {
+    let result = sqlx::query("DELETE FROM flag_overrides WHERE flag_id = $1 AND user_identifier = $2")
+        .bind(flag_id)
+        .bind(user_identifier)
+        .execute(executor)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to clear override: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to clear override".to_string())
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Override not found".to_string()).into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,222 @@
+use axum::{
+    extract::{FromRequestParts, Path},
+    http::{request::Parts, StatusCode},
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::routes::error::ApiError;
+use crate::routes::middleware_auth::JwtUser;
+use crate::state::AppState;
+
+// Ownership checks shared by the management handlers
+// Missing resources and other users' resources both answer 404, see require_auth
+
+/// A project owned by the authenticated user
+pub struct VerifiedProject {
+    pub project_id: Uuid,
+    pub user_id: Uuid,
+}
+
+/// An environment in a project owned by the authenticated user
+pub struct VerifiedEnvironment {
+    pub project_id: Uuid,
+    pub environment_id: Uuid,
+    pub user_id: Uuid,
+}
+
+/// A flag in an environment of a project owned by the authenticated user
+pub struct VerifiedFlag {
+    pub project_id: Uuid,
+    pub environment_id: Uuid,
+    pub flag_id: Uuid,
+    pub user_id: Uuid,
+}
+
+// Path params are read by name, so the extractors work on routes with further params
+// (rule ids, keys); the projects router names its id `id`
+#[derive(Deserialize)]
+struct ProjectPath {
+    #[serde(alias = "id")]
+    project_id: Uuid,
+}
+
+#[derive(Deserialize)]
+struct EnvironmentPath {
+    project_id: Uuid,
+    environment_id: Uuid,
+}
+
+#[derive(Deserialize)]
+struct FlagPath {
+    project_id: Uuid,
+    environment_id: Uuid,
+    flag_id: Uuid,
+}
+
+/// Check the user owns the project
+pub async fn verify_project(db: &PgPool, project_id: Uuid, user_id: Uuid) -> Result<(), (StatusCode, String)> {
+    let exists = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1 AND created_by = $2)",
+    )
+    .bind(project_id)
+    .bind(user_id)
+    .fetch_one(db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to check project: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Project not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Check the environment belongs to the project and the user owns the project
+pub async fn verify_environment(
+    db: &PgPool,
+    project_id: Uuid,
+    environment_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), (StatusCode, String)> {
+    let exists = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM environments e
+            JOIN projects p ON e.project_id = p.id
+            WHERE e.id = $1 AND e.project_id = $2 AND p.created_by = $3
+        )
+        "#,
+    )
+    .bind(environment_id)
+    .bind(project_id)
+    .bind(user_id)
+    .fetch_one(db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to check environment: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Environment not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Check the flag belongs to the environment and project and the user owns the project
+pub async fn verify_flag(
+    db: &PgPool,
+    project_id: Uuid,
+    environment_id: Uuid,
+    flag_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), (StatusCode, String)> {
+    let exists = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM feature_flags f
+            JOIN environments e ON f.environment_id = e.id
+            JOIN projects p ON e.project_id = p.id
+            WHERE f.id = $1 AND f.environment_id = $2 AND e.project_id = $3 AND p.created_by = $4
+        )
+        "#,
+    )
+    .bind(flag_id)
+    .bind(environment_id)
+    .bind(project_id)
+    .bind(user_id)
+    .fetch_one(db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to check flag: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, "Flag not found".to_string()));
+    }
+    Ok(())
+}
+
+async fn user_and_path<T>(parts: &mut Parts, state: &AppState) -> Result<(Uuid, T), ApiError>
+where
+    T: serde::de::DeserializeOwned + Send,
+{
+    let JwtUser(user_id) = JwtUser::from_request_parts(parts, state).await?;
+    let Path(path) = Path::<T>::from_request_parts(parts, state)
+        .await
+        .map_err(|rejection| ApiError::Status(rejection.status(), rejection.body_text()))?;
+    Ok((user_id, path))
+}
+
+impl FromRequestParts<AppState> for VerifiedProject {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let (user_id, path) = user_and_path::<ProjectPath>(parts, state).await?;
+        verify_project(&state.db, path.project_id, user_id).await?;
+        Ok(Self { project_id: path.project_id, user_id })
+    }
+}
+
+impl FromRequestParts<AppState> for VerifiedEnvironment {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let (user_id, path) = user_and_path::<EnvironmentPath>(parts, state).await?;
+        verify_environment(&state.db, path.project_id, path.environment_id, user_id).await?;
+        Ok(Self {
+            project_id: path.project_id,
+            environment_id: path.environment_id,
+            user_id,
+        })
+    }
+}
+
+impl FromRequestParts<AppState> for VerifiedFlag {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let (user_id, path) = user_and_path::<FlagPath>(parts, state).await?;
+        verify_flag(&state.db, path.project_id, path.environment_id, path.flag_id, user_id).await?;
+        Ok(Self {
+            project_id: path.project_id,
+            environment_id: path.environment_id,
+            flag_id: path.flag_id,
+            user_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_params_by_name() {
+        let project_id = Uuid::new_v4();
+        let environment_id = Uuid::new_v4();
+        let flag_id = Uuid::new_v4();
+
+        // The projects router calls the project id `id`
+        let path: ProjectPath = serde_json::from_value(serde_json::json!({ "id": project_id })).unwrap();
+        assert_eq!(path.project_id, project_id);
+
+        // Rule routes carry a rule id after the flag id
+        let path: FlagPath = serde_json::from_value(serde_json::json!({
+            "project_id": project_id,
+            "environment_id": environment_id,
+            "flag_id": flag_id,
+            "rule_id": Uuid::new_v4(),
+        }))
+        .unwrap();
+        assert_eq!((path.project_id, path.environment_id, path.flag_id), (project_id, environment_id, flag_id));
+
+        let missing: Result<EnvironmentPath, _> = serde_json::from_value(serde_json::json!({ "project_id": project_id }));
+        assert!(missing.is_err());
+    }
+}
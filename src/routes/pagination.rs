@@ -0,0 +1,150 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Page size used when the client does not ask for one
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+
+/// Largest page size a client can request
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+/// Keyset position in a list ordered newest first by `(created_at DESC, id DESC)`
+/// The next page holds the rows strictly older than the cursor, so rows inserted while
+/// paging never shift later pages the way an OFFSET would
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: i64,
+}
+
+impl Cursor {
+    /// Opaque form handed to clients as `next_cursor`
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:{}", self.created_at.timestamp_micros(), self.id))
+    }
+
+    pub fn decode(value: &str) -> Result<Self, String> {
+        let invalid = || "Invalid cursor".to_string();
+        let bytes = URL_SAFE_NO_PAD.decode(value.trim()).map_err(|_| invalid())?;
+        let text = String::from_utf8(bytes).map_err(|_| invalid())?;
+        let (micros, id) = text.split_once(':').ok_or_else(invalid)?;
+
+        let micros = micros.parse::<i64>().map_err(|_| invalid())?;
+        Ok(Cursor {
+            created_at: DateTime::from_timestamp_micros(micros).ok_or_else(invalid)?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+    pub cursor: Option<String>,  // next_cursor from the previous page
+    pub limit: Option<i64>,
+}
+
+impl PageParams {
+    /// Requested page size, clamped to 1..=MAX_PAGE_SIZE
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    pub fn cursor(&self) -> Result<Option<Cursor>, String> {
+        self.cursor.as_deref().map(Cursor::decode).transpose()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,  // None on the last page
+}
+
+/// Build a page from rows fetched with `LIMIT limit + 1`
+/// The extra row only signals that another page exists and is dropped
+pub fn paginate<T>(mut rows: Vec<T>, limit: i64, cursor_of: impl Fn(&T) -> Cursor) -> Page<T> {
+    let limit = limit as usize;
+    let has_more = rows.len() > limit;
+    rows.truncate(limit);
+
+    let next_cursor = if has_more {
+        rows.last().map(|row| cursor_of(row).encode())
+    } else {
+        None
+    };
+
+    Page { items: rows, next_cursor }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    // Mirrors the SQL: WHERE (created_at, id) < cursor ORDER BY created_at DESC, id DESC LIMIT n + 1
+    fn fetch_page(table: &[Cursor], params: &PageParams) -> Page<Cursor> {
+        let after = params.cursor().unwrap();
+        let mut rows: Vec<Cursor> = table
+            .iter()
+            .filter(|row| after.is_none_or(|c| (row.created_at, row.id) < (c.created_at, c.id)))
+            .copied()
+            .collect();
+        rows.sort_by_key(|row| std::cmp::Reverse((row.created_at, row.id)));
+        rows.truncate(params.limit() as usize + 1);
+        paginate(rows, params.limit(), |row| *row)
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor {
+            created_at: DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap(),
+            id: 42,
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()), Ok(cursor));
+
+        assert!(Cursor::decode("not a cursor!").is_err());
+        assert!(Cursor::decode(&URL_SAFE_NO_PAD.encode("123")).is_err());
+        assert!(Cursor::decode(&URL_SAFE_NO_PAD.encode("abc:1")).is_err());
+    }
+
+    #[test]
+    fn test_page_params_limit() {
+        assert_eq!(PageParams::default().limit(), DEFAULT_PAGE_SIZE);
+        assert_eq!(PageParams { cursor: None, limit: Some(0) }.limit(), 1);
+        assert_eq!(PageParams { cursor: None, limit: Some(10_000) }.limit(), MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_pagination_stable_under_interleaved_inserts() {
+        // Rows sharing a timestamp are ordered by id
+        let mut table: Vec<Cursor> = (1..=7)
+            .map(|id| Cursor { created_at: at(id / 2), id })
+            .collect();
+        let mut seen = Vec::new();
+        let mut params = PageParams { cursor: None, limit: Some(3) };
+        let mut next_id = 100;
+
+        loop {
+            let page = fetch_page(&table, &params);
+            seen.extend(page.items.iter().map(|row| row.id));
+
+            // New rows arrive between page requests, including one at an already seen timestamp
+            table.push(Cursor { created_at: at(10), id: next_id });
+            table.push(Cursor { created_at: at(3), id: next_id + 1 });
+            next_id += 2;
+
+            match page.next_cursor {
+                Some(cursor) => params.cursor = Some(cursor),
+                None => break,
+            }
+        }
+
+        // Every original row exactly once, newest first, none of the later inserts
+        assert_eq!(seen, vec![7, 6, 5, 4, 3, 2, 1]);
+    }
+}
@@ -0,0 +1,569 @@
+pub mod routes;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::routes::error::ApiError;
+use crate::routes::flags::{UpdateFlagRequest, UpdateMode};
+use crate::routes::overrides::SetOverrideRequest;
+use crate::routes::ownership::VerifiedFlag;
+use crate::routes::rules::{CreateRuleRequest, UpdateRuleRequest};
+
+// MODELS
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct PendingChange {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub environment_id: Uuid,
+    pub flag_id: Uuid,
+    pub flag_key: String,
+    pub mode: String,
+    pub change: Value,
+    pub base_version: i64,
+    pub reason: Option<String>,
+    pub status: String,
+    pub proposed_by: Option<Uuid>,
+    pub decided_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+impl PendingChange {
+    /// The rule or override edit the change makes, None for flag updates
+    pub fn targeting(&self) -> Option<TargetingChange> {
+        TargetingChange::parse(&self.mode, &self.change)
+    }
+}
+
+/// A flag update, or a rule or override edit, waiting for approval
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PendingChangeResponse {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub environment_id: Uuid,
+    pub flag_id: Uuid,
+    pub flag_key: String,
+    /// `patch` or `replace` for flag updates, as for the PATCH and PUT flag endpoints;
+    /// `rule_create`, `rule_update`, `rule_delete`, `override_set` or `override_clear` for targeting edits
+    pub mode: String,
+    /// The update body, applied as sent when the change is approved; targeting edits also name the rule or user
+    #[schema(value_type = Object)]
+    pub change: Value,
+    /// Flag version the change was proposed against, the flag's rules_version for targeting edits
+    pub base_version: i64,
+    pub reason: Option<String>,
+    /// `pending`, `approved` or `rejected`
+    pub status: String,
+    pub proposed_by: Option<Uuid>,
+    pub decided_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+impl From<PendingChange> for PendingChangeResponse {
+    fn from(change: PendingChange) -> Self {
+        Self {
+            id: change.id,
+            project_id: change.project_id,
+            environment_id: change.environment_id,
+            flag_id: change.flag_id,
+            flag_key: change.flag_key,
+            mode: change.mode,
+            change: change.change,
+            base_version: change.base_version,
+            reason: change.reason,
+            status: change.status,
+            proposed_by: change.proposed_by,
+            decided_by: change.decided_by,
+            created_at: change.created_at,
+            decided_at: change.decided_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddApproverRequest {
+    /// Email of an existing user
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct ApproverResponse {
+    pub user_id: Uuid,
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// A write to an environment's flags, named by the endpoint that makes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagWrite {
+    Update,  // PATCH and PUT
+    Toggle,
+    Pause,  // Pause and resume
+    Lifecycle,  // Promote and deprecate
+    Rename,
+    AliasDelete,
+    Delete,
+    DisableAll,
+    RuleCreate,
+    RuleBulkCreate,
+    RuleUpdate,
+    RuleReorder,
+    RuleDuplicate,
+    RuleDelete,
+    OverrideSet,
+    OverrideClear,
+}
+
+// What happens to a write in an environment that requires approval
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalPolicy {
+    /// Stored as a pending change
+    Propose,
+    /// Refused, the write has no pending change form so applying it would skip review
+    Reject,
+    /// Applied immediately, see FlagWrite::DisableAll
+    Exempt,
+}
+
+impl FlagWrite {
+    pub fn approval_policy(&self) -> ApprovalPolicy {
+        match self {
+            FlagWrite::Update
+            | FlagWrite::Toggle
+            | FlagWrite::RuleCreate
+            | FlagWrite::RuleUpdate
+            | FlagWrite::RuleDelete
+            | FlagWrite::OverrideSet
+            | FlagWrite::OverrideClear => ApprovalPolicy::Propose,
+            // The kill switch only turns flags off, waiting for an approver in an incident defeats its purpose
+            FlagWrite::DisableAll => ApprovalPolicy::Exempt,
+            FlagWrite::Pause
+            | FlagWrite::Lifecycle
+            | FlagWrite::Rename
+            | FlagWrite::AliasDelete
+            | FlagWrite::Delete
+            | FlagWrite::RuleBulkCreate
+            | FlagWrite::RuleReorder
+            | FlagWrite::RuleDuplicate => ApprovalPolicy::Reject,
+        }
+    }
+
+    /// What the write does, for error messages
+    pub fn description(&self) -> &'static str {
+        match self {
+            FlagWrite::Update => "updating a flag",
+            FlagWrite::Toggle => "toggling a flag",
+            FlagWrite::Pause => "pausing or resuming a flag",
+            FlagWrite::Lifecycle => "changing a flag's lifecycle state",
+            FlagWrite::Rename => "renaming a flag",
+            FlagWrite::AliasDelete => "removing a flag alias",
+            FlagWrite::Delete => "deleting a flag",
+            FlagWrite::DisableAll => "disabling every flag",
+            FlagWrite::RuleCreate => "creating a rule",
+            FlagWrite::RuleBulkCreate => "creating rules in bulk",
+            FlagWrite::RuleUpdate => "updating a rule",
+            FlagWrite::RuleReorder => "reordering rules",
+            FlagWrite::RuleDuplicate => "duplicating a rule",
+            FlagWrite::RuleDelete => "deleting a rule",
+            FlagWrite::OverrideSet => "setting an override",
+            FlagWrite::OverrideClear => "clearing an override",
+        }
+    }
+}
+
+/// A rule or override edit held back until an approver accepts it
+/// Stored as its kind in the `mode` column and the rest in `change`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "mode", content = "change", rename_all = "snake_case")]
+pub enum TargetingChange {
+    RuleCreate { rule: CreateRuleRequest },
+    RuleUpdate { rule_id: Uuid, rule: UpdateRuleRequest },
+    RuleDelete { rule_id: Uuid },
+    OverrideSet {
+        user_identifier: String,
+        #[serde(rename = "override")]
+        value: SetOverrideRequest,
+    },
+    OverrideClear { user_identifier: String },
+}
+
+impl TargetingChange {
+    /// Values of the `mode` and `change` columns
+    pub fn columns(&self) -> (String, Value) {
+        let mut tagged = serde_json::to_value(self).unwrap_or_default();
+        let mode = tagged["mode"].as_str().unwrap_or_default().to_string();
+        (mode, tagged["change"].take())
+    }
+
+    /// Read a stored change back, None for flag updates (`patch` and `replace`)
+    pub fn parse(mode: &str, change: &Value) -> Option<Self> {
+        serde_json::from_value(serde_json::json!({ "mode": mode, "change": change })).ok()
+    }
+}
+
+// What a pending change does once approved
+pub enum ProposedChange<'a> {
+    Flag(UpdateMode, &'a UpdateFlagRequest),
+    Targeting(&'a TargetingChange),
+}
+
+impl ProposedChange<'_> {
+    /// Values of the `mode` and `change` columns
+    fn columns(&self) -> (String, Value) {
+        match self {
+            ProposedChange::Flag(mode, update) => (mode.as_str().to_string(), change_body(update)),
+            ProposedChange::Targeting(change) => change.columns(),
+        }
+    }
+}
+
+// A change to hold back until an approver accepts it
+pub struct NewPendingChange<'a> {
+    pub project_id: Uuid,
+    pub environment_id: Uuid,
+    pub flag_id: Uuid,
+    pub proposed_by: Uuid,
+    pub change: ProposedChange<'a>,
+    pub base_version: i64,
+    pub reason: Option<String>,
+}
+
+// HELPER FUNCTIONS
+
+/// Columns of a pending change joined with its flag's key, for queries over `pending_changes c`
+pub const PENDING_CHANGE_COLUMNS: &str = "c.id, c.project_id, c.environment_id, c.flag_id, f.key AS flag_key, c.mode, c.change, \
+     c.base_version, c.reason, c.status, c.proposed_by, c.decided_by, c.created_at, c.decided_at";
+
+/// The part of an update body stored with a pending change: the settings it sets
+/// expected_version is replaced by base_version and reason has its own column
+pub fn change_body(update: &UpdateFlagRequest) -> Value {
+    let mut body = serde_json::to_value(update).unwrap_or_default();
+    if let Value::Object(fields) = &mut body {
+        fields.retain(|name, value| !value.is_null() && name != "expected_version" && name != "reason");
+    }
+    body
+}
+
+/// Check a user may approve or reject a change: it must still be pending, and proposers
+/// can't approve their own changes (checking the user is an approver is up to the caller)
+pub fn check_decision(status: &str, proposed_by: Option<Uuid>, user_id: Uuid) -> Result<(), (StatusCode, String)> {
+    if status != "pending" {
+        return Err((StatusCode::CONFLICT, format!("Change was already {}", status)));
+    }
+    if proposed_by == Some(user_id) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Changes must be approved by someone other than the user who proposed them".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// How a write goes ahead once the environment's approval setting has been checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteGate {
+    /// Write to the live flag now
+    Apply,
+    /// Store the write as a pending change, the live flag is untouched until it is approved
+    Propose,
+}
+
+/// Decide how a write goes ahead in an environment, refusing it with 409 approval_required
+/// when the environment requires approval and the write can't wait as a pending change
+pub fn check_direct_write(requires_approval: bool, write: FlagWrite) -> Result<WriteGate, ApiError> {
+    if !requires_approval {
+        return Ok(WriteGate::Apply);
+    }
+    match write.approval_policy() {
+        ApprovalPolicy::Propose => Ok(WriteGate::Propose),
+        ApprovalPolicy::Exempt => Ok(WriteGate::Apply),
+        ApprovalPolicy::Reject => Err(ApiError::ApprovalRequired(format!(
+            "The environment requires approval and {} can't be proposed as a pending change",
+            write.description()
+        ))),
+    }
+}
+
+/// `check_direct_write` for the environment's current requires_approval
+pub async fn direct_write_gate(db: &PgPool, environment_id: Uuid, write: FlagWrite) -> Result<WriteGate, ApiError> {
+    let requires_approval = sqlx::query_scalar::<_, bool>("SELECT requires_approval FROM environments WHERE id = $1")
+        .bind(environment_id)
+        .fetch_optional(db)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to check environment: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?
+        .unwrap_or(false);
+    check_direct_write(requires_approval, write)
+}
+
+/// Check a targeting edit's flag still has the rules and overrides the edit was proposed against
+pub fn check_rules_version(base_version: i64, rules_version: i64) -> Result<(), (StatusCode, String)> {
+    if base_version != rules_version {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "The flag's rules or overrides changed since the change was proposed (rules version {}, now {})",
+                base_version, rules_version
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Mark a change approved in the transaction applying it, 409 if it was decided in the meantime
+pub async fn mark_approved(conn: &mut PgConnection, change_id: Uuid, approver_id: Uuid) -> Result<(), (StatusCode, String)> {
+    let decided = sqlx::query(
+        r#"
+        UPDATE pending_changes
+        SET status = 'approved', decided_by = $2, decided_at = NOW()
+        WHERE id = $1 AND status = 'pending'
+        "#,
+    )
+    .bind(change_id)
+    .bind(approver_id)
+    .execute(conn)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to approve change: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to apply change".to_string())
+    })?
+    .rows_affected();
+    // Someone else approved or rejected it in the meantime
+    if decided == 0 {
+        return Err((StatusCode::CONFLICT, "Change was already decided".to_string()));
+    }
+    Ok(())
+}
+
+/// Whether the user is one of the project's approvers
+pub async fn is_approver(db: &PgPool, project_id: Uuid, user_id: Uuid) -> Result<bool, (StatusCode, String)> {
+    sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM project_approvers WHERE project_id = $1 AND user_id = $2)",
+    )
+    .bind(project_id)
+    .bind(user_id)
+    .fetch_one(db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to check approver: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })
+}
+
+/// Store a change as a pending change instead of applying it
+pub async fn propose(db: &PgPool, new: NewPendingChange<'_>) -> Result<PendingChange, (StatusCode, String)> {
+    let (mode, change) = new.change.columns();
+    sqlx::query_as::<_, PendingChange>(&format!(
+        r#"
+        WITH c AS (
+            INSERT INTO pending_changes (project_id, environment_id, flag_id, mode, change, base_version, reason, proposed_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+        )
+        SELECT {} FROM c JOIN feature_flags f ON f.id = c.flag_id
+        "#,
+        PENDING_CHANGE_COLUMNS
+    ))
+    .bind(new.project_id)
+    .bind(new.environment_id)
+    .bind(new.flag_id)
+    .bind(mode)
+    .bind(change)
+    .bind(new.base_version)
+    .bind(&new.reason)
+    .bind(new.proposed_by)
+    .fetch_one(db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to create pending change: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to propose change".to_string())
+    })
+}
+
+/// Propose a rule or override edit against the flag's current rules_version, answering 202
+pub async fn propose_targeting(db: &PgPool, flag: &VerifiedFlag, change: &TargetingChange) -> Result<Response, ApiError> {
+    let rules_version = sqlx::query_scalar::<_, i64>("SELECT rules_version FROM feature_flags WHERE id = $1")
+        .bind(flag.flag_id)
+        .fetch_optional(db)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to check flag: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Flag not found".to_string()))?;
+
+    let change = propose(
+        db,
+        NewPendingChange {
+            project_id: flag.project_id,
+            environment_id: flag.environment_id,
+            flag_id: flag.flag_id,
+            proposed_by: flag.user_id,
+            change: ProposedChange::Targeting(change),
+            base_version: rules_version,
+            reason: None,
+        },
+    )
+    .await?;
+    Ok((StatusCode::ACCEPTED, Json(PendingChangeResponse::from(change))).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::AnonymousBehavior;
+
+    #[test]
+    fn test_pending_change_round_trip() {
+        let update: UpdateFlagRequest = serde_json::from_value(serde_json::json!({
+            "enabled": true,
+            "rollout_percentage": 25,
+            "anonymous_behavior": "always_off",
+            "expected_version": 3,
+            "reason": "Launch",
+        }))
+        .unwrap();
+
+        // Proposing stores only the settings the update changes
+        let body = change_body(&update);
+        assert_eq!(
+            body,
+            serde_json::json!({ "enabled": true, "rollout_percentage": 25, "anonymous_behavior": "always_off" })
+        );
+
+        // Approving reads back the same update, the version comes from base_version instead
+        let approved: UpdateFlagRequest = serde_json::from_value(body).unwrap();
+        assert_eq!(approved.enabled, Some(true));
+        assert_eq!(approved.rollout_percentage, Some(25));
+        assert_eq!(approved.anonymous_behavior, Some(AnonymousBehavior::AlwaysOff));
+        assert_eq!(approved.changed_fields(), update.changed_fields());
+        assert_eq!((approved.expected_version, approved.reason), (None, None));
+
+        for mode in [UpdateMode::Patch, UpdateMode::Replace] {
+            assert_eq!(UpdateMode::parse(mode.as_str()), Some(mode));
+        }
+        assert_eq!(UpdateMode::parse("merge"), None);
+    }
+
+    #[test]
+    fn test_targeting_change_round_trip() {
+        let rule_id = Uuid::new_v4();
+        let update: UpdateRuleRequest = serde_json::from_value(serde_json::json!({ "enabled": false })).unwrap();
+        let change = TargetingChange::RuleUpdate { rule_id, rule: update };
+
+        // Stored as its kind in mode and the rest in change
+        let (mode, body) = change.columns();
+        assert_eq!(mode, "rule_update");
+        assert_eq!(body["rule_id"], serde_json::json!(rule_id));
+        assert_eq!(body["rule"]["enabled"], false);
+
+        // Approving reads back the same edit
+        match TargetingChange::parse(&mode, &body) {
+            Some(TargetingChange::RuleUpdate { rule_id: parsed, rule }) => {
+                assert_eq!(parsed, rule_id);
+                assert_eq!(rule.enabled, Some(false));
+                assert_eq!(rule.rule_value, None);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let change = TargetingChange::OverrideSet {
+            user_identifier: "user-1".to_string(),
+            value: serde_json::from_value(serde_json::json!({ "enabled": true })).unwrap(),
+        };
+        let (mode, body) = change.columns();
+        assert_eq!(mode, "override_set");
+        assert_eq!(body, serde_json::json!({ "user_identifier": "user-1", "override": { "enabled": true, "payload": null } }));
+        assert!(matches!(
+            TargetingChange::parse(&mode, &body),
+            Some(TargetingChange::OverrideSet { user_identifier, value }) if user_identifier == "user-1" && value.enabled
+        ));
+
+        let (mode, body) = TargetingChange::RuleDelete { rule_id }.columns();
+        assert_eq!((mode.as_str(), body), ("rule_delete", serde_json::json!({ "rule_id": rule_id })));
+        let (mode, _) = TargetingChange::OverrideClear { user_identifier: "user-1".to_string() }.columns();
+        assert_eq!(mode, "override_clear");
+
+        // Flag updates aren't targeting edits
+        assert!(TargetingChange::parse("patch", &serde_json::json!({ "enabled": true })).is_none());
+        assert!(TargetingChange::parse("replace", &serde_json::json!({ "name": "Checkout" })).is_none());
+    }
+
+    #[test]
+    fn test_check_rules_version() {
+        assert!(check_rules_version(4, 4).is_ok());
+        let (status, message) = check_rules_version(4, 6).unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(message.contains("rules version 4, now 6"), "{}", message);
+    }
+
+    #[test]
+    fn test_check_decision() {
+        let proposer = Uuid::new_v4();
+        let approver = Uuid::new_v4();
+
+        assert!(check_decision("pending", Some(proposer), approver).is_ok());
+        // A proposer whose account was deleted leaves the change approvable
+        assert!(check_decision("pending", None, approver).is_ok());
+
+        let (status, _) = check_decision("pending", Some(proposer), proposer).unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, message) = check_decision("approved", Some(proposer), approver).unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(message, "Change was already approved");
+        assert!(check_decision("rejected", Some(proposer), approver).is_err());
+    }
+
+    #[test]
+    fn test_check_direct_write() {
+        use ApprovalPolicy::*;
+        let writes = [
+            (FlagWrite::Update, Propose),
+            (FlagWrite::Toggle, Propose),
+            (FlagWrite::Pause, Reject),
+            (FlagWrite::Lifecycle, Reject),
+            (FlagWrite::Rename, Reject),
+            (FlagWrite::AliasDelete, Reject),
+            (FlagWrite::Delete, Reject),
+            (FlagWrite::DisableAll, Exempt),
+            (FlagWrite::RuleCreate, Propose),
+            (FlagWrite::RuleBulkCreate, Reject),
+            (FlagWrite::RuleUpdate, Propose),
+            (FlagWrite::RuleReorder, Reject),
+            (FlagWrite::RuleDuplicate, Reject),
+            (FlagWrite::RuleDelete, Propose),
+            (FlagWrite::OverrideSet, Propose),
+            (FlagWrite::OverrideClear, Propose),
+        ];
+
+        for (write, policy) in writes {
+            assert_eq!(write.approval_policy(), policy, "{:?}", write);
+
+            // Without approval every write applies directly
+            assert_eq!(check_direct_write(false, write).unwrap(), WriteGate::Apply, "{:?}", write);
+
+            match (policy, check_direct_write(true, write)) {
+                (Propose, Ok(gate)) => assert_eq!(gate, WriteGate::Propose, "{:?}", write),
+                (Exempt, Ok(gate)) => assert_eq!(gate, WriteGate::Apply, "{:?}", write),
+                (Reject, Err(ApiError::ApprovalRequired(message))) => {
+                    assert!(message.contains(write.description()), "{}", message)
+                }
+                (policy, result) => panic!("{:?} with {:?} gave {:?}", write, policy, result),
+            }
+        }
+    }
+}
@@ -0,0 +1,456 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use uuid::Uuid;
+
+use super::{
+    check_decision, check_rules_version, is_approver, mark_approved, AddApproverRequest, ApproverResponse, PendingChange,
+    PendingChangeResponse, TargetingChange, PENDING_CHANGE_COLUMNS,
+};
+use crate::audit::{self, AuditAction, AuditEntry};
+use crate::evaluation::normalize_email;
+use crate::routes::error::{ApiError, JsonBody};
+use crate::routes::flags::{self, CreateFlagResponse};
+use crate::routes::middleware_auth::JwtUser;
+use crate::routes::overrides::{self, OverrideResponse};
+use crate::routes::ownership::VerifiedProject;
+use crate::routes::rules::{self, RuleResponse};
+use crate::state::AppState;
+
+// HANDLERS
+
+/// List the users who may approve pending changes in the project
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/approvers",
+    tag = "pending-changes",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    responses(
+        (status = 200, description = "Approvers of the project", body = Vec<ApproverResponse>),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_approvers(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+) -> Result<impl IntoResponse, ApiError> {
+    let approvers = sqlx::query_as::<_, ApproverResponse>(
+        r#"
+        SELECT a.user_id, u.email, a.created_at
+        FROM project_approvers a
+        JOIN users u ON u.id = a.user_id
+        WHERE a.project_id = $1
+        ORDER BY a.created_at ASC
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch approvers: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch approvers".to_string())
+    })?;
+
+    Ok(Json(approvers))
+}
+
+/// Let a user approve pending changes in the project
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}/approvers",
+    tag = "pending-changes",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    request_body = AddApproverRequest,
+    responses(
+        (status = 201, description = "User added as an approver", body = ApproverResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project or user not found"),
+        (status = 409, description = "User is already an approver"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn add_approver(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, user_id }: VerifiedProject,
+    JsonBody(payload): JsonBody<AddApproverRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let email = normalize_email(&payload.email);
+    let approver_id = sqlx::query_scalar::<_, Uuid>("SELECT id FROM users WHERE email = $1")
+        .bind(&email)
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to fetch user: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No user with email '{}'", email)))?;
+
+    let created_at = sqlx::query_scalar::<_, chrono::DateTime<chrono::Utc>>(
+        r#"
+        INSERT INTO project_approvers (project_id, user_id, created_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        RETURNING created_at
+        "#,
+    )
+    .bind(project_id)
+    .bind(approver_id)
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to add approver: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to add approver".to_string())
+    })?
+    .ok_or_else(|| (StatusCode::CONFLICT, format!("'{}' is already an approver", email)))?;
+
+    let response = ApproverResponse {
+        user_id: approver_id,
+        email,
+        created_at,
+    };
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Stop a user from approving pending changes in the project
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{id}/approvers/{user_id}",
+    tag = "pending-changes",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        ("user_id" = Uuid, Path, description = "Approver's user id"),
+    ),
+    responses(
+        (status = 204, description = "Approver removed"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project or approver not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn remove_approver(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    Path((_, approver_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let result = sqlx::query("DELETE FROM project_approvers WHERE project_id = $1 AND user_id = $2")
+        .bind(project_id)
+        .bind(approver_id)
+        .execute(&state.db)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to remove approver: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to remove approver".to_string())
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Approver not found".to_string()).into());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List the changes waiting for approval in a project, oldest first
+/// Visible to the project owner and its approvers
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/pending-changes",
+    tag = "pending-changes",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    responses(
+        (status = 200, description = "Pending changes", body = Vec<PendingChangeResponse>),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    JwtUser(user_id): JwtUser,
+    Path(project_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let can_view = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1 AND created_by = $2)
+            OR EXISTS(SELECT 1 FROM project_approvers WHERE project_id = $1 AND user_id = $2)
+        "#,
+    )
+    .bind(project_id)
+    .bind(user_id)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to check project: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+    if !can_view {
+        return Err((StatusCode::NOT_FOUND, "Project not found".to_string()).into());
+    }
+
+    let changes = sqlx::query_as::<_, PendingChange>(&format!(
+        r#"
+        SELECT {} FROM pending_changes c
+        JOIN feature_flags f ON f.id = c.flag_id
+        WHERE c.project_id = $1 AND c.status = 'pending'
+        ORDER BY c.created_at ASC
+        "#,
+        PENDING_CHANGE_COLUMNS
+    ))
+    .bind(project_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch pending changes: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch pending changes".to_string())
+    })?;
+
+    let response: Vec<PendingChangeResponse> = changes.into_iter().map(PendingChangeResponse::from).collect();
+    Ok(Json(response))
+}
+
+/// Approve a pending change, applying it to the live flag
+/// Answers as the endpoint the change was proposed through: the updated flag, rule or override, or 204 for deletions
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}/pending-changes/{change_id}/approve",
+    tag = "pending-changes",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        ("change_id" = Uuid, Path, description = "Pending change id"),
+    ),
+    responses(
+        (status = 200, description = "Updated flag with warnings about confusing settings, or the rule or override as stored", body = CreateFlagResponse),
+        (status = 204, description = "The approved change deleted a rule or cleared an override"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "The change was proposed by the same user"),
+        (status = 404, description = "Change not found, the user is not an approver of the project, or the rule or override is gone"),
+        (status = 409, description = "Change was already decided, or the flag or its targeting changed since it was proposed"),
+        (status = 422, description = "The change no longer passes validation", body = crate::routes::error::ValidationErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn approve(
+    State(state): State<AppState>,
+    JwtUser(user_id): JwtUser,
+    Path((project_id, change_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, ApiError> {
+    let change = load_for_decision(&state, project_id, change_id, user_id).await?;
+    if let Some(targeting) = change.targeting() {
+        return apply_targeting_change(&state, &change, &targeting, user_id).await;
+    }
+    let response = flags::routes::apply_pending_change(&state, &change, user_id).await?;
+    Ok(Json(response).into_response())
+}
+
+// Apply an approved rule or override edit, checking it again as if it were sent now
+// Fails with 409 if any rule or override of the flag changed since the edit was proposed
+async fn apply_targeting_change(
+    state: &AppState,
+    change: &PendingChange,
+    targeting: &TargetingChange,
+    approver_id: Uuid,
+) -> Result<Response, ApiError> {
+    // The edit, the approval and its audit entry are written together
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+    mark_approved(&mut tx, change.id, approver_id).await?;
+
+    // Lock the flag so its targeting can't change between the check and the edit
+    let rules_version = sqlx::query_scalar::<_, i64>("SELECT rules_version FROM feature_flags WHERE id = $1 FOR UPDATE")
+        .bind(change.flag_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to lock flag: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to apply change".to_string())
+        })?;
+    check_rules_version(change.base_version, rules_version)?;
+
+    let flag_id = change.flag_id;
+    let response = match targeting {
+        TargetingChange::RuleCreate { rule } => {
+            let rule = rules::routes::apply_create(&mut tx, flag_id, rule, approver_id).await?;
+            Json(RuleResponse::from(rule)).into_response()
+        }
+        TargetingChange::RuleUpdate { rule_id, rule } => {
+            let rule = rules::routes::apply_update(&mut tx, flag_id, *rule_id, rule, approver_id).await?;
+            Json(RuleResponse::from(rule)).into_response()
+        }
+        TargetingChange::RuleDelete { rule_id } => {
+            rules::routes::delete_rule(&mut *tx, flag_id, *rule_id).await?;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        TargetingChange::OverrideSet { user_identifier, value } => {
+            let stored = overrides::routes::upsert_override(&mut *tx, flag_id, user_identifier, value, approver_id).await?;
+            Json(OverrideResponse::from(stored)).into_response()
+        }
+        TargetingChange::OverrideClear { user_identifier } => {
+            overrides::routes::delete_override(&mut *tx, flag_id, user_identifier).await?;
+            StatusCode::NO_CONTENT.into_response()
+        }
+    };
+
+    audit::record(
+        &mut tx,
+        AuditEntry {
+            project_id: change.project_id,
+            environment_id: Some(change.environment_id),
+            actor_id: approver_id,
+            action: AuditAction::FlagTargetingApprove,
+            details: serde_json::json!({
+                "flag_id": flag_id,
+                "flag_key": change.flag_key,
+                "pending_change_id": change.id,
+                "proposed_by": change.proposed_by,
+                "mode": change.mode,
+            }),
+            reason: change.reason.clone(),
+        },
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to record audit entry: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to apply change".to_string())
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    Ok(response)
+}
+
+/// Reject a pending change, leaving the live flag as it is
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}/pending-changes/{change_id}/reject",
+    tag = "pending-changes",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        ("change_id" = Uuid, Path, description = "Pending change id"),
+    ),
+    responses(
+        (status = 200, description = "Rejected change", body = PendingChangeResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 403, description = "The change was proposed by the same user"),
+        (status = 404, description = "Change not found, or the user is not an approver of the project"),
+        (status = 409, description = "Change was already decided"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn reject(
+    State(state): State<AppState>,
+    JwtUser(user_id): JwtUser,
+    Path((project_id, change_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, ApiError> {
+    load_for_decision(&state, project_id, change_id, user_id).await?;
+
+    // The rejection and its audit entry are written together
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    let change = sqlx::query_as::<_, PendingChange>(&format!(
+        r#"
+        WITH c AS (
+            UPDATE pending_changes
+            SET status = 'rejected', decided_by = $2, decided_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            RETURNING *
+        )
+        SELECT {} FROM c JOIN feature_flags f ON f.id = c.flag_id
+        "#,
+        PENDING_CHANGE_COLUMNS
+    ))
+    .bind(change_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to reject change: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to reject change".to_string())
+    })?
+    // Someone else approved or rejected it in the meantime
+    .ok_or((StatusCode::CONFLICT, "Change was already decided".to_string()))?;
+
+    audit::record(
+        &mut tx,
+        AuditEntry {
+            project_id,
+            environment_id: Some(change.environment_id),
+            actor_id: user_id,
+            action: AuditAction::FlagChangeReject,
+            details: serde_json::json!({
+                "flag_id": change.flag_id,
+                "flag_key": change.flag_key,
+                "pending_change_id": change.id,
+                "proposed_by": change.proposed_by,
+            }),
+            reason: None,
+        },
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to record audit entry: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to reject change".to_string())
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    Ok(Json(PendingChangeResponse::from(change)))
+}
+
+// A change the user may approve or reject: they are an approver of its project, it is
+// still pending and someone else proposed it
+// Non-approvers get the same 404 as a missing change, like other users' projects
+async fn load_for_decision(
+    state: &AppState,
+    project_id: Uuid,
+    change_id: Uuid,
+    user_id: Uuid,
+) -> Result<PendingChange, (StatusCode, String)> {
+    let not_found = || (StatusCode::NOT_FOUND, "Pending change not found".to_string());
+    if !is_approver(&state.db, project_id, user_id).await? {
+        return Err(not_found());
+    }
+
+    let change = sqlx::query_as::<_, PendingChange>(&format!(
+        r#"
+        SELECT {} FROM pending_changes c
+        JOIN feature_flags f ON f.id = c.flag_id
+        WHERE c.id = $1 AND c.project_id = $2
+        "#,
+        PENDING_CHANGE_COLUMNS
+    ))
+    .bind(change_id)
+    .bind(project_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch pending change: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?
+    .ok_or_else(not_found)?;
+
+    check_decision(&change.status, change.proposed_by, user_id)?;
+    Ok(change)
+}
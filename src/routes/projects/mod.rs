@@ -1,8 +1,13 @@
 pub mod routes;
 
+use rand::{rngs::OsRng, CryptoRng, Rng};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+use crate::routes::environments::{normalize_environment_key, validate_environment_key};
+use crate::routes::sdk_auth::SdkKeyType;
+
 // MODELS
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -10,52 +15,258 @@ pub struct Project {
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
-    pub sdk_key: String,
     pub created_by: Uuid,
+    pub max_rollout_increase_percent: Option<i32>,
+    pub default_environment_key: Option<String>,
+    pub evaluate_rate_limit_per_minute: Option<i32>,
+    pub default_rollout_percentage: Option<i32>,
+    pub unknown_flag_default: String,
+    pub evaluation_sample_rate: f64,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct CreateProjectRequest {
     pub name: String,
     pub description: Option<String>,
+    pub max_rollout_increase_percent: Option<i32>,  // Guardrail on flag rollout jumps, unlimited if omitted
+    pub default_environment_key: Option<String>,  // Used by SDK evaluate requests without an environment
+    pub evaluate_rate_limit_per_minute: Option<i32>,  // SDK evaluate requests per minute, 0 for unlimited
+    pub default_rollout_percentage: Option<i32>,  // Rollout of new flags that don't set one, 0 if omitted
+    pub unknown_flag_default: Option<UnknownFlagDefault>,  // Answer for unknown flag keys in SDK evaluate, off if omitted
+    pub evaluation_sample_rate: Option<f64>,  // Share of SDK evaluations logged, 0.0 to 1.0, 1.0 if omitted
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct UpdateProjectRequest {
     pub name: Option<String>,
     pub description: Option<String>,
+    pub max_rollout_increase_percent: Option<i32>,
+    pub default_environment_key: Option<String>,  // Empty string clears the default
+    pub evaluate_rate_limit_per_minute: Option<i32>,
+    pub default_rollout_percentage: Option<i32>,
+    pub unknown_flag_default: Option<UnknownFlagDefault>,
+    pub evaluation_sample_rate: Option<f64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ProjectResponse {
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
-    pub sdk_key: String,
+    // SDK keys are only included when they are issued (create / regenerate-key),
+    // use /api/projects/{id}/sdk-keys to list them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sdk_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_sdk_key: Option<String>,
+    pub max_rollout_increase_percent: Option<i32>,
+    pub default_environment_key: Option<String>,
+    pub evaluate_rate_limit_per_minute: Option<i32>,
+    pub default_rollout_percentage: Option<i32>,
+    pub unknown_flag_default: String,
+    pub evaluation_sample_rate: f64,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+// What SDK evaluate answers for requested flag keys the environment doesn't serve
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownFlagDefault {
+    /// The flag is reported disabled with reason "unknown_flag", like SDKs failing open
+    #[default]
+    Off,
+    /// The whole request fails with 404, for callers that would rather fail closed
+    Error,
+}
+
+impl UnknownFlagDefault {
+    /// Value stored in the `projects.unknown_flag_default` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnknownFlagDefault::Off => "off",
+            UnknownFlagDefault::Error => "error",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "error" => UnknownFlagDefault::Error,
+            _ => UnknownFlagDefault::Off,
+        }
+    }
+}
+
+/// Aggregate counts for a project dashboard
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct ProjectSummary {
+    pub environment_count: i64,
+    pub flag_count: i64,
+    pub enabled_flag_count: i64,
+    pub rule_count: i64,
+    pub last_evaluated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// One entry of a project's audit log
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct AuditLogEntry {
+    pub id: i64,
+    pub environment_id: Option<Uuid>,
+    pub actor_id: Uuid,
+    pub action: String,
+    pub details: serde_json::Value,
+    pub reason: Option<String>,  // Why the change was made, when the user gave a reason
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TransferProjectRequest {
+    pub email: String,    // Email of the user who becomes the owner
+    pub confirm: String,  // Must repeat the project name
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RegenerateKeyParams {
+    pub key_type: Option<SdkKeyType>,  // Which key to rotate, defaults to the server key
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PurgeEvaluationsParams {
+    pub older_than_days: i64,  // Evaluation logs older than this many days are deleted
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PurgeEvaluationsResponse {
+    pub deleted: i64,
+    pub older_than: chrono::DateTime<chrono::Utc>,
+}
+
 // HELPER FUNCTIONS
 
-/// Generate a secure SDK key
+/// Validate the project's maximum rollout increase per flag update
+pub fn validate_max_rollout_increase(percent: i32) -> Result<(), String> {
+    if !(0..=100).contains(&percent) {
+        return Err("max_rollout_increase_percent must be between 0 and 100".to_string());
+    }
+    Ok(())
+}
+
+/// Validate and normalize a default environment key, an empty key clears the default
+pub fn parse_default_environment_key(key: &str) -> Result<Option<String>, String> {
+    if key.trim().is_empty() {
+        return Ok(None);
+    }
+    validate_environment_key(key).map_err(|e| format!("default_environment_key: {}", e.message))?;
+    Ok(Some(normalize_environment_key(key)))
+}
+
+/// Validate a project's SDK evaluate rate limit
+pub fn validate_evaluate_rate_limit(limit: i32) -> Result<(), String> {
+    if limit < 0 {
+        return Err("evaluate_rate_limit_per_minute cannot be negative, use 0 for unlimited".to_string());
+    }
+    Ok(())
+}
+
+/// Validate the rollout new flags in the project start with
+pub fn validate_default_rollout(percent: i32) -> Result<(), String> {
+    if !(0..=100).contains(&percent) {
+        return Err("default_rollout_percentage must be between 0 and 100".to_string());
+    }
+    Ok(())
+}
+
+/// Validate the share of SDK evaluations the project logs
+pub fn validate_evaluation_sample_rate(rate: f64) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&rate) {
+        return Err("evaluation_sample_rate must be between 0.0 and 1.0".to_string());
+    }
+    Ok(())
+}
+
+/// Check a transfer request against the project and the resolved target user
+pub fn validate_transfer(project_name: &str, confirm: &str, owner_id: Uuid, new_owner_id: Uuid) -> Result<(), String> {
+    if confirm != project_name {
+        return Err("confirm must match the project name exactly".to_string());
+    }
+    if owner_id == new_owner_id {
+        return Err("You already own this project".to_string());
+    }
+    Ok(())
+}
+
+/// Rows deleted per statement when purging evaluation logs, keeps each lock short
+pub const EVALUATION_PURGE_BATCH_SIZE: i64 = 5_000;
+
+/// Longest retention accepted by the purge endpoint (100 years)
+pub const MAX_RETENTION_DAYS: i64 = 36_500;
+
+/// Validate the retention period of an evaluation log purge
+pub fn validate_retention_days(days: i64) -> Result<(), String> {
+    if !(1..=MAX_RETENTION_DAYS).contains(&days) {
+        return Err(format!(
+            "older_than_days must be between 1 and {}",
+            MAX_RETENTION_DAYS
+        ));
+    }
+    Ok(())
+}
+
+/// Generate a secure SDK key from the OS random source
 /// Format: "sdk_" + 32 random alphanumeric characters
 pub fn generate_sdk_key() -> String {
-    use rand::Rng;
+    generate_sdk_key_with(&mut OsRng)
+}
+
+/// Generate an SDK key from the given RNG, which must be cryptographically secure
+/// Tests pass a seeded RNG to get a known key
+pub fn generate_sdk_key_with<R: Rng + CryptoRng>(rng: &mut R) -> String {
+    format!("sdk_{}", random_key_suffix(rng))
+}
+
+/// Generate a secure client-side SDK key
+/// Format: "client_" + 32 random alphanumeric characters
+pub fn generate_client_sdk_key() -> String {
+    format!("client_{}", random_key_suffix(&mut OsRng))
+}
+
+/// Generate a project API token for CI and scripts
+/// Format: "fftok_" + 32 random alphanumeric characters
+pub fn generate_api_token() -> String {
+    format!("{}{}", crate::routes::api_tokens::API_TOKEN_PREFIX, random_key_suffix(&mut OsRng))
+}
+
+/// Hash an SDK key for storage; only the hash is persisted
+/// Format: lowercase hex-encoded SHA-256
+pub fn hash_sdk_key(key: &str) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Non-secret prefix of an SDK key shown in listings (e.g. "sdk_a1b2")
+pub fn sdk_key_prefix(key: &str) -> String {
+    const VISIBLE_SECRET_CHARS: usize = 4;
+    let secret_start = key.find('_').map(|i| i + 1).unwrap_or(0);
+    key.chars().take(secret_start + VISIBLE_SECRET_CHARS).collect()
+}
+
+/// 32 random alphanumeric characters used as the secret part of SDK keys
+fn random_key_suffix<R: Rng + CryptoRng>(rng: &mut R) -> String {
     const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
     const KEY_LENGTH: usize = 32;
 
-    let mut rng = rand::thread_rng();
-    let key: String = (0..KEY_LENGTH)
+    (0..KEY_LENGTH)
         .map(|_| {
             let idx = rng.gen_range(0..CHARSET.len());
             CHARSET[idx] as char
         })
-        .collect();
-
-    format!("sdk_{}", key)
+        .collect()
 }
 
 #[cfg(test)]
@@ -71,4 +282,122 @@ mod tests {
         assert_eq!(key1.len(), 36); // "sdk_" (4) + 32 chars
         assert_ne!(key1, key2); // Should be random
     }
+
+    #[test]
+    fn test_generate_sdk_key_with_seeded_rng() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        // The same seed gives the same key, a different one doesn't
+        let key = generate_sdk_key_with(&mut StdRng::seed_from_u64(42));
+        assert_eq!(key, generate_sdk_key_with(&mut StdRng::seed_from_u64(42)));
+        assert_ne!(key, generate_sdk_key_with(&mut StdRng::seed_from_u64(43)));
+
+        // Pinned for the locked rand version, StdRng's algorithm may change in a new major version
+        assert_eq!(key, "sdk_GHNzczT0ia5FyiGnaGdO0EFdvBXmyOA3");
+    }
+
+    #[test]
+    fn test_hash_sdk_key() {
+        let key = generate_sdk_key();
+        let hash = hash_sdk_key(&key);
+
+        assert_eq!(hash.len(), 64); // SHA-256 as hex
+        assert_eq!(hash, hash_sdk_key(&key)); // Deterministic
+        assert_ne!(hash, hash_sdk_key(&generate_sdk_key()));
+        assert_eq!(
+            hash_sdk_key("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_parse_default_environment_key() {
+        assert_eq!(parse_default_environment_key("Staging"), Ok(Some("staging".to_string())));
+        assert_eq!(parse_default_environment_key(""), Ok(None));
+        assert_eq!(parse_default_environment_key("  "), Ok(None));
+        assert!(parse_default_environment_key("1production").is_err());
+        assert!(parse_default_environment_key("prod env").is_err());
+    }
+
+    #[test]
+    fn test_validate_evaluate_rate_limit() {
+        assert!(validate_evaluate_rate_limit(0).is_ok());
+        assert!(validate_evaluate_rate_limit(600).is_ok());
+        assert!(validate_evaluate_rate_limit(-1).is_err());
+    }
+
+    #[test]
+    fn test_validate_default_rollout() {
+        assert!(validate_default_rollout(0).is_ok());
+        assert!(validate_default_rollout(10).is_ok());
+        assert!(validate_default_rollout(100).is_ok());
+        assert!(validate_default_rollout(-1).is_err());
+        assert!(validate_default_rollout(101).is_err());
+    }
+
+    #[test]
+    fn test_validate_evaluation_sample_rate() {
+        assert!(validate_evaluation_sample_rate(0.0).is_ok());
+        assert!(validate_evaluation_sample_rate(0.25).is_ok());
+        assert!(validate_evaluation_sample_rate(1.0).is_ok());
+        assert!(validate_evaluation_sample_rate(-0.1).is_err());
+        assert!(validate_evaluation_sample_rate(1.5).is_err());
+        assert!(validate_evaluation_sample_rate(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_validate_max_rollout_increase() {
+        assert!(validate_max_rollout_increase(0).is_ok());
+        assert!(validate_max_rollout_increase(25).is_ok());
+        assert!(validate_max_rollout_increase(100).is_ok());
+        assert!(validate_max_rollout_increase(-1).is_err());
+        assert!(validate_max_rollout_increase(101).is_err());
+    }
+
+    #[test]
+    fn test_validate_transfer() {
+        let (owner, other) = (Uuid::new_v4(), Uuid::new_v4());
+
+        assert!(validate_transfer("My App", "My App", owner, other).is_ok());
+        assert!(validate_transfer("My App", "my app", owner, other).is_err());
+        assert!(validate_transfer("My App", "", owner, other).is_err());
+        assert!(validate_transfer("My App", "My App", owner, owner)
+            .unwrap_err()
+            .contains("already own"));
+    }
+
+    #[test]
+    fn test_validate_retention_days() {
+        assert!(validate_retention_days(1).is_ok());
+        assert!(validate_retention_days(90).is_ok());
+        assert!(validate_retention_days(MAX_RETENTION_DAYS).is_ok());
+        assert!(validate_retention_days(0).is_err());
+        assert!(validate_retention_days(-7).is_err());
+        assert!(validate_retention_days(MAX_RETENTION_DAYS + 1).is_err());
+    }
+
+    #[test]
+    fn test_sdk_key_prefix() {
+        assert_eq!(sdk_key_prefix("sdk_abcdefgh"), "sdk_abcd");
+        assert_eq!(sdk_key_prefix("client_abcdefgh"), "client_abcd");
+    }
+
+    #[test]
+    fn test_generate_client_sdk_key() {
+        let key = generate_client_sdk_key();
+
+        assert!(key.starts_with("client_"));
+        assert_eq!(key.len(), 39); // "client_" (7) + 32 chars
+        assert_ne!(key, generate_client_sdk_key());
+    }
+
+    #[test]
+    fn test_generate_api_token() {
+        let token = generate_api_token();
+
+        assert!(token.starts_with("fftok_"));
+        assert_eq!(token.len(), 38); // "fftok_" (6) + 32 chars
+        assert_eq!(sdk_key_prefix(&token), token[..10]);
+        assert_ne!(token, generate_api_token());
+    }
 }
@@ -1,6 +1,6 @@
 // src/routes/projects/routes.rs
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Json,
@@ -8,9 +8,19 @@ use axum::{
 use uuid::Uuid;
 
 use super::{
-    generate_sdk_key, CreateProjectRequest, Project, ProjectResponse, UpdateProjectRequest,
+    generate_client_sdk_key, generate_sdk_key, hash_sdk_key, parse_default_environment_key, sdk_key_prefix,
+    validate_default_rollout, validate_evaluate_rate_limit, validate_evaluation_sample_rate, validate_max_rollout_increase,
+    validate_retention_days, validate_transfer,
+    AuditLogEntry, CreateProjectRequest, Project, ProjectResponse, ProjectSummary, PurgeEvaluationsParams,
+    PurgeEvaluationsResponse, RegenerateKeyParams, TransferProjectRequest, UpdateProjectRequest, EVALUATION_PURGE_BATCH_SIZE,
 };
+use crate::audit::{self, AuditAction, AuditEntry};
+use crate::evaluation::normalize_email;
+use crate::routes::error::{ApiError, JsonBody};
 use crate::routes::middleware_auth::JwtUser;
+use crate::routes::pagination::{paginate, Cursor, Page, PageParams};
+use crate::routes::sdk_auth::SdkKeyType;
+use crate::routes::ownership::VerifiedProject;
 use crate::state::AppState;
 
 /// Default environments to create for every new project
@@ -23,13 +33,57 @@ const DEFAULT_ENVIRONMENTS: &[(&str, &str)] = &[
 
 /// Create a new project
 /// Automatically creates default environments (production, staging) within a transaction
+#[utoipa::path(
+    post,
+    path = "/api/projects",
+    tag = "projects",
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the original response when a create is retried with the same key"),
+    ),
+    request_body = CreateProjectRequest,
+    responses(
+        (status = 201, description = "Project created with its default environments and SDK keys", body = ProjectResponse),
+        (status = 400, description = "Invalid request"),
+        (status = 401, description = "Missing or invalid token"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn create(
     State(state): State<AppState>,
     JwtUser(user_id): JwtUser, // ← Tuple struct destructuring
-    Json(payload): Json<CreateProjectRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Generate a secure SDK key (this is what client apps will use)
+    JsonBody(payload): JsonBody<CreateProjectRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    if let Some(percent) = payload.max_rollout_increase_percent {
+        validate_max_rollout_increase(percent).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    if let Some(limit) = payload.evaluate_rate_limit_per_minute {
+        validate_evaluate_rate_limit(limit).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    if let Some(percent) = payload.default_rollout_percentage {
+        validate_default_rollout(percent).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    if let Some(rate) = payload.evaluation_sample_rate {
+        validate_evaluation_sample_rate(rate).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+
+    // Only the default environments exist yet, so the SDK default must be one of them
+    let default_environment_key = match payload.default_environment_key.as_deref() {
+        Some(key) => parse_default_environment_key(key).map_err(|e| (StatusCode::BAD_REQUEST, e))?,
+        None => None,
+    };
+    if let Some(key) = &default_environment_key {
+        if !DEFAULT_ENVIRONMENTS.iter().any(|(env_key, _)| env_key == key) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("default_environment_key: Environment '{}' not found in this project", key),
+            ).into());
+        }
+    }
+
+    // Generate secure SDK keys (server key sees all flags, client key only client-visible ones)
+    // Only their hashes are stored, the plaintext is returned once in this response
     let sdk_key = generate_sdk_key();
+    let client_sdk_key = generate_client_sdk_key();
 
     // Start a transaction to ensure project + default environments are created atomically
     let mut tx = state.db.begin().await.map_err(|e| {
@@ -43,15 +97,20 @@ pub async fn create(
     // Insert the project
     let project = sqlx::query_as::<_, Project>(
         r#"
-        INSERT INTO projects (name, description, sdk_key, created_by)
-        VALUES ($1, $2, $3, $4)
+        INSERT INTO projects (name, description, created_by, max_rollout_increase_percent, default_environment_key, evaluate_rate_limit_per_minute, default_rollout_percentage, unknown_flag_default, evaluation_sample_rate)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
         RETURNING *
         "#,
     )
     .bind(&payload.name)
     .bind(&payload.description)
-    .bind(&sdk_key)
     .bind(user_id)
+    .bind(payload.max_rollout_increase_percent)
+    .bind(&default_environment_key)
+    .bind(payload.evaluate_rate_limit_per_minute)
+    .bind(payload.default_rollout_percentage)
+    .bind(payload.unknown_flag_default.unwrap_or_default().as_str())
+    .bind(payload.evaluation_sample_rate.unwrap_or(1.0))
     .fetch_one(&mut *tx)
     .await
     .map_err(|e| {
@@ -62,6 +121,33 @@ pub async fn create(
         )
     })?;
 
+    // Issue the default server and client SDK keys
+    for (key, key_type, label) in [
+        (&sdk_key, SdkKeyType::Server, "Default server key"),
+        (&client_sdk_key, SdkKeyType::Client, "Default client key"),
+    ] {
+        sqlx::query(
+            r#"
+            INSERT INTO project_sdk_keys (project_id, key_hash, key_prefix, key_type, label)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(project.id)
+        .bind(hash_sdk_key(key))
+        .bind(sdk_key_prefix(key))
+        .bind(key_type.as_str())
+        .bind(label)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to create default SDK key: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to create default SDK key: {}", e),
+            )
+        })?;
+    }
+
     // Create default environments (production and staging)
     for (env_key, env_name) in DEFAULT_ENVIRONMENTS {
         sqlx::query(
@@ -98,7 +184,14 @@ pub async fn create(
         id: project.id,
         name: project.name,
         description: project.description,
-        sdk_key: project.sdk_key,
+        sdk_key: Some(sdk_key),
+        client_sdk_key: Some(client_sdk_key),
+        max_rollout_increase_percent: project.max_rollout_increase_percent,
+        default_environment_key: project.default_environment_key,
+        evaluate_rate_limit_per_minute: project.evaluate_rate_limit_per_minute,
+        default_rollout_percentage: project.default_rollout_percentage,
+        unknown_flag_default: project.unknown_flag_default,
+        evaluation_sample_rate: project.evaluation_sample_rate,
         created_at: project.created_at,
         updated_at: project.updated_at,
     };
@@ -107,10 +200,20 @@ pub async fn create(
 }
 
 /// List all projects for the authenticated user
+#[utoipa::path(
+    get,
+    path = "/api/projects",
+    tag = "projects",
+    responses(
+        (status = 200, description = "Projects owned by the user", body = Vec<ProjectResponse>),
+        (status = 401, description = "Missing or invalid token"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn list(
     State(state): State<AppState>,
     JwtUser(user_id): JwtUser,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let projects = sqlx::query_as::<_, Project>(
         r#"
         SELECT * FROM projects
@@ -135,7 +238,14 @@ pub async fn list(
             id: p.id,
             name: p.name,
             description: p.description,
-            sdk_key: p.sdk_key,
+            sdk_key: None,
+            client_sdk_key: None,
+            max_rollout_increase_percent: p.max_rollout_increase_percent,
+            default_environment_key: p.default_environment_key,
+            evaluate_rate_limit_per_minute: p.evaluate_rate_limit_per_minute,
+            default_rollout_percentage: p.default_rollout_percentage,
+            unknown_flag_default: p.unknown_flag_default,
+            evaluation_sample_rate: p.evaluation_sample_rate,
             created_at: p.created_at,
             updated_at: p.updated_at,
         })
@@ -145,19 +255,31 @@ pub async fn list(
 }
 
 /// Get a single project by ID
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}",
+    tag = "projects",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    responses(
+        (status = 200, description = "Project", body = ProjectResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path(project_id): Path<Uuid>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    VerifiedProject { project_id, .. }: VerifiedProject,
+) -> Result<impl IntoResponse, ApiError> {
     let project = sqlx::query_as::<_, Project>(
         r#"
         SELECT * FROM projects
-        WHERE id = $1 AND created_by = $2
+        WHERE id = $1
         "#,
     )
     .bind(project_id)
-    .bind(user_id)
     .fetch_optional(&state.db)
     .await
     .map_err(|e| {
@@ -174,41 +296,295 @@ pub async fn get(
                 id: p.id,
                 name: p.name,
                 description: p.description,
-                sdk_key: p.sdk_key,
+                sdk_key: None,
+                client_sdk_key: None,
+                max_rollout_increase_percent: p.max_rollout_increase_percent,
+                default_environment_key: p.default_environment_key,
+                evaluate_rate_limit_per_minute: p.evaluate_rate_limit_per_minute,
+                default_rollout_percentage: p.default_rollout_percentage,
+                unknown_flag_default: p.unknown_flag_default,
+                evaluation_sample_rate: p.evaluation_sample_rate,
                 created_at: p.created_at,
                 updated_at: p.updated_at,
             };
             Ok(Json(response))
         }
-        None => Err((StatusCode::NOT_FOUND, "Project not found".to_string())),
+        None => Err((StatusCode::NOT_FOUND, "Project not found".to_string()).into()),
     }
 }
 
-/// Update a project
-pub async fn update(
+/// Check a project exists and is yours without fetching it, 200 or 404 with no body
+#[utoipa::path(
+    head,
+    path = "/api/projects/{id}",
+    tag = "projects",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    responses(
+        (status = 200, description = "Project exists"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn exists(_: VerifiedProject) -> StatusCode {
+    StatusCode::OK
+}
+
+/// Get dashboard counts for a project in a single round trip
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/summary",
+    tag = "projects",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    responses(
+        (status = 200, description = "Aggregate counts for the project", body = ProjectSummary),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn summary(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path(project_id): Path<Uuid>,
-    Json(payload): Json<UpdateProjectRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // First check if project exists and belongs to user
-    let exists = sqlx::query_scalar::<_, bool>(
-        "SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1 AND created_by = $2)",
+    VerifiedProject { project_id, .. }: VerifiedProject,
+) -> Result<impl IntoResponse, ApiError> {
+    let summary = sqlx::query_as::<_, ProjectSummary>(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM environments e WHERE e.project_id = p.id) AS environment_count,
+            (SELECT COUNT(*) FROM feature_flags f WHERE f.project_id = p.id) AS flag_count,
+            (SELECT COUNT(*) FROM feature_flags f WHERE f.project_id = p.id AND f.enabled) AS enabled_flag_count,
+            (SELECT COUNT(*) FROM flag_rules r
+                JOIN feature_flags f ON r.flag_id = f.id
+                WHERE f.project_id = p.id) AS rule_count,
+            (SELECT MAX(fe.evaluated_at) FROM flag_evaluations fe
+                JOIN feature_flags f ON fe.flag_id = f.id
+                WHERE f.project_id = p.id) AS last_evaluated_at
+        FROM projects p
+        WHERE p.id = $1
+        "#,
     )
     .bind(project_id)
-    .bind(user_id)
-    .fetch_one(&state.db)
+    .fetch_optional(&state.db)
     .await
     .map_err(|e| {
-        eprintln!("Failed to check project: {:?}", e);
+        eprintln!("Failed to fetch project summary: {:?}", e);
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            "Database error".to_string(),
+            "Failed to fetch project summary".to_string(),
+        )
+    })?;
+
+    match summary {
+        Some(s) => Ok(Json(s)),
+        None => Err((StatusCode::NOT_FOUND, "Project not found".to_string()).into()),
+    }
+}
+
+/// List a project's audit log, newest first
+/// Uses keyset pagination on (created_at, id), pass `next_cursor` back as `?cursor=`
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/audit-log",
+    tag = "projects",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        PageParams,
+    ),
+    responses(
+        (status = 200, description = "One page of the audit log, newest first", body = Page<AuditLogEntry>),
+        (status = 400, description = "Invalid cursor"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn audit_log(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    Query(params): Query<PageParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let cursor = params.cursor().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let limit = params.limit();
+
+    let rows = sqlx::query_as::<_, AuditLogEntry>(
+        r#"
+        SELECT id, environment_id, actor_id, action, details, reason, created_at
+        FROM audit_log
+        WHERE project_id = $1
+        AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4
+        "#,
+    )
+    .bind(project_id)
+    .bind(cursor.map(|c| c.created_at))
+    .bind(cursor.map(|c| c.id))
+    .bind(limit + 1)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch audit log: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch audit log".to_string())
+    })?;
+
+    let page = paginate(rows, limit, |row| Cursor {
+        created_at: row.created_at,
+        id: row.id,
+    });
+
+    Ok(Json(page))
+}
+
+/// Delete evaluation logs older than `older_than_days` for all of a project's flags
+/// Rows are removed in batches so a large purge never holds long locks on the table
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{id}/evaluations",
+    tag = "projects",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        PurgeEvaluationsParams,
+    ),
+    responses(
+        (status = 200, description = "Evaluation logs deleted", body = PurgeEvaluationsResponse),
+        (status = 400, description = "Invalid retention"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn purge_evaluations(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, user_id }: VerifiedProject,
+    Query(params): Query<PurgeEvaluationsParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_retention_days(params.older_than_days).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let older_than = state.clock.now() - chrono::Duration::days(params.older_than_days);
+    let mut deleted: i64 = 0;
+
+    loop {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM flag_evaluations
+            WHERE id IN (
+                SELECT fe.id FROM flag_evaluations fe
+                JOIN feature_flags f ON fe.flag_id = f.id
+                WHERE f.project_id = $1 AND fe.evaluated_at < $2
+                LIMIT $3
+            )
+            "#,
         )
+        .bind(project_id)
+        .bind(older_than)
+        .bind(EVALUATION_PURGE_BATCH_SIZE)
+        .execute(&state.db)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to purge evaluations: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to purge evaluations".to_string())
+        })?;
+
+        let batch = result.rows_affected() as i64;
+        deleted += batch;
+        if batch < EVALUATION_PURGE_BATCH_SIZE {
+            break;
+        }
+    }
+
+    let mut conn = state.db.acquire().await.map_err(|e| {
+        eprintln!("Failed to acquire connection: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
     })?;
+    audit::record(
+        &mut conn,
+        AuditEntry {
+            project_id,
+            environment_id: None,
+            actor_id: user_id,
+            action: AuditAction::EvaluationsPurge,
+            details: serde_json::json!({
+                "older_than": older_than,
+                "deleted": deleted,
+            }),
+            reason: None,
+        },
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to record audit entry: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    Ok(Json(PurgeEvaluationsResponse { deleted, older_than }))
+}
 
-    if !exists {
-        return Err((StatusCode::NOT_FOUND, "Project not found".to_string()));
+/// Update a project
+#[utoipa::path(
+    put,
+    path = "/api/projects/{id}",
+    tag = "projects",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    request_body = UpdateProjectRequest,
+    responses(
+        (status = 200, description = "Updated project", body = ProjectResponse),
+        (status = 400, description = "Invalid request"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    JsonBody(payload): JsonBody<UpdateProjectRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    if let Some(percent) = payload.max_rollout_increase_percent {
+        validate_max_rollout_increase(percent).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    if let Some(limit) = payload.evaluate_rate_limit_per_minute {
+        validate_evaluate_rate_limit(limit).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    if let Some(percent) = payload.default_rollout_percentage {
+        validate_default_rollout(percent).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    if let Some(rate) = payload.evaluation_sample_rate {
+        validate_evaluation_sample_rate(rate).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    let default_environment_key = match payload.default_environment_key.as_deref() {
+        Some(key) => Some(parse_default_environment_key(key).map_err(|e| (StatusCode::BAD_REQUEST, e))?),
+        None => None,
+    };
+
+    // A new SDK default must name one of the project's environments
+    if let Some(Some(key)) = &default_environment_key {
+        let environment_exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM environments WHERE project_id = $1 AND key = $2)",
+        )
+        .bind(project_id)
+        .bind(key)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to check environment: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+
+        if !environment_exists {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("default_environment_key: Environment '{}' not found in this project", key),
+            ).into());
+        }
     }
 
     // Build dynamic update query based on what fields are provided
@@ -223,12 +599,32 @@ pub async fn update(
         query.push_str(&format!(", description = ${}", bind_count));
         bind_count += 1;
     }
+    if payload.max_rollout_increase_percent.is_some() {
+        query.push_str(&format!(", max_rollout_increase_percent = ${}", bind_count));
+        bind_count += 1;
+    }
+    if default_environment_key.is_some() {
+        query.push_str(&format!(", default_environment_key = ${}", bind_count));
+        bind_count += 1;
+    }
+    if payload.evaluate_rate_limit_per_minute.is_some() {
+        query.push_str(&format!(", evaluate_rate_limit_per_minute = ${}", bind_count));
+        bind_count += 1;
+    }
+    if payload.default_rollout_percentage.is_some() {
+        query.push_str(&format!(", default_rollout_percentage = ${}", bind_count));
+        bind_count += 1;
+    }
+    if payload.unknown_flag_default.is_some() {
+        query.push_str(&format!(", unknown_flag_default = ${}", bind_count));
+        bind_count += 1;
+    }
+    if payload.evaluation_sample_rate.is_some() {
+        query.push_str(&format!(", evaluation_sample_rate = ${}", bind_count));
+        bind_count += 1;
+    }
 
-    query.push_str(&format!(
-        " WHERE id = ${} AND created_by = ${} RETURNING *",
-        bind_count,
-        bind_count + 1
-    ));
+    query.push_str(&format!(" WHERE id = ${} RETURNING *", bind_count));
 
     let mut query_builder = sqlx::query_as::<_, Project>(&query);
 
@@ -238,10 +634,27 @@ pub async fn update(
     if let Some(description) = payload.description {
         query_builder = query_builder.bind(description);
     }
+    if let Some(percent) = payload.max_rollout_increase_percent {
+        query_builder = query_builder.bind(percent);
+    }
+    if let Some(key) = default_environment_key {
+        query_builder = query_builder.bind(key);
+    }
+    if let Some(limit) = payload.evaluate_rate_limit_per_minute {
+        query_builder = query_builder.bind(limit);
+    }
+    if let Some(percent) = payload.default_rollout_percentage {
+        query_builder = query_builder.bind(percent);
+    }
+    if let Some(default) = payload.unknown_flag_default {
+        query_builder = query_builder.bind(default.as_str());
+    }
+    if let Some(rate) = payload.evaluation_sample_rate {
+        query_builder = query_builder.bind(rate);
+    }
 
     let project = query_builder
         .bind(project_id)
-        .bind(user_id)
         .fetch_one(&state.db)
         .await
         .map_err(|e| {
@@ -256,7 +669,14 @@ pub async fn update(
         id: project.id,
         name: project.name,
         description: project.description,
-        sdk_key: project.sdk_key,
+        sdk_key: None,
+        client_sdk_key: None,
+        max_rollout_increase_percent: project.max_rollout_increase_percent,
+        default_environment_key: project.default_environment_key,
+        evaluate_rate_limit_per_minute: project.evaluate_rate_limit_per_minute,
+        default_rollout_percentage: project.default_rollout_percentage,
+        unknown_flag_default: project.unknown_flag_default,
+        evaluation_sample_rate: project.evaluation_sample_rate,
         created_at: project.created_at,
         updated_at: project.updated_at,
     };
@@ -265,19 +685,31 @@ pub async fn update(
 }
 
 /// Delete a project (this will cascade delete all flags)
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{id}",
+    tag = "projects",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    responses(
+        (status = 204, description = "Project deleted"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn delete(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path(project_id): Path<Uuid>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    VerifiedProject { project_id, .. }: VerifiedProject,
+) -> Result<impl IntoResponse, ApiError> {
     let result = sqlx::query(
         r#"
         DELETE FROM projects
-        WHERE id = $1 AND created_by = $2
+        WHERE id = $1
         "#,
     )
     .bind(project_id)
-    .bind(user_id)
     .execute(&state.db)
     .await
     .map_err(|e| {
@@ -289,32 +721,60 @@ pub async fn delete(
     })?;
 
     if result.rows_affected() == 0 {
-        return Err((StatusCode::NOT_FOUND, "Project not found".to_string()));
+        return Err((StatusCode::NOT_FOUND, "Project not found".to_string()).into());
     }
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 /// Regenerate SDK key for a project (useful if key is compromised)
+/// Revokes every active key of that type and issues a fresh one, so existing clients stop working immediately;
+/// use the sdk-keys endpoints for zero-downtime rotation instead.
+/// Pass `?key_type=client` to rotate the client-side key instead of the server key
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}/regenerate-key",
+    tag = "projects",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        RegenerateKeyParams,
+    ),
+    responses(
+        (status = 200, description = "Project with the newly issued SDK key", body = ProjectResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn regenerate_key(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path(project_id): Path<Uuid>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let new_sdk_key = generate_sdk_key();
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    Query(params): Query<RegenerateKeyParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let key_type = params.key_type.unwrap_or(SdkKeyType::Server);
+    let new_sdk_key = match key_type {
+        SdkKeyType::Server => generate_sdk_key(),
+        SdkKeyType::Client => generate_client_sdk_key(),
+    };
+
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
 
     let project = sqlx::query_as::<_, Project>(
         r#"
         UPDATE projects
-        SET sdk_key = $1, updated_at = NOW()
-        WHERE id = $2 AND created_by = $3
+        SET updated_at = NOW()
+        WHERE id = $1
         RETURNING *
         "#,
     )
-    .bind(&new_sdk_key)
     .bind(project_id)
-    .bind(user_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(|e| {
         eprintln!("Failed to regenerate SDK key: {:?}", e);
@@ -324,18 +784,216 @@ pub async fn regenerate_key(
         )
     })?;
 
-    match project {
-        Some(p) => {
-            let response = ProjectResponse {
-                id: p.id,
-                name: p.name,
-                description: p.description,
-                sdk_key: p.sdk_key,
-                created_at: p.created_at,
-                updated_at: p.updated_at,
-            };
-            Ok(Json(response))
-        }
-        None => Err((StatusCode::NOT_FOUND, "Project not found".to_string())),
-    }
+    let project = match project {
+        Some(p) => p,
+        None => return Err((StatusCode::NOT_FOUND, "Project not found".to_string()).into()),
+    };
+
+    // Revoke all active keys of this type, then issue the replacement
+    sqlx::query(
+        r#"
+        UPDATE project_sdk_keys
+        SET revoked_at = NOW()
+        WHERE project_id = $1 AND key_type = $2 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(project_id)
+    .bind(key_type.as_str())
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to revoke SDK keys: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to regenerate SDK key".to_string(),
+        )
+    })?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO project_sdk_keys (project_id, key_hash, key_prefix, key_type, label)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(project_id)
+    .bind(hash_sdk_key(&new_sdk_key))
+    .bind(sdk_key_prefix(&new_sdk_key))
+    .bind(key_type.as_str())
+    .bind("Regenerated key")
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to insert SDK key: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to regenerate SDK key".to_string(),
+        )
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
+
+    let (sdk_key, client_sdk_key) = match key_type {
+        SdkKeyType::Server => (Some(new_sdk_key), None),
+        SdkKeyType::Client => (None, Some(new_sdk_key)),
+    };
+
+    let response = ProjectResponse {
+        id: project.id,
+        name: project.name,
+        description: project.description,
+        sdk_key,
+        client_sdk_key,
+        max_rollout_increase_percent: project.max_rollout_increase_percent,
+        default_environment_key: project.default_environment_key,
+        evaluate_rate_limit_per_minute: project.evaluate_rate_limit_per_minute,
+        default_rollout_percentage: project.default_rollout_percentage,
+        unknown_flag_default: project.unknown_flag_default,
+        evaluation_sample_rate: project.evaluation_sample_rate,
+        created_at: project.created_at,
+        updated_at: project.updated_at,
+    };
+
+    Ok(Json(response))
+}
+
+/// Hand a project to another registered user
+/// The caller must repeat the project name in `confirm` and loses access once it's done
+#[utoipa::path(
+    post,
+    path = "/api/projects/{id}/transfer",
+    tag = "projects",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+    ),
+    request_body = TransferProjectRequest,
+    responses(
+        (status = 200, description = "Project now owned by the new user", body = ProjectResponse),
+        (status = 400, description = "Confirmation does not match the project name"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project or user not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn transfer(
+    State(state): State<AppState>,
+    JwtUser(user_id): JwtUser,
+    Path(project_id): Path<Uuid>,
+    JsonBody(payload): JsonBody<TransferProjectRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
+
+    let project_name = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM projects WHERE id = $1 AND created_by = $2 FOR UPDATE",
+    )
+    .bind(project_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch project: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Project not found".to_string()))?;
+
+    let email = normalize_email(&payload.email);
+    let new_owner_id = sqlx::query_scalar::<_, Uuid>("SELECT id FROM users WHERE email = $1")
+        .bind(&email)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to fetch user: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No user with email '{}'", email)))?;
+
+    validate_transfer(&project_name, &payload.confirm, user_id, new_owner_id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let project = sqlx::query_as::<_, Project>(
+        r#"
+        UPDATE projects
+        SET created_by = $2, updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(project_id)
+    .bind(new_owner_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to transfer project: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to transfer project".to_string(),
+        )
+    })?;
+
+    audit::record(
+        &mut tx,
+        AuditEntry {
+            project_id,
+            environment_id: None,
+            actor_id: user_id,
+            action: AuditAction::ProjectTransfer,
+            details: serde_json::json!({
+                "from_user_id": user_id,
+                "to_user_id": new_owner_id,
+                "to_email": email,
+            }),
+            reason: None,
+        },
+    )
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to record audit entry: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to transfer project".to_string(),
+        )
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
+
+    let response = ProjectResponse {
+        id: project.id,
+        name: project.name,
+        description: project.description,
+        sdk_key: None,
+        client_sdk_key: None,
+        max_rollout_increase_percent: project.max_rollout_increase_percent,
+        default_environment_key: project.default_environment_key,
+        evaluate_rate_limit_per_minute: project.evaluate_rate_limit_per_minute,
+        default_rollout_percentage: project.default_rollout_percentage,
+        unknown_flag_default: project.unknown_flag_default,
+        evaluation_sample_rate: project.evaluation_sample_rate,
+        created_at: project.created_at,
+        updated_at: project.updated_at,
+    };
+
+    Ok(Json(response))
 }
@@ -0,0 +1,124 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+/// Header carrying the id of a request, accepted from the client and set on every response
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Maximum accepted length of an incoming X-Request-Id
+pub const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Id of the request being handled, None outside the request_id middleware
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Ids from upstream services are kept so a request can be followed across them;
+/// anything that would be awkward in a log line or error body is replaced
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+/// Middleware giving every request an id: the client's X-Request-Id when valid, a new UUID
+/// otherwise. The id is echoed in the X-Request-Id response header, added to JSON error bodies
+/// and printed with server errors so a reported id can be found in the logs.
+pub async fn request_id(req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(req)).await;
+
+    if response.status().is_server_error() {
+        eprintln!("Request {} {} {} failed with {}", id, method, path, response.status());
+    }
+
+    // Only visible ASCII gets here, so the header value is always valid
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER.clone(), value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::error::json_error;
+    use axum::{http::StatusCode, middleware, routing::get, Router};
+
+    #[test]
+    fn test_is_valid_request_id() {
+        assert!(is_valid_request_id("3f1c2a9e-7d4b-4c1e-9a8f-0b6d5e4c3b2a"));
+        assert!(is_valid_request_id("gateway:req_01.abc"));
+        assert!(is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LENGTH)));
+
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LENGTH + 1)));
+        assert!(!is_valid_request_id("has space"));
+        assert!(!is_valid_request_id("quote\"d"));
+    }
+
+    #[tokio::test]
+    async fn test_request_id_header_and_error_body() {
+        let app = Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route("/missing", get(|| async { json_error(StatusCode::NOT_FOUND, "Flag not found") }))
+            .layer(middleware::from_fn(request_id));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = reqwest::Client::new();
+
+        // Without an incoming id a UUID is generated
+        let response = client.get(format!("http://{}/ok", addr)).send().await.unwrap();
+        let id = response.headers()["x-request-id"].to_str().unwrap();
+        assert!(Uuid::parse_str(id).is_ok());
+
+        // A valid incoming id is kept, and shows up in JSON error bodies
+        let response = client
+            .get(format!("http://{}/missing", addr))
+            .header("x-request-id", "upstream-123")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["x-request-id"], "upstream-123");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["request_id"], "upstream-123");
+        assert_eq!(body["error"], "Flag not found");
+
+        // An invalid incoming id is replaced
+        let response = client
+            .get(format!("http://{}/ok", addr))
+            .header("x-request-id", "not valid!")
+            .send()
+            .await
+            .unwrap();
+        let id = response.headers()["x-request-id"].to_str().unwrap();
+        assert!(Uuid::parse_str(id).is_ok());
+
+        // Outside the middleware there is no id, and the body leaves it out
+        assert_eq!(current(), None);
+        let bytes = axum::body::to_bytes(json_error(StatusCode::NOT_FOUND, "x").into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(body.get("request_id").is_none());
+    }
+}
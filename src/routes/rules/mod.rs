@@ -1,8 +1,14 @@
 pub mod routes;
 
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::evaluation::expression::Expression;
+use crate::evaluation::{UserContext, normalize_email, parse_json_list, parse_list_value, parse_local_time_window, parse_percentage, parse_timestamp};
+use crate::routes::error::ValidationError;
 
 // MODELS
 
@@ -12,75 +18,469 @@ pub struct FlagRule {
     pub flag_id: Uuid,
     pub rule_type: String,
     pub rule_value: String,
+    pub rule_value_json: Option<serde_json::Value>,
     pub enabled: bool,
     pub priority: i32,
+    pub negate: bool,
+    pub payload: Option<serde_json::Value>,
+    pub rollout_percentage: Option<i32>,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct CreateRuleRequest {
     pub rule_type: String,
-    pub rule_value: String,
+    #[serde(default)]
+    pub rule_value: String,  // Leave out when sending rule_value_json
+    /// Entries of a user_id_in or email_domain_in rule as an array of strings, instead of a comma-separated rule_value
+    pub rule_value_json: Option<serde_json::Value>,
     pub enabled: Option<bool>,
     pub priority: Option<i32>,
+    pub negate: Option<bool>,
+    pub payload: Option<serde_json::Value>,  // Returned with the evaluation when this rule fires
+    pub rollout_percentage: Option<i32>,  // Only fire for this share of the matched users, all of them if omitted
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct UpdateRuleRequest {
     pub rule_value: Option<String>,
+    pub rule_value_json: Option<serde_json::Value>,  // Replaces the value of a list rule, see CreateRuleRequest
     pub enabled: Option<bool>,
     pub priority: Option<i32>,
+    pub negate: Option<bool>,
+    pub payload: Option<serde_json::Value>,  // Returned with the evaluation when this rule fires
+    pub rollout_percentage: Option<i32>,  // 100 makes the rule fire for every matched user again
+}
+
+// Optional body of the duplicate endpoint
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct DuplicateRuleRequest {
+    /// Priority of the copy, defaults to one above the source so it is evaluated just before it
+    pub priority: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListRulesParams {
+    pub enabled: Option<bool>,  // Only enabled (true) or disabled (false) rules
+    pub rule_type: Option<String>,  // Only rules of this type
+}
+
+impl ListRulesParams {
+    /// The rule_type filter, checked against the known types
+    pub fn rule_type(&self) -> Result<Option<&str>, ValidationError> {
+        match self.rule_type.as_deref().map(str::trim) {
+            Some(rule_type) => validate_rule_type(rule_type).map(|()| Some(rule_type)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReorderRulesRequest {
+    pub rule_ids: Vec<Uuid>,  // Every rule of the flag, highest priority first
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TestRuleRequest {
+    pub rule_type: String,
+    #[serde(default)]
+    pub rule_value: String,
+    pub rule_value_json: Option<serde_json::Value>,
+    pub negate: Option<bool>,
+    pub contexts: Vec<UserContext>,  // Sample users to check the draft rule against
+}
+
+// Whether a draft rule would fire for one sample context, in request order
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RuleTestResult {
+    pub user_id: Option<String>,
+    pub user_email: Option<String>,
+    pub matched: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TestRuleResponse {
+    pub rule_type: String,
+    pub rule_value: String,  // Normalized as it would be saved
+    pub rule_value_json: Option<serde_json::Value>,
+    pub negate: bool,
+    pub evaluated_at: DateTime<Utc>,
+    pub results: Vec<RuleTestResult>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct RuleResponse {
     pub id: Uuid,
     pub flag_id: Uuid,
     pub rule_type: String,
     pub rule_value: String,
+    pub rule_value_json: Option<serde_json::Value>,
     pub enabled: bool,
     pub priority: i32,
+    pub negate: bool,
+    pub payload: Option<serde_json::Value>,
+    pub rollout_percentage: Option<i32>,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
     pub created_at: DateTime<Utc>,
 }
 
+impl From<FlagRule> for RuleResponse {
+    fn from(r: FlagRule) -> Self {
+        RuleResponse {
+            id: r.id,
+            flag_id: r.flag_id,
+            rule_type: r.rule_type,
+            rule_value: r.rule_value,
+            rule_value_json: r.rule_value_json,
+            enabled: r.enabled,
+            priority: r.priority,
+            negate: r.negate,
+            payload: r.payload,
+            rollout_percentage: r.rollout_percentage,
+            created_by: r.created_by,
+            updated_by: r.updated_by,
+            created_at: r.created_at,
+        }
+    }
+}
+
+/// An environment of the project and the flag with the requested key in it, if any
+#[derive(Debug, sqlx::FromRow)]
+pub struct EnvironmentFlagRow {
+    pub environment_id: Uuid,
+    pub environment_key: String,
+    pub flag_id: Option<Uuid>,
+}
+
+/// A flag's rules in one environment, for comparing targeting across environments
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EnvironmentRules {
+    pub environment_id: Uuid,
+    pub environment_key: String,
+    pub flag_id: Option<Uuid>,  // None when the flag key isn't defined in this environment
+    pub rules: Vec<RuleResponse>,
+}
+
 // HELPER FUNCTIONS
 
+/// Group rules under the environment of their flag, keeping every environment and the rules' order
+pub fn group_rules_by_environment(environments: Vec<EnvironmentFlagRow>, rules: Vec<FlagRule>) -> Vec<EnvironmentRules> {
+    let mut by_flag: HashMap<Uuid, Vec<RuleResponse>> = HashMap::new();
+    for rule in rules {
+        by_flag.entry(rule.flag_id).or_default().push(rule.into());
+    }
+
+    environments
+        .into_iter()
+        .map(|env| EnvironmentRules {
+            rules: env.flag_id.and_then(|id| by_flag.remove(&id)).unwrap_or_default(),
+            environment_id: env.environment_id,
+            environment_key: env.environment_key,
+            flag_id: env.flag_id,
+        })
+        .collect()
+}
+
+/// Maximum number of entries allowed in a list rule value
+pub const MAX_RULE_LIST_SIZE: usize = 500;
+
+/// Maximum number of sample contexts in a rule test
+pub const MAX_RULE_TEST_CONTEXTS: usize = 100;
+
+/// Maximum number of rules in one bulk import
+pub const MAX_BULK_RULES: usize = 100;
+
+/// Every rule type evaluate_flag understands, also enforced by the flag_rules_rule_type_check constraint
+pub const RULE_TYPES: &[&str] = &[
+    "user_id",
+    "user_email",
+    "email_domain",
+    "email_domain_in",
+    "user_id_in",
+    "active_after",
+    "active_before",
+    "local_time_window",
+    "percentage",
+    "attribute_is_true",
+    "expression",
+];
+
+/// Rule types whose value is a list, the only ones that take rule_value_json
+pub const LIST_RULE_TYPES: &[&str] = &["user_id_in", "email_domain_in"];
+
+/// Name of the CHECK constraint on flag_rules.rule_type
+pub const RULE_TYPE_CONSTRAINT: &str = "flag_rules_rule_type_check";
+
+/// Gap between priorities assigned by a reorder, leaves room to insert rules in between later
+pub const REORDER_PRIORITY_STEP: i32 = 10;
+
+/// Priority for a duplicated rule, the requested one or one above the source rule
+pub fn duplicate_priority(source_priority: i32, requested: Option<i32>) -> i32 {
+    requested.unwrap_or_else(|| source_priority.saturating_add(1))
+}
+
+/// Check that a reorder lists each of the flag's rules exactly once
+pub fn validate_rule_order(requested: &[Uuid], existing: &[Uuid]) -> Result<(), String> {
+    let mut seen = std::collections::HashSet::new();
+    for id in requested {
+        if !existing.contains(id) {
+            return Err(format!("Rule {} does not belong to this flag", id));
+        }
+        if !seen.insert(id) {
+            return Err(format!("Rule {} is listed more than once", id));
+        }
+    }
+
+    if let Some(missing) = existing.iter().find(|id| !seen.contains(id)) {
+        return Err(format!("Rule {} is missing, the list must include every rule of the flag", missing));
+    }
+
+    Ok(())
+}
+
+/// Validate every rule of a bulk import and return their normalized values in request order
+/// One invalid rule rejects the whole batch, the message says which one
+pub fn validate_bulk_rules(rules: &[CreateRuleRequest]) -> Result<Vec<RuleValue>, ValidationError> {
+    if rules.is_empty() {
+        return Err(ValidationError::new("rules", "required", "At least one rule is required"));
+    }
+    if rules.len() > MAX_BULK_RULES {
+        return Err(ValidationError::new(
+            "rules",
+            "too_long",
+            format!("Too many rules (Max: {})", MAX_BULK_RULES),
+        ));
+    }
+
+    validate_rule_list(rules)
+}
+
+/// Validate new rules and return their normalized values in order, for bulk imports and previews
+/// One invalid rule rejects them all, the message says which one
+pub fn validate_rule_list<'a>(
+    rules: impl IntoIterator<Item = &'a CreateRuleRequest>,
+) -> Result<Vec<RuleValue>, ValidationError> {
+    rules
+        .into_iter()
+        .enumerate()
+        .map(|(index, rule)| {
+            validate_rule_type(&rule.rule_type)
+                .and_then(|_| {
+                    let rule_value = resolve_rule_value(&rule.rule_type, &rule.rule_value, rule.rule_value_json.as_ref())?;
+                    if let Some(percentage) = rule.rollout_percentage {
+                        validate_rule_rollout(&rule.rule_type, percentage)?;
+                    }
+                    Ok(rule_value)
+                })
+                .map_err(|e| ValidationError {
+                    message: format!("Rule {}: {}", index, e.message),
+                    ..e
+                })
+        })
+        .collect()
+}
+
+/// Check the number of sample contexts in a rule test
+pub fn validate_test_contexts(contexts: &[UserContext]) -> Result<(), ValidationError> {
+    if contexts.is_empty() {
+        return Err(ValidationError::new("contexts", "required", "At least one context is required"));
+    }
+    if contexts.len() > MAX_RULE_TEST_CONTEXTS {
+        return Err(ValidationError::new(
+            "contexts",
+            "too_long",
+            format!("Too many contexts (Max: {})", MAX_RULE_TEST_CONTEXTS),
+        ));
+    }
+    Ok(())
+}
+
+/// Priorities for rules in the requested order, descending so the first rule is evaluated first
+pub fn reorder_priorities(count: usize) -> Vec<i32> {
+    (0..count)
+        .map(|position| (count - position) as i32 * REORDER_PRIORITY_STEP)
+        .collect()
+}
+
 /// Validate rule type
-pub fn validate_rule_type(rule_type: &str) -> Result<(), String> {
+pub fn validate_rule_type(rule_type: &str) -> Result<(), ValidationError> {
+    if RULE_TYPES.contains(&rule_type) {
+        return Ok(());
+    }
+    Err(ValidationError::new(
+        "rule_type",
+        "unknown_rule_type",
+        format!("Invalid rule type '{}'. Must be one of: {}", rule_type, RULE_TYPES.join(", ")),
+    ))
+}
+
+/// Validate a rule's own rollout, the share of matched users it fires for
+/// Percentage and time window rules never fire on their own, so they can't take one
+pub fn validate_rule_rollout(rule_type: &str, percentage: i32) -> Result<(), ValidationError> {
+    if !(0..=100).contains(&percentage) {
+        return Err(ValidationError::new(
+            "rollout_percentage",
+            "out_of_range",
+            "rollout_percentage must be between 0 and 100",
+        ));
+    }
+    if matches!(rule_type, "percentage" | "active_after" | "active_before" | "local_time_window") {
+        return Err(ValidationError::new(
+            "rollout_percentage",
+            "conflict",
+            format!("{} rules don't fire on their own and can't have a rollout_percentage", rule_type),
+        ));
+    }
+    Ok(())
+}
+
+/// Map a rule write rejected by the flag_rules_rule_type_check constraint to the same 400 as validate_rule_type
+/// Takes the Postgres error code and constraint name, None for any other database error
+pub fn rule_type_violation(code: Option<&str>, constraint: Option<&str>, rule_type: &str) -> Option<ValidationError> {
+    if code == Some("23514") && constraint == Some(RULE_TYPE_CONSTRAINT) {
+        return Some(validate_rule_type(rule_type).err().unwrap_or_else(|| {
+            // The API accepts a type the database doesn't know, the migration list is behind RULE_TYPES
+            ValidationError::new("rule_type", "unknown_rule_type", format!("Rule type '{}' is not supported yet", rule_type))
+        }));
+    }
+    None
+}
+
+/// Normalize a rule value before it is validated and stored
+/// Email values are lowercased so they match regardless of how the SDK sends the address
+pub fn normalize_rule_value(rule_type: &str, rule_value: &str) -> String {
     match rule_type {
-        "user_id" | "user_email" | "email_domain" => Ok(()),
-        _ => Err(format!(
-            "Invalid rule type '{}'. Must be one of: user_id, user_email, email_domain",
-            rule_type
-        )),
+        "user_email" | "email_domain" | "email_domain_in" => normalize_email(rule_value),
+        _ => rule_value.to_string(),
     }
 }
 
+/// A validated rule value as it is stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleValue {
+    pub text: String,  // rule_value; for a rule_value_json list, the same list as JSON text
+    pub json: Option<serde_json::Value>,  // rule_value_json
+}
+
+/// Normalize and validate a rule's value, given either as rule_value or, for list rules, as rule_value_json
+/// A JSON list is also stored as JSON text in rule_value, which parse_list_value reads the same way
+pub fn resolve_rule_value(
+    rule_type: &str,
+    rule_value: &str,
+    rule_value_json: Option<&serde_json::Value>,
+) -> Result<RuleValue, ValidationError> {
+    let Some(json) = rule_value_json else {
+        let text = normalize_rule_value(rule_type, rule_value);
+        validate_rule_value(rule_type, &text)?;
+        return Ok(RuleValue { text, json: None });
+    };
+
+    let invalid = |code, message: &str| ValidationError::new("rule_value_json", code, message);
+    if !LIST_RULE_TYPES.contains(&rule_type) {
+        return Err(invalid(
+            "not_supported",
+            &format!("rule_value_json is only supported for {} rules", LIST_RULE_TYPES.join(" and ")),
+        ));
+    }
+    if !rule_value.trim().is_empty() {
+        return Err(invalid("conflict", "Send either rule_value or rule_value_json, not both"));
+    }
+    let entries: Vec<String> = parse_json_list(json)
+        .ok_or_else(|| invalid("invalid_format", "rule_value_json must be an array of strings"))?
+        .iter()
+        .map(|entry| normalize_rule_value(rule_type, entry))
+        .collect();
+
+    // Validated as the equivalent JSON text rule_value, so both forms get the same checks
+    let text = serde_json::Value::from(entries.clone()).to_string();
+    validate_rule_value(rule_type, &text).map_err(|e| ValidationError { field: "rule_value_json", ..e })?;
+    Ok(RuleValue { text, json: Some(serde_json::Value::from(entries)) })
+}
+
+/// Validate one domain of an email_domain or email_domain_in rule
+fn validate_email_domain(domain: &str) -> Result<(), ValidationError> {
+    if !domain.starts_with('@') {
+        return Err(ValidationError::new(
+            "rule_value",
+            "invalid_format",
+            "Email domain must start with '@' (e.g., @company.com)",
+        ));
+    }
+    if domain.len() < 3 {
+        return Err(ValidationError::new("rule_value", "too_short", "Email domain too short"));
+    }
+    Ok(())
+}
+
 /// Validate rule value based on type
-pub fn validate_rule_value(rule_type: &str, rule_value: &str) -> Result<(), String> {
+pub fn validate_rule_value(rule_type: &str, rule_value: &str) -> Result<(), ValidationError> {
+    let invalid = |code, message: &str| Err(ValidationError::new("rule_value", code, message));
+
     if rule_value.trim().is_empty() {
-        return Err("Rule value cannot be empty".to_string());
+        return invalid("required", "Rule value cannot be empty");
     }
 
     match rule_type {
-        "email_domain" => {
-            if !rule_value.starts_with('@') {
-                return Err("Email domain must start with '@' (e.g., @company.com)".to_string());
+        "email_domain" => validate_email_domain(rule_value)?,
+        "email_domain_in" => {
+            let domains = parse_list_value(rule_value);
+            if domains.is_empty() {
+                return invalid("required", "Email domain list cannot be empty");
             }
-            if rule_value.len() < 3 {
-                return Err("Email domain too short".to_string());
+            if domains.len() > MAX_RULE_LIST_SIZE {
+                return invalid(
+                    "too_long",
+                    &format!("Email domain list is too long (Max: {} entries)", MAX_RULE_LIST_SIZE),
+                );
             }
-        }
-        "user_email" => {
-            if !rule_value.contains('@') {
-                return Err("Invalid email format".to_string());
+            for domain in &domains {
+                validate_email_domain(domain).map_err(|e| ValidationError {
+                    message: format!("{}: {}", domain, e.message),
+                    ..e
+                })?;
             }
         }
-        "user_id" => {
-            if rule_value.len() < 1 {
-                return Err("User ID cannot be empty".to_string());
+        "user_email" if !rule_value.contains('@') => {
+            return invalid("invalid_format", "Invalid email format");
+        }
+        "user_id" if rule_value.is_empty() => {
+            return invalid("required", "User ID cannot be empty");
+        }
+        "user_id_in" => {
+            let ids = parse_list_value(rule_value);
+            if ids.is_empty() {
+                return invalid("required", "User ID list cannot be empty");
+            }
+            if ids.len() > MAX_RULE_LIST_SIZE {
+                return invalid(
+                    "too_long",
+                    &format!("User ID list is too long (Max: {} entries)", MAX_RULE_LIST_SIZE),
+                );
             }
         }
+        "attribute_is_true" if rule_value.trim().chars().any(char::is_whitespace) => {
+            return invalid("invalid_char", "Attribute name cannot contain spaces");
+        }
+        "percentage" if parse_percentage(rule_value).is_none() => {
+            return invalid("out_of_range", "Percentage must be a whole number between 0 and 100");
+        }
+        "active_after" | "active_before" if parse_timestamp(rule_value).is_none() => {
+            return invalid("invalid_format", "Timestamp must be RFC3339 (e.g., 2025-01-01T00:00:00Z)");
+        }
+        "local_time_window" if parse_local_time_window(rule_value).is_none() => {
+            return invalid("invalid_format", "Time window must be HH:MM-HH:MM with different times (e.g., 09:00-17:00)");
+        }
+        "expression" => {
+            Expression::parse(rule_value).map_err(|e| ValidationError::new("rule_value", "invalid_expression", e))?;
+        }
         _ => {}
     }
 
@@ -96,7 +496,212 @@ mod tests {
         assert!(validate_rule_type("user_id").is_ok());
         assert!(validate_rule_type("user_email").is_ok());
         assert!(validate_rule_type("email_domain").is_ok());
+        assert!(validate_rule_type("user_id_in").is_ok());
+        assert!(validate_rule_type("active_after").is_ok());
+        assert!(validate_rule_type("active_before").is_ok());
+        assert!(validate_rule_type("local_time_window").is_ok());
+        assert!(validate_rule_type("percentage").is_ok());
+        assert!(validate_rule_type("attribute_is_true").is_ok());
         assert!(validate_rule_type("invalid").is_err());
+
+        let err = validate_rule_type("invalid").unwrap_err();
+        assert_eq!((err.field, err.code), ("rule_type", "unknown_rule_type"));
+    }
+
+    #[test]
+    fn test_list_rules_filters() {
+        use axum::extract::Query;
+        let parse = |uri: &str| Query::<ListRulesParams>::try_from_uri(&uri.parse().unwrap()).map(|Query(p)| p);
+
+        // No filters lists everything
+        let params = parse("/rules").unwrap();
+        assert_eq!((params.enabled, params.rule_type().unwrap()), (None, None));
+
+        // Enabled state
+        assert_eq!(parse("/rules?enabled=true").unwrap().enabled, Some(true));
+        assert_eq!(parse("/rules?enabled=false").unwrap().enabled, Some(false));
+        assert!(parse("/rules?enabled=yes").is_err());
+
+        // Rule type, checked against the known types
+        let params = parse("/rules?rule_type=email_domain&enabled=true").unwrap();
+        assert_eq!((params.enabled, params.rule_type().unwrap()), (Some(true), Some("email_domain")));
+        let err = parse("/rules?rule_type=attribute_match").unwrap().rule_type().unwrap_err();
+        assert_eq!((err.field, err.code), ("rule_type", "unknown_rule_type"));
+    }
+
+    #[test]
+    fn test_rule_type_constraint() {
+        // The database constraint must allow exactly the types the API accepts
+        let migration = include_str!("../../../migrations/20261017000033_add_expression_rule_type.sql");
+        assert!(migration.contains(RULE_TYPE_CONSTRAINT));
+        let (_, allowed) = migration.split_once("IN (").unwrap();
+        let allowed: Vec<&str> = allowed
+            .split(')')
+            .next()
+            .unwrap()
+            .split(',')
+            .map(|t| t.trim().trim_matches('\''))
+            .collect();
+        assert_eq!(allowed, RULE_TYPES);
+
+        // A constraint violation is reported like the app-level check
+        let err = rule_type_violation(Some("23514"), Some(RULE_TYPE_CONSTRAINT), "attribute_match").unwrap();
+        assert_eq!((err.field, err.code), ("rule_type", "unknown_rule_type"));
+        assert!(err.message.contains("attribute_match"));
+        assert!(rule_type_violation(Some("23514"), Some(RULE_TYPE_CONSTRAINT), "user_id").is_some());
+
+        // Other constraints and errors are left alone
+        assert!(rule_type_violation(Some("23514"), Some("flag_rules_other_check"), "attribute_match").is_none());
+        assert!(rule_type_violation(Some("23505"), Some(RULE_TYPE_CONSTRAINT), "attribute_match").is_none());
+        assert!(rule_type_violation(None, None, "attribute_match").is_none());
+    }
+
+    #[test]
+    fn test_validate_test_contexts() {
+        let context = UserContext {
+            user_id: Some("user123".to_string()),
+            user_email: None,
+            anonymous_id: None,
+            custom_attributes: Default::default(),
+        };
+        assert!(validate_test_contexts(std::slice::from_ref(&context)).is_ok());
+        assert!(validate_test_contexts(&vec![context.clone(); MAX_RULE_TEST_CONTEXTS]).is_ok());
+
+        let err = validate_test_contexts(&[]).unwrap_err();
+        assert_eq!((err.field, err.code), ("contexts", "required"));
+        let err = validate_test_contexts(&vec![context; MAX_RULE_TEST_CONTEXTS + 1]).unwrap_err();
+        assert_eq!((err.field, err.code), ("contexts", "too_long"));
+    }
+
+    #[test]
+    fn test_validate_rule_order() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let existing = [a, b, c];
+
+        assert!(validate_rule_order(&[c, a, b], &existing).is_ok());
+        assert!(validate_rule_order(&[], &[]).is_ok());
+
+        assert!(validate_rule_order(&[c, a], &existing).unwrap_err().contains("missing"));
+        assert!(validate_rule_order(&[c, a, b, a], &existing).unwrap_err().contains("more than once"));
+        assert!(validate_rule_order(&[c, a, b, Uuid::new_v4()], &existing)
+            .unwrap_err()
+            .contains("does not belong"));
+    }
+
+    #[test]
+    fn test_resolve_rule_value() {
+        use serde_json::json;
+
+        // Scalar values are normalized and validated as before
+        let value = resolve_rule_value("user_email", " Jane@Company.com", None).unwrap();
+        assert_eq!(value, RuleValue { text: "jane@company.com".to_string(), json: None });
+
+        // A JSON list keeps entries with commas whole, and is mirrored as JSON text in rule_value
+        let value = resolve_rule_value("user_id_in", "", Some(&json!(["acme,inc", " bob ", ""]))).unwrap();
+        assert_eq!(value.json, Some(json!(["acme,inc", "bob"])));
+        assert_eq!(value.text, r#"["acme,inc","bob"]"#);
+        assert_eq!(parse_list_value(&value.text), vec!["acme,inc", "bob"]);
+
+        // Email domains are lowercased and checked one by one
+        let value = resolve_rule_value("email_domain_in", "", Some(&json!(["@Company.com"]))).unwrap();
+        assert_eq!(value.json, Some(json!(["@company.com"])));
+        let err = resolve_rule_value("email_domain_in", "", Some(&json!(["company.com"]))).unwrap_err();
+        assert_eq!((err.field, err.code), ("rule_value_json", "invalid_format"));
+
+        for (rule_type, rule_value, json, code) in [
+            ("user_id", "", json!(["alice"]), "not_supported"),
+            ("user_id_in", "alice", json!(["bob"]), "conflict"),
+            ("user_id_in", "", json!("alice,bob"), "invalid_format"),
+            ("user_id_in", "", json!(["alice", 5]), "invalid_format"),
+            ("user_id_in", "", json!([" "]), "required"),
+        ] {
+            let err = resolve_rule_value(rule_type, rule_value, Some(&json)).unwrap_err();
+            assert_eq!((err.field, err.code), ("rule_value_json", code), "{}", json);
+        }
+        let too_many: Vec<String> = (0..=MAX_RULE_LIST_SIZE).map(|i| format!("user_{}", i)).collect();
+        let err = resolve_rule_value("user_id_in", "", Some(&json!(too_many))).unwrap_err();
+        assert_eq!(err.code, "too_long");
+    }
+
+    fn rule(rule_type: &str, rule_value: &str) -> CreateRuleRequest {
+        CreateRuleRequest {
+            rule_type: rule_type.to_string(),
+            rule_value: rule_value.to_string(),
+            rule_value_json: None,
+            enabled: None,
+            priority: None,
+            negate: None,
+            payload: None,
+            rollout_percentage: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_bulk_rules() {
+        let values = validate_bulk_rules(&[rule("user_email", " Jane@Company.com"), rule("percentage", "25")]).unwrap();
+        let values: Vec<&str> = values.iter().map(|value| value.text.as_str()).collect();
+        assert_eq!(values, vec!["jane@company.com", "25"]);
+
+        // One invalid rule rejects the whole batch before anything is inserted
+        let err = validate_bulk_rules(&[
+            rule("user_id", "user123"),
+            rule("percentage", "250"),
+            rule("email_domain", "@company.com"),
+        ])
+        .unwrap_err();
+        assert_eq!(err.field, "rule_value");
+        assert!(err.message.starts_with("Rule 1: "), "{}", err.message);
+
+        let err = validate_bulk_rules(&[rule("user_id", "user123"), rule("attribute_match", "x")]).unwrap_err();
+        assert_eq!((err.field, err.code), ("rule_type", "unknown_rule_type"));
+
+        assert_eq!(validate_bulk_rules(&[]).unwrap_err().code, "required");
+        let too_many: Vec<CreateRuleRequest> = (0..=MAX_BULK_RULES).map(|_| rule("user_id", "user123")).collect();
+        assert_eq!(validate_bulk_rules(&too_many).unwrap_err().code, "too_long");
+    }
+
+    #[test]
+    fn test_validate_rule_rollout() {
+        assert!(validate_rule_rollout("email_domain", 0).is_ok());
+        assert!(validate_rule_rollout("user_id_in", 20).is_ok());
+        assert!(validate_rule_rollout("attribute_is_true", 100).is_ok());
+
+        let err = validate_rule_rollout("user_id", 101).unwrap_err();
+        assert_eq!((err.field, err.code), ("rollout_percentage", "out_of_range"));
+        assert_eq!(validate_rule_rollout("user_id", -1).unwrap_err().code, "out_of_range");
+        assert_eq!(validate_rule_rollout("percentage", 20).unwrap_err().code, "conflict");
+        assert_eq!(validate_rule_rollout("active_after", 20).unwrap_err().code, "conflict");
+
+        let mut gated = rule("email_domain", "@company.com");
+        gated.rollout_percentage = Some(150);
+        assert_eq!(validate_bulk_rules(&[gated]).unwrap_err().field, "rollout_percentage");
+    }
+
+    #[test]
+    fn test_duplicate_priority() {
+        assert_eq!(duplicate_priority(10, None), 11);
+        assert_eq!(duplicate_priority(10, Some(0)), 0);
+        assert_eq!(duplicate_priority(i32::MAX, None), i32::MAX);
+
+        let body: DuplicateRuleRequest = serde_json::from_str("{}").unwrap();
+        assert!(body.priority.is_none());
+    }
+
+    #[test]
+    fn test_reorder_priorities() {
+        assert_eq!(reorder_priorities(3), vec![30, 20, 10]);
+        assert!(reorder_priorities(0).is_empty());
+    }
+
+    #[test]
+    fn test_normalize_rule_value() {
+        assert_eq!(normalize_rule_value("user_email", " John@Company.com "), "john@company.com");
+        assert_eq!(normalize_rule_value("email_domain", "@Company.COM"), "@company.com");
+        assert!(validate_rule_value("email_domain", &normalize_rule_value("email_domain", " @Company.com")).is_ok());
+        assert_eq!(normalize_rule_value("email_domain_in", " @Company.COM,@Sub.io "), "@company.com,@sub.io");
+
+        // Other rule types are stored as given
+        assert_eq!(normalize_rule_value("user_id", "User_123"), "User_123");
     }
 
     #[test]
@@ -106,6 +711,15 @@ mod tests {
         assert!(validate_rule_value("email_domain", "company.com").is_err());
         assert!(validate_rule_value("email_domain", "@c").is_err());
 
+        // Email domain list, every entry follows the email_domain rules
+        assert!(validate_rule_value("email_domain_in", "@company.com, @subsidiary.io").is_ok());
+        assert!(validate_rule_value("email_domain_in", r#"["@company.com", "@subsidiary.io"]"#).is_ok());
+        assert!(validate_rule_value("email_domain_in", " , ").is_err());
+        let err = validate_rule_value("email_domain_in", "@company.com,subsidiary.io").unwrap_err();
+        assert_eq!(err.code, "invalid_format");
+        assert!(err.message.starts_with("subsidiary.io: "));
+        assert_eq!(validate_rule_value("email_domain_in", "@company.com,@c").unwrap_err().code, "too_short");
+
         // User email
         assert!(validate_rule_value("user_email", "user@example.com").is_ok());
         assert!(validate_rule_value("user_email", "invalid").is_err());
@@ -113,5 +727,130 @@ mod tests {
         // User ID
         assert!(validate_rule_value("user_id", "user_123").is_ok());
         assert!(validate_rule_value("user_id", "").is_err());
+
+        // User ID list
+        assert!(validate_rule_value("user_id_in", "alice,bob").is_ok());
+        assert!(validate_rule_value("user_id_in", r#"["alice", "bob"]"#).is_ok());
+        assert!(validate_rule_value("user_id_in", " , ").is_err());
+        assert!(validate_rule_value("user_id_in", "[]").is_err());
+        let too_many = (0..=MAX_RULE_LIST_SIZE).map(|i| i.to_string()).collect::<Vec<_>>().join(",");
+        assert!(validate_rule_value("user_id_in", &too_many).is_err());
+
+        // Time window
+        assert!(validate_rule_value("active_after", "2025-01-01T00:00:00Z").is_ok());
+        assert!(validate_rule_value("active_before", "2025-01-01T09:00:00+02:00").is_ok());
+        assert!(validate_rule_value("active_after", "2025-01-01").is_err());
+        assert!(validate_rule_value("local_time_window", "09:00-17:00").is_ok());
+        assert!(validate_rule_value("local_time_window", "22:00-06:00").is_ok());
+        assert!(validate_rule_value("local_time_window", "09:00-09:00").is_err());
+        assert!(validate_rule_value("local_time_window", "9-5").is_err());
+        assert!(validate_rule_value("expression", r#"{"==": [{"var": "custom_attributes.plan"}, "pro"]}"#).is_ok());
+        let err = validate_rule_value("expression", r#"{"regex": [{"var": "user_id"}, ".*"]}"#).unwrap_err();
+        assert_eq!((err.field, err.code), ("rule_value", "invalid_expression"));
+
+        // Boolean attribute
+        assert!(validate_rule_value("attribute_is_true", "beta_opt_in").is_ok());
+        assert!(validate_rule_value("attribute_is_true", "  ").is_err());
+        assert!(validate_rule_value("attribute_is_true", "beta opt in").is_err());
+
+        assert!(validate_rule_value("percentage", "50").is_ok());
+        assert!(validate_rule_value("percentage", "0").is_ok());
+        assert!(validate_rule_value("percentage", "100").is_ok());
+        assert!(validate_rule_value("percentage", "101").is_err());
+        assert!(validate_rule_value("percentage", "-1").is_err());
+        assert!(validate_rule_value("percentage", "half").is_err());
+
+        let err = validate_rule_value("email_domain", "company.com").unwrap_err();
+        assert_eq!((err.field, err.code), ("rule_value", "invalid_format"));
+        assert_eq!(validate_rule_value("user_id", " ").unwrap_err().code, "required");
+        assert_eq!(validate_rule_value("percentage", "101").unwrap_err().code, "out_of_range");
+        assert!(validate_rule_value("user_email", "invalid").unwrap_err().message.contains("email"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_rule_payload_round_trip() {
+        let payload = serde_json::json!({
+            "timeout_ms": 2500,
+            "variant": "blue",
+            "tags": ["beta", null, 1.5],
+            "nested": { "enabled": true }
+        });
+        let body = serde_json::json!({
+            "rule_type": "user_id",
+            "rule_value": "user123",
+            "payload": payload,
+        });
+
+        let request: CreateRuleRequest = serde_json::from_value(body).unwrap();
+        assert_eq!(request.payload.as_ref(), Some(&payload));
+
+        let response = RuleResponse {
+            id: Uuid::nil(),
+            flag_id: Uuid::nil(),
+            rule_type: request.rule_type,
+            rule_value: request.rule_value,
+            rule_value_json: request.rule_value_json,
+            enabled: true,
+            priority: 0,
+            negate: false,
+            payload: request.payload,
+            rollout_percentage: request.rollout_percentage,
+            created_by: None,
+            updated_by: None,
+            created_at: Utc::now(),
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["payload"], payload);
+
+        // Payload is optional
+        let request: UpdateRuleRequest = serde_json::from_str(r#"{"enabled": false}"#).unwrap();
+        assert!(request.payload.is_none());
+    }
+
+    #[test]
+    fn test_group_rules_by_environment() {
+        let (production, staging, development) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (production_flag, staging_flag) = (Uuid::new_v4(), Uuid::new_v4());
+        let rule = |flag_id: Uuid, rule_value: &str, priority: i32| FlagRule {
+            id: Uuid::new_v4(),
+            flag_id,
+            rule_type: "user_id".to_string(),
+            rule_value: rule_value.to_string(),
+            rule_value_json: None,
+            enabled: true,
+            priority,
+            negate: false,
+            payload: None,
+            rollout_percentage: None,
+            created_by: None,
+            updated_by: None,
+            created_at: Utc::now(),
+        };
+        let environment = |environment_id: Uuid, key: &str, flag_id: Option<Uuid>| EnvironmentFlagRow {
+            environment_id,
+            environment_key: key.to_string(),
+            flag_id,
+        };
+
+        let grouped = group_rules_by_environment(
+            vec![
+                environment(development, "development", None),
+                environment(production, "production", Some(production_flag)),
+                environment(staging, "staging", Some(staging_flag)),
+            ],
+            vec![rule(production_flag, "alice", 10), rule(staging_flag, "bob", 5), rule(production_flag, "carol", 1)],
+        );
+
+        // Every environment is listed, the one without the flag has no flag id and no rules
+        let keys: Vec<&str> = grouped.iter().map(|e| e.environment_key.as_str()).collect();
+        assert_eq!(keys, ["development", "production", "staging"]);
+        assert_eq!(grouped[0].flag_id, None);
+        assert!(grouped[0].rules.is_empty());
+
+        // Rules keep their order within each environment
+        let values: Vec<&str> = grouped[1].rules.iter().map(|r| r.rule_value.as_str()).collect();
+        assert_eq!(values, ["alice", "carol"]);
+        assert_eq!(grouped[2].flag_id, Some(staging_flag));
+        assert_eq!(grouped[2].rules.len(), 1);
+    }
+}
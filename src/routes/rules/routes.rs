@@ -1,181 +1,544 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     Json,
 };
+use sqlx::PgConnection;
 use uuid::Uuid;
 
-use crate::routes::middleware_auth::JwtUser;
+use crate::evaluation::{test_rule, AnonymousBehavior, BucketingKey, FlagData, HashAlgorithm, RuleData};
+use crate::routes::error::{ApiError, JsonBody, ValidationError, ValidationErrorBody};
+use crate::routes::flags::normalize_flag_key;
+use crate::routes::ownership::{VerifiedFlag, VerifiedProject};
+use crate::routes::pending_changes::{self, FlagWrite, PendingChangeResponse, TargetingChange, WriteGate};
 use crate::state::AppState;
 use super::{
-    CreateRuleRequest, UpdateRuleRequest, FlagRule, RuleResponse,
-    validate_rule_type, validate_rule_value
+    CreateRuleRequest, ListRulesParams, UpdateRuleRequest, DuplicateRuleRequest, ReorderRulesRequest, TestRuleRequest, TestRuleResponse, RuleTestResult,
+    FlagRule, RuleResponse, RuleValue, EnvironmentFlagRow, EnvironmentRules, duplicate_priority, group_rules_by_environment, resolve_rule_value, rule_type_violation, validate_bulk_rules, reorder_priorities, validate_rule_order, validate_rule_type,
+    validate_rule_rollout, validate_test_contexts
 };
 
 // HANDLERS
 
 /// Create a new targeting rule for a flag
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rules",
+    tag = "rules",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the original response when a create is retried with the same key"),
+    ),
+    request_body = CreateRuleRequest,
+    responses(
+        (status = 201, description = "Rule created", body = RuleResponse),
+        (status = 202, description = "The environment requires approval, the rule was stored as a pending change", body = PendingChangeResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Invalid rule type or value", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn create(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id, flag_id)): Path<(Uuid, Uuid, Uuid)>,
-    Json(payload): Json<CreateRuleRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    verified: VerifiedFlag,
+    JsonBody(payload): JsonBody<CreateRuleRequest>,
+) -> Result<Response, ApiError> {
+    let rule_value = validate_create(&payload)?;
+
+    // In environments that require approval the rule waits for an approver
+    if pending_changes::direct_write_gate(&state.db, verified.environment_id, FlagWrite::RuleCreate).await? == WriteGate::Propose {
+        return pending_changes::propose_targeting(&state.db, &verified, &TargetingChange::RuleCreate { rule: payload }).await;
+    }
+
+    let rule = insert_rule(&state.db, verified.flag_id, &payload, &rule_value, verified.user_id).await?;
+    Ok((StatusCode::CREATED, Json(RuleResponse::from(rule))).into_response())
+}
+
+/// Apply an approved rule create in the approval's transaction, checking the rule again
+pub async fn apply_create(conn: &mut PgConnection, flag_id: Uuid, payload: &CreateRuleRequest, user_id: Uuid) -> Result<FlagRule, ApiError> {
+    let rule_value = validate_create(payload)?;
+    insert_rule(conn, flag_id, payload, &rule_value, user_id).await
+}
+
+// Checks a new rule must pass, when it is created and again when its proposal is approved
+fn validate_create(payload: &CreateRuleRequest) -> Result<RuleValue, ValidationError> {
     // Validate rule type
-    validate_rule_type(&payload.rule_type)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    validate_rule_type(&payload.rule_type)?;
 
     // Validate rule value
-    validate_rule_value(&payload.rule_type, &payload.rule_value)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let rule_value = resolve_rule_value(&payload.rule_type, &payload.rule_value, payload.rule_value_json.as_ref())?;
+    if let Some(percentage) = payload.rollout_percentage {
+        validate_rule_rollout(&payload.rule_type, percentage)?;
+    }
+    Ok(rule_value)
+}
 
-    // Verify flag exists, belongs to the environment, and user owns the project
-    let flag_exists = sqlx::query_scalar::<_, bool>(
+async fn insert_rule<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    flag_id: Uuid,
+    payload: &CreateRuleRequest,
+    rule_value: &RuleValue,
+    user_id: Uuid,
+) -> Result<FlagRule, ApiError> {
+    sqlx::query_as::<_, FlagRule>(
         r#"
-        SELECT EXISTS(
-            SELECT 1 FROM feature_flags f
-            JOIN environments e ON f.environment_id = e.id
-            JOIN projects p ON e.project_id = p.id
-            WHERE f.id = $1 AND f.environment_id = $2 AND e.project_id = $3 AND p.created_by = $4
-        )
-        "#
+        INSERT INTO flag_rules (flag_id, rule_type, rule_value, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, rule_value_json)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $9, $8, $8, $10)
+        RETURNING id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, created_at
+        "#,
     )
     .bind(flag_id)
-    .bind(environment_id)
-    .bind(project_id)
+    .bind(&payload.rule_type)
+    .bind(&rule_value.text)
+    .bind(payload.enabled.unwrap_or(true))
+    .bind(payload.priority.unwrap_or(0))
+    .bind(payload.negate.unwrap_or(false))
+    .bind(&payload.payload)
     .bind(user_id)
-    .fetch_one(&state.db)
+    .bind(payload.rollout_percentage)
+    .bind(&rule_value.json)
+    .fetch_one(executor)
+    .await
+    .map_err(|e| -> ApiError {
+        if let Some(db_error) = e.as_database_error() {
+            if let Some(error) = rule_type_violation(db_error.code().as_deref(), db_error.constraint(), &payload.rule_type) {
+                return error.into();
+            }
+        }
+        eprintln!("Failed to create rule: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)).into()
+    })
+}
+
+/// List all rules for a flag
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rules",
+    tag = "rules",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ListRulesParams,
+    ),
+    responses(
+        (status = 200, description = "Rules of the flag matching the filters, highest priority first", body = Vec<RuleResponse>),
+        (status = 400, description = "Malformed enabled filter"),
+        (status = 422, description = "Unknown rule_type filter", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    VerifiedFlag { flag_id, .. }: VerifiedFlag,
+    Query(params): Query<ListRulesParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let rule_type = params.rule_type()?;
+
+    // Fetch the flag's rules, narrowed by the optional filters
+    let rules = sqlx::query_as::<_, FlagRule>(
+        r#"
+        SELECT id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, created_at
+        FROM flag_rules
+        WHERE flag_id = $1 AND ($2::BOOLEAN IS NULL OR enabled = $2) AND ($3::TEXT IS NULL OR rule_type = $3)
+        ORDER BY priority DESC, created_at DESC
+        "#,
+    )
+    .bind(flag_id)
+    .bind(params.enabled)
+    .bind(rule_type)
+    .fetch_all(&state.db)
     .await
     .map_err(|e| {
-        eprintln!("Failed to check flag: {:?}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        eprintln!("Failed to fetch rules: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch rules".to_string())
     })?;
 
-    if !flag_exists {
-        return Err((StatusCode::NOT_FOUND, "Flag not found".to_string()));
+    let response: Vec<RuleResponse> = rules
+        .into_iter()
+        .map(RuleResponse::from)
+        .collect();
+
+    Ok(Json(response))
+}
+
+/// List a flag key's rules in every environment of the project, to spot targeting that has drifted
+/// Environments without the key are listed with no flag id and no rules; a key no environment defines is a 404
+#[utoipa::path(
+    get,
+    path = "/api/projects/{id}/flags/by-key/{key}/rules",
+    tag = "rules",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        ("key" = String, Path, description = "Flag key"),
+    ),
+    responses(
+        (status = 200, description = "Rules per environment, highest priority first", body = Vec<EnvironmentRules>),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Project or flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_by_flag_key(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    Path((_, key)): Path<(Uuid, String)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let environments = sqlx::query_as::<_, EnvironmentFlagRow>(
+        r#"
+        SELECT e.id AS environment_id, e.key AS environment_key, f.id AS flag_id
+        FROM environments e
+        LEFT JOIN feature_flags f ON f.environment_id = e.id AND f.key = $2
+        WHERE e.project_id = $1
+        ORDER BY e.key
+        "#,
+    )
+    .bind(project_id)
+    .bind(normalize_flag_key(&key))
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flags: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flags".to_string())
+    })?;
+
+    let flag_ids: Vec<Uuid> = environments.iter().filter_map(|env| env.flag_id).collect();
+    if flag_ids.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "Flag not found".to_string()).into());
     }
 
-    // Create the rule
-    let rule = sqlx::query_as::<_, FlagRule>(
+    // Same order as the per-flag rule list
+    let rules = sqlx::query_as::<_, FlagRule>(
         r#"
-        INSERT INTO flag_rules (flag_id, rule_type, rule_value, enabled, priority)
-        VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, flag_id, rule_type, rule_value, enabled, priority, created_at
+        SELECT id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, created_at
+        FROM flag_rules
+        WHERE flag_id = ANY($1)
+        ORDER BY priority DESC, created_at DESC
         "#,
     )
-    .bind(flag_id)
-    .bind(&payload.rule_type)
-    .bind(&payload.rule_value)
-    .bind(payload.enabled.unwrap_or(true))
-    .bind(payload.priority.unwrap_or(0))
-    .fetch_one(&state.db)
+    .bind(&flag_ids)
+    .fetch_all(&state.db)
     .await
     .map_err(|e| {
-        eprintln!("Failed to create rule: {:?}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))
+        eprintln!("Failed to fetch rules: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch rules".to_string())
     })?;
 
-    let response = RuleResponse {
-        id: rule.id,
-        flag_id: rule.flag_id,
-        rule_type: rule.rule_type,
-        rule_value: rule.rule_value,
-        enabled: rule.enabled,
-        priority: rule.priority,
-        created_at: rule.created_at,
+    Ok(Json(group_rules_by_environment(environments, rules)))
+}
+
+/// Check a draft rule against sample contexts without saving it
+/// The rule is judged on its own; other rules, the kill switch and the rollout are ignored
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rules/test",
+    tag = "rules",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    request_body = TestRuleRequest,
+    responses(
+        (status = 200, description = "Whether the rule fires for each context", body = TestRuleResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Invalid rule type, value or contexts", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn test(
+    State(state): State<AppState>,
+    VerifiedFlag { flag_id, .. }: VerifiedFlag,
+    JsonBody(payload): JsonBody<TestRuleRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_rule_type(&payload.rule_type)?;
+    let rule_value = resolve_rule_value(&payload.rule_type, &payload.rule_value, payload.rule_value_json.as_ref())?;
+    validate_test_contexts(&payload.contexts)?;
+
+    // Percentage rules bucket users by the flag's key, hash algorithm and bucketing keys
+    let (flag_key, hash_algorithm, bucketing_keys) = sqlx::query_as::<_, (String, String, Vec<String>)>(
+        r#"
+        SELECT key, hash_algorithm, bucketing_keys FROM feature_flags WHERE id = $1
+        "#
+    )
+    .bind(flag_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flag: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flag".to_string())
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Flag not found".to_string()))?;
+
+    let flag = FlagData {
+        key: flag_key,
+        enabled: true,
+        rollout_percentage: 0,
+        anonymous_behavior: AnonymousBehavior::Bucket,
+        hash_algorithm: HashAlgorithm::from_db(&hash_algorithm),
+        bucketing_keys: BucketingKey::from_db(&bucketing_keys),
+        temporarily_disabled_until: None,
+        overrides: Vec::new(),
     };
+    let rule = RuleData {
+        id: None,
+        rule_type: payload.rule_type,
+        rule_value: rule_value.text,
+        rule_value_json: rule_value.json,
+        enabled: true,
+        priority: 0,
+        negate: payload.negate.unwrap_or(false),
+        payload: None,
+        rollout_percentage: None,
+    };
+    let now = state.clock.now();
 
-    Ok((StatusCode::CREATED, Json(response)))
+    let results = payload
+        .contexts
+        .into_iter()
+        .map(|context| {
+            let outcome = test_rule(&flag, &rule, &context, now);
+            RuleTestResult {
+                user_id: context.user_id,
+                user_email: context.user_email,
+                matched: outcome.matched,
+                skipped: outcome.skipped,
+            }
+        })
+        .collect();
+
+    Ok(Json(TestRuleResponse {
+        rule_type: rule.rule_type,
+        rule_value: rule.rule_value,
+        rule_value_json: rule.rule_value_json,
+        negate: rule.negate,
+        evaluated_at: now,
+        results,
+    }))
 }
 
-/// List all rules for a flag
-pub async fn list(
+/// Reorder all rules of a flag in one transaction
+/// Rules are given descending priorities in the order listed, the first being evaluated first
+#[utoipa::path(
+    put,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rules/reorder",
+    tag = "rules",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+    ),
+    request_body = ReorderRulesRequest,
+    responses(
+        (status = 200, description = "Rules with their new priorities", body = Vec<RuleResponse>),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Rule ids do not match the flag's rules"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+        (status = 409, description = "The environment requires approval"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn reorder(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id, flag_id)): Path<(Uuid, Uuid, Uuid)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Verify flag exists and user owns the project
-    let flag_exists = sqlx::query_scalar::<_, bool>(
+    VerifiedFlag { environment_id, flag_id, user_id, .. }: VerifiedFlag,
+    JsonBody(payload): JsonBody<ReorderRulesRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    pending_changes::direct_write_gate(&state.db, environment_id, FlagWrite::RuleReorder).await?;
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    // Lock the flag so rules can't be added or removed while reordering,
+    // rule changes update the flag's rules_version and wait on this lock
+    let flag = sqlx::query_scalar::<_, Uuid>(
         r#"
-        SELECT EXISTS(
-            SELECT 1 FROM feature_flags f
-            JOIN environments e ON f.environment_id = e.id
-            JOIN projects p ON e.project_id = p.id
-            WHERE f.id = $1 AND f.environment_id = $2 AND e.project_id = $3 AND p.created_by = $4
-        )
+        SELECT id FROM feature_flags WHERE id = $1 FOR UPDATE
         "#
     )
     .bind(flag_id)
-    .bind(environment_id)
-    .bind(project_id)
-    .bind(user_id)
-    .fetch_one(&state.db)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(|e| {
         eprintln!("Failed to check flag: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
     })?;
 
-    if !flag_exists {
-        return Err((StatusCode::NOT_FOUND, "Flag not found".to_string()));
+    if flag.is_none() {
+        return Err((StatusCode::NOT_FOUND, "Flag not found".to_string()).into());
     }
 
-    // Fetch all rules for the flag
+    let existing: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM flag_rules WHERE flag_id = $1")
+        .bind(flag_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to fetch rules: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch rules".to_string())
+        })?;
+
+    validate_rule_order(&payload.rule_ids, &existing).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
     let rules = sqlx::query_as::<_, FlagRule>(
         r#"
-        SELECT id, flag_id, rule_type, rule_value, enabled, priority, created_at
-        FROM flag_rules
-        WHERE flag_id = $1
-        ORDER BY priority DESC, created_at DESC
+        UPDATE flag_rules r
+        SET priority = u.priority, updated_by = $4
+        FROM UNNEST($1::uuid[], $2::int[]) AS u(id, priority)
+        WHERE r.id = u.id AND r.flag_id = $3
+        RETURNING r.id, r.flag_id, r.rule_type, r.rule_value, r.rule_value_json, r.enabled, r.priority, r.negate, r.payload, r.rollout_percentage, r.created_by, r.updated_by, r.created_at
         "#,
     )
+    .bind(&payload.rule_ids)
+    .bind(reorder_priorities(payload.rule_ids.len()))
     .bind(flag_id)
-    .fetch_all(&state.db)
+    .bind(user_id)
+    .fetch_all(&mut *tx)
     .await
     .map_err(|e| {
-        eprintln!("Failed to fetch rules: {:?}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch rules".to_string())
+        eprintln!("Failed to reorder rules: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to reorder rules".to_string())
     })?;
 
-    let response: Vec<RuleResponse> = rules
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    let mut response: Vec<RuleResponse> = rules
         .into_iter()
-        .map(|r| RuleResponse {
-            id: r.id,
-            flag_id: r.flag_id,
-            rule_type: r.rule_type,
-            rule_value: r.rule_value,
-            enabled: r.enabled,
-            priority: r.priority,
-            created_at: r.created_at,
-        })
+        .map(RuleResponse::from)
         .collect();
+    response.sort_by_key(|r| std::cmp::Reverse(r.priority));
 
     Ok(Json(response))
 }
 
+/// Create several rules for a flag at once, e.g. when importing targeting from another system
+/// All rules are validated up front and inserted in one transaction, so either all or none are created
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rules/bulk",
+    tag = "rules",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the original response when an import is retried with the same key"),
+    ),
+    request_body = Vec<CreateRuleRequest>,
+    responses(
+        (status = 201, description = "Created rules, highest priority first", body = [RuleResponse]),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "A rule has an invalid type or value, nothing was created", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Flag not found"),
+        (status = 409, description = "The environment requires approval"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn bulk_create(
+    State(state): State<AppState>,
+    VerifiedFlag { environment_id, flag_id, user_id, .. }: VerifiedFlag,
+    JsonBody(payload): JsonBody<Vec<CreateRuleRequest>>,
+) -> Result<impl IntoResponse, ApiError> {
+    pending_changes::direct_write_gate(&state.db, environment_id, FlagWrite::RuleBulkCreate).await?;
+    let rule_values = validate_bulk_rules(&payload)?;
+
+    let mut tx = state.db.begin().await.map_err(|e| {
+        eprintln!("Failed to start transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    let mut rules = Vec::with_capacity(payload.len());
+    for (rule, rule_value) in payload.iter().zip(&rule_values) {
+        let created = sqlx::query_as::<_, FlagRule>(
+            r#"
+            INSERT INTO flag_rules (flag_id, rule_type, rule_value, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, rule_value_json)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $9, $8, $8, $10)
+            RETURNING id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, created_at
+            "#,
+        )
+        .bind(flag_id)
+        .bind(&rule.rule_type)
+        .bind(&rule_value.text)
+        .bind(rule.enabled.unwrap_or(true))
+        .bind(rule.priority.unwrap_or(0))
+        .bind(rule.negate.unwrap_or(false))
+        .bind(&rule.payload)
+        .bind(user_id)
+        .bind(rule.rollout_percentage)
+        .bind(&rule_value.json)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| -> ApiError {
+            if let Some(db_error) = e.as_database_error() {
+                if let Some(error) = rule_type_violation(db_error.code().as_deref(), db_error.constraint(), &rule.rule_type) {
+                    return error.into();
+                }
+            }
+            eprintln!("Failed to create rule: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create rules".to_string()).into()
+        })?;
+        rules.push(created);
+    }
+
+    // Dropping the transaction on any error above rolls back the rules inserted so far
+    tx.commit().await.map_err(|e| {
+        eprintln!("Failed to commit transaction: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    let mut response: Vec<RuleResponse> = rules
+        .into_iter()
+        .map(RuleResponse::from)
+        .collect();
+    // Stable sort, rules with the same priority keep their import order
+    response.sort_by_key(|r| std::cmp::Reverse(r.priority));
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
 /// Get a single rule by ID
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rules/{rule_id}",
+    tag = "rules",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ("rule_id" = Uuid, Path, description = "Rule id"),
+    ),
+    responses(
+        (status = 200, description = "Rule", body = RuleResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Rule not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id, flag_id, rule_id)): Path<(Uuid, Uuid, Uuid, Uuid)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Fetch rule and verify ownership
+    VerifiedFlag { flag_id, .. }: VerifiedFlag,
+    Path((_, _, _, rule_id)): Path<(Uuid, Uuid, Uuid, Uuid)>,
+) -> Result<impl IntoResponse, ApiError> {
     let rule = sqlx::query_as::<_, FlagRule>(
         r#"
-        SELECT r.id, r.flag_id, r.rule_type, r.rule_value, r.enabled, r.priority, r.created_at
-        FROM flag_rules r
-        JOIN feature_flags f ON r.flag_id = f.id
-        JOIN environments e ON f.environment_id = e.id
-        JOIN projects p ON e.project_id = p.id
-        WHERE r.id = $1 AND r.flag_id = $2 AND f.environment_id = $3 AND e.project_id = $4 AND p.created_by = $5
+        SELECT id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, created_at
+        FROM flag_rules WHERE id = $1 AND flag_id = $2
         "#,
     )
     .bind(rule_id)
     .bind(flag_id)
-    .bind(environment_id)
-    .bind(project_id)
-    .bind(user_id)
     .fetch_optional(&state.db)
     .await
     .map_err(|e| {
@@ -185,122 +548,267 @@ pub async fn get(
 
     match rule {
         Some(r) => {
-            let response = RuleResponse {
-                id: r.id,
-                flag_id: r.flag_id,
-                rule_type: r.rule_type,
-                rule_value: r.rule_value,
-                enabled: r.enabled,
-                priority: r.priority,
-                created_at: r.created_at,
-            };
+            let response = RuleResponse::from(r);
             Ok(Json(response))
         }
-        None => Err((StatusCode::NOT_FOUND, "Rule not found".to_string())),
+        None => Err((StatusCode::NOT_FOUND, "Rule not found".to_string()).into()),
     }
 }
 
 /// Update a rule
+#[utoipa::path(
+    put,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rules/{rule_id}",
+    tag = "rules",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ("rule_id" = Uuid, Path, description = "Rule id"),
+    ),
+    request_body = UpdateRuleRequest,
+    responses(
+        (status = 200, description = "Updated rule", body = RuleResponse),
+        (status = 202, description = "The environment requires approval, the update was stored as a pending change", body = PendingChangeResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "Invalid rule type or value", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Rule not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn update(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id, flag_id, rule_id)): Path<(Uuid, Uuid, Uuid, Uuid)>,
-    Json(payload): Json<UpdateRuleRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    // Check if rule exists and user owns the project
+    verified: VerifiedFlag,
+    Path((_, _, _, rule_id)): Path<(Uuid, Uuid, Uuid, Uuid)>,
+    JsonBody(payload): JsonBody<UpdateRuleRequest>,
+) -> Result<Response, ApiError> {
+    let existing_rule = load_rule(&state.db, verified.flag_id, rule_id).await?;
+    let rule_value = validate_update(&existing_rule, &payload)?;
+
+    // In environments that require approval the update waits for an approver
+    if pending_changes::direct_write_gate(&state.db, verified.environment_id, FlagWrite::RuleUpdate).await? == WriteGate::Propose {
+        let change = TargetingChange::RuleUpdate { rule_id, rule: payload };
+        return pending_changes::propose_targeting(&state.db, &verified, &change).await;
+    }
+
+    let rule = write_rule_update(&state.db, rule_id, &payload, rule_value.as_ref(), verified.user_id).await?;
+    Ok(Json(RuleResponse::from(rule)).into_response())
+}
+
+/// Apply an approved rule update in the approval's transaction, checking it against the rule as it is now
+pub async fn apply_update(
+    conn: &mut PgConnection,
+    flag_id: Uuid,
+    rule_id: Uuid,
+    payload: &UpdateRuleRequest,
+    user_id: Uuid,
+) -> Result<FlagRule, ApiError> {
+    let existing_rule = load_rule(&mut *conn, flag_id, rule_id).await?;
+    let rule_value = validate_update(&existing_rule, payload)?;
+    write_rule_update(conn, rule_id, payload, rule_value.as_ref(), user_id).await
+}
+
+// A rule of the flag, 404 for rules of other flags
+async fn load_rule<'e>(executor: impl sqlx::PgExecutor<'e>, flag_id: Uuid, rule_id: Uuid) -> Result<FlagRule, ApiError> {
+    // Check the rule belongs to the flag
     let rule = sqlx::query_as::<_, FlagRule>(
         r#"
-        SELECT r.id, r.flag_id, r.rule_type, r.rule_value, r.enabled, r.priority, r.created_at
-        FROM flag_rules r
-        JOIN feature_flags f ON r.flag_id = f.id
-        JOIN environments e ON f.environment_id = e.id
-        JOIN projects p ON e.project_id = p.id
-        WHERE r.id = $1 AND r.flag_id = $2 AND f.environment_id = $3 AND e.project_id = $4 AND p.created_by = $5
+        SELECT id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, created_at
+        FROM flag_rules WHERE id = $1 AND flag_id = $2
         "#,
     )
     .bind(rule_id)
     .bind(flag_id)
-    .bind(environment_id)
-    .bind(project_id)
-    .bind(user_id)
-    .fetch_optional(&state.db)
+    .fetch_optional(executor)
     .await
     .map_err(|e| {
         eprintln!("Failed to check rule: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
     })?;
 
-    let existing_rule = match rule {
-        Some(r) => r,
-        None => return Err((StatusCode::NOT_FOUND, "Rule not found".to_string())),
-    };
+    match rule {
+        Some(r) => Ok(r),
+        None => Err((StatusCode::NOT_FOUND, "Rule not found".to_string()).into()),
+    }
+}
 
-    // Validate rule value if provided
-    if let Some(ref value) = payload.rule_value {
-        validate_rule_value(&existing_rule.rule_type, value)
-            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+// Checks an update must pass against the rule, the new value if it sets one
+fn validate_update(existing_rule: &FlagRule, payload: &UpdateRuleRequest) -> Result<Option<RuleValue>, ValidationError> {
+    // Validate rule value if provided, in either form
+    let rule_value = match (&payload.rule_value, &payload.rule_value_json) {
+        (None, None) => None,
+        (value, json) => Some(resolve_rule_value(
+            &existing_rule.rule_type,
+            value.as_deref().unwrap_or_default(),
+            json.as_ref(),
+        )?),
+    };
+    if let Some(percentage) = payload.rollout_percentage {
+        validate_rule_rollout(&existing_rule.rule_type, percentage)?;
     }
+    Ok(rule_value)
+}
 
+async fn write_rule_update<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    rule_id: Uuid,
+    payload: &UpdateRuleRequest,
+    rule_value: Option<&RuleValue>,
+    user_id: Uuid,
+) -> Result<FlagRule, ApiError> {
     // Update the rule using COALESCE
     let updated_rule = sqlx::query_as::<_, FlagRule>(
         r#"
         UPDATE flag_rules
         SET
             rule_value = COALESCE($2, rule_value),
+            rule_value_json = CASE WHEN $2::TEXT IS NULL THEN rule_value_json ELSE $9 END,
             enabled = COALESCE($3, enabled),
-            priority = COALESCE($4, priority)
+            priority = COALESCE($4, priority),
+            negate = COALESCE($5, negate),
+            payload = COALESCE($6, payload),
+            updated_by = $7,
+            rollout_percentage = COALESCE($8, rollout_percentage)
         WHERE id = $1
-        RETURNING id, flag_id, rule_type, rule_value, enabled, priority, created_at
+        RETURNING id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, created_at
         "#
     )
     .bind(rule_id)
-    .bind(payload.rule_value.as_deref())
+    .bind(rule_value.map(|value| &value.text))
     .bind(payload.enabled)
     .bind(payload.priority)
-    .fetch_one(&state.db)
+    .bind(payload.negate)
+    .bind(&payload.payload)
+    .bind(user_id)
+    .bind(payload.rollout_percentage)
+    .bind(rule_value.and_then(|value| value.json.as_ref()))
+    .fetch_one(executor)
     .await
     .map_err(|e| {
         eprintln!("Failed to update rule: {:?}", e);
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update rule".to_string())
     })?;
 
-    let response = RuleResponse {
-        id: updated_rule.id,
-        flag_id: updated_rule.flag_id,
-        rule_type: updated_rule.rule_type,
-        rule_value: updated_rule.rule_value,
-        enabled: updated_rule.enabled,
-        priority: updated_rule.priority,
-        created_at: updated_rule.created_at,
-    };
-
-    Ok(Json(response))
+    Ok(updated_rule)
 }
 
-/// Delete a rule
-pub async fn delete(
+/// Duplicate a rule
+/// The copy keeps the source's type, value, enabled state, negation and payload
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rules/{rule_id}/duplicate",
+    tag = "rules",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ("rule_id" = Uuid, Path, description = "Id of the rule to copy"),
+    ),
+    request_body(content = Option<DuplicateRuleRequest>, description = "Optional priority for the copy"),
+    responses(
+        (status = 201, description = "Copied rule", body = RuleResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Rule not found"),
+        (status = 409, description = "The environment requires approval"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn duplicate(
     State(state): State<AppState>,
-    JwtUser(user_id): JwtUser,
-    Path((project_id, environment_id, flag_id, rule_id)): Path<(Uuid, Uuid, Uuid, Uuid)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let result = sqlx::query(
+    VerifiedFlag { environment_id, flag_id, user_id, .. }: VerifiedFlag,
+    Path((_, _, _, rule_id)): Path<(Uuid, Uuid, Uuid, Uuid)>,
+    payload: Option<JsonBody<DuplicateRuleRequest>>,
+) -> Result<impl IntoResponse, ApiError> {
+    pending_changes::direct_write_gate(&state.db, environment_id, FlagWrite::RuleDuplicate).await?;
+    let JsonBody(payload) = payload.unwrap_or_default();
+
+    // Check the source rule belongs to the flag
+    let source = sqlx::query_as::<_, FlagRule>(
         r#"
-        DELETE FROM flag_rules
-        WHERE id = $1 AND flag_id = $2
-        AND EXISTS(
-            SELECT 1 FROM feature_flags f
-            JOIN environments e ON f.environment_id = e.id
-            JOIN projects p ON e.project_id = p.id
-            WHERE f.id = $2 AND f.environment_id = $3 AND e.project_id = $4 AND p.created_by = $5
-        )
+        SELECT id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, created_at
+        FROM flag_rules WHERE id = $1 AND flag_id = $2
         "#,
     )
     .bind(rule_id)
     .bind(flag_id)
-    .bind(environment_id)
-    .bind(project_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to check rule: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Rule not found".to_string()))?;
+
+    let rule = sqlx::query_as::<_, FlagRule>(
+        r#"
+        INSERT INTO flag_rules (flag_id, rule_type, rule_value, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, rule_value_json)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $9, $8, $8, $10)
+        RETURNING id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage, created_by, updated_by, created_at
+        "#,
+    )
+    .bind(flag_id)
+    .bind(&source.rule_type)
+    .bind(&source.rule_value)
+    .bind(source.enabled)
+    .bind(duplicate_priority(source.priority, payload.priority))
+    .bind(source.negate)
+    .bind(&source.payload)
     .bind(user_id)
-    .execute(&state.db)
+    .bind(source.rollout_percentage)
+    .bind(&source.rule_value_json)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to duplicate rule: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to duplicate rule".to_string())
+    })?;
+
+    let response = RuleResponse::from(rule);
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Delete a rule
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{project_id}/environments/{environment_id}/flags/{flag_id}/rules/{rule_id}",
+    tag = "rules",
+    params(
+        ("project_id" = Uuid, Path, description = "Project id"),
+        ("environment_id" = Uuid, Path, description = "Environment id"),
+        ("flag_id" = Uuid, Path, description = "Flag id"),
+        ("rule_id" = Uuid, Path, description = "Rule id"),
+    ),
+    responses(
+        (status = 204, description = "Rule deleted"),
+        (status = 202, description = "The environment requires approval, the deletion was stored as a pending change", body = PendingChangeResponse),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 404, description = "Rule not found"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete(
+    State(state): State<AppState>,
+    verified: VerifiedFlag,
+    Path((_, _, _, rule_id)): Path<(Uuid, Uuid, Uuid, Uuid)>,
+) -> Result<Response, ApiError> {
+    // In environments that require approval the deletion waits for an approver
+    if pending_changes::direct_write_gate(&state.db, verified.environment_id, FlagWrite::RuleDelete).await? == WriteGate::Propose {
+        load_rule(&state.db, verified.flag_id, rule_id).await?;
+        return pending_changes::propose_targeting(&state.db, &verified, &TargetingChange::RuleDelete { rule_id }).await;
+    }
+
+    delete_rule(&state.db, verified.flag_id, rule_id).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Delete a rule of the flag, directly or when its deletion is approved
+pub async fn delete_rule<'e>(executor: impl sqlx::PgExecutor<'e>, flag_id: Uuid, rule_id: Uuid) -> Result<(), ApiError> {
+    let result = sqlx::query("DELETE FROM flag_rules WHERE id = $1 AND flag_id = $2")
+    .bind(rule_id)
+    .bind(flag_id)
+    .execute(executor)
     .await
     .map_err(|e| {
         eprintln!("Failed to delete rule: {:?}", e);
@@ -308,8 +816,8 @@ pub async fn delete(
     })?;
 
     if result.rows_affected() == 0 {
-        return Err((StatusCode::NOT_FOUND, "Rule not found".to_string()));
+        return Err((StatusCode::NOT_FOUND, "Rule not found".to_string()).into());
     }
 
-    Ok(StatusCode::NO_CONTENT)
+    Ok(())
 }
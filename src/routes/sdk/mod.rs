@@ -1,24 +1,668 @@
 pub mod routes;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use uuid::Uuid;
 
 // Re-export UserContext from evaluation module
-pub use crate::evaluation::UserContext;
+pub use crate::evaluation::{RuleTrace, UserContext};
+use crate::routes::environments::normalize_environment_key;
+use crate::routes::error::ValidationError;
+use crate::routes::flags::LifecycleState;
+use crate::routes::projects::UnknownFlagDefault;
+use crate::routes::sdk_auth::SdkKeyType;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EvaluateParams {
+    #[serde(default)]
+    pub debug: bool,  // Include a per-rule evaluation trace and the X-Evaluation-Time-Ms header in the response
+    #[serde(default = "default_include_reasons")]
+    pub include_reasons: bool,  // false returns the compact { "flags": { "key": true } } shape
+    #[serde(default)]
+    pub include_meta: bool,  // Add the evaluated environment and evaluated_at to the response
+    #[serde(default)]
+    pub include_versions: bool,  // Add each flag's configuration version, for caching results per flag
+}
+
+impl Default for EvaluateParams {
+    fn default() -> Self {
+        Self {
+            debug: false,
+            include_reasons: default_include_reasons(),
+            include_meta: false,
+            include_versions: false,
+        }
+    }
+}
+
+fn default_include_reasons() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct EvaluateRequest {
-    pub environment: String,  // Environment key (e.g., "production", "staging")
+    pub environment: Option<String>,  // Environment key, the project's default_environment_key if omitted
     pub context: UserContext,
+    #[serde(default)]
+    pub flags: Option<Vec<String>>,  // Only evaluate these flag keys, every flag the key can see if omitted
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct EvaluateResponse {
     pub flags: HashMap<String, FlagState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EvaluatedEnvironment>,  // Only with ?include_meta=true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evaluated_at: Option<DateTime<Utc>>,  // Only with ?include_meta=true
+}
+
+/// Environment the flags were evaluated in, returned with `?include_meta=true`
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EvaluatedEnvironment {
+    pub key: String,
+    pub name: String,
+    pub config_version: i64,  // Same value as /sdk/v1/config's version, changes once per flag or rule mutation
+}
+
+impl EvaluateResponse {
+    /// Only the enabled state of each flag, for clients that don't need reasons or payloads
+    pub fn compact(self) -> CompactEvaluateResponse {
+        CompactEvaluateResponse {
+            flags: self
+                .flags
+                .into_iter()
+                .map(|(key, state)| (key, state.enabled))
+                .collect(),
+            environment: self.environment,
+            evaluated_at: self.evaluated_at,
+        }
+    }
+}
+
+/// Evaluate response for `?include_reasons=false`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CompactEvaluateResponse {
+    pub flags: HashMap<String, bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EvaluatedEnvironment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evaluated_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct FlagState {
     pub enabled: bool,
     pub reason: String,
-}
\ No newline at end of file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Vec<RuleTrace>>,
+    pub identified: bool,  // false when the context had none of the flag's bucketing identifiers, so rollout results aren't sticky
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,  // Only sent as true, the flag is due for removal and callers should stop checking it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<i64>,  // Only with ?include_versions=true, see flag_state_version
+}
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ConfigParams {
+    pub environment: String,  // Environment key (e.g., "production", "staging")
+}
+
+/// A flag as listed by /sdk/v1/flags, before any context is applied
+#[derive(Debug, sqlx::FromRow)]
+pub struct FlagDefaultRow {
+    pub key: String,
+    pub enabled: bool,
+    pub temporarily_disabled_until: Option<DateTime<Utc>>,
+    pub lifecycle_state: String,
+}
+
+/// Served flag keys and whether each is on before targeting: enabled and not paused at `now`
+/// Drafts are left out, as they are everywhere else in the SDK API
+pub fn flag_defaults(rows: Vec<FlagDefaultRow>, now: DateTime<Utc>) -> BTreeMap<String, bool> {
+    rows.into_iter()
+        .filter(|row| LifecycleState::from_db(&row.lifecycle_state).is_served())
+        .map(|row| {
+            let paused = row.temporarily_disabled_until.is_some_and(|until| now < until);
+            (row.key, row.enabled && !paused)
+        })
+        .collect()
+}
+
+/// Full flag definitions for SDKs that evaluate locally
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfigResponse {
+    pub environment: String,
+    pub version: i64,  // Bumped on every flag or rule change in the environment
+    pub flags: Vec<ConfigFlag>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfigFlag {
+    pub key: String,
+    pub enabled: bool,
+    pub rollout_percentage: i32,
+    pub anonymous_behavior: String,
+    pub hash_algorithm: String,  // How rollout buckets are computed, see the API docs
+    pub bucketing_keys: Vec<String>,  // Identifiers to bucket on in order, empty for the default order
+    pub temporarily_disabled_until: Option<DateTime<Utc>>,  // Evaluate to false until then
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,  // Only sent as true, see FlagState
+    pub rules: Vec<ConfigRule>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<ConfigOverride>,  // Checked before everything but `enabled` and the pause, server keys only
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfigOverride {
+    pub user_identifier: String,  // A user_id, or an email matched ignoring case
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfigRule {
+    pub id: Uuid,  // Part of the hash input for the rule's own rollout
+    pub rule_type: String,
+    pub rule_value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_value_json: Option<serde_json::Value>,  // List entries, read instead of rule_value when present
+    pub enabled: bool,
+    pub priority: i32,
+    pub negate: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollout_percentage: Option<i32>,
+}
+
+/// Reason reported for requested flag keys the environment doesn't serve
+pub const UNKNOWN_FLAG_REASON: &str = "unknown_flag";
+
+/// Requested flag keys missing from the served ones, each reported once in request order
+pub fn unknown_flag_keys<'a>(requested: &'a [String], served: &[&str]) -> Vec<&'a str> {
+    let mut unknown: Vec<&str> = Vec::new();
+    for key in requested {
+        if !served.contains(&key.as_str()) && !unknown.contains(&key.as_str()) {
+            unknown.push(key);
+        }
+    }
+    unknown
+}
+
+/// States for unknown flag keys under the project's unknown_flag_default, or the 404 it asks for
+/// Drafts and flags hidden from client keys count as unknown, so their existence isn't leaked
+pub fn unknown_flag_states(
+    unknown: &[&str],
+    default: UnknownFlagDefault,
+) -> Result<HashMap<String, FlagState>, String> {
+    match (default, unknown.first()) {
+        (UnknownFlagDefault::Error, Some(key)) => Err(format!("Flag '{}' not found", key)),
+        _ => Ok(unknown
+            .iter()
+            .map(|key| {
+                let state = FlagState {
+                    enabled: false,
+                    reason: UNKNOWN_FLAG_REASON.to_string(),
+                    payload: None,
+                    trace: None,
+                    identified: false,
+                    deprecated: false,
+                    version: None,
+                };
+                (key.to_string(), state)
+            })
+            .collect()),
+    }
+}
+
+/// Version of a flag's configuration reported with `?include_versions=true`
+/// Flag updates bump `version` and rule or override changes bump `rules_version`, both only grow,
+/// so their sum changes whenever anything that feeds the flag's evaluation does
+pub fn flag_state_version(version: i64, rules_version: i64) -> i64 {
+    version + rules_version
+}
+
+/// A former key of a flag in the evaluated environment, see the rename endpoint
+#[derive(Debug, sqlx::FromRow)]
+pub struct FlagAliasRow {
+    pub alias: String,
+    pub flag_id: Uuid,
+}
+
+/// Aliases answered with a served flag's state, as (alias, current key) pairs
+/// A live flag key wins over an alias spelled the same, and aliases of flags that aren't served stay unknown
+pub fn resolve_flag_aliases(aliases: &[FlagAliasRow], served: &[(Uuid, &str)]) -> Vec<(String, String)> {
+    aliases
+        .iter()
+        .filter(|alias| !served.iter().any(|(_, key)| *key == alias.alias))
+        .filter_map(|alias| {
+            served
+                .iter()
+                .find(|(id, _)| *id == alias.flag_id)
+                .map(|(_, key)| (alias.alias.clone(), key.to_string()))
+        })
+        .collect()
+}
+
+/// The state reported under an old key: the renamed flag's result, flagged as deprecated
+pub fn aliased_flag_state(state: &FlagState, key: &str) -> FlagState {
+    FlagState {
+        reason: format!("{} (flag renamed to '{}', this key is deprecated)", state.reason, key),
+        deprecated: true,
+        ..state.clone()
+    }
+}
+
+/// Keep each evaluation record with probability `rate`, the project's evaluation_sample_rate
+/// Records are drawn independently, so logged counts divided by the rate estimate the real ones
+pub fn sample_evaluations<T>(records: Vec<T>, rate: f64, rng: &mut impl rand::Rng) -> Vec<T> {
+    if rate >= 1.0 {
+        return records;
+    }
+    if rate.is_nan() || rate <= 0.0 {
+        return Vec::new();
+    }
+    records.into_iter().filter(|_| rng.gen_bool(rate)).collect()
+}
+
+/// Environment an evaluate request applies to: the one it names, else the project's default
+/// A named key is lowercased like on environment create, so "Production" finds `production`
+pub fn resolve_environment_key(
+    requested: Option<&str>,
+    default_key: Option<String>,
+) -> Result<String, ValidationError> {
+    match requested.map(str::trim).filter(|key| !key.is_empty()) {
+        Some(key) => Ok(normalize_environment_key(key)),
+        None => default_key.ok_or_else(|| {
+            ValidationError::new(
+                "environment",
+                "required",
+                "environment is required, the project has no default_environment_key",
+            )
+        }),
+    }
+}
+
+// Client keys see fewer flags than server keys, so the key type is part of the tag
+pub fn config_etag(version: i64, key_type: SdkKeyType) -> String {
+    format!("\"{}-{}\"", version, key_type.as_str())
+}
+
+/// Header reporting where an evaluate request spent its time, sent with `?debug=true`
+pub const EVALUATION_TIME_HEADER: &str = "x-evaluation-time-ms";
+
+/// Time one evaluate request spent waiting on the database vs evaluating flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvaluateTimings {
+    pub db: Duration,
+    pub evaluation: Duration,
+}
+
+impl EvaluateTimings {
+    /// Milliseconds per phase, e.g. "db=4.210, eval=0.318, total=4.528"
+    pub fn header_value(&self) -> String {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        format!(
+            "db={:.3}, eval={:.3}, total={:.3}",
+            ms(self.db),
+            ms(self.evaluation),
+            ms(self.db + self.evaluation)
+        )
+    }
+}
+
+// True when an If-None-Match header already names the current tag ("*" matches anything)
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',').any(|candidate| {
+        let candidate = candidate.trim();
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_environment_key() {
+        let default_key = || Some("production".to_string());
+
+        // An explicit environment wins over the default
+        assert_eq!(resolve_environment_key(Some("staging"), default_key()).unwrap(), "staging");
+        assert_eq!(resolve_environment_key(Some("staging"), None).unwrap(), "staging");
+        assert_eq!(resolve_environment_key(Some(" Production "), default_key()).unwrap(), "production");
+
+        // Omitted or blank falls back to the default
+        assert_eq!(resolve_environment_key(None, default_key()).unwrap(), "production");
+        assert_eq!(resolve_environment_key(Some("  "), default_key()).unwrap(), "production");
+
+        let err = resolve_environment_key(None, None).unwrap_err();
+        assert_eq!((err.field, err.code), ("environment", "required"));
+    }
+
+    #[test]
+    fn test_compact_evaluate_response() {
+        let state = |enabled: bool, reason: &str| FlagState {
+            enabled,
+            reason: reason.to_string(),
+            payload: Some(serde_json::json!({"timeout_ms": 2500})),
+            trace: None,
+            identified: true,
+            deprecated: false,
+            version: None,
+        };
+        let response = EvaluateResponse {
+            flags: HashMap::from([
+                ("dark_mode".to_string(), FlagState { deprecated: true, ..state(true, "User in 50% rollout") }),
+                ("new_checkout".to_string(), state(false, "Flag is globally disabled")),
+            ]),
+            environment: None,
+            evaluated_at: None,
+        };
+
+        let full = serde_json::to_value(&response).unwrap();
+        assert_eq!(full["flags"]["dark_mode"]["reason"], "User in 50% rollout");
+        // Deprecation is only mentioned for deprecated flags
+        assert_eq!(full["flags"]["dark_mode"]["deprecated"], true);
+        assert!(full["flags"]["new_checkout"].get("deprecated").is_none());
+
+        let compact = serde_json::to_value(response.compact()).unwrap();
+        assert_eq!(
+            compact,
+            serde_json::json!({ "flags": { "dark_mode": true, "new_checkout": false } })
+        );
+    }
+
+    #[test]
+    fn test_identified_vs_anonymous_contexts() {
+        use crate::evaluation::{AnonymousBehavior, BucketingKey, FlagData, HashAlgorithm};
+
+        let flag = |bucketing_keys: Vec<BucketingKey>| FlagData {
+            key: "dark_mode".to_string(),
+            enabled: true,
+            rollout_percentage: 50,
+            anonymous_behavior: AnonymousBehavior::Bucket,
+            hash_algorithm: HashAlgorithm::default(),
+            bucketing_keys,
+            temporarily_disabled_until: None,
+            overrides: Vec::new(),
+        };
+        let context = |user_id: Option<&str>, anonymous_id: Option<&str>| UserContext {
+            user_id: user_id.map(str::to_string),
+            user_email: None,
+            anonymous_id: anonymous_id.map(str::to_string),
+            custom_attributes: HashMap::new(),
+        };
+        let identified = |flag: &FlagData, context: &UserContext| flag.bucketing_identifier(context).is_some();
+
+        let default_keys = flag(Vec::new());
+        assert!(identified(&default_keys, &context(Some("user-1"), None)));
+        // A client-generated anonymous_id still keeps the rollout sticky
+        assert!(identified(&default_keys, &context(None, Some("device-1"))));
+        assert!(!identified(&default_keys, &context(None, None)));
+
+        // Only the flag's own bucketing keys count
+        let by_account = flag(vec![BucketingKey::Attribute("account_id".to_string())]);
+        assert!(!identified(&by_account, &context(Some("user-1"), None)));
+
+        let state = FlagState {
+            enabled: false,
+            reason: "User excluded from 50% rollout".to_string(),
+            payload: None,
+            trace: None,
+            identified: false,
+            deprecated: false,
+            version: None,
+        };
+        // Sent either way, unlike deprecated
+        assert_eq!(serde_json::to_value(&state).unwrap()["identified"], false);
+    }
+
+    #[test]
+    fn test_unknown_flag_default() {
+        let requested = vec!["dark_mode".to_string(), "typo_flag".to_string(), "typo_flag".to_string()];
+        let unknown = unknown_flag_keys(&requested, &["dark_mode"]);
+        assert_eq!(unknown, vec!["typo_flag"]);
+
+        // off answers with a disabled flag, so clients fail open
+        let states = unknown_flag_states(&unknown, UnknownFlagDefault::Off).unwrap();
+        let state = &states["typo_flag"];
+        assert!(!state.enabled);
+        assert_eq!(state.reason, UNKNOWN_FLAG_REASON);
+        assert_eq!(states.len(), 1);
+
+        // error fails the request instead
+        let err = unknown_flag_states(&unknown, UnknownFlagDefault::Error).unwrap_err();
+        assert_eq!(err, "Flag 'typo_flag' not found");
+
+        // Nothing to answer when every requested key is served
+        assert!(unknown_flag_keys(&requested[..1], &["dark_mode"]).is_empty());
+        assert!(unknown_flag_states(&[], UnknownFlagDefault::Error).unwrap().is_empty());
+
+        let request: EvaluateRequest = serde_json::from_value(serde_json::json!({ "context": { "user_id": "u1" } })).unwrap();
+        assert!(request.flags.is_none());
+        assert_eq!(UnknownFlagDefault::from_db("error"), UnknownFlagDefault::Error);
+        assert_eq!(UnknownFlagDefault::default().as_str(), "off");
+    }
+
+    #[test]
+    fn test_flag_alias_resolution() {
+        let checkout = Uuid::new_v4();
+        let banner = Uuid::new_v4();
+        let draft = Uuid::new_v4();
+        let alias = |alias: &str, flag_id| FlagAliasRow { alias: alias.to_string(), flag_id };
+        let aliases = vec![
+            alias("old_checkout", checkout),
+            alias("legacy_checkout", checkout),
+            alias("promo_banner", banner),
+            alias("hidden_flag", draft),
+        ];
+        // A later flag took over promo_banner as its live key, and the draft isn't served
+        let served = [(checkout, "new_checkout"), (banner, "banner"), (Uuid::new_v4(), "promo_banner")];
+
+        let resolved = resolve_flag_aliases(&aliases, &served);
+        assert_eq!(
+            resolved,
+            vec![
+                ("old_checkout".to_string(), "new_checkout".to_string()),
+                ("legacy_checkout".to_string(), "new_checkout".to_string()),
+            ]
+        );
+
+        // The old key answers with the renamed flag's result, marked deprecated
+        let state = FlagState {
+            enabled: true,
+            reason: "User in 50% rollout".to_string(),
+            payload: Some(serde_json::json!({ "variant": "b" })),
+            trace: None,
+            identified: true,
+            deprecated: false,
+            version: None,
+        };
+        let aliased = aliased_flag_state(&state, "new_checkout");
+        assert!(aliased.enabled && aliased.identified && aliased.deprecated);
+        assert_eq!(aliased.payload, state.payload);
+        assert_eq!(aliased.reason, "User in 50% rollout (flag renamed to 'new_checkout', this key is deprecated)");
+
+        // Aliases of an unserved flag stay unknown
+        let requested = vec!["hidden_flag".to_string(), "old_checkout".to_string()];
+        let mut answered: Vec<&str> = served.iter().map(|(_, key)| *key).collect();
+        answered.extend(resolved.iter().map(|(alias, _)| alias.as_str()));
+        assert_eq!(unknown_flag_keys(&requested, &answered), vec!["hidden_flag"]);
+    }
+
+    #[test]
+    fn test_sampling_reduces_logged_evaluations() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let records: Vec<usize> = (0..10_000).collect();
+
+        // The default rate logs every evaluation, 0 logs none
+        assert_eq!(sample_evaluations(records.clone(), 1.0, &mut rng).len(), 10_000);
+        assert!(sample_evaluations(records.clone(), 0.0, &mut rng).is_empty());
+
+        // A 10% rate logs about a tenth, and extrapolating by the rate recovers the total
+        let sampled = sample_evaluations(records.clone(), 0.1, &mut rng);
+        assert!((800..1_200).contains(&sampled.len()), "{}", sampled.len());
+        let estimate = sampled.len() as f64 / 0.1;
+        assert!((estimate - 10_000.0).abs() < 2_000.0);
+
+        // Kept records stay in order
+        assert!(sampled.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_flag_defaults() {
+        let now = "2026-10-17T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let row = |key: &str, enabled: bool, paused_until: Option<DateTime<Utc>>, lifecycle_state: &str| FlagDefaultRow {
+            key: key.to_string(),
+            enabled,
+            temporarily_disabled_until: paused_until,
+            lifecycle_state: lifecycle_state.to_string(),
+        };
+
+        let defaults = flag_defaults(
+            vec![
+                row("new_checkout", true, None, "active"),
+                row("dark_mode", false, None, "active"),
+                row("old_banner", true, None, "deprecated"),
+                row("unreleased", true, None, "draft"),
+                row("paused", true, Some(now + chrono::Duration::hours(1)), "active"),
+                row("pause_over", true, Some(now - chrono::Duration::hours(1)), "active"),
+            ],
+            now,
+        );
+
+        // Drafts are not served, deprecated flags still are
+        assert!(!defaults.contains_key("unreleased"));
+        let expected: BTreeMap<String, bool> = [
+            ("dark_mode", false),
+            ("new_checkout", true),
+            ("old_banner", true),
+            ("pause_over", true),
+            ("paused", false),
+        ]
+        .into_iter()
+        .map(|(key, enabled)| (key.to_string(), enabled))
+        .collect();
+        assert_eq!(defaults, expected);
+    }
+
+    #[test]
+    fn test_evaluate_response_meta() {
+        let evaluated_at = "2026-10-17T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let response = || EvaluateResponse {
+            flags: HashMap::new(),
+            environment: Some(EvaluatedEnvironment {
+                key: "production".to_string(),
+                name: "Production".to_string(),
+                config_version: 42,
+            }),
+            evaluated_at: Some(evaluated_at),
+        };
+        let meta = serde_json::json!({
+            "flags": {},
+            "environment": { "key": "production", "name": "Production", "config_version": 42 },
+            "evaluated_at": "2026-10-17T12:00:00Z",
+        });
+
+        assert_eq!(serde_json::to_value(response()).unwrap(), meta);
+        assert_eq!(serde_json::to_value(response().compact()).unwrap(), meta);
+
+        // Without meta the payload stays as it was
+        let plain = EvaluateResponse {
+            environment: None,
+            evaluated_at: None,
+            ..response()
+        };
+        assert_eq!(serde_json::to_value(plain).unwrap(), serde_json::json!({ "flags": {} }));
+    }
+
+    #[test]
+    fn test_flag_state_versions() {
+        // A toggle bumps the flag's version, a rule or override change its rules_version
+        let before = flag_state_version(3, 5);
+        let toggled = flag_state_version(4, 5);
+        let retargeted = flag_state_version(4, 6);
+        assert!(before < toggled && toggled < retargeted);
+
+        let state = |version| FlagState {
+            enabled: true,
+            reason: "Flag enabled globally".to_string(),
+            payload: None,
+            trace: None,
+            identified: true,
+            deprecated: false,
+            version,
+        };
+        let json = serde_json::to_value(state(Some(toggled))).unwrap();
+        assert_eq!(json["version"], 9);
+        assert_ne!(json["version"], serde_json::to_value(state(Some(before))).unwrap()["version"]);
+
+        // Only sent when asked for
+        assert!(serde_json::to_value(state(None)).unwrap().get("version").is_none());
+
+        // Old keys of a renamed flag report the flag's version
+        assert_eq!(aliased_flag_state(&state(Some(toggled)), "new_checkout").version, Some(9));
+    }
+
+    #[test]
+    fn test_evaluate_params_default_to_reasons() {
+        let params: EvaluateParams = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(params.include_reasons);
+        assert!(!params.debug);
+        assert!(!params.include_meta);
+        assert!(!params.include_versions);
+        assert!(EvaluateParams::default().include_reasons);
+
+        let params: EvaluateParams = serde_json::from_value(serde_json::json!({ "include_reasons": false })).unwrap();
+        assert!(!params.include_reasons);
+    }
+
+    #[test]
+    fn test_evaluate_timings_header() {
+        let timings = EvaluateTimings {
+            db: Duration::from_micros(4_210),
+            evaluation: Duration::from_micros(318),
+        };
+        assert_eq!(timings.header_value(), "db=4.210, eval=0.318, total=4.528");
+        assert_eq!(EvaluateTimings::default().header_value(), "db=0.000, eval=0.000, total=0.000");
+    }
+
+    #[test]
+    fn test_config_etag_matching() {
+        let etag = config_etag(7, SdkKeyType::Server);
+        assert_eq!(etag, "\"7-server\"");
+        assert_ne!(etag, config_etag(7, SdkKeyType::Client));
+
+        assert!(etag_matches("\"7-server\"", &etag));
+        assert!(etag_matches("W/\"7-server\"", &etag));
+        assert!(etag_matches("\"6-server\", \"7-server\"", &etag));
+        assert!(etag_matches("*", &etag));
+        assert!(!etag_matches("\"6-server\"", &etag));
+        assert!(!etag_matches("\"7-client\"", &etag));
+    }
+
+    #[test]
+    fn test_config_version_bumped_once_per_transaction() {
+        // Every bump of config_version must be skipped when the current transaction already made one,
+        // otherwise a bulk import or disable-all would move the version once per row
+        let migration = include_str!("../../../migrations/20261017000029_bump_config_version_once_per_transaction.sql");
+        let (_, function) = migration.split_once("CREATE OR REPLACE FUNCTION bump_environment_config_version()").unwrap();
+        let bumps: Vec<&str> = function.split("UPDATE environments").skip(1).collect();
+        assert_eq!(bumps.len(), 3);
+        for bump in bumps {
+            let statement = bump.split(';').next().unwrap();
+            assert!(statement.contains("config_version = config_version + 1"));
+            assert!(statement.contains("config_version_txid = txid_current()"));
+            assert!(statement.contains("AND config_version_txid IS DISTINCT FROM txid_current()"));
+        }
+    }
+}
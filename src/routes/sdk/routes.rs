@@ -1,21 +1,42 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::evaluation::{evaluate_flag, FlagData, RuleData};
-use crate::routes::sdk_auth::SdkProject;
+use crate::evaluation::cache::CacheKey;
+use crate::evaluation::{try_evaluate_flag, AnonymousBehavior, BucketingKey, FlagData, FlagEvaluation, HashAlgorithm, RuleData};
+use crate::routes::environments::normalize_environment_key;
+use crate::routes::flags::LifecycleState;
+use crate::routes::error::{ApiError, JsonBody, ValidationError, ValidationErrorBody};
+use crate::routes::overrides::{load_all_overrides, load_overrides};
+use crate::routes::projects::UnknownFlagDefault;
+use crate::routes::sdk_auth::{ProjectRateLimit, SdkKeyType, SdkProject};
 use crate::state::AppState;
-use super::{EvaluateRequest, EvaluateResponse, FlagState};
+use super::{
+    aliased_flag_state, config_etag, etag_matches, flag_defaults, flag_state_version, resolve_environment_key, resolve_flag_aliases, sample_evaluations, unknown_flag_keys, unknown_flag_states, ConfigFlag, ConfigOverride, ConfigParams, ConfigResponse, ConfigRule,
+    EvaluateParams, EvaluateRequest, FlagAliasRow, FlagDefaultRow, EvaluateResponse, EvaluateTimings, EvaluatedEnvironment, FlagState,
+    EVALUATION_TIME_HEADER,
+};
 
 // Database row types for batch queries
 #[derive(Debug, sqlx::FromRow)]
 struct EnvironmentRow {
     id: Uuid,
+    name: String,
+    config_version: i64,
+    evaluation_sample_rate: f64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ConfigEnvironmentRow {
+    id: Uuid,
+    config_version: i64,
 }
 
 #[derive(Debug, sqlx::FromRow)]
@@ -24,32 +45,104 @@ struct FlagRow {
     key: String,
     enabled: bool,
     rollout_percentage: i32,
+    anonymous_behavior: String,
+    hash_algorithm: String,
+    bucketing_keys: Vec<String>,
+    temporarily_disabled_until: Option<DateTime<Utc>>,
+    lifecycle_state: String,
+    version: i64,
+    rules_version: i64,
 }
 
 #[derive(Debug, sqlx::FromRow)]
 struct RuleRow {
+    id: Uuid,
     flag_id: Uuid,
     rule_type: String,
     rule_value: String,
+    rule_value_json: Option<serde_json::Value>,
     enabled: bool,
     priority: i32,
+    negate: bool,
+    payload: Option<serde_json::Value>,
+    rollout_percentage: Option<i32>,
 }
 
 /// Evaluate all flags for a project/environment based on user context
 /// Uses optimized batch loading of rules to minimize database round trips
+/// Pass `?debug=true` to include a per-rule evaluation trace for each flag,
+/// or `?include_reasons=false` for a compact map of flag keys to enabled states
+/// `?include_meta=true` adds the evaluated environment and `evaluated_at`
+/// `?include_versions=true` adds each flag's configuration version
+#[utoipa::path(
+    post,
+    path = "/sdk/v1/evaluate",
+    tag = "sdk",
+    params(
+        EvaluateParams,
+    ),
+    request_body = EvaluateRequest,
+    responses(
+        (status = 200, description = "Evaluated flags keyed by flag key, a CompactEvaluateResponse with include_reasons=false", body = EvaluateResponse),
+        (status = 400, description = "Malformed JSON body or missing fields"),
+        (status = 422, description = "No environment given and the project has no default, or debug or include_versions without reasons", body = ValidationErrorBody),
+        (status = 401, description = "Missing or invalid SDK key"),
+        (status = 404, description = "Environment not found, or a requested flag key is unknown and the project's unknown_flag_default is error"),
+        (status = 429, description = "Project evaluate rate limit exceeded, see the Retry-After header"),
+    ),
+    security(("sdk_key" = []))
+)]
 pub async fn evaluate(
     State(state): State<AppState>,
     SdkProject(project_id): SdkProject,
-    Json(request): Json<EvaluateRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+    ProjectRateLimit(rate_limit): ProjectRateLimit,
+    key_type: SdkKeyType,
+    Query(params): Query<EvaluateParams>,
+    JsonBody(request): JsonBody<EvaluateRequest>,
+) -> Result<Response, ApiError> {
+    // The trace lives next to the reason, so the compact shape can't carry it
+    if params.debug && !params.include_reasons {
+        return Err(ValidationError::new("include_reasons", "conflict", "debug=true requires include_reasons").into());
+    }
+    if params.include_versions && !params.include_reasons {
+        return Err(ValidationError::new("include_reasons", "conflict", "include_versions=true requires include_reasons").into());
+    }
+
+    // Refuse over-limit requests before touching the database
+    state
+        .rate_limiter
+        .check(project_id, rate_limit, Instant::now())
+        .map_err(ApiError::RateLimited)?;
+
+    // Phases are timed on every request, Instant::now is too cheap to matter, but only reported with debug=true
+    let started = Instant::now();
     let context = request.context;
-    let environment_key = request.environment;
+    let requested_keys = request.flags;
+    let requested = request.environment.as_deref().map(str::trim).filter(|key| !key.is_empty());
+
+    // Only look up the project's default when the request doesn't name an environment
+    let default_key = match requested {
+        Some(_) => None,
+        None => sqlx::query_scalar::<_, Option<String>>(
+            "SELECT default_environment_key FROM projects WHERE id = $1",
+        )
+        .bind(project_id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to fetch default environment: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch environment".to_string())
+        })?,
+    };
+    let environment_key = resolve_environment_key(requested, default_key)?;
 
     // Step 1: Verify environment exists and get environment_id
     let environment: Option<EnvironmentRow> = sqlx::query_as(
         r#"
-        SELECT id FROM environments
-        WHERE project_id = $1 AND key = $2
+        SELECT e.id, e.name, e.config_version, p.evaluation_sample_rate
+        FROM environments e
+        JOIN projects p ON e.project_id = p.id
+        WHERE e.project_id = $1 AND e.key = $2
         "#,
     )
     .bind(project_id)
@@ -61,25 +154,61 @@ pub async fn evaluate(
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch environment".to_string())
     })?;
 
-    let environment_id = match environment {
-        Some(env) => env.id,
+    let environment = match environment {
+        Some(env) => env,
         None => {
             return Err((
                 StatusCode::NOT_FOUND,
                 format!("Environment '{}' not found", environment_key),
-            ));
+            )
+            .into());
         }
     };
+    let environment_id = environment.id;
+    let sample_rate = environment.evaluation_sample_rate;
 
-    // Step 2: Fetch all flags for this environment in one query
+    // All flags in one response are evaluated against the same instant
+    let now = state.clock.now();
+    let (meta_environment, evaluated_at) = if params.include_meta {
+        let meta = EvaluatedEnvironment {
+            key: environment_key.clone(),
+            name: environment.name,
+            config_version: environment.config_version,
+        };
+        (Some(meta), Some(now))
+    } else {
+        (None, None)
+    };
+
+    // Old keys of renamed flags, only the requested ones when the request names flags
+    let aliases: Vec<FlagAliasRow> = sqlx::query_as(
+        "SELECT alias, flag_id FROM flag_aliases WHERE environment_id = $1 AND ($2::TEXT[] IS NULL OR alias = ANY($2))",
+    )
+    .bind(environment_id)
+    .bind(&requested_keys)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flag aliases: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flags".to_string())
+    })?;
+    let aliased_ids: Vec<Uuid> = aliases.iter().map(|alias| alias.flag_id).collect();
+
+    // Step 2: Fetch all flags for this environment in one query, including flags requested by an old key
+    // Client keys only see flags explicitly marked client_visible, and no key sees drafts
     let flags: Vec<FlagRow> = sqlx::query_as(
         r#"
-        SELECT id, key, enabled, rollout_percentage
+        SELECT id, key, enabled, rollout_percentage, anonymous_behavior, hash_algorithm, bucketing_keys, temporarily_disabled_until, lifecycle_state, version, rules_version
         FROM feature_flags
-        WHERE environment_id = $1
+        WHERE environment_id = $1 AND ($2 OR client_visible) AND lifecycle_state = ANY($3)
+          AND ($4::TEXT[] IS NULL OR key = ANY($4) OR id = ANY($5))
         "#,
     )
     .bind(environment_id)
+    .bind(key_type == SdkKeyType::Server)
+    .bind(LifecycleState::served_states())
+    .bind(&requested_keys)
+    .bind(&aliased_ids)
     .fetch_all(&state.db)
     .await
     .map_err(|e| {
@@ -87,10 +216,44 @@ pub async fn evaluate(
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flags".to_string())
     })?;
 
+    // Requested keys the environment doesn't serve are answered per the project's unknown_flag_default,
+    // which is only looked up when there are any
+    // Aliases count as served when the flag they name is
+    let served: Vec<(Uuid, &str)> = flags.iter().map(|flag| (flag.id, flag.key.as_str())).collect();
+    let aliased = resolve_flag_aliases(&aliases, &served);
+    let mut result_flags = HashMap::new();
+    if let Some(requested) = &requested_keys {
+        let mut served: Vec<&str> = served.iter().map(|(_, key)| *key).collect();
+        served.extend(aliased.iter().map(|(alias, _)| alias.as_str()));
+        let unknown = unknown_flag_keys(requested, &served);
+        if !unknown.is_empty() {
+            let default = sqlx::query_scalar::<_, String>("SELECT unknown_flag_default FROM projects WHERE id = $1")
+                .bind(project_id)
+                .fetch_one(&state.db)
+                .await
+                .map_err(|e| {
+                    eprintln!("Failed to fetch unknown flag default: {:?}", e);
+                    (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flags".to_string())
+                })?;
+            result_flags = unknown_flag_states(&unknown, UnknownFlagDefault::from_db(&default))
+                .map_err(|e| (StatusCode::NOT_FOUND, e))?;
+        }
+    }
+
     if flags.is_empty() {
-        return Ok(Json(EvaluateResponse {
-            flags: HashMap::new(),
-        }));
+        let timings = EvaluateTimings {
+            db: started.elapsed(),
+            evaluation: Duration::ZERO,
+        };
+        let response = evaluate_response(
+            EvaluateResponse {
+                flags: result_flags,
+                environment: meta_environment,
+                evaluated_at,
+            },
+            params.include_reasons,
+        );
+        return Ok(with_timings(response, params.debug.then_some(timings), &environment_key, 0));
     }
 
     // Step 3: Collect all flag IDs for batch rule loading
@@ -99,7 +262,7 @@ pub async fn evaluate(
     // Step 4: Preload ALL rules for ALL flags in ONE query (key optimization!)
     let rules: Vec<RuleRow> = sqlx::query_as(
         r#"
-        SELECT flag_id, rule_type, rule_value, enabled, priority
+        SELECT id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage
         FROM flag_rules
         WHERE flag_id = ANY($1)
         ORDER BY priority DESC
@@ -117,28 +280,37 @@ pub async fn evaluate(
     let mut rules_by_flag: HashMap<Uuid, Vec<RuleData>> = HashMap::new();
     for rule in rules {
         let rule_data = RuleData {
+            id: Some(rule.id),
             rule_type: rule.rule_type,
             rule_value: rule.rule_value,
+            rule_value_json: rule.rule_value_json,
             enabled: rule.enabled,
             priority: rule.priority,
+            negate: rule.negate,
+            payload: rule.payload,
+            rollout_percentage: rule.rollout_percentage,
         };
+        #[allow(clippy::unwrap_or_default)]
         rules_by_flag
             .entry(rule.flag_id)
             .or_insert_with(Vec::new)
             .push(rule_data);
     }
 
+    // Overrides for this user, loaded the same way
+    let mut overrides_by_flag = load_overrides(&state.db, &flag_ids, &context).await.map_err(|e| {
+        eprintln!("Failed to fetch overrides: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch overrides".to_string())
+    })?;
+
     // Step 6: Evaluate each flag using the preloaded rules
-    let mut result_flags = HashMap::new();
+    let cache_now = std::time::Instant::now();
+    let mut timings = EvaluateTimings {
+        db: cache_now - started,
+        evaluation: Duration::ZERO,
+    };
     let mut evaluation_records = Vec::new();
 
-    let user_identifier = context
-        .user_id
-        .as_ref()
-        .or(context.user_email.as_ref())
-        .map(|s| s.as_str())
-        .unwrap_or("anonymous");
-
     for flag in &flags {
         // Get rules for this flag from our preloaded HashMap (O(1) lookup)
         let flag_rules = rules_by_flag.get(&flag.id).map(|v| v.as_slice()).unwrap_or(&[]);
@@ -148,10 +320,55 @@ pub async fn evaluate(
             key: flag.key.clone(),
             enabled: flag.enabled,
             rollout_percentage: flag.rollout_percentage,
+            anonymous_behavior: AnonymousBehavior::from_db(&flag.anonymous_behavior),
+            hash_algorithm: HashAlgorithm::from_db(&flag.hash_algorithm),
+            bucketing_keys: BucketingKey::from_db(&flag.bucketing_keys),
+            temporarily_disabled_until: flag.temporarily_disabled_until,
+            overrides: overrides_by_flag.remove(&flag.id).unwrap_or_default(),
         };
 
-        // Evaluate the flag
-        let evaluation = evaluate_flag(&flag_data, flag_rules, &context);
+        // Reuse a recent result for the same flag configuration and user
+        // Debug traces, time-window and attribute rules depend on more than the key, so they are never cached,
+        // and neither are paused flags whose result changes when the pause ends, random anonymous rolls
+        // or flags bucketing on custom attributes
+        let cacheable = !params.debug
+            && !flag_data.is_paused(now)
+            && !flag_data.rolls_randomly(&context)
+            && !flag_data.buckets_on_attributes()
+            && !flag_rules
+                .iter()
+                .any(|rule| rule.is_time_window() || rule.uses_custom_attributes());
+        let cache_key = cacheable.then(|| CacheKey {
+            flag_id: flag.id,
+            flag_version: flag.version,
+            rules_version: flag.rules_version,
+            user_id: context.user_id.clone(),
+            user_email: context.user_email.clone(),
+            anonymous_id: context.anonymous_id.clone(),
+        });
+        let cached = cache_key
+            .as_ref()
+            .and_then(|key| state.evaluation_cache.get(key, cache_now));
+
+        // Evaluate the flag, a flag that fails to evaluate is served off instead of failing the whole request
+        let evaluation = match cached {
+            Some(evaluation) => evaluation,
+            None => match try_evaluate_flag(&flag_data, flag_rules, &context, now, params.debug) {
+                Ok(evaluation) => {
+                    if let Some(key) = cache_key {
+                        state.evaluation_cache.insert(key, &evaluation, cache_now);
+                    }
+                    evaluation
+                }
+                Err(e) => {
+                    eprintln!("Failed to evaluate flag {} ({}): {}", flag.key, flag.id, e);
+                    FlagEvaluation::evaluation_error()
+                }
+            },
+        };
+
+        // The identifier the flag buckets on, None when rollouts can't stick to this context
+        let user_identifier = flag_data.bucketing_identifier(&context);
 
         // Store result
         result_flags.insert(
@@ -159,15 +376,37 @@ pub async fn evaluate(
             FlagState {
                 enabled: evaluation.enabled,
                 reason: evaluation.reason,
+                payload: evaluation.payload,
+                trace: evaluation.trace,
+                identified: user_identifier.is_some(),
+                deprecated: LifecycleState::from_db(&flag.lifecycle_state) == LifecycleState::Deprecated,
+                version: params.include_versions.then(|| flag_state_version(flag.version, flag.rules_version)),
             },
         );
 
-        // Collect evaluation record for batch insert
+        // Collect evaluation record for batch insert, logged under the identifier the flag buckets on
+        let user_identifier = user_identifier.unwrap_or("anonymous");
         evaluation_records.push((flag.id, user_identifier.to_string(), evaluation.enabled));
     }
 
+    // Old keys get their renamed flag's result, and a flag asked for only by an old key isn't reported under its new one
+    for (alias, key) in &aliased {
+        if let Some(state) = result_flags.get(key) {
+            let state = aliased_flag_state(state, key);
+            result_flags.insert(alias.clone(), state);
+        }
+    }
+    if let Some(requested) = &requested_keys {
+        result_flags.retain(|key, _| requested.contains(key));
+    }
+
+    timings.evaluation = cache_now.elapsed();
+    let evaluated_count = flags.len();
+
     // Step 7: Batch insert evaluation logs (async, don't block response)
-    // Using a single INSERT with multiple values for efficiency
+    // Using a single INSERT with multiple values for efficiency, only the project's sampled share is logged
+    let log_started = Instant::now();
+    let evaluation_records = sample_evaluations(evaluation_records, sample_rate, &mut rand::thread_rng());
     if !evaluation_records.is_empty() {
         let flag_ids: Vec<Uuid> = evaluation_records.iter().map(|(id, _, _)| *id).collect();
         let user_ids: Vec<String> = evaluation_records.iter().map(|(_, u, _)| u.clone()).collect();
@@ -175,16 +414,257 @@ pub async fn evaluate(
 
         let _ = sqlx::query(
             r#"
-            INSERT INTO flag_evaluations (flag_id, user_identifier, result)
-            SELECT * FROM UNNEST($1::uuid[], $2::text[], $3::bool[])
+            INSERT INTO flag_evaluations (flag_id, user_identifier, result, sample_rate)
+            SELECT *, $4::DOUBLE PRECISION FROM UNNEST($1::uuid[], $2::text[], $3::bool[])
             "#,
         )
         .bind(&flag_ids)
         .bind(&user_ids)
         .bind(&results)
+        .bind(sample_rate)
         .execute(&state.db)
         .await;
     }
+    timings.db += log_started.elapsed();
+
+    let response = EvaluateResponse {
+        flags: result_flags,
+        environment: meta_environment,
+        evaluated_at,
+    };
+    let response = evaluate_response(response, params.include_reasons);
+    Ok(with_timings(response, params.debug.then_some(timings), &environment_key, evaluated_count))
+}
+
+// Logs the phase timings and adds them as a header, for debug requests only
+fn with_timings(mut response: Response, timings: Option<EvaluateTimings>, environment_key: &str, flag_count: usize) -> Response {
+    if let Some(timings) = timings {
+        let value = timings.header_value();
+        eprintln!("Evaluated {} flags in environment '{}': {} ms", flag_count, environment_key, value);
+        if let Ok(value) = header::HeaderValue::from_str(&value) {
+            response.headers_mut().insert(EVALUATION_TIME_HEADER, value);
+        }
+    }
+    response
+}
+
+// Full response, or just the enabled states when reasons were turned off
+fn evaluate_response(response: EvaluateResponse, include_reasons: bool) -> Response {
+    if include_reasons {
+        Json(response).into_response()
+    } else {
+        Json(response.compact()).into_response()
+    }
+}
+
+/// List the environment's flag keys with their on/off state before any user context is applied
+/// For clients that need the available flags before they know the user; targeting and rollouts are not applied
+#[utoipa::path(
+    get,
+    path = "/sdk/v1/flags",
+    tag = "sdk",
+    params(ConfigParams),
+    responses(
+        (status = 200, description = "Flag keys and whether each is enabled, sorted by key", body = BTreeMap<String, bool>),
+        (status = 401, description = "Missing or invalid SDK key"),
+        (status = 404, description = "Environment not found"),
+    ),
+    security(("sdk_key" = []))
+)]
+pub async fn flags(
+    State(state): State<AppState>,
+    SdkProject(project_id): SdkProject,
+    key_type: SdkKeyType,
+    Query(params): Query<ConfigParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let environment_key = normalize_environment_key(&params.environment);
+    let environment_id = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM environments WHERE project_id = $1 AND key = $2",
+    )
+    .bind(project_id)
+    .bind(&environment_key)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch environment: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch environment".to_string())
+    })?
+    .ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Environment '{}' not found", params.environment),
+        )
+    })?;
+
+    // Client keys only see flags explicitly marked client_visible, flag_defaults drops drafts
+    let rows: Vec<FlagDefaultRow> = sqlx::query_as(
+        r#"
+        SELECT key, enabled, temporarily_disabled_until, lifecycle_state
+        FROM feature_flags
+        WHERE environment_id = $1 AND ($2 OR client_visible)
+        "#,
+    )
+    .bind(environment_id)
+    .bind(key_type == SdkKeyType::Server)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flags: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flags".to_string())
+    })?;
+
+    Ok(Json(flag_defaults(rows, state.clock.now())))
+}
+
+/// Return the unevaluated flag and rule definitions for an environment
+/// SDKs poll this with If-None-Match and only re-download when the version changes
+#[utoipa::path(
+    get,
+    path = "/sdk/v1/config",
+    tag = "sdk",
+    params(
+        ConfigParams,
+        ("If-None-Match" = Option<String>, Header, description = "ETag from the previous download"),
+    ),
+    responses(
+        (status = 200, description = "Flag and rule definitions for local evaluation", body = ConfigResponse),
+        (status = 304, description = "Configuration unchanged since the given ETag"),
+        (status = 401, description = "Missing or invalid SDK key"),
+        (status = 404, description = "Environment not found"),
+    ),
+    security(("sdk_key" = []))
+)]
+pub async fn config(
+    State(state): State<AppState>,
+    SdkProject(project_id): SdkProject,
+    key_type: SdkKeyType,
+    Query(params): Query<ConfigParams>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let environment_key = normalize_environment_key(&params.environment);
+    let environment: Option<ConfigEnvironmentRow> = sqlx::query_as(
+        r#"
+        SELECT id, config_version FROM environments
+        WHERE project_id = $1 AND key = $2
+        "#,
+    )
+    .bind(project_id)
+    .bind(&environment_key)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch environment: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch environment".to_string())
+    })?;
+
+    let environment = environment.ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Environment '{}' not found", params.environment),
+        )
+    })?;
+
+    // Nothing changed since the SDK's last download
+    let etag = config_etag(environment.config_version, key_type);
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| etag_matches(v, &etag));
+    if not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    // Client keys only see flags explicitly marked client_visible, and no key sees drafts
+    let flags: Vec<FlagRow> = sqlx::query_as(
+        r#"
+        SELECT id, key, enabled, rollout_percentage, anonymous_behavior, hash_algorithm, bucketing_keys, temporarily_disabled_until, lifecycle_state, version, rules_version
+        FROM feature_flags
+        WHERE environment_id = $1 AND ($2 OR client_visible) AND lifecycle_state = ANY($3)
+        ORDER BY key
+        "#,
+    )
+    .bind(environment.id)
+    .bind(key_type == SdkKeyType::Server)
+    .bind(LifecycleState::served_states())
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch flags: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch flags".to_string())
+    })?;
+
+    let flag_ids: Vec<Uuid> = flags.iter().map(|f| f.id).collect();
+    let rules: Vec<RuleRow> = sqlx::query_as(
+        r#"
+        SELECT id, flag_id, rule_type, rule_value, rule_value_json, enabled, priority, negate, payload, rollout_percentage
+        FROM flag_rules
+        WHERE flag_id = ANY($1)
+        ORDER BY priority DESC
+        "#,
+    )
+    .bind(&flag_ids)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch rules: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch rules".to_string())
+    })?;
+
+    // Overrides name users, so client keys that ship config to browsers don't get them
+    let mut overrides_by_flag = if key_type == SdkKeyType::Server {
+        load_all_overrides(&state.db, &flag_ids).await.map_err(|e| {
+            eprintln!("Failed to fetch overrides: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch overrides".to_string())
+        })?
+    } else {
+        HashMap::new()
+    };
+
+    let mut rules_by_flag: HashMap<Uuid, Vec<ConfigRule>> = HashMap::new();
+    for rule in rules {
+        rules_by_flag.entry(rule.flag_id).or_default().push(ConfigRule {
+            id: rule.id,
+            rule_type: rule.rule_type,
+            rule_value: rule.rule_value,
+            rule_value_json: rule.rule_value_json,
+            enabled: rule.enabled,
+            priority: rule.priority,
+            negate: rule.negate,
+            payload: rule.payload,
+            rollout_percentage: rule.rollout_percentage,
+        });
+    }
+
+    let flags = flags
+        .into_iter()
+        .map(|flag| ConfigFlag {
+            rules: rules_by_flag.remove(&flag.id).unwrap_or_default(),
+            overrides: overrides_by_flag
+                .remove(&flag.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|o| ConfigOverride {
+                    user_identifier: o.user_identifier,
+                    enabled: o.enabled,
+                    payload: o.payload,
+                })
+                .collect(),
+            key: flag.key,
+            enabled: flag.enabled,
+            rollout_percentage: flag.rollout_percentage,
+            anonymous_behavior: flag.anonymous_behavior,
+            hash_algorithm: flag.hash_algorithm,
+            bucketing_keys: flag.bucketing_keys,
+            temporarily_disabled_until: flag.temporarily_disabled_until,
+            deprecated: LifecycleState::from_db(&flag.lifecycle_state) == LifecycleState::Deprecated,
+        })
+        .collect();
+
+    let response = ConfigResponse {
+        environment: environment_key,
+        version: environment.config_version,
+        flags,
+    };
 
-    Ok(Json(EvaluateResponse { flags: result_flags }))
+    Ok(([(header::ETAG, etag)], Json(response)).into_response())
 }
@@ -5,9 +5,45 @@ use axum::{
     middleware::Next,
     response::{IntoResponse, Response},
 };
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use sqlx::PgPool;
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 
+use crate::routes::error::{ApiError, AuthRejection, SDK_KEY_CHALLENGE};
+use crate::routes::projects::hash_sdk_key;
+use crate::routes::sdk_keys::should_touch_last_used;
+
+/// Kind of SDK key used to authenticate a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SdkKeyType {
+    /// Server-side key, can evaluate every flag
+    Server,
+    /// Client-side key, can only evaluate flags marked client_visible
+    Client,
+}
+
+impl SdkKeyType {
+    /// Value stored in the `project_sdk_keys.key_type` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SdkKeyType::Server => "server",
+            SdkKeyType::Client => "client",
+        }
+    }
+
+    /// Parses a stored `key_type`, None for anything else so an unexpected value never grants server access
+    pub fn from_stored(value: &str) -> Option<Self> {
+        match value {
+            "server" => Some(SdkKeyType::Server),
+            "client" => Some(SdkKeyType::Client),
+            _ => None,
+        }
+    }
+}
+
 /// Extractor for SDK authentication, returns the project_id
 pub struct SdkProject(pub Uuid);
 
@@ -15,7 +51,7 @@ impl<S> FromRequestParts<S> for SdkProject
 where
     S: Send + Sync,
 {
-    type Rejection = (StatusCode, &'static str);
+    type Rejection = ApiError;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
         parts
@@ -23,11 +59,55 @@ where
             .get::<Uuid>()
             .copied()
             .map(SdkProject)
-            .ok_or((StatusCode::UNAUTHORIZED, "missing project"))
+            .ok_or_else(|| ApiError::Status(StatusCode::UNAUTHORIZED, "missing project".to_string()))
+    }
+}
+
+impl<S> FromRequestParts<S> for SdkKeyType
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<SdkKeyType>()
+            .copied()
+            .ok_or_else(|| ApiError::Status(StatusCode::UNAUTHORIZED, "missing key type".to_string()))
+    }
+}
+
+/// Extractor for the authenticated project's own evaluate rate limit, None when it uses the default
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectRateLimit(pub Option<i32>);
+
+impl<S> FromRequestParts<S> for ProjectRateLimit
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<ProjectRateLimit>()
+            .copied()
+            .ok_or_else(|| ApiError::Status(StatusCode::UNAUTHORIZED, "missing project".to_string()))
     }
 }
 
-/// Middleware to validate SDK key and inject project_id
+#[derive(Debug, sqlx::FromRow)]
+struct SdkKeyRow {
+    id: Uuid,
+    project_id: Uuid,
+    key_hash: String,
+    key_type: String,
+    last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    evaluate_rate_limit_per_minute: Option<i32>,
+}
+
+/// Middleware to validate SDK key and inject project_id and key type
 pub async fn require_sdk_key(
     mut req: Request,
     next: Next,
@@ -41,7 +121,7 @@ pub async fn require_sdk_key(
     let sdk_key = match sdk_key {
         Some(key) => key,
         None => {
-            return Err((StatusCode::UNAUTHORIZED, "Missing X-SDK-Key header"));
+            return Err(AuthRejection::unauthorized(SDK_KEY_CHALLENGE, "Missing X-SDK-Key header"));
         }
     };
 
@@ -49,28 +129,78 @@ pub async fn require_sdk_key(
     let pool = req
         .extensions()
         .get::<PgPool>()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Database pool not found"))?;
+        .ok_or(AuthRejection::internal("Database pool not found"))?;
+
+    // Keys are stored hashed, so look up by the hash of the presented key.
+    // The lookup never touches the plaintext, so response timing can't leak key prefixes.
+    let key_hash = hash_sdk_key(sdk_key);
 
-    // Verify SDK key and get project_id
-    let project = sqlx::query!(
+    // Verify SDK key is active (not revoked or expired) and get project_id
+    let project: Option<SdkKeyRow> = sqlx::query_as(
         r#"
-        SELECT id FROM projects WHERE sdk_key = $1
+        SELECT k.id, k.project_id, k.key_hash, k.key_type, k.last_used_at, p.evaluate_rate_limit_per_minute
+        FROM project_sdk_keys k
+        JOIN projects p ON k.project_id = p.id
+        WHERE k.key_hash = $1
+        AND k.revoked_at IS NULL
+        AND (k.expires_at IS NULL OR k.expires_at > NOW())
         "#,
-        sdk_key
     )
+    .bind(&key_hash)
     .fetch_optional(pool)
     .await
     .map_err(|e| {
         eprintln!("Database error validating SDK key: {:?}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+        AuthRejection::internal("Database error")
     })?;
 
+    // Re-check the stored hash in constant time before trusting the row
+    let project = project.filter(|p| bool::from(p.key_hash.as_bytes().ct_eq(key_hash.as_bytes())));
+
     match project {
         Some(p) => {
-            // Insert project_id into request extensions
-            req.extensions_mut().insert(p.id);
+            // Insert project_id and key type into request extensions
+            let Some(key_type) = SdkKeyType::from_stored(&p.key_type) else {
+                eprintln!("SDK key {} has unknown key_type {:?}, rejecting it", p.id, p.key_type);
+                return Err(AuthRejection::unauthorized(SDK_KEY_CHALLENGE, "Invalid SDK key"));
+            };
+            // Record key usage in the background (throttled) so evaluation isn't slowed down
+            if should_touch_last_used(p.last_used_at, chrono::Utc::now()) {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let result = sqlx::query(
+                        "UPDATE project_sdk_keys SET last_used_at = NOW() WHERE id = $1",
+                    )
+                    .bind(p.id)
+                    .execute(&pool)
+                    .await;
+                    if let Err(e) = result {
+                        eprintln!("Failed to update SDK key last_used_at: {:?}", e);
+                    }
+                });
+            }
+
+            req.extensions_mut().insert(p.project_id);
+            req.extensions_mut().insert(key_type);
+            req.extensions_mut().insert(ProjectRateLimit(p.evaluate_rate_limit_per_minute));
             Ok(next.run(req).await)
         }
-        None => Err((StatusCode::UNAUTHORIZED, "Invalid SDK key")),
+        // Unknown, revoked and expired keys look the same to the caller
+        None => Err(AuthRejection::unauthorized(SDK_KEY_CHALLENGE, "Invalid SDK key")),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_type_from_stored() {
+        for key_type in [SdkKeyType::Server, SdkKeyType::Client] {
+            assert_eq!(SdkKeyType::from_stored(key_type.as_str()), Some(key_type));
+        }
+        assert_eq!(SdkKeyType::from_stored("Server"), None);
+        assert_eq!(SdkKeyType::from_stored("admin"), None);
+        assert_eq!(SdkKeyType::from_stored(""), None);
+    }
+}
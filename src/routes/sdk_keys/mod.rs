@@ -0,0 +1,106 @@
+pub mod routes;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::routes::sdk_auth::SdkKeyType;
+
+// MODELS
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SdkKey {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub key_hash: String,
+    pub key_prefix: String,
+    pub key_type: String,
+    pub label: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateSdkKeyRequest {
+    pub key_type: Option<SdkKeyType>,
+    pub label: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SdkKeyResponse {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    // Plaintext key, only returned once when the key is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub key_prefix: String,
+    pub key_type: String,
+    pub label: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// HELPER FUNCTIONS
+
+/// Minimum time between two `last_used_at` writes for the same key
+pub const LAST_USED_THROTTLE_SECS: i64 = 60;
+
+/// Whether a key's `last_used_at` is stale enough to be refreshed
+pub fn should_touch_last_used(last_used_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    match last_used_at {
+        Some(t) => now - t >= chrono::Duration::seconds(LAST_USED_THROTTLE_SECS),
+        None => true,
+    }
+}
+
+/// Validate that a requested expiry is in the future
+pub fn validate_expires_at(expires_at: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), String> {
+    if expires_at <= now {
+        return Err("expires_at must be in the future".to_string());
+    }
+
+    Ok(())
+}
+
+/// Validate optional key label length
+pub fn validate_label(label: &str) -> Result<(), String> {
+    if label.len() > 100 {
+        return Err("Label is too long (Max: 100 characters)".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_validate_expires_at() {
+        let now = Utc::now();
+        assert!(validate_expires_at(now + Duration::days(30), now).is_ok());
+        assert!(validate_expires_at(now, now).is_err());
+        assert!(validate_expires_at(now - Duration::seconds(1), now).is_err());
+    }
+
+    #[test]
+    fn test_should_touch_last_used() {
+        let now = Utc::now();
+        assert!(should_touch_last_used(None, now));
+        assert!(!should_touch_last_used(Some(now - Duration::seconds(30)), now));
+        assert!(should_touch_last_used(Some(now - Duration::seconds(60)), now));
+    }
+
+    #[test]
+    fn test_validate_label() {
+        assert!(validate_label("CI pipeline").is_ok());
+        assert!(validate_label(&"x".repeat(101)).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::{validate_expires_at, validate_label, CreateSdkKeyRequest, SdkKey, SdkKeyResponse};
+use crate::routes::error::{ApiError, JsonBody};
+use crate::routes::projects::{
+    generate_client_sdk_key, generate_sdk_key, hash_sdk_key, sdk_key_prefix,
+};
+use crate::routes::sdk_auth::SdkKeyType;
+use crate::routes::ownership::VerifiedProject;
+use crate::state::AppState;
+
+// HANDLERS
+
+/// Create an additional SDK key for a project
+/// The plaintext key is only returned in this response; only its hash is stored
+pub async fn create(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    JsonBody(payload): JsonBody<CreateSdkKeyRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    if let Some(expires_at) = payload.expires_at {
+        validate_expires_at(expires_at, Utc::now()).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    if let Some(ref label) = payload.label {
+        validate_label(label).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+
+    let key_type = payload.key_type.unwrap_or(SdkKeyType::Server);
+    let key = match key_type {
+        SdkKeyType::Server => generate_sdk_key(),
+        SdkKeyType::Client => generate_client_sdk_key(),
+    };
+
+    let sdk_key = sqlx::query_as::<_, SdkKey>(
+        r#"
+        INSERT INTO project_sdk_keys (project_id, key_hash, key_prefix, key_type, label, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, project_id, key_hash, key_prefix, key_type, label, expires_at, revoked_at, last_used_at, created_at
+        "#,
+    )
+    .bind(project_id)
+    .bind(hash_sdk_key(&key))
+    .bind(sdk_key_prefix(&key))
+    .bind(key_type.as_str())
+    .bind(&payload.label)
+    .bind(payload.expires_at)
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to create SDK key: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to create SDK key".to_string(),
+        )
+    })?;
+
+    let response = SdkKeyResponse {
+        id: sdk_key.id,
+        project_id: sdk_key.project_id,
+        key: Some(key),
+        key_prefix: sdk_key.key_prefix,
+        key_type: sdk_key.key_type,
+        label: sdk_key.label,
+        expires_at: sdk_key.expires_at,
+        revoked_at: sdk_key.revoked_at,
+        last_used_at: sdk_key.last_used_at,
+        created_at: sdk_key.created_at,
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// List all SDK keys for a project, including revoked and expired ones
+pub async fn list(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+) -> Result<impl IntoResponse, ApiError> {
+    let keys = sqlx::query_as::<_, SdkKey>(
+        r#"
+        SELECT id, project_id, key_hash, key_prefix, key_type, label, expires_at, revoked_at, last_used_at, created_at
+        FROM project_sdk_keys
+        WHERE project_id = $1
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch SDK keys: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch SDK keys".to_string(),
+        )
+    })?;
+
+    let response: Vec<SdkKeyResponse> = keys
+        .into_iter()
+        .map(|k| SdkKeyResponse {
+            id: k.id,
+            project_id: k.project_id,
+            key: None,
+            key_prefix: k.key_prefix,
+            key_type: k.key_type,
+            label: k.label,
+            expires_at: k.expires_at,
+            revoked_at: k.revoked_at,
+            last_used_at: k.last_used_at,
+            created_at: k.created_at,
+        })
+        .collect();
+
+    Ok(Json(response))
+}
+
+/// Revoke a single SDK key; other keys of the project keep working
+pub async fn revoke(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    Path((_, key_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let result = sqlx::query(
+        r#"
+        UPDATE project_sdk_keys
+        SET revoked_at = NOW()
+        WHERE id = $1 AND project_id = $2 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(key_id)
+    .bind(project_id)
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to revoke SDK key: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to revoke SDK key".to_string(),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "SDK key not found".to_string()).into());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,76 @@
+use crate::routes::error::ValidationError;
+
+/// Longest slug produced, the length limit flag and environment keys share
+pub const MAX_SLUG_LENGTH: usize = 64;
+
+/// Derive a key from a display name, e.g. "New Checkout (beta)" becomes "new-checkout-beta"
+/// Letters and digits are lowercased, runs of spaces, hyphens and underscores become one hyphen,
+/// and everything else is dropped, including leading digits since keys must start with a letter
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphabetic() || (c.is_ascii_digit() && !slug.is_empty()) {
+            slug.push(c.to_ascii_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.truncate(MAX_SLUG_LENGTH);
+    slug.trim_end_matches('-').to_string()
+}
+
+/// The key a create request gave, or one derived from `name` when it left the key out
+/// The result still goes through the entity's own key validation
+pub fn key_or_slug(key: Option<&str>, name: &str) -> Result<String, ValidationError> {
+    if let Some(key) = key {
+        return Ok(key.to_string());
+    }
+
+    let slug = slugify(name);
+    if slug.is_empty() {
+        return Err(ValidationError::new(
+            "key",
+            "required",
+            "Could not derive a key from the name, send a key explicitly",
+        ));
+    }
+    Ok(slug)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        let cases = [
+            ("New Checkout", "new-checkout"),
+            ("Dark mode (beta)", "dark-mode-beta"),
+            ("  Production  ", "production"),
+            ("EU - West_2", "eu-west-2"),
+            ("Checkout v2.0", "checkout-v20"),
+            ("2025 Redesign", "redesign"),
+            ("Café Menü", "caf-men"),
+            ("!!!", ""),
+            ("", ""),
+        ];
+        for (name, slug) in cases {
+            assert_eq!(slugify(name), slug, "{:?}", name);
+        }
+
+        // Long names are cut at the key limit without leaving a trailing hyphen
+        let long = format!("{} tail", "a".repeat(MAX_SLUG_LENGTH - 1));
+        assert_eq!(slugify(&long), "a".repeat(MAX_SLUG_LENGTH - 1));
+    }
+
+    #[test]
+    fn test_key_or_slug() {
+        // An explicit key is used as given, validation happens afterwards
+        assert_eq!(key_or_slug(Some("My_Key"), "Other Name").unwrap(), "My_Key");
+        assert_eq!(key_or_slug(None, "Staging EU").unwrap(), "staging-eu");
+
+        let err = key_or_slug(None, "123 !!").unwrap_err();
+        assert_eq!((err.field, err.code), ("key", "required"));
+    }
+}
@@ -0,0 +1,67 @@
+pub mod routes;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::webhooks::WebhookFormat;
+
+// MODELS
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub url: String,
+    pub format: String,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub format: Option<WebhookFormat>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookResponse {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub url: String,
+    pub format: String,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+// HELPER FUNCTIONS
+
+/// Validate webhook URL
+pub fn validate_webhook_url(url: &str) -> Result<(), String> {
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err("Webhook URL must start with http:// or https://".to_string());
+    }
+
+    if url.len() > 2048 {
+        return Err("Webhook URL is too long (Max: 2048 characters)".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_webhook_url() {
+        assert!(validate_webhook_url("https://hooks.slack.com/services/T000/B000/XXX").is_ok());
+        assert!(validate_webhook_url("http://localhost:8080/hook").is_ok());
+
+        assert!(validate_webhook_url("").is_err());
+        assert!(validate_webhook_url("ftp://example.com").is_err());
+        assert!(validate_webhook_url(&format!("https://{}", "a".repeat(2048))).is_err());
+    }
+}
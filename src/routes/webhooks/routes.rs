@@ -0,0 +1,126 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use uuid::Uuid;
+
+use super::{validate_webhook_url, CreateWebhookRequest, Webhook, WebhookResponse};
+use crate::routes::error::{ApiError, JsonBody};
+use crate::routes::ownership::VerifiedProject;
+use crate::state::AppState;
+use crate::webhooks::WebhookFormat;
+
+// HANDLERS
+
+/// Register a webhook that is notified about flag changes in the project
+pub async fn create(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    JsonBody(payload): JsonBody<CreateWebhookRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    validate_webhook_url(&payload.url).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let webhook = sqlx::query_as::<_, Webhook>(
+        r#"
+        INSERT INTO project_webhooks (project_id, url, format, enabled)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, project_id, url, format, enabled, created_at
+        "#,
+    )
+    .bind(project_id)
+    .bind(&payload.url)
+    .bind(payload.format.unwrap_or(WebhookFormat::Json).as_str())
+    .bind(payload.enabled.unwrap_or(true))
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to create webhook: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to create webhook".to_string(),
+        )
+    })?;
+
+    let response = WebhookResponse {
+        id: webhook.id,
+        project_id: webhook.project_id,
+        url: webhook.url,
+        format: webhook.format,
+        enabled: webhook.enabled,
+        created_at: webhook.created_at,
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// List all webhooks of a project
+pub async fn list(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+) -> Result<impl IntoResponse, ApiError> {
+    let webhooks = sqlx::query_as::<_, Webhook>(
+        r#"
+        SELECT id, project_id, url, format, enabled, created_at
+        FROM project_webhooks
+        WHERE project_id = $1
+        ORDER BY created_at ASC
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to fetch webhooks: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to fetch webhooks".to_string(),
+        )
+    })?;
+
+    let response: Vec<WebhookResponse> = webhooks
+        .into_iter()
+        .map(|w| WebhookResponse {
+            id: w.id,
+            project_id: w.project_id,
+            url: w.url,
+            format: w.format,
+            enabled: w.enabled,
+            created_at: w.created_at,
+        })
+        .collect();
+
+    Ok(Json(response))
+}
+
+/// Delete a webhook
+pub async fn delete(
+    State(state): State<AppState>,
+    VerifiedProject { project_id, .. }: VerifiedProject,
+    Path((_, webhook_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM project_webhooks
+        WHERE id = $1 AND project_id = $2
+        "#,
+    )
+    .bind(webhook_id)
+    .bind(project_id)
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        eprintln!("Failed to delete webhook: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to delete webhook".to_string(),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Webhook not found".to_string()).into());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
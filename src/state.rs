@@ -1,6 +1,18 @@
 use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::evaluation::cache::EvaluationCache;
+use crate::evaluation::Clock;
+use crate::password::PasswordPolicy;
+use crate::rate_limit::RateLimiter;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: PgPool,
+    pub clock: Arc<dyn Clock>,
+    pub http: reqwest::Client,  // Outgoing HTTP (webhook delivery)
+    pub evaluation_cache: Arc<EvaluationCache>,
+    pub password_policy: PasswordPolicy,  // Strength rules for new passwords
+    pub rate_limiter: Arc<RateLimiter>,  // Per-project SDK evaluate limits
+    pub strict_flag_coherence: bool,  // Reject rollouts on disabled flags instead of warning
 }
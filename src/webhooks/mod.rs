@@ -0,0 +1,213 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// Payload format of a webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// Raw JSON event payload
+    Json,
+    /// Slack incoming-webhook message (`{"text": "..."}`)
+    Slack,
+}
+
+impl WebhookFormat {
+    /// Value stored in the `project_webhooks.format` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookFormat::Json => "json",
+            WebhookFormat::Slack => "slack",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "slack" => WebhookFormat::Slack,
+            _ => WebhookFormat::Json,
+        }
+    }
+}
+
+// What happened to a flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagChangeAction {
+    Created,
+    Updated,
+    Enabled,
+    Disabled,
+    Paused,
+    Resumed,
+    Promoted,
+    Deprecated,
+    Deleted,
+}
+
+impl FlagChangeAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FlagChangeAction::Created => "created",
+            FlagChangeAction::Updated => "updated",
+            FlagChangeAction::Enabled => "enabled",
+            FlagChangeAction::Disabled => "disabled",
+            FlagChangeAction::Paused => "paused",
+            FlagChangeAction::Resumed => "resumed",
+            FlagChangeAction::Promoted => "promoted",
+            FlagChangeAction::Deprecated => "deprecated",
+            FlagChangeAction::Deleted => "deleted",
+        }
+    }
+}
+
+// A flag change, as raised by the management API
+#[derive(Debug, Clone)]
+pub struct FlagChangeEvent {
+    pub project_id: Uuid,
+    pub environment_id: Uuid,
+    pub flag_key: String,
+    pub action: FlagChangeAction,
+    pub actor_id: Uuid,
+}
+
+// Event with ids resolved to human-readable names, ready to be formatted
+#[derive(Debug, Clone)]
+pub struct ResolvedFlagChange {
+    pub project_id: Uuid,
+    pub environment_key: String,
+    pub flag_key: String,
+    pub action: FlagChangeAction,
+    pub actor_email: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct WebhookRow {
+    url: String,
+    format: String,
+}
+
+/// Build the request body for a webhook in the given format
+pub fn format_payload(change: &ResolvedFlagChange, format: WebhookFormat) -> Value {
+    match format {
+        WebhookFormat::Json => json!({
+            "event": format!("flag.{}", change.action.as_str()),
+            "project_id": change.project_id,
+            "environment": change.environment_key,
+            "flag_key": change.flag_key,
+            "actor": change.actor_email,
+            "timestamp": change.occurred_at,
+        }),
+        WebhookFormat::Slack => json!({
+            "text": format!(
+                "Flag `{}` {} in `{}` by {}",
+                change.flag_key,
+                change.action.as_str(),
+                change.environment_key,
+                change.actor_email
+            ),
+        }),
+    }
+}
+
+/// Notify all enabled webhooks of the project about a flag change
+/// Runs in the background so management requests aren't slowed down by slow receivers
+pub fn dispatch(db: PgPool, http: reqwest::Client, event: FlagChangeEvent) {
+    tokio::spawn(async move {
+        if let Err(e) = deliver(&db, &http, event).await {
+            eprintln!("Failed to dispatch webhooks: {:?}", e);
+        }
+    });
+}
+
+async fn deliver(
+    db: &PgPool,
+    http: &reqwest::Client,
+    event: FlagChangeEvent,
+) -> Result<(), sqlx::Error> {
+    let webhooks: Vec<WebhookRow> = sqlx::query_as(
+        r#"
+        SELECT url, format FROM project_webhooks
+        WHERE project_id = $1 AND enabled = TRUE
+        "#,
+    )
+    .bind(event.project_id)
+    .fetch_all(db)
+    .await?;
+
+    if webhooks.is_empty() {
+        return Ok(());
+    }
+
+    let environment_key: String = sqlx::query_scalar("SELECT key FROM environments WHERE id = $1")
+        .bind(event.environment_id)
+        .fetch_optional(db)
+        .await?
+        .unwrap_or_else(|| event.environment_id.to_string());
+
+    let actor_email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = $1")
+        .bind(event.actor_id)
+        .fetch_optional(db)
+        .await?
+        .unwrap_or_else(|| event.actor_id.to_string());
+
+    let change = ResolvedFlagChange {
+        project_id: event.project_id,
+        environment_key,
+        flag_key: event.flag_key,
+        action: event.action,
+        actor_email,
+        occurred_at: Utc::now(),
+    };
+
+    for webhook in webhooks {
+        let body = format_payload(&change, WebhookFormat::from_db(&webhook.format));
+        if let Err(e) = http.post(&webhook.url).json(&body).send().await {
+            eprintln!("Failed to deliver webhook to {}: {:?}", webhook.url, e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_change() -> ResolvedFlagChange {
+        ResolvedFlagChange {
+            project_id: Uuid::nil(),
+            environment_key: "production".to_string(),
+            flag_key: "new_checkout".to_string(),
+            action: FlagChangeAction::Enabled,
+            actor_email: "alice@x.com".to_string(),
+            occurred_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_slack_format() {
+        let payload = format_payload(&sample_change(), WebhookFormat::Slack);
+        assert_eq!(
+            payload["text"],
+            "Flag `new_checkout` enabled in `production` by alice@x.com"
+        );
+        assert_eq!(payload.as_object().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_json_format() {
+        let payload = format_payload(&sample_change(), WebhookFormat::Json);
+        assert_eq!(payload["event"], "flag.enabled");
+        assert_eq!(payload["environment"], "production");
+        assert_eq!(payload["flag_key"], "new_checkout");
+        assert_eq!(payload["actor"], "alice@x.com");
+    }
+
+    #[test]
+    fn test_format_from_db() {
+        assert_eq!(WebhookFormat::from_db("slack"), WebhookFormat::Slack);
+        assert_eq!(WebhookFormat::from_db("json"), WebhookFormat::Json);
+    }
+}